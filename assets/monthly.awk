@@ -0,0 +1,19 @@
+# Averages the daily rates of the ECB's eurofxref-hist.csv by month, into
+# `month,code,per_eur` lines under a header.
+NR == 1 {
+    for (i = 2; i <= NF; i++) code[i] = $i
+    next
+}
+{
+    month = substr($1, 1, 7)
+    for (i = 2; i <= NF; i++) {
+        if (code[i] == "" || $i == "N/A" || $i == "") continue
+        key = month "," code[i]
+        sum[key] += $i
+        days[key]++
+    }
+}
+END {
+    print "month,code,per_eur"
+    for (key in sum) printf "%s,%.6f\n", key, sum[key] / days[key] | "sort"
+}
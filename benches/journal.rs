@@ -0,0 +1,57 @@
+use coinjar::bench::{generate, BenchJournal, Query};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    for size in SIZES {
+        let text = generate(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &text, |b, text| {
+            b.iter(|| BenchJournal::parse(black_box(text)))
+        });
+    }
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let mut group = c.benchmark_group("query");
+    group.sample_size(10);
+    for size in SIZES {
+        let journal = BenchJournal::parse(&generate(size));
+        for query in [Query::All, Query::Accn, Query::AmountAbove, Query::And] {
+            let id = BenchmarkId::new(format!("{:?}", query), size);
+            group.bench_with_input(id, &query, |b, query| {
+                b.iter(|| journal.query(black_box(*query)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn balance(c: &mut Criterion) {
+    let mut group = c.benchmark_group("balance");
+    for size in SIZES {
+        let journal = BenchJournal::parse(&generate(size));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| journal.balance())
+        });
+    }
+    group.finish();
+}
+
+fn save(c: &mut Criterion) {
+    let mut group = c.benchmark_group("save");
+    group.sample_size(10);
+    for size in SIZES {
+        let journal = BenchJournal::parse(&generate(size));
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter(|| journal.save())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, query, balance, save);
+criterion_main!(benches);
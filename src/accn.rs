@@ -1,56 +1,210 @@
 pub(crate) mod entry;
 
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    str::FromStr,
+};
 
+use anyhow::{bail, Result};
+
+use chrono::NaiveDate;
 use itertools::Itertools;
-use uuid::Uuid;
+use rust_decimal::Decimal;
+
+use crate::{
+    error::CoinError,
+    period::Period,
+    util::{fold, next_id},
+    valuable::Money,
+};
 
 pub(crate) use self::entry::{AccnEntry, AccnEntryMut};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
 pub(crate) struct Accn {
-    id: Uuid,
+    /// From [`next_id`], the default one is the root.
+    id: u64,
 }
 
 impl Accn {
     // WARNING: This should never be public, this way Accn can be used as a query key without check
     fn new() -> Self {
-        Self { id: Uuid::new_v4() }
+        Self { id: next_id() }
     }
 
-    pub(crate) fn into_accn_mut(self, tree: &mut AccnTree) -> AccnEntryMut {
+    pub(crate) fn into_accn_mut(self, tree: &mut AccnTree) -> AccnEntryMut<'_> {
         tree.accn_mut(self)
     }
 
-    pub(crate) fn into_accn(self, tree: &AccnTree) -> AccnEntry {
+    pub(crate) fn into_accn(self, tree: &AccnTree) -> AccnEntry<'_> {
         tree.accn(self)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 struct AccnData {
     name: String,
     parent: Option<Accn>,
+    /// In the order they were opened, kept in step with `parent`.
+    children: Vec<Accn>,
+    /// Whether an `open` directive names the accn.
+    declared: bool,
+    /// Dates given by the `open` and `close` directives.
+    opened: Option<NaiveDate>,
+    closed: Option<NaiveDate>,
+    /// Code of the currency the accn is declared to hold.
+    currency: Option<String>,
+    /// Code of the currency the balances of the accn and its descendants
+    /// are reported in.
+    report: Option<String>,
+    /// Yearly interest the balance accrues, like `0.05` for 5%.
+    interest: Option<Decimal>,
+    /// Classes given by `class` directives, like `liquid` or `fixed-cost`.
+    classes: BTreeSet<String>,
+    /// Tax category given by a `tax` directive, like `wages`.
+    tax: Option<String>,
+    /// Statement cycle given by a `cycle` directive.
+    cycle: Option<BillingCycle>,
+    /// What may be spent per period, given by a `budget` directive.
+    budget: Option<Budget>,
+    /// Shares of what comes in moved on, given by `sweep` directives.
+    sweeps: Vec<Sweep>,
+    /// How a gross salary booked to the accn breaks down, given by
+    /// `paycheck` directives.
+    paycheck: Option<Paycheck>,
+    /// Where the last pull of the bank's txns into the accn left off,
+    /// given by a `sync` directive.
+    sync: Option<String>,
+}
+
+/// Amount an accn and its descendants may take per period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Budget {
+    pub(crate) money: Money,
+    pub(crate) period: Period,
+}
+
+/// Share of what is booked to an accn to move to `to` whenever a txn books
+/// more than `over` to it, like part of a salary put into savings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Sweep {
+    pub(crate) over: Option<Money>,
+    /// Like `0.2` for 20%.
+    pub(crate) share: Decimal,
+    pub(crate) to: Accn,
+}
+
+/// Where a gross salary goes: what is withheld from it, in the order
+/// declared, and the accn the rest is deposited to.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Paycheck {
+    pub(crate) deposit: Option<Accn>,
+    pub(crate) deductions: Vec<(Accn, Deduction)>,
+}
+
+/// A withholding from a paycheck, like taxes or a retirement contribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Deduction {
+    /// Share of the gross, like `0.22` for 22%.
+    Share(Decimal),
+    Fixed(Money),
+}
+
+/// Days of the month a card statement closes and its payment is due, the
+/// due day falling in the month after the closing when it is not later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BillingCycle {
+    pub(crate) closing: u32,
+    pub(crate) due: u32,
+}
+
+/// What happens when a posting names an accn that does not exist yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum AutoCreate {
+    /// Create it silently.
+    #[default]
+    Auto,
+    /// Ask before creating it from the REPL. Journal files are taken as
+    /// already confirmed.
+    Confirm,
+    /// Refuse it unless it was declared with `open`.
+    Strict,
 }
 
-#[derive(Debug)]
+impl AutoCreate {
+    /// Decide whether the accn `name` may be created, asking `confirm` in
+    /// confirm mode.
+    pub(crate) fn check(
+        self,
+        name: &str,
+        confirm: impl FnOnce(&str) -> Result<bool>,
+    ) -> Result<(), CoinError> {
+        match self {
+            AutoCreate::Auto => {}
+            AutoCreate::Confirm => {
+                let confirmed = confirm(name).map_err(|e| format!("{:#}", e))?;
+                if !confirmed {
+                    return Err(format!("{} not created", name).into());
+                }
+            }
+            AutoCreate::Strict => return Err(CoinError::UnknownAccount(name.to_string())),
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AutoCreate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(AutoCreate::Auto),
+            "confirm" => Ok(AutoCreate::Confirm),
+            "strict" => Ok(AutoCreate::Strict),
+            _ => bail!(
+                "unknown autocreate policy {}, expected auto, confirm or strict",
+                s
+            ),
+        }
+    }
+}
+
+impl Display for AutoCreate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutoCreate::Auto => "auto",
+            AutoCreate::Confirm => "confirm",
+            AutoCreate::Strict => "strict",
+        }
+        .fmt(f)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct AccnTree {
     root: Accn,
-    accns: HashMap<Accn, AccnData>,
+    /// Ordered by id, which is the order the accns were opened in.
+    accns: BTreeMap<Accn, AccnData>,
+    autocreate: AutoCreate,
 }
 
 impl AccnTree {
     pub(crate) fn new() -> Self {
         let root = Accn::default();
-        let mut accns = HashMap::new();
+        let mut accns = BTreeMap::new();
         accns.insert(
             root,
             AccnData {
                 name: "root".to_string(),
-                parent: None,
+                ..Default::default()
             },
         );
-        let mut ret = Self { root, accns };
+        let mut ret = Self {
+            root,
+            accns,
+            autocreate: AutoCreate::default(),
+        };
 
         ret.open_accn(root, "asset");
         ret.open_accn(root, "liability");
@@ -61,22 +215,38 @@ impl AccnTree {
         ret
     }
 
-    pub(crate) fn root(&self) -> AccnEntry {
+    pub(crate) fn root(&self) -> AccnEntry<'_> {
         self.accn(self.root)
     }
 
-    pub(crate) fn root_mut(&mut self) -> AccnEntryMut {
+    pub(crate) fn root_mut(&mut self) -> AccnEntryMut<'_> {
         self.accn_mut(self.root)
     }
 
-    pub(crate) fn expense(&self) -> AccnEntry {
+    pub(crate) fn asset(&self) -> AccnEntry<'_> {
+        self.root().child("asset").unwrap()
+    }
+
+    pub(crate) fn liability(&self) -> AccnEntry<'_> {
+        self.root().child("liability").unwrap()
+    }
+
+    pub(crate) fn expense(&self) -> AccnEntry<'_> {
         self.root().child("expense").unwrap()
     }
 
-    pub(crate) fn income(&self) -> AccnEntry {
+    pub(crate) fn income(&self) -> AccnEntry<'_> {
         self.root().child("income").unwrap()
     }
 
+    pub(crate) fn autocreate(&self) -> AutoCreate {
+        self.autocreate
+    }
+
+    pub(crate) fn set_autocreate(&mut self, policy: AutoCreate) {
+        self.autocreate = policy;
+    }
+
     fn open_accn(&mut self, parent: Accn, name: &str) -> Accn {
         let accn = Accn::new();
         self.accns.insert(
@@ -84,20 +254,39 @@ impl AccnTree {
             AccnData {
                 name: name.to_string(),
                 parent: Some(parent),
+                ..Default::default()
             },
         );
+        self.accns.get_mut(&parent).unwrap().children.push(accn);
         accn
     }
 
-    fn accn(&self, accn: Accn) -> AccnEntry {
+    /// Remove `accn`, which may not be the root, one of the accns every tree
+    /// starts with or have children. Whatever still refers to it is the
+    /// business of the caller.
+    pub(crate) fn remove(&mut self, accn: Accn) -> Result<()> {
+        let entry = self.accn(accn);
+        if entry.parent().is_none_or(|parent| parent.id() == self.root) {
+            bail!("{} cannot be removed", entry);
+        }
+        if entry.children().next().is_some() {
+            bail!("{} still has sub-accns", entry);
+        }
+        let data = self.accns.remove(&accn).unwrap();
+        let parent = self.accns.get_mut(&data.parent.unwrap()).unwrap();
+        parent.children.retain(|child| *child != accn);
+        Ok(())
+    }
+
+    fn accn(&self, accn: Accn) -> AccnEntry<'_> {
         AccnEntry { accn, tree: self }
     }
 
-    fn accn_mut(&mut self, accn: Accn) -> AccnEntryMut {
+    fn accn_mut(&mut self, accn: Accn) -> AccnEntryMut<'_> {
         AccnEntryMut { accn, tree: self }
     }
 
-    fn accns(&self) -> impl Iterator<Item = AccnEntry> {
+    pub(crate) fn accns(&self) -> impl Iterator<Item = AccnEntry<'_>> {
         self.accns.keys().copied().map(move |accn| self.accn(accn))
     }
 
@@ -105,13 +294,73 @@ impl AccnTree {
     pub(crate) fn by_name_unique<'a, 'b>(
         &'a self,
         name: &'b str,
-    ) -> Result<AccnEntry<'a>, impl Iterator<Item = AccnEntry<'a>> + 'b>
+    ) -> Result<AccnEntry<'a>, Box<impl Iterator<Item = AccnEntry<'a>> + 'b>>
     where
         'a: 'b,
     {
         self.accns()
             .filter(move |accn| accn.name() == name)
             .exactly_one()
+            .map_err(Box::new)
+    }
+
+    /// The autocreate policy and the `open` and `close` directives of every
+    /// accn, so they survive saving the journal.
+    pub(crate) fn directives(&self) -> String {
+        let accns = self
+            .accns()
+            .sorted_by_key(|accn| accn.abs_name())
+            .collect_vec();
+        let opens = accns.iter().filter(|accn| accn.is_declared()).map(|accn| {
+            let mut line = "open".to_string();
+            if let Some(date) = accn.opened() {
+                line += &format!(" {}", date);
+            }
+            line += &format!(" {}", accn);
+            if let Some(code) = accn.currency() {
+                line += &format!(" currency {}", code);
+            }
+            if let Some(code) = accn.own_report_currency() {
+                line += &format!(" report {}", code);
+            }
+            line
+        });
+        let closes = accns.iter().filter_map(|accn| {
+            let date = accn.closed()?;
+            Some(format!("close {} {}", date, accn))
+        });
+        let interests = accns.iter().filter_map(|accn| {
+            let rate = accn.interest()? * Decimal::ONE_HUNDRED;
+            Some(format!("interest {} {}%", accn, rate.normalize()))
+        });
+        let classes = accns
+            .iter()
+            .filter(|accn| !accn.own_classes().is_empty())
+            .map(|accn| format!("class {} {}", accn, accn.own_classes().iter().join(" ")));
+        let taxes = accns.iter().filter_map(|accn| {
+            let category = accn.own_tax_category()?;
+            Some(format!("tax {} {}", accn, category))
+        });
+        let cycles = accns.iter().filter_map(|accn| {
+            let cycle = accn.cycle()?;
+            Some(format!("cycle {} {} {}", accn, cycle.closing, cycle.due))
+        });
+        let syncs = accns.iter().filter_map(|accn| {
+            let cursor = accn.sync_cursor()?;
+            Some(format!("sync {} {}", accn, cursor))
+        });
+        let policy = (self.autocreate != AutoCreate::default())
+            .then(|| format!("autocreate {}", self.autocreate));
+        policy
+            .into_iter()
+            .chain(opens)
+            .chain(closes)
+            .chain(interests)
+            .chain(classes)
+            .chain(taxes)
+            .chain(cycles)
+            .chain(syncs)
+            .join("\n")
     }
 
     /// Takes a fuzzy input as `ex:common:food` and returns every accn that
@@ -121,11 +370,9 @@ impl AccnTree {
     pub(crate) fn by_name_fuzzy<'a>(
         &'a self,
         name: impl AccnPath<'a>,
-    ) -> impl Iterator<Item = AccnEntry<'_>> + '_ {
+    ) -> impl Iterator<Item = AccnEntry<'a>> + 'a {
         fn fuzzy_match(matcher: &str, matchee: &str) -> bool {
-            matcher
-                .to_lowercase()
-                .contains(matchee.to_lowercase().as_str())
+            fold(matcher).contains(fold(matchee).as_str())
         }
 
         let parts = name.accn_path().collect_vec();
@@ -167,7 +414,7 @@ pub(crate) trait AccnPath<'a> {
 
 impl<'a> AccnPath<'a> for &'a str {
     fn accn_path(self) -> impl Iterator<Item = &'a str> {
-        self.split(':')
+        self.split(':').map(|part| part.trim_matches('"'))
     }
 }
 
@@ -199,6 +446,56 @@ mod test {
         assert_eq!(entry, vec!["aa", "aab", "aaab", "bab", "baab"]);
     }
 
+    #[test]
+    fn test_resolve_child() {
+        let mut tree = AccnTree::new();
+        let strict = tree
+            .root_mut()
+            .resolve_child("expense", AutoCreate::Strict, |_| Ok(false));
+        assert!(strict.is_ok(), "existing accns are always resolved");
+
+        let err = tree
+            .root_mut()
+            .or_open_child("expense")
+            .resolve_child("food", AutoCreate::Strict, |_| Ok(true))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "expense:food does not exist, declare it with `open expense:food`"
+        );
+
+        let declined = tree.root_mut().or_open_child("expense").resolve_child(
+            "food",
+            AutoCreate::Confirm,
+            |_| Ok(false),
+        );
+        assert!(declined.is_err());
+        assert!(tree.by_name_unique("food").is_err());
+
+        tree.root_mut()
+            .or_open_child("expense")
+            .resolve_child("food", AutoCreate::Confirm, |name| {
+                Ok(name == "expense:food")
+            })
+            .unwrap();
+        assert!(tree.by_name_unique("food").is_ok());
+    }
+
+    #[test]
+    fn test_by_name_fuzzy_unicode() {
+        let mut tree = AccnTree::new();
+        tree.root_mut()
+            .or_open_child("expense")
+            .or_open_child("Groß Einkauf")
+            .or_open_child("café");
+
+        let names = |matcher| tree.by_name_fuzzy(matcher).map(|e| e.name()).collect_vec();
+        assert_eq!(names("gross:CAFE"), ["café"]);
+        assert_eq!(names("\"ex:groß einkauf\":cafe"), ["café"]);
+        assert_eq!(names("einkäuf"), ["Groß Einkauf"]);
+    }
+
     #[test]
     fn test_by_name_fuzzy_root() {
         let tree = AccnTree::new();
@@ -1,7 +1,10 @@
 use std::fmt::{Display, Write};
 
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
 use indenter::indented;
 use itertools::Itertools;
+use unicode_normalization::char::is_combining_mark;
 
 use super::*;
 #[derive(Clone, Copy, Debug)]
@@ -10,9 +13,22 @@ pub(crate) struct AccnEntry<'a> {
     pub(super) tree: &'a AccnTree,
 }
 
+/// Names written as is, the rest need quotes, as in the `ident` rule.
+fn is_plain(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(char::is_alphabetic)
+        && chars.all(|c| c.is_alphanumeric() || is_combining_mark(c) || "-@_".contains(c))
+}
+
+/// The absolute name, quoted when a part of it is not a plain ident, so
+/// the journal reads it back.
 impl Display for AccnEntry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.abs_name().fmt(f)
+        let name = self.abs_name();
+        match name.is_empty() || name.split(':').all(is_plain) {
+            true => name.fmt(f),
+            false => format!("\"{}\"", name).fmt(f),
+        }
     }
 }
 
@@ -45,18 +61,25 @@ impl<'a> AccnEntry<'a> {
 
         Ok(())
     }
-    fn children(self) -> impl Iterator<Item = AccnEntry<'a>> {
-        self.tree
-            .accns
+    pub(crate) fn children(self) -> impl Iterator<Item = AccnEntry<'a>> {
+        self.data()
+            .children
             .iter()
-            .filter(move |(_, data)| data.parent == Some(self.accn))
-            .map(move |(accn, _)| accn.into_accn(self.tree))
+            .map(move |accn| accn.into_accn(self.tree))
     }
 
     fn ancestors(self) -> impl Iterator<Item = AccnEntry<'a>> {
         std::iter::successors(Some(self), move |accn| accn.parent())
     }
 
+    /// Ancestor right below the root, like `expense` for `expense:food`.
+    pub(crate) fn top_level(self) -> AccnEntry<'a> {
+        self.ancestors()
+            .take_while(|accn| accn.parent().is_some())
+            .last()
+            .unwrap_or(self)
+    }
+
     pub(crate) fn is_descendent_of(self, other: AccnEntry<'a>) -> bool {
         self.ancestors().any(|accn| accn == other)
     }
@@ -98,7 +121,7 @@ impl<'a> AccnEntry<'a> {
         ))
     }
 
-    fn parent(self) -> Option<AccnEntry<'a>> {
+    pub(crate) fn parent(self) -> Option<AccnEntry<'a>> {
         let parent = self.data().parent?;
         Some(parent.into_accn(self.tree))
     }
@@ -115,6 +138,86 @@ impl<'a> AccnEntry<'a> {
         &self.tree.accns[&self.accn].name
     }
 
+    pub(crate) fn is_declared(self) -> bool {
+        self.data().declared
+    }
+
+    pub(crate) fn opened(self) -> Option<NaiveDate> {
+        self.data().opened
+    }
+
+    pub(crate) fn closed(self) -> Option<NaiveDate> {
+        self.data().closed
+    }
+
+    pub(crate) fn currency(self) -> Option<&'a str> {
+        self.data().currency.as_deref()
+    }
+
+    /// Currency declared to report the accn itself in.
+    pub(crate) fn own_report_currency(self) -> Option<&'a str> {
+        self.data().report.as_deref()
+    }
+
+    /// Currency to report the balance of the accn in, its own or else that
+    /// of its nearest ancestor that has one.
+    pub(crate) fn report_currency(self) -> Option<&'a str> {
+        self.ancestors().find_map(|accn| accn.own_report_currency())
+    }
+
+    pub(crate) fn interest(self) -> Option<Decimal> {
+        self.data().interest
+    }
+
+    /// Where the last pull of the bank's txns into the accn left off.
+    pub(crate) fn sync_cursor(self) -> Option<&'a str> {
+        self.data().sync.as_deref()
+    }
+
+    pub(crate) fn budget(self) -> Option<Budget> {
+        self.data().budget
+    }
+
+    pub(crate) fn sweeps(self) -> &'a [Sweep] {
+        &self.data().sweeps
+    }
+
+    pub(crate) fn paycheck(self) -> Option<&'a Paycheck> {
+        self.data().paycheck.as_ref()
+    }
+
+    pub(crate) fn cycle(self) -> Option<BillingCycle> {
+        self.data().cycle
+    }
+
+    /// Classes declared for the accn itself.
+    pub(crate) fn own_classes(self) -> &'a BTreeSet<String> {
+        &self.data().classes
+    }
+
+    /// Classes of the accn, including those it inherits from its ancestors.
+    pub(crate) fn classes(self) -> BTreeSet<&'a str> {
+        self.ancestors()
+            .flat_map(|accn| accn.own_classes().iter().map(String::as_str))
+            .collect()
+    }
+
+    pub(crate) fn has_class(self, class: &str) -> bool {
+        self.ancestors()
+            .any(|accn| accn.own_classes().contains(class))
+    }
+
+    /// Tax category declared for the accn itself.
+    pub(crate) fn own_tax_category(self) -> Option<&'a str> {
+        self.data().tax.as_deref()
+    }
+
+    /// Tax category of the accn or, failing that, of its nearest ancestor
+    /// that has one.
+    pub(crate) fn tax_category(self) -> Option<&'a str> {
+        self.ancestors().find_map(|accn| accn.own_tax_category())
+    }
+
     pub(crate) fn abs_name(self) -> String {
         self.ancestors()
             .collect_vec()
@@ -163,6 +266,128 @@ impl<'a> AccnEntryMut<'a> {
         }
     }
 
+    fn data_mut(&mut self) -> &mut AccnData {
+        self.tree.accns.get_mut(&self.accn).unwrap()
+    }
+
+    /// Record the `open` directive of the accn.
+    pub(crate) fn declare_open(mut self, date: Option<NaiveDate>, currency: Option<&str>) -> Self {
+        let data = self.data_mut();
+        data.declared = true;
+        data.opened = date.or(data.opened);
+        data.currency = currency.map(str::to_string).or(data.currency.take());
+        self
+    }
+
+    /// Record the currency the `open` directive of the accn reports it in.
+    pub(crate) fn declare_report(mut self, code: &str) -> Self {
+        self.data_mut().report = Some(code.to_string());
+        self
+    }
+
+    /// Record the `interest` directive of the accn.
+    pub(crate) fn declare_interest(mut self, rate: Decimal) -> Self {
+        self.data_mut().interest = Some(rate);
+        self
+    }
+
+    /// Record the `sync` directive of the accn.
+    pub(crate) fn declare_sync(mut self, cursor: &str) -> Self {
+        self.data_mut().sync = Some(cursor.to_string());
+        self
+    }
+
+    /// Record the `budget` directive of the accn.
+    pub(crate) fn declare_budget(mut self, budget: Budget) -> Self {
+        self.data_mut().budget = Some(budget);
+        self
+    }
+
+    /// Record a `sweep` directive of the accn.
+    pub(crate) fn declare_sweep(mut self, sweep: Sweep) -> Self {
+        self.data_mut().sweeps.push(sweep);
+        self
+    }
+
+    /// Record the `paycheck` directive naming where the rest of the accn's
+    /// paychecks is deposited.
+    pub(crate) fn declare_deposit(mut self, deposit: Accn) -> Self {
+        self.data_mut()
+            .paycheck
+            .get_or_insert_with(Default::default)
+            .deposit = Some(deposit);
+        self
+    }
+
+    /// Record a `paycheck` directive withholding from the accn's paychecks.
+    pub(crate) fn declare_deduction(mut self, to: Accn, deduction: Deduction) -> Self {
+        let paycheck = self
+            .data_mut()
+            .paycheck
+            .get_or_insert_with(Default::default);
+        paycheck.deductions.push((to, deduction));
+        self
+    }
+
+    /// Record the `cycle` directive of the accn.
+    pub(crate) fn declare_cycle(mut self, cycle: BillingCycle) -> Self {
+        self.data_mut().cycle = Some(cycle);
+        self
+    }
+
+    /// Record a `class` directive of the accn.
+    pub(crate) fn declare_class(mut self, class: &str) -> Self {
+        self.data_mut().classes.insert(class.to_string());
+        self
+    }
+
+    /// Record the `tax` directive of the accn.
+    pub(crate) fn declare_tax(mut self, category: &str) -> Self {
+        self.data_mut().tax = Some(category.to_string());
+        self
+    }
+
+    /// Record the `close` directive of the accn.
+    pub(crate) fn declare_close(mut self, date: NaiveDate) -> Result<Self, CoinError> {
+        let data = self.data_mut();
+        if data.opened.is_some_and(|opened| opened > date) {
+            return Err(format!("{} closed before it was opened", self).into());
+        }
+        self.data_mut().closed = Some(date);
+        Ok(self)
+    }
+
+    /// Give the accn a new name, which no sibling may have.
+    pub(crate) fn rename(mut self, name: &str) -> Result<Self> {
+        let taken = self
+            .as_ref()
+            .parent()
+            .is_some_and(|parent| parent.child(name).is_some());
+        if taken {
+            bail!("cannot rename {}, {} is taken", self, name);
+        }
+        self.data_mut().name = name.to_string();
+        Ok(self)
+    }
+
+    /// Child `name`, created if `policy` allows it. In confirm mode
+    /// `confirm` is asked with the full name of the new accn.
+    pub(crate) fn resolve_child(
+        self,
+        name: &str,
+        policy: AutoCreate,
+        confirm: impl FnOnce(&str) -> Result<bool>,
+    ) -> Result<AccnEntryMut<'a>, CoinError> {
+        if self.as_ref().child(name).is_none() {
+            let full_name = match self.as_ref().parent() {
+                Some(_) => format!("{}:{}", self, name),
+                None => name.to_string(),
+            };
+            policy.check(&full_name, confirm)?;
+        }
+        Ok(self.or_open_child(name))
+    }
+
     pub(crate) fn or_open_child(self, name: &str) -> AccnEntryMut<'a> {
         let child = self.as_ref().child(name);
 
@@ -206,6 +431,18 @@ mod test {
         assert_eq!(asset.parent(), Some(tree.root()));
     }
 
+    #[test]
+    fn test_children() {
+        let mut tree = example_tree();
+        tree.root_mut()
+            .or_open_child("assets")
+            .or_open_child("cash");
+        let asset = tree.root().child("assets").unwrap();
+        let names = asset.children().map(|accn| accn.name()).collect_vec();
+        assert_eq!(names, ["bank", "cash"]);
+        assert_eq!(tree.root().children().count(), 6);
+    }
+
     #[test]
     fn test_ancestor() {
         let example_tree = example_tree();
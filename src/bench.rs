@@ -0,0 +1,57 @@
+//! Entry points for the benches under `benches/`, which only see what the
+//! library makes public.
+
+use crate::journal::{register::QueryType, Journal};
+
+/// Text of a generated journal of about `txns` txns over a year.
+pub fn generate(txns: usize) -> String {
+    Journal::generate_example(1, 12, (txns / 366).max(1)).to_string()
+}
+
+/// Shapes of posting queries worth timing.
+#[derive(Debug, Clone, Copy)]
+pub enum Query {
+    All,
+    Accn,
+    AmountAbove,
+    /// Accn, amount and currency at once.
+    And,
+}
+
+pub struct BenchJournal(Journal);
+
+impl BenchJournal {
+    pub fn parse(s: &str) -> Self {
+        Self(Journal::from_str(s).expect("bench journal must parse"))
+    }
+
+    /// Number of postings matching `query`.
+    pub fn query(&self, query: Query) -> usize {
+        let journal = &self.0;
+        let above = || {
+            let money = journal.parse_money("$50").unwrap().money();
+            QueryType::AmountAbove(money)
+        };
+        let query = match query {
+            Query::All => QueryType::All,
+            Query::Accn => QueryType::MatchAccn("food".to_string()),
+            Query::AmountAbove => above(),
+            Query::And => QueryType::And(vec![
+                QueryType::MatchAccn("expense".to_string()),
+                above(),
+                QueryType::CurrencyIs("USD".to_string()),
+            ]),
+        };
+        journal.query(query).into_regs().count()
+    }
+
+    /// Net worth, the sum of every asset and liability posting.
+    pub fn balance(&self) -> usize {
+        self.0.net_worth().into_iter().count()
+    }
+
+    /// Text the journal saves as.
+    pub fn save(&self) -> String {
+        self.0.to_string()
+    }
+}
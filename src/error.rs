@@ -0,0 +1,205 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::NaiveDate;
+use pest::Span;
+use rust_decimal::Decimal;
+
+use crate::journal::parser::Rule;
+
+/// What can go wrong reading, building or valuing a journal.
+#[derive(Debug)]
+pub enum CoinError {
+    /// Input the grammar or a directive rejects, rendered with the line it
+    /// is on, and what was wrong with it if known.
+    Parse {
+        line: usize,
+        col: usize,
+        rendered: String,
+        source: Option<Box<CoinError>>,
+    },
+    UnknownCurrency(String),
+    /// Postings that do not add up and have no posting left to take the
+    /// residual, as currency codes and amounts.
+    UnbalancedTxn {
+        residual: Vec<(String, Decimal)>,
+    },
+    UnknownAccount(String),
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+    RateUnavailable {
+        from: String,
+        to: String,
+        date: NaiveDate,
+        /// Why a rate provider could not answer, if one was asked.
+        reason: Option<String>,
+    },
+    /// Anything else the input gets wrong, like a sub-unit clashing with a
+    /// currency code.
+    Invalid(String),
+}
+
+impl CoinError {
+    /// `source` as the cause of a parse error at `span`.
+    pub(crate) fn at(msg: &str, span: Span, source: impl Into<CoinError>) -> Self {
+        let mut err = Self::from(parse_err(msg, span));
+        if let CoinError::Parse { source: cause, .. } = &mut err {
+            *cause = Some(Box::new(source.into()));
+        }
+        err
+    }
+
+    /// Short stable name of the kind of error, the one of its cause for
+    /// parse errors with a known cause.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CoinError::Parse {
+                source: Some(source),
+                ..
+            } => source.code(),
+            CoinError::Parse { .. } => "parse",
+            CoinError::UnknownCurrency(_) => "unknown-currency",
+            CoinError::UnbalancedTxn { .. } => "unbalanced",
+            CoinError::UnknownAccount(_) => "unknown-accn",
+            CoinError::Io { .. } => "io",
+            CoinError::RateUnavailable { .. } => "rate-unavailable",
+            CoinError::Invalid(_) => "invalid",
+        }
+    }
+
+    pub(crate) fn io(path: &str, source: std::io::Error) -> Self {
+        CoinError::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+}
+
+pub(crate) fn parse_err(msg: &str, span: Span) -> pest::error::Error<Rule> {
+    use pest::error::{Error, ErrorVariant};
+    Error::new_from_span(
+        ErrorVariant::CustomError {
+            message: msg.to_string(),
+        },
+        span,
+    )
+}
+
+impl From<pest::error::Error<Rule>> for CoinError {
+    fn from(err: pest::error::Error<Rule>) -> Self {
+        let (line, col) = match err.line_col {
+            pest::error::LineColLocation::Pos(pos) => pos,
+            pest::error::LineColLocation::Span(start, _) => start,
+        };
+        CoinError::Parse {
+            line,
+            col,
+            rendered: err.to_string(),
+            source: None,
+        }
+    }
+}
+
+impl From<String> for CoinError {
+    fn from(msg: String) -> Self {
+        CoinError::Invalid(msg)
+    }
+}
+
+impl Display for CoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinError::Parse {
+                rendered, source, ..
+            } => {
+                write!(f, "{}", rendered)?;
+                // `{:#}` names the cause too, like an anyhow chain does
+                match source {
+                    Some(source) if f.alternate() => write!(f, ": {:#}", source),
+                    _ => Ok(()),
+                }
+            }
+            CoinError::UnknownCurrency(code) => write!(f, "code {} not found", code),
+            CoinError::UnbalancedTxn { residual } => {
+                write!(f, "transaction not balanced")?;
+                for (i, (code, amount)) in residual.iter().enumerate() {
+                    let sep = if i == 0 { ", off by" } else { "," };
+                    write!(f, "{} {} {}", sep, amount, code)?;
+                }
+                Ok(())
+            }
+            CoinError::UnknownAccount(name) => {
+                write!(
+                    f,
+                    "{} does not exist, declare it with `open {}`",
+                    name, name
+                )
+            }
+            CoinError::Io { path, source } => write!(f, "{}: {}", path, source),
+            CoinError::RateUnavailable {
+                from,
+                to,
+                date,
+                reason,
+            } => {
+                write!(f, "no exchange rate from {} to {} on {}", from, to, date)?;
+                match reason {
+                    Some(reason) => write!(f, ": {}", reason),
+                    None => Ok(()),
+                }
+            }
+            CoinError::Invalid(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for CoinError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CoinError::Parse {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            CoinError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::journal::Journal;
+
+    use super::*;
+
+    #[test]
+    fn test_error_kinds() {
+        let unbalanced = "2024-01-01 lunch\n    expense:food  $12\n    asset:bank  $-10";
+        let err = Journal::from_str(unbalanced).unwrap_err();
+        let CoinError::Parse { line, source, .. } = err else {
+            panic!("expected a parse error, got {:?}", err);
+        };
+        assert_eq!(line, 1);
+        assert_eq!(
+            Journal::from_str(unbalanced).unwrap_err().code(),
+            "unbalanced"
+        );
+        assert!(matches!(
+            source.as_deref(),
+            Some(CoinError::UnbalancedTxn { residual }) if residual == &[("USD".to_string(), Decimal::TWO)]
+        ));
+
+        let err = Journal::from_str("2024-01-01 lunch\n    expense:food  12 XYZ\n    asset:bank")
+            .unwrap_err();
+        let err = anyhow::Error::from(err);
+        assert!(format!("{:#}", err).contains("code XYZ not found"));
+        assert!(matches!(
+            err.root_cause().downcast_ref(),
+            Some(CoinError::UnknownCurrency(code)) if code == "XYZ"
+        ));
+
+        let err = Journal::from_file("./example/missing.coin").unwrap_err();
+        assert!(matches!(err, CoinError::Io { .. }));
+    }
+}
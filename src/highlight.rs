@@ -0,0 +1,189 @@
+//! Semantic tokens of a journal, for editors to highlight it the way the
+//! grammar reads it.
+
+use std::ops::Range;
+
+use pest::{iterators::Pair, Parser};
+use serde_json::{json, Value};
+
+use crate::{
+    error::CoinError,
+    journal::parser::{IdentParser, Rule},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Date,
+    Accn,
+    /// A money amount with its currency, like `$-10.00` or `12 EUR`.
+    Amount,
+    /// A currency code or symbol outside of an amount, as in directives.
+    Currency,
+    Tag,
+    /// A `; key: value` metadata line of a txn.
+    Meta,
+    Comment,
+}
+
+impl TokenKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::Date => "date",
+            TokenKind::Accn => "accn",
+            TokenKind::Amount => "amount",
+            TokenKind::Currency => "currency",
+            TokenKind::Tag => "tag",
+            TokenKind::Meta => "meta",
+            TokenKind::Comment => "comment",
+        }
+    }
+
+    fn of(rule: Rule) -> Option<Self> {
+        match rule {
+            Rule::date => Some(TokenKind::Date),
+            Rule::accn => Some(TokenKind::Accn),
+            Rule::money_var_1 | Rule::money_var_2 | Rule::money_var_3 | Rule::money_var_4 => {
+                Some(TokenKind::Amount)
+            }
+            Rule::code | Rule::symbol => Some(TokenKind::Currency),
+            Rule::tag => Some(TokenKind::Tag),
+            Rule::meta => Some(TokenKind::Meta),
+            _ => None,
+        }
+    }
+}
+
+/// A highlighted range of the input, by byte offsets and by the 1-based
+/// line and column it starts at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub range: Range<usize>,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Tokens of the journal `input` in the order they appear, failing where
+/// the journal does not parse.
+pub fn highlight(input: &str) -> Result<Vec<Token>, CoinError> {
+    let pairs = IdentParser::parse(Rule::grammar, input)?;
+    let mut ranges = Vec::new();
+    for pair in pairs {
+        collect(pair, &mut ranges);
+    }
+
+    // comments are skipped by the grammar, so look for what it left out,
+    // walking the tokens, which come in order, along with the lines
+    let mut comments = Vec::new();
+    let mut offset = 0;
+    let mut next = 0;
+    for line in input.split_inclusive('\n') {
+        let start = line
+            .char_indices()
+            .map(|(i, _)| offset + i)
+            .filter(|i| input.as_bytes()[*i] == b';')
+            .find(|i| {
+                while ranges.get(next).is_some_and(|(_, range)| range.end <= *i) {
+                    next += 1;
+                }
+                ranges.get(next).is_none_or(|(_, range)| range.start > *i)
+            });
+        if let Some(start) = start {
+            let end = offset + line.trim_end_matches(['\r', '\n']).len();
+            comments.push((TokenKind::Comment, start..end));
+        }
+        offset += line.len();
+    }
+    ranges.extend(comments);
+    ranges.sort_by_key(|(_, range)| range.start);
+
+    let (mut line, mut line_start, mut counted) = (1, 0, 0);
+    Ok(ranges
+        .into_iter()
+        .map(|(kind, range)| {
+            let since = &input[counted..range.start];
+            line += since.matches('\n').count();
+            if let Some(i) = since.rfind('\n') {
+                line_start = counted + i + 1;
+            }
+            counted = range.start;
+            let col = range.start - line_start + 1;
+            Token {
+                kind,
+                range,
+                line,
+                col,
+            }
+        })
+        .collect())
+}
+
+/// Ranges of the outermost tokens in `pair`, an amount is one token rather
+/// than a number and a currency.
+fn collect(pair: Pair<Rule>, ranges: &mut Vec<(TokenKind, Range<usize>)>) {
+    let span = pair.as_span();
+    match (pair.as_rule(), TokenKind::of(pair.as_rule())) {
+        (Rule::meta, Some(kind)) => {
+            // a meta pair starts with the line break before it
+            let start = span.start() + span.as_str().find(';').unwrap_or(0);
+            ranges.push((kind, start..span.end()));
+        }
+        (_, Some(kind)) => ranges.push((kind, span.start()..span.end())),
+        (_, None) => pair.into_inner().for_each(|pair| collect(pair, ranges)),
+    }
+}
+
+/// `tokens` as a JSON array of objects with kind, start, end, line and col.
+pub fn to_json(tokens: &[Token]) -> String {
+    let tokens: Vec<Value> = tokens
+        .iter()
+        .map(|token| {
+            json!({
+                "kind": token.kind.name(),
+                "start": token.range.start,
+                "end": token.range.end,
+                "line": token.line,
+                "col": token.col,
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&tokens).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let input = "; groceries\ncurrency EUR € prefix\n2024-01-01\nlunch; with bob\n    ; id: lunch-1\n    expense:food  €12 #work ; paid\n    asset:bank\n";
+        let tokens = highlight(input).unwrap();
+        let kinds = tokens
+            .iter()
+            .map(|token| (token.kind.name(), &input[token.range.clone()]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                ("comment", "; groceries"),
+                ("currency", "EUR"),
+                ("currency", "€"),
+                ("date", "2024-01-01"),
+                ("comment", "; with bob"),
+                ("meta", "; id: lunch-1"),
+                ("accn", "expense:food"),
+                ("amount", "€12"),
+                ("tag", "#work"),
+                ("comment", "; paid"),
+                ("accn", "asset:bank"),
+            ]
+        );
+        let amount = &tokens[7];
+        assert_eq!((amount.line, amount.col), (6, 19));
+        let paid = &tokens[9];
+        assert_eq!((paid.line, paid.col), (6, 31));
+        let bank = &tokens[10];
+        assert_eq!((bank.line, bank.col), (7, 5));
+        assert!(highlight("2024-01-01\nlunch\n    expense:food  €12 €").is_err());
+    }
+}
@@ -1,45 +1,121 @@
+pub mod anomaly;
+pub mod archive;
+pub mod beancount;
+pub mod budget;
+pub mod class;
+pub mod cycle;
+pub mod diff;
+pub mod dimension;
 pub mod entry;
+pub mod example;
+pub mod exposure;
+pub mod gains;
+pub mod gnucash;
+pub mod graph;
+pub mod guard;
+pub mod heatmap;
+pub mod ical;
+pub mod info;
+pub mod interest;
+pub mod link;
+pub mod lint;
+pub mod lots;
+pub mod matching;
+pub mod merge;
+pub mod note;
 pub mod parser;
+pub mod paycheck;
+pub mod payee;
+pub mod pivot;
+pub mod portfolio;
+pub mod prune;
+pub mod qif;
+pub mod ratios;
+pub mod reclass;
 pub mod register;
+pub mod reimburse;
+pub mod rounding;
+pub mod series;
+pub mod share;
+pub mod snapshot;
+pub mod subscription;
+pub mod sweep;
+pub mod tax;
+pub mod template;
+pub mod timesheet;
+pub mod trial;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
 
-use std::{collections::HashMap, fmt::Display};
-
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use chrono::NaiveDate;
 
 use colored::Colorize;
-use itertools::Itertools;
-use rust_decimal::prelude::Zero;
-use uuid::Uuid;
+use itertools::{Either, Itertools};
+use rust_decimal::{prelude::Zero, Decimal};
+use slotmap::{new_key_type, SlotMap};
+use tracing::{info, info_span, warn};
 
 use crate::{
-    accn::{Accn, AccnTree},
-    valuable::{CurrencyStore, Money, Valuable},
+    accn::{Accn, AccnEntry, AccnTree},
+    error::CoinError,
+    util::next_id,
+    valuable::{CurrencyStore, ExchangeBook, Money, ProviderChain, RateProvider, Valuable},
 };
 
-use self::entry::{PostingEntry, TxnEntry, TxnEntryMut};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Posting {
-    id: Uuid,
-}
+use self::{
+    dimension::Dimensions,
+    entry::{PostingEntry, TxnEntry, TxnEntryMut},
+    note::Notes,
+    snapshot::Snapshots,
+};
 
-impl Posting {
-    fn new() -> Self {
-        Self { id: Uuid::new_v4() }
-    }
+new_key_type! {
+    /// Index of a posting in the store, whose slot is not reused by a later
+    /// posting once it is removed.
+    struct Posting;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct PostingData {
     accn: Accn,
     money: Money,
     txn: Txn,
+    /// Tags like `#work` written after the amount.
+    tags: Vec<String>,
+    /// Hours the money pays for, when written as hours at a rate.
+    timed: Option<Timed>,
+}
+
+/// Hours worked at an hourly rate, written like `-3.5 HRS @ $120`, which
+/// make the money of a posting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timed {
+    pub(crate) hours: Decimal,
+    pub(crate) rate: Money,
+}
+
+impl Timed {
+    /// Price of the hours, to the minor units of the rate's currency unless
+    /// the rate is finer.
+    pub(crate) fn money(self, store: &CurrencyStore) -> Money {
+        let dp = self
+            .rate
+            .amount()
+            .scale()
+            .max(store.minor_units(self.rate.currency()));
+        let amount = (self.hours * self.rate.amount()).round_dp(dp).normalize();
+        Money::new(amount, self.rate.currency())
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub(crate) struct Txn {
-    id: Uuid,
+    /// From [`next_id`], unique across the journals of a workspace.
+    id: u64,
 }
 
 impl Txn {
@@ -48,24 +124,38 @@ impl Txn {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TxnData {
     date: NaiveDate,
+    /// Who the txn was with, from a `payee | narration` description.
+    payee: Option<String>,
+    /// The description, or its narration if it names a payee.
     description: String,
+    meta: Vec<(String, String)>,
     postings: Vec<Posting>,
+    /// Line of the journal file the txn was read from.
+    line: Option<usize>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub(crate) struct TxnStore {
-    txns: HashMap<Txn, TxnData>,
-    postings: HashMap<Posting, PostingData>,
+    /// Ordered by id, which is the order the txns were added in.
+    txns: BTreeMap<Txn, TxnData>,
+    /// Postings side by side in one `Vec`, which costs less memory and
+    /// iterates faster than a map of their own.
+    postings: SlotMap<Posting, PostingData>,
+    /// Kept here so any change to the txns drops the snapshots it outdates.
+    snapshots: Snapshots,
+    /// Accn taking what is left of a txn below the precision of a currency.
+    rounding: Option<Accn>,
 }
 
 impl TxnStore {
     pub(crate) fn remove(&mut self, txn: Txn) -> Option<()> {
         let txn = self.txns.remove(&txn)?;
+        self.snapshots.invalidate(txn.date);
         for posting in txn.postings {
-            self.postings.remove(&posting);
+            self.postings.remove(posting);
         }
         Some(())
     }
@@ -73,29 +163,74 @@ impl TxnStore {
 
 pub(crate) struct TxnBuilder {
     date: NaiveDate,
+    payee: Option<String>,
     desc: String,
+    meta: Vec<(String, String)>,
+    line: Option<usize>,
     postings: Vec<PostingData>,
     inferred_posting: Option<Accn>,
+    inferred_tags: Vec<String>,
 
     txn: Txn,
 }
 
 impl TxnBuilder {
+    /// A description like `Acme | lunch` names the payee Acme, one without
+    /// a `|` is all narration.
     pub(crate) fn new(date: NaiveDate, desc: String) -> Self {
+        let (payee, desc) = match desc.split_once('|') {
+            Some((payee, narration)) if !payee.trim().is_empty() => {
+                (Some(payee.trim().to_string()), narration.trim().to_string())
+            }
+            _ => (None, desc),
+        };
         Self {
             date,
+            payee,
             desc,
+            meta: Vec::new(),
+            line: None,
             postings: Vec::new(),
-            txn: Txn { id: Uuid::new_v4() },
+            txn: Txn { id: next_id() },
             inferred_posting: None,
+            inferred_tags: Vec::new(),
         }
     }
 
     fn with_strict_posting(&mut self, accn: Accn, money: Money) -> &mut Self {
+        self.with_strict_tagged_posting(accn, money, Vec::new())
+    }
+
+    fn with_strict_tagged_posting(
+        &mut self,
+        accn: Accn,
+        money: Money,
+        tags: Vec<String>,
+    ) -> &mut Self {
         self.postings.push(PostingData {
             accn,
             money,
             txn: self.txn,
+            tags,
+            timed: None,
+        });
+        self
+    }
+
+    /// Add a posting of `timed` hours, its money their price at the rate.
+    pub(crate) fn with_timed_posting(
+        &mut self,
+        accn: Accn,
+        timed: Timed,
+        tags: Vec<String>,
+        store: &CurrencyStore,
+    ) -> &mut Self {
+        self.postings.push(PostingData {
+            accn,
+            money: timed.money(store),
+            txn: self.txn,
+            tags,
+            timed: Some(timed),
         });
         self
     }
@@ -114,6 +249,20 @@ impl TxnBuilder {
         }
     }
 
+    pub(crate) fn with_meta(
+        &mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> &mut Self {
+        self.meta.push((key.into(), value.into()));
+        self
+    }
+
+    pub(crate) fn at_line(&mut self, line: usize) -> &mut Self {
+        self.line = Some(line);
+        self
+    }
+
     fn with_inferred_posting(&mut self, accn: Accn) -> &mut Self {
         self.inferred_posting = Some(accn);
         self
@@ -130,6 +279,21 @@ impl TxnBuilder {
         }
     }
 
+    pub(crate) fn with_tagged_posting(
+        &mut self,
+        accn: Accn,
+        money: Option<Money>,
+        tags: Vec<String>,
+    ) -> &mut Self {
+        match money {
+            Some(money) => self.with_strict_tagged_posting(accn, money, tags),
+            None => {
+                self.inferred_tags = tags;
+                self.with_inferred_posting(accn)
+            }
+        }
+    }
+
     pub(crate) fn with_posting_combined(&mut self, accn: Accn, money: Option<Money>) -> &mut Self {
         match money {
             Some(money) => self.with_strict_posting_combined(accn, money),
@@ -137,44 +301,77 @@ impl TxnBuilder {
         }
     }
 
-    fn try_infer_inbalence(&mut self) -> Result<()> {
+    /// Post to `rounding` what the txn is off by below the precision of each
+    /// currency, and what the inferred posting would take below it.
+    fn round_inbalance(&mut self, store: &CurrencyStore, rounding: Accn) {
+        for money in self.inbalance().sorted(store) {
+            let rounded = money.amount().round_dp(store.minor_units(money.currency()));
+            let residual = money.amount() - rounded;
+            if !residual.is_zero() && (rounded.is_zero() || self.inferred_posting.is_some()) {
+                self.with_strict_posting(rounding, Money::new(-residual, money.currency()));
+            }
+        }
+    }
+
+    fn try_infer_inbalence(
+        &mut self,
+        store: &CurrencyStore,
+        rounding: Option<Accn>,
+    ) -> Result<(), CoinError> {
+        if let Some(rounding) = rounding {
+            self.round_inbalance(store, rounding);
+        }
         let inbalance = self.inbalance();
+        if inbalance.is_zero() {
+            return Ok(());
+        }
 
-        match !inbalance.is_zero() {
-            true => {
-                for money in inbalance {
-                    self.with_strict_posting(
-                        self.inferred_posting
-                            .ok_or_else(|| anyhow!("transaction not balanced"))?,
-                        -money,
-                    );
-                }
-            }
-            false => (),
+        let Some(inferred) = self.inferred_posting else {
+            let residual = inbalance
+                .sorted(store)
+                .into_iter()
+                .map(|money| (store.code(money.currency()).to_string(), money.amount()))
+                .collect();
+            return Err(CoinError::UnbalancedTxn { residual });
         };
-
+        for money in inbalance.sorted(store) {
+            // rounded already, only the trailing zeros are left to drop
+            let money = match rounding {
+                Some(_) => {
+                    let units = store.minor_units(money.currency());
+                    Money::new(money.amount().round_dp(units), money.currency())
+                }
+                None => money,
+            };
+            self.with_strict_tagged_posting(inferred, -money, self.inferred_tags.clone());
+        }
         Ok(())
     }
 
-    pub(crate) fn build(mut self, txn_store: &mut TxnStore) -> Result<Txn> {
-        self.try_infer_inbalence()?;
+    pub(crate) fn build(
+        mut self,
+        txn_store: &mut TxnStore,
+        store: &CurrencyStore,
+    ) -> Result<Txn, CoinError> {
+        self.try_infer_inbalence(store, txn_store.rounding)?;
 
-        let (posting_id, posting): (Vec<_>, Vec<_>) = self
+        let postings = self
             .postings
             .into_iter()
-            .map(|p| (Posting::new(), p))
-            .unzip();
+            .map(|p| txn_store.postings.insert(p))
+            .collect();
 
         let txn = TxnData {
             date: self.date,
+            payee: self.payee,
             description: self.desc,
-            postings: posting_id.clone(),
+            meta: self.meta,
+            postings,
+            line: self.line,
         };
 
+        txn_store.snapshots.invalidate(self.date);
         txn_store.txns.insert(self.txn, txn);
-        txn_store
-            .postings
-            .extend(posting_id.into_iter().zip(posting));
 
         Ok(self.txn)
     }
@@ -206,25 +403,54 @@ impl<'a> TxnBuilderMut<'a> {
         self
     }
 
-    pub(crate) fn build(self) -> Result<TxnEntry<'a>> {
-        let txn = self.builder.build(&mut self.journal.txns)?;
+    pub(crate) fn with_meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.builder.with_meta(key, value);
+        self
+    }
+
+    pub(crate) fn build(self) -> Result<TxnEntry<'a>, CoinError> {
+        let journal = &mut *self.journal;
+        let txn = self.builder.build(&mut journal.txns, &journal.currencies)?;
         Ok(TxnEntry::new(txn, self.journal))
     }
 }
 
-#[derive(Debug)]
-pub(crate) struct Journal {
+/// What importing another tool's export added to a journal and left out.
+#[derive(Debug, Default)]
+pub(crate) struct Imported {
+    pub(crate) added: Vec<Txn>,
+    /// Txns already imported before.
+    pub(crate) skipped: usize,
+    /// What the import left out or found amiss, for a look afterwards.
+    pub(crate) warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Journal {
     accns: AccnTree,
     txns: TxnStore,
     currencies: CurrencyStore,
+    rates: ExchangeBook,
+    dimensions: Dimensions,
+    notes: Notes,
 }
 
 impl Journal {
-    pub(crate) fn new(accns: AccnTree, txns: TxnStore, currencies: CurrencyStore) -> Self {
+    pub(crate) fn new(
+        accns: AccnTree,
+        txns: TxnStore,
+        currencies: CurrencyStore,
+        rates: ExchangeBook,
+        dimensions: Dimensions,
+        notes: Notes,
+    ) -> Self {
         Self {
             accns,
             txns,
             currencies,
+            rates,
+            dimensions,
+            notes,
         }
     }
 
@@ -236,6 +462,10 @@ impl Journal {
             .map(move |txn| TxnEntry::new(txn, self))
     }
 
+    pub(crate) fn contains_txn(&self, txn: Txn) -> bool {
+        self.txns.txns.contains_key(&txn)
+    }
+
     pub(crate) fn txn(&self, txn: Txn) -> TxnEntry<'_> {
         TxnEntry::new(txn, self)
     }
@@ -248,10 +478,16 @@ impl Journal {
         self.txns
             .postings
             .keys()
-            .copied()
             .map(move |posting| posting.into_posting(self))
     }
 
+    /// Codes of every currency some posting is in.
+    pub(crate) fn used_codes(&self) -> BTreeSet<&str> {
+        self.postings()
+            .map(|p| self.currencies.code(p.money().money().currency()))
+            .collect()
+    }
+
     pub(crate) fn new_txn(&mut self, date: NaiveDate, desc: String) -> TxnBuilderMut<'_> {
         TxnBuilderMut {
             builder: TxnBuilder::new(date, desc),
@@ -266,6 +502,89 @@ impl Journal {
     pub(crate) fn accns_mut(&mut self) -> &mut AccnTree {
         &mut self.accns
     }
+
+    pub(crate) fn currencies(&self) -> &CurrencyStore {
+        &self.currencies
+    }
+
+    pub(crate) fn rates(&self) -> &ExchangeBook {
+        &self.rates
+    }
+
+    /// Run an import, taking back the txns it added if it fails halfway.
+    fn importing(
+        &mut self,
+        import: impl FnOnce(&mut Self, &mut Imported) -> Result<()>,
+    ) -> Result<Imported> {
+        let _span = info_span!("import").entered();
+        let mut imported = Imported::default();
+        match import(self, &mut imported) {
+            Ok(()) => {
+                info!(added = imported.added.len(), "imported");
+                Ok(imported)
+            }
+            Err(e) => {
+                warn!(
+                    added = imported.added.len(),
+                    "import failed, taking back its txns"
+                );
+                for txn in imported.added {
+                    self.txns.remove(txn);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Sum of every posting booked to `accn` or any of its descendants.
+    pub(crate) fn balance(&self, accn: AccnEntry) -> Valuable {
+        self.postings()
+            .filter(|p| p.accn().is_descendent_of(accn))
+            .map(|p| p.money().money())
+            .sum()
+    }
+
+    /// Net worth converted to `code` at the rates of `date`, preferring the
+    /// rates recorded in the journal over those of `fallback`.
+    pub(crate) fn net_worth_in(
+        &self,
+        code: &str,
+        date: NaiveDate,
+        fallback: &dyn RateProvider,
+    ) -> Result<Valuable, CoinError> {
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        self.net_worth()
+            .into_iter()
+            .map(|money| {
+                let money = money.into_money(&self.currencies);
+                money.convert_to(code, date, &rates).map(Money::from)
+            })
+            .sum()
+    }
+
+    pub(crate) fn net_worth(&self) -> Valuable {
+        self.balance(self.accns.asset()) + self.balance(self.accns.liability())
+    }
+
+    /// Directives written ahead of the txns, each group followed by a blank
+    /// line.
+    fn directives(&self) -> String {
+        [
+            self.currencies.to_string(),
+            self.accns.directives(),
+            self.rates.to_string(),
+            self.dimensions.to_string(),
+            self.budget_directives(),
+            self.sweep_directives(),
+            self.paycheck_directives(),
+            self.rounding_directive(),
+            self.snapshot_directives(),
+        ]
+        .into_iter()
+        .filter(|directives| !directives.is_empty())
+        .map(|directives| directives + "\n\n")
+        .collect()
+    }
 }
 
 impl Display for Journal {
@@ -276,6 +595,56 @@ impl Display for Journal {
 
             writeln!(f, "\n{}", "Transactions:".cyan().bold())?;
         }
-        self.txns().format("\n\n").fmt(f)
+        self.directives().fmt(f)?;
+        // by date, notes of a day before its txns and txns of the same day
+        // in the order they were added
+        let notes = self
+            .notes
+            .iter()
+            .map(|note| (note.date, Either::Left(note)));
+        let txns = self
+            .txns()
+            .sorted_by_key(|txn| txn.date())
+            .map(|txn| (txn.date(), Either::Right(txn)));
+        notes
+            .merge_by(txns, |(a, _), (b, _)| a <= b)
+            .map(|(_, entry)| entry)
+            .format("\n\n")
+            .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_posting_slots() {
+        let mut journal = Journal::from_str(
+            r#"2024-01-01 lunch
+    expense:food  $12
+    asset:bank"#,
+        )
+        .unwrap();
+        let txn = journal.txns().next().unwrap();
+        let txn = txn.id();
+        let old = journal.txns.txns[&txn].postings.clone();
+        journal.txns.remove(txn);
+        assert!(journal.txns.postings.is_empty());
+
+        // a new posting lands in a freed slot without answering to the old key
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        let money = journal.parse_money("$5").unwrap().money();
+        journal
+            .new_txn(
+                NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+                "coffee".into(),
+            )
+            .with_posting(bank, Some(money))
+            .with_posting(bank, Some(-money))
+            .build()
+            .unwrap();
+        assert_eq!(journal.txns.postings.len(), 2);
+        assert!(old.iter().all(|p| !journal.txns.postings.contains_key(*p)));
     }
 }
@@ -0,0 +1,194 @@
+use std::{collections::HashMap, fmt::Display};
+
+use itertools::Itertools;
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::accn::AccnEntry;
+
+use super::{entry::PostingEntry, Journal};
+
+/// How far a posting may stray from the other postings of its account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Method {
+    /// Flag amounts more than `k` standard deviations away from the mean.
+    ZScore(f64),
+    /// Flag amounts more than `k` interquartile ranges outside the quartiles.
+    Iqr(f64),
+}
+
+impl Default for Method {
+    fn default() -> Self {
+        Method::ZScore(3.0)
+    }
+}
+
+impl Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::ZScore(k) => write!(f, "zscore {}", k),
+            Method::Iqr(k) => write!(f, "iqr {}", k),
+        }
+    }
+}
+
+/// Detection method per account, accounts without their own method inherit
+/// the one of their nearest configured ancestor.
+#[derive(Debug, Default)]
+pub(crate) struct AnomalyDetector {
+    default: Method,
+    overrides: HashMap<String, Method>,
+}
+
+impl AnomalyDetector {
+    pub(crate) fn with_method(&mut self, accn: &str, method: Method) -> &mut Self {
+        self.overrides.insert(accn.to_string(), method);
+        self
+    }
+
+    fn method(&self, accn: &str) -> Method {
+        let mut path = accn;
+        loop {
+            if let Some(method) = self.overrides.get(path) {
+                return *method;
+            }
+            match path.rsplit_once(':') {
+                Some((parent, _)) => path = parent,
+                None => return self.default,
+            }
+        }
+    }
+}
+
+pub(crate) struct Anomaly<'a> {
+    posting: PostingEntry<'a>,
+    method: Method,
+    score: f64,
+}
+
+impl<'a> Anomaly<'a> {
+    pub(crate) fn accn(&self) -> AccnEntry<'a> {
+        self.posting.accn()
+    }
+}
+
+impl Display for Anomaly<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let txn = self.posting.txn();
+        write!(
+            f,
+            "{} {:<40} {:<30} {:>12} {:>8.2} ({})",
+            txn.date(),
+            txn.desc(),
+            self.posting.accn(),
+            self.posting.money().to_string(),
+            self.score,
+            self.method
+        )
+    }
+}
+
+/// Score of every amount under `method`, where a score above 1 is an outlier.
+fn scores(amounts: &[f64], method: Method) -> Option<Vec<f64>> {
+    let n = amounts.len() as f64;
+    match method {
+        Method::ZScore(k) => {
+            if amounts.len() < 3 {
+                return None;
+            }
+            let mean = amounts.iter().sum::<f64>() / n;
+            let var = amounts.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+            let std = var.sqrt();
+            (std > 0.0).then(|| amounts.iter().map(|x| (x - mean).abs() / std / k).collect())
+        }
+        Method::Iqr(k) => {
+            if amounts.len() < 4 {
+                return None;
+            }
+            let sorted = amounts
+                .iter()
+                .copied()
+                .sorted_by(f64::total_cmp)
+                .collect_vec();
+            let quantile = |q: f64| {
+                let pos = q * (sorted.len() - 1) as f64;
+                let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+                sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+            };
+            let (q1, q3) = (quantile(0.25), quantile(0.75));
+            let iqr = q3 - q1;
+            (iqr > 0.0).then(|| {
+                amounts
+                    .iter()
+                    .map(|x| match (*x < q1, *x > q3) {
+                        (true, _) => (q1 - x) / iqr / k,
+                        (_, true) => (x - q3) / iqr / k,
+                        _ => 0.0,
+                    })
+                    .collect()
+            })
+        }
+    }
+}
+
+impl Journal {
+    /// Postings that deviate strongly from the other postings of the same
+    /// account and currency, most suspicious first.
+    pub(crate) fn anomalies(&self, detector: &AnomalyDetector) -> Vec<Anomaly<'_>> {
+        let groups = self
+            .postings()
+            .into_group_map_by(|p| (p.accn().id(), p.money().money().currency()));
+
+        groups
+            .into_values()
+            .flat_map(|postings| {
+                let method = detector.method(&postings[0].accn().abs_name());
+                let amounts = postings
+                    .iter()
+                    .map(|p| p.money().money().amount().to_f64().unwrap_or_default())
+                    .collect_vec();
+                let scores = scores(&amounts, method).unwrap_or_default();
+                postings
+                    .into_iter()
+                    .zip(scores)
+                    .filter(|(_, score)| *score > 1.0)
+                    .map(move |(posting, score)| Anomaly {
+                        posting,
+                        method,
+                        score,
+                    })
+            })
+            .sorted_by(|a, b| b.score.total_cmp(&a.score))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_zscore() {
+        let amounts = [10.0, 11.0, 9.0, 10.0, 10.0, 10.0, 100.0];
+        let zscores = scores(&amounts, Method::ZScore(2.0)).unwrap();
+        let flagged = zscores.iter().filter(|s| **s > 1.0).count();
+        assert_eq!(flagged, 1);
+        assert!(zscores[6] > 1.0);
+        assert!(scores(&[1.0, 1.0, 1.0], Method::ZScore(2.0)).is_none());
+    }
+
+    #[test]
+    fn test_iqr() {
+        let amounts = [10.0, 12.0, 11.0, 13.0, 9.0, 50.0];
+        let scores = scores(&amounts, Method::Iqr(1.5)).unwrap();
+        assert!(scores[5] > 1.0);
+        assert!(scores[..5].iter().all(|s| *s <= 1.0));
+    }
+
+    #[test]
+    fn test_method_inherited() {
+        let mut detector = AnomalyDetector::default();
+        detector.with_method("expense:food", Method::Iqr(1.5));
+        assert_eq!(detector.method("expense:food:snacks"), Method::Iqr(1.5));
+        assert_eq!(detector.method("expense:rent"), Method::default());
+    }
+}
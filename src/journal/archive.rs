@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use itertools::{Either, Itertools};
+
+use crate::{accn::Accn, valuable::Valuable};
+
+use super::{Journal, Txn, TxnBuilder};
+
+/// Result of archiving the old txns of a journal.
+pub(crate) struct Archive {
+    /// The archived txns and notes as a journal of their own, directives
+    /// included.
+    pub(crate) history: String,
+    pub(crate) archived: Vec<Txn>,
+    /// Opening balance txns that took their place.
+    pub(crate) openings: Vec<Txn>,
+}
+
+impl Journal {
+    /// Replace every txn dated before `before` by one opening balance txn
+    /// per accn, dated the day before, so balances from then on stay the
+    /// same. The counterpart of each is `equity:opening`, and accns that
+    /// come to nothing get none. Notes dated
+    /// before `before` go to the archive with the txns.
+    pub(crate) fn archive(&mut self, before: NaiveDate) -> Result<Archive> {
+        let old = self
+            .txns()
+            .filter(|txn| txn.date() < before)
+            .sorted_by_key(|txn| (txn.date(), txn.desc().to_string()))
+            .collect_vec();
+        if old.is_empty() {
+            bail!("no transactions before {}", before);
+        }
+        let archived = old.iter().map(|txn| txn.id()).collect_vec();
+        let entries = self
+            .notes(None, before.pred_opt(), None)
+            .map(|note| (note.date, Either::Left(note)))
+            .merge_by(
+                old.into_iter().map(|txn| (txn.date(), Either::Right(txn))),
+                |(a, _), (b, _)| a <= b,
+            )
+            .map(|(_, entry)| entry);
+        let history = format!("{}{}\n", self.directives(), entries.format("\n\n"));
+        self.notes.remove_before(before);
+
+        let opening = self
+            .accns
+            .root_mut()
+            .or_open_child("equity")
+            .or_open_child("opening")
+            .declare_open(None, None)
+            .into_ref()
+            .id();
+        let mut balances: BTreeMap<String, (Accn, Valuable)> = BTreeMap::new();
+        for posting in self.postings().filter(|p| p.txn().date() < before) {
+            let accn = posting.accn();
+            if accn.id() == opening {
+                // what is left of it after archiving is its new balance
+                continue;
+            }
+            let (_, balance) = balances
+                .entry(accn.abs_name())
+                .or_insert_with(|| (accn.id(), Valuable::default()));
+            *balance += posting.money().money();
+        }
+
+        for txn in &archived {
+            self.txns.remove(*txn);
+        }
+
+        let date = before.pred_opt().unwrap_or(before);
+        let mut openings = Vec::new();
+        for (accn, balance) in balances.into_values() {
+            let moneys = balance
+                .into_iter()
+                .filter(|money| !money.amount().is_zero())
+                .collect_vec();
+            if moneys.is_empty() {
+                continue;
+            }
+            let mut txn = TxnBuilder::new(date, "opening balance".to_string());
+            for money in moneys {
+                txn.with_posting(accn, Some(money));
+            }
+            txn.with_posting(opening, None);
+            openings.push(txn.build(&mut self.txns, &self.currencies)?);
+        }
+
+        Ok(Archive {
+            history,
+            archived,
+            openings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal::prelude::Zero;
+
+    use super::*;
+
+    const INPUT: &str = r#"2022-03-01 salary
+    asset:bank  $3000
+    income:salary
+
+2022-06-01 rent
+    expense:rent  $1200
+    asset:bank
+
+2022-07-01
+> Flew to Osaka.
+
+2022-07-01 trip
+    expense:travel  ¥20000
+    asset:bank
+
+2023-01-10
+> Back at work.
+
+2023-02-01 rent
+    expense:rent  $1200
+    asset:bank"#;
+
+    #[test]
+    fn test_archive() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let balances = |journal: &Journal| {
+            ["bank", "rent", "salary", "travel"].map(|name| {
+                let accn = journal.accns().by_name_unique(name).ok().unwrap();
+                journal
+                    .balance(accn)
+                    .into_valuable(journal.currencies())
+                    .to_string()
+            })
+        };
+        let before = balances(&journal);
+
+        let archive = journal
+            .archive(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+            .unwrap();
+        assert_eq!(archive.archived.len(), 3);
+        assert_eq!(archive.openings.len(), 4);
+        assert_eq!(journal.txns().count(), 5);
+        assert_eq!(balances(&journal), before);
+
+        let opening = journal.accns().by_name_unique("opening").ok().unwrap();
+        assert!(journal.balance(opening).is_zero());
+
+        let history = Journal::from_str(&archive.history).unwrap();
+        assert_eq!(history.txns().count(), 3);
+        assert_eq!(history.notes(None, None, None).count(), 1);
+        let notes = journal.notes(None, None, None).collect_vec();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].lines, ["Back at work."]);
+
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        assert!(journal
+            .archive(NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_archive_zero_balance() {
+        let mut journal = Journal::from_str(
+            r#"2022-03-01 atm
+    asset:cash  $100
+    asset:bank
+
+2022-03-02 groceries
+    expense:food  $100
+    asset:cash"#,
+        )
+        .unwrap();
+        let archive = journal
+            .archive(NaiveDate::from_ymd_opt(2023, 1, 1).unwrap())
+            .unwrap();
+        assert_eq!(archive.openings.len(), 2);
+        assert!(!journal.to_string().contains("asset:cash"));
+    }
+}
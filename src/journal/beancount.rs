@@ -0,0 +1,523 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::Accn,
+    valuable::{Currency, Money},
+};
+
+use super::{gnucash::code, link::LINK_META, Imported, Journal, TxnBuilder};
+
+/// Metadata key identifying a txn imported from Beancount, so importing an
+/// overlapping ledger again skips what is already there.
+pub(crate) const BEANCOUNT_META: &str = "beancount";
+
+/// Beancount's root accounts and the accn roots they are.
+const ROOTS: [(&str, &str); 5] = [
+    ("Assets", "asset"),
+    ("Liabilities", "liability"),
+    ("Equity", "equity"),
+    ("Income", "income"),
+    ("Expenses", "expense"),
+];
+
+/// Flags a transaction starts with, `*` for a cleared one.
+const FLAGS: &str = "*!&#?%PSTCURM";
+
+/// A dated directive with the indented lines below it.
+struct Entry<'a> {
+    line: usize,
+    date: NaiveDate,
+    kind: &'a str,
+    rest: &'a str,
+    body: Vec<(usize, &'a str)>,
+}
+
+/// A `balance` assertion, checked once every txn is in.
+struct Assertion {
+    line: usize,
+    date: NaiveDate,
+    accn: Accn,
+    amount: Decimal,
+    tolerance: Decimal,
+    code: String,
+}
+
+/// The line up to a `;` comment outside of quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Words of `s`, a quoted string as one word without its quotes, each
+/// with whether it was quoted.
+fn words(s: &str) -> Vec<(String, bool)> {
+    let mut words = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '"' => {
+                let mut word = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => word.extend(chars.next()),
+                        c => word.push(c),
+                    }
+                }
+                words.push((word, true));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+                words.push((word, false));
+            }
+        }
+    }
+    words
+}
+
+fn parse_number(s: &str) -> Result<Decimal> {
+    s.replace(',', "")
+        .parse()
+        .with_context(|| format!("invalid number {}", s))
+}
+
+/// Amounts like `1,250.00 USD`.
+fn parse_amount(s: &str) -> Result<(Decimal, String)> {
+    match s.split_whitespace().collect_vec()[..] {
+        [number, commodity] => Ok((parse_number(number)?, code(commodity)?)),
+        _ => bail!("invalid amount {}", s),
+    }
+}
+
+/// Metadata lines like `invoice: "2024-17"`, keys start lowercase where
+/// accounts start uppercase.
+fn parse_meta(line: &str) -> Option<(&str, String)> {
+    let (key, value) = line.split_once(':')?;
+    let is_key = key.starts_with(|c: char| c.is_ascii_lowercase())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    let value = value.trim();
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(value) => value,
+        None => value,
+    };
+    is_key.then(|| (key, value.to_string()))
+}
+
+/// Entries of a ledger. Undated lines, like options and plugins, are left
+/// out with a warning.
+fn entries<'a>(input: &'a str, warnings: &mut Vec<String>) -> Vec<Entry<'a>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut in_entry = false;
+    for (i, line) in input.lines().enumerate() {
+        let text = strip_comment(line).trim_end();
+        if text.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(entry) = entries.last_mut().filter(|_| in_entry) {
+                entry.body.push((i + 1, text.trim()));
+            }
+            continue;
+        }
+        let (first, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let Ok(date) = first.parse() else {
+            in_entry = false;
+            // `*` starts the headings of org-mode ledgers
+            if !first.starts_with('*') {
+                warnings.push(format!("line {}: `{}` is not supported", i + 1, first));
+            }
+            continue;
+        };
+        let rest = rest.trim();
+        let (kind, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        entries.push(Entry {
+            line: i + 1,
+            date,
+            kind,
+            rest: rest.trim(),
+            body: Vec::new(),
+        });
+        in_entry = true;
+    }
+    entries
+}
+
+impl Journal {
+    /// Accn of a Beancount account like `Assets:US:Checking`, opened if new.
+    fn beancount_accn(&mut self, name: &str) -> Result<Accn> {
+        let mut parts = name.split(':');
+        let top = parts.next().unwrap();
+        let (_, root) = ROOTS
+            .iter()
+            .find(|(root, _)| *root == top)
+            .ok_or_else(|| anyhow!("account {} is not under a Beancount root", name))?;
+        let mut accn = self.accns.root_mut().or_open_child(root);
+        for part in parts {
+            accn = accn.or_open_child(part);
+        }
+        Ok(accn.into_ref().id())
+    }
+
+    /// Currency of `code`, declared if new like a stock.
+    fn beancount_currency(&mut self, code: &str) -> Currency {
+        if self.currencies.get_by_code(code).is_none() {
+            self.currencies.declare(code, None, None);
+        }
+        self.currencies.get_by_code(code).unwrap()
+    }
+
+    /// Money of an amount like `10 AAPL`.
+    fn beancount_money(&mut self, amount: &str) -> Result<Money> {
+        let (amount, code) = parse_amount(amount)?;
+        Ok(Money::new(amount, self.beancount_currency(&code)))
+    }
+
+    /// Import the accounts, commodities, prices and transactions of a
+    /// Beancount ledger. Balance assertions are checked once everything is
+    /// in, and directives without a counterpart, like `pad` and `note`, are
+    /// left out with a warning.
+    pub(crate) fn import_beancount(&mut self, input: &str) -> Result<Imported> {
+        self.importing(|journal, import| journal.read_beancount(input, import))
+    }
+
+    fn read_beancount(&mut self, input: &str, import: &mut Imported) -> Result<()> {
+        let imported: HashSet<String> = self
+            .txns()
+            .filter_map(|txn| txn.meta(BEANCOUNT_META).map(str::to_string))
+            .collect();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut assertions = Vec::new();
+
+        for entry in entries(input, &mut import.warnings) {
+            let in_entry = || format!("in Beancount line {}", entry.line);
+            let words = entry.rest.split_whitespace().collect_vec();
+            match entry.kind {
+                "open" => {
+                    let name = words
+                        .first()
+                        .ok_or_else(|| anyhow!("open without an account"))?;
+                    let accn = self.beancount_accn(name).with_context(in_entry)?;
+                    // one commodity is the accn's currency, several are not kept
+                    let codes = words.get(1).map(|codes| codes.split(',').collect_vec());
+                    let code = match codes.as_deref() {
+                        Some([commodity]) => Some(code(commodity).with_context(in_entry)?),
+                        _ => None,
+                    };
+                    accn.into_accn_mut(&mut self.accns)
+                        .declare_open(Some(entry.date), code.as_deref());
+                }
+                "close" => {
+                    let name = words
+                        .first()
+                        .ok_or_else(|| anyhow!("close without an account"))?;
+                    let accn = self.beancount_accn(name).with_context(in_entry)?;
+                    accn.into_accn_mut(&mut self.accns)
+                        .declare_close(entry.date)
+                        .with_context(in_entry)?;
+                }
+                "commodity" => {
+                    let commodity = words
+                        .first()
+                        .ok_or_else(|| anyhow!("commodity without a code"))?;
+                    self.beancount_currency(&code(commodity).with_context(in_entry)?);
+                }
+                "price" => {
+                    let (commodity, price) = entry
+                        .rest
+                        .split_once(char::is_whitespace)
+                        .ok_or_else(|| anyhow!("price without an amount"))
+                        .with_context(in_entry)?;
+                    let (price, currency) = parse_amount(price).with_context(in_entry)?;
+                    let commodity = code(commodity)?;
+                    self.beancount_currency(&commodity);
+                    self.beancount_currency(&currency);
+                    self.rates.insert(entry.date, &commodity, &currency, price);
+                }
+                "balance" => {
+                    let assertion = match words[..] {
+                        [name, amount, commodity] => (name, amount, None, commodity),
+                        [name, amount, "~", tolerance, commodity] => {
+                            (name, amount, Some(tolerance), commodity)
+                        }
+                        _ => bail!("invalid balance assertion {} {}", in_entry(), entry.rest),
+                    };
+                    let (name, amount, tolerance, commodity) = assertion;
+                    assertions.push(Assertion {
+                        line: entry.line,
+                        date: entry.date,
+                        accn: self.beancount_accn(name).with_context(in_entry)?,
+                        amount: parse_number(amount).with_context(in_entry)?,
+                        tolerance: tolerance
+                            .map(parse_number)
+                            .transpose()
+                            .with_context(in_entry)?
+                            .unwrap_or_default(),
+                        code: code(commodity)?,
+                    });
+                }
+                kind if kind == "txn" || (kind.len() == 1 && FLAGS.contains(kind)) => {
+                    let (key, mut builder) = self
+                        .beancount_txn(&entry, &mut seen)
+                        .with_context(in_entry)?;
+                    if imported.contains(&key) {
+                        import.skipped += 1;
+                        continue;
+                    }
+                    builder.with_meta(BEANCOUNT_META, key);
+                    let txn = builder
+                        .build(&mut self.txns, &self.currencies)
+                        .with_context(in_entry)?;
+                    import.added.push(txn);
+                }
+                kind => import.warnings.push(format!(
+                    "line {}: `{}` directives are not supported",
+                    entry.line, kind
+                )),
+            }
+        }
+
+        for assertion in assertions {
+            let accn = assertion.accn.into_accn(&self.accns);
+            let day_before = assertion.date.pred_opt().unwrap();
+            let balance = self.balance_at(accn, day_before);
+            let balance = balance
+                .into_valuable(&self.currencies)
+                .amounts()
+                .into_iter()
+                .find(|(code, _)| *code == assertion.code)
+                .map_or(Decimal::ZERO, |(_, amount)| amount);
+            if (balance - assertion.amount).abs() > assertion.tolerance {
+                import.warnings.push(format!(
+                    "line {}: {} is {} {} on {}, not {} {} as asserted",
+                    assertion.line,
+                    accn,
+                    balance,
+                    assertion.code,
+                    assertion.date,
+                    assertion.amount,
+                    assertion.code
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Txn of a transaction entry, keyed by what identifies it across
+    /// imports. A posting at a cost or price, like `10 AAPL {180 USD}`, is
+    /// balanced through `equity:trading`, which also takes what booking
+    /// against the lots would have.
+    fn beancount_txn(
+        &mut self,
+        entry: &Entry,
+        seen: &mut HashMap<String, usize>,
+    ) -> Result<(String, TxnBuilder)> {
+        let words = words(entry.rest);
+        let strings = words
+            .iter()
+            .filter(|(_, quoted)| *quoted)
+            .map(|(word, _)| word.as_str())
+            .collect_vec();
+        let desc = match strings[..] {
+            [] => String::new(),
+            [narration] => narration.to_string(),
+            [payee, narration] => format!("{} | {}", payee, narration),
+            _ => bail!("more than a payee and a narration"),
+        };
+        let unquoted = || words.iter().filter(|(_, quoted)| !quoted);
+        let tags = unquoted()
+            .filter_map(|(word, _)| word.strip_prefix('#'))
+            .map(str::to_string)
+            .collect_vec();
+        let links = unquoted()
+            .filter_map(|(word, _)| word.strip_prefix('^'))
+            .collect_vec();
+
+        let mut builder = TxnBuilder::new(entry.date, desc);
+        if !["*", "txn"].contains(&entry.kind) {
+            builder.with_meta("flag", entry.kind);
+        }
+        if !links.is_empty() {
+            builder.with_meta(LINK_META, links.join(", "));
+        }
+        let (mut sides, mut inferred, mut converted) = (Vec::new(), false, false);
+        for (_, line) in &entry.body {
+            // metadata of a posting is kept with the txn
+            if let Some((key, value)) = parse_meta(line) {
+                builder.with_meta(key, value);
+                continue;
+            }
+            sides.push(line.split_whitespace().join(" "));
+
+            let (line, price) = match line.split_once('@') {
+                Some((line, price)) => (line, Some(price)),
+                None => (*line, None),
+            };
+            let (line, cost) = match line.find('{') {
+                Some(i) => (&line[..i], Some(&line[i..])),
+                None => (line, None),
+            };
+            let mut words = line.split_whitespace().peekable();
+            words.next_if(|word| word.len() == 1 && FLAGS.contains(*word));
+            let name = words
+                .next()
+                .ok_or_else(|| anyhow!("posting without an account"))?;
+            let accn = self.beancount_accn(name)?;
+            let units = words.join(" ");
+            if units.is_empty() {
+                builder.with_tagged_posting(accn, None, tags.clone());
+                inferred = true;
+                continue;
+            }
+            let units = self.beancount_money(&units)?;
+
+            builder.with_tagged_posting(accn, Some(units), tags.clone());
+
+            // what the units are worth, by the price or else by the cost
+            let weight = match (price, cost) {
+                (Some(price), _) => match price.strip_prefix('@') {
+                    Some(total) => self.beancount_weight(total, units, true)?,
+                    None => self.beancount_weight(price, units, false)?,
+                },
+                (None, Some(cost)) => {
+                    let is_total = cost.starts_with("{{");
+                    let cost = cost.trim_matches(|c| c == '{' || c == '}');
+                    // a cost may also name the date and label of its lot
+                    let amount = cost.split(',').find(|part| parse_amount(part).is_ok());
+                    match amount {
+                        Some(amount) => self.beancount_weight(amount, units, is_total)?,
+                        None => None,
+                    }
+                }
+                (None, None) => None,
+            };
+            if let Some(weight) = weight {
+                let trading = self.trading_accn();
+                builder
+                    .with_posting_combined(trading, Some(-units))
+                    .with_posting_combined(trading, Some(weight));
+                converted = true;
+            }
+        }
+        if converted && !inferred {
+            builder.with_posting(self.trading_accn(), None);
+        }
+
+        let key = format!("{} {}", entry.date, sides.iter().sorted().join(", "));
+        let n = seen.entry(key.clone()).or_default();
+        *n += 1;
+        let key = match *n {
+            1 => key,
+            n => format!("{} #{}", key, n),
+        };
+        Ok((key, builder))
+    }
+
+    /// What `units` are worth at the price or cost `amount`, for each unit
+    /// or for all of them.
+    fn beancount_weight(
+        &mut self,
+        amount: &str,
+        units: Money,
+        is_total: bool,
+    ) -> Result<Option<Money>> {
+        let amount = amount.trim();
+        if amount.is_empty() {
+            return Ok(None);
+        }
+        let price = self.beancount_money(amount)?;
+        let weight = match (is_total, units.amount().is_sign_negative()) {
+            (true, true) => -price.amount(),
+            (true, false) => price.amount(),
+            (false, _) => price.amount() * units.amount(),
+        };
+        Ok(Some(Money::new(weight, price.currency())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LEDGER: &str = r#"option "title" "Household"
+plugin "beancount.plugins.auto_accounts"
+
+2024-01-01 open Assets:US:Checking USD
+2024-01-01 open Assets:Brokerage:AAPL AAPL
+2024-01-01 commodity AAPL
+  name: "Apple Inc."
+
+2024-01-01 * "Employer" "January salary" #work ^pay-01
+  invoice: "2024-01"
+  Assets:US:Checking   3,000.00 USD
+  Income:Salary       -3,000.00 USD
+
+2024-01-05 * "Broker" "Buy Apple"
+  Assets:Brokerage:AAPL  10 AAPL {180.00 USD}
+  Assets:US:Checking    -1,800.00 USD
+
+2024-01-06 price AAPL 185.00 USD
+
+2024-01-10 ! "Groceries"
+  Expenses:Food   45.10 USD ; weekly
+  Assets:US:Checking
+
+2024-01-15 pad Assets:US:Checking Equity:Opening-Balances
+2024-01-20 balance Assets:US:Checking 1154.90 USD
+2024-01-20 balance Assets:Brokerage:AAPL 11 AAPL
+2024-01-31 close Assets:Brokerage:AAPL
+"#;
+
+    #[test]
+    fn test_import_beancount() {
+        let mut journal = Journal::from_str("").unwrap();
+        let import = journal.import_beancount(LEDGER).unwrap();
+        assert_eq!(import.added.len(), 3);
+        assert_eq!(import.warnings.len(), 4, "{:?}", import.warnings);
+        assert!(import.warnings[3].contains("asset:Brokerage:AAPL is 10 AAPL"));
+
+        let checking = journal.accns().by_name_unique("Checking").ok().unwrap();
+        assert_eq!(checking.abs_name(), "asset:US:Checking");
+        assert_eq!(checking.currency(), Some("USD"));
+        assert_eq!(
+            journal
+                .balance(checking)
+                .into_valuable(journal.currencies())
+                .to_string(),
+            "$1154.90"
+        );
+        let salary = journal.txns().next().unwrap();
+        assert_eq!(salary.payee(), Some("Employer"));
+        assert_eq!(salary.meta("invoice"), Some("2024-01"));
+        assert_eq!(salary.meta(LINK_META), Some("pay-01"));
+        assert_eq!(salary.tags().collect_vec(), ["work"]);
+        let groceries = journal.txns().last().unwrap();
+        assert_eq!(groceries.meta("flag"), Some("!"));
+        let date = NaiveDate::from_ymd_opt(2024, 1, 6).unwrap();
+        assert_eq!(
+            journal.rates.get("AAPL", "USD", date).unwrap(),
+            Decimal::from(185)
+        );
+
+        let mut journal = Journal::from_str(&journal.to_string()).unwrap();
+        let again = journal.import_beancount(LEDGER).unwrap();
+        assert_eq!((again.added.len(), again.skipped), (0, 3));
+    }
+}
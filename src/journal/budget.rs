@@ -0,0 +1,391 @@
+use std::fmt::{Display, Write};
+
+use chrono::NaiveDate;
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::{AccnEntry, Budget},
+    valuable::Money,
+};
+
+use super::{snapshot::Cutoff, Journal, Txn};
+
+/// Cells of the bar `bal` draws next to a budgeted accn.
+pub(crate) const BAR_WIDTH: usize = 10;
+
+/// Budget of one accn over the period containing a date, with what was
+/// spent under it and what the budgets below it carve out of it.
+pub(crate) struct BudgetLine<'a> {
+    pub(crate) accn: AccnEntry<'a>,
+    pub(crate) budget: Budget,
+    pub(crate) start: NaiveDate,
+    /// Spent under the accn in the period, children included, in the
+    /// currency of the budget.
+    pub(crate) actual: Decimal,
+    /// Budgets of the nearest budgeted descendants, over the period of this
+    /// one, none if there are none.
+    pub(crate) carved: Option<Decimal>,
+}
+
+impl BudgetLine<'_> {
+    pub(crate) fn remaining(&self) -> Decimal {
+        self.budget.money.amount() - self.actual
+    }
+
+    /// Whether the budgets below the accn ask for more than it has.
+    pub(crate) fn over_allocated(&self) -> bool {
+        self.carved
+            .is_some_and(|carved| carved > self.budget.money.amount())
+    }
+
+    /// Share of the budget spent as a bar of `width` cells with the percent
+    /// after it, red once the budget is overspent.
+    pub(crate) fn bar(&self, width: usize) -> String {
+        let budget = self.budget.money.amount();
+        let share = match budget.is_zero() {
+            true => Decimal::ONE,
+            false => (self.actual / budget).max(Decimal::ZERO),
+        };
+        let filled = (share.min(Decimal::ONE) * Decimal::from(width))
+            .round()
+            .try_into()
+            .unwrap_or(width);
+        let bar = format!("{}{}", "█".repeat(filled), "░".repeat(width - filled));
+        let percent = (share * Decimal::ONE_HUNDRED).round();
+        let bar = format!("{} {:>3}%", bar, percent);
+        match self.remaining() < Decimal::ZERO {
+            true => bar.red().to_string(),
+            false => bar,
+        }
+    }
+
+    /// Budgeted ancestors of the accn, for indenting it below them.
+    fn depth(&self) -> usize {
+        std::iter::successors(self.accn.parent(), |accn| accn.parent())
+            .filter(|accn| accn.budget().is_some())
+            .count()
+    }
+}
+
+/// A budget that new txns took over its amount.
+pub(crate) struct BudgetAlert<'a> {
+    journal: &'a Journal,
+    line: BudgetLine<'a>,
+    /// Days of the period left after today.
+    days_left: i64,
+}
+
+impl Display for BudgetAlert<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line = &self.line;
+        let money = |amount| {
+            Money::new(amount, line.budget.money.currency())
+                .into_money(&self.journal.currencies)
+                .to_string()
+        };
+        write!(
+            f,
+            "{}: {} is {} over its {} budget for {}, {} days left",
+            "warning".yellow().bold(),
+            line.accn.abs_name(),
+            money(-line.remaining()),
+            money(line.budget.money.amount()),
+            line.budget.period.label(line.start),
+            self.days_left
+        )
+    }
+}
+
+/// Every budget as of a date, as printed by `budget`.
+pub(crate) struct BudgetReport<'a> {
+    journal: &'a Journal,
+    lines: Vec<BudgetLine<'a>>,
+}
+
+impl Journal {
+    pub(super) fn budget_directives(&self) -> String {
+        self.accns
+            .accns()
+            .filter_map(|accn| Some((accn, accn.budget()?)))
+            .sorted_by_key(|(accn, _)| accn.abs_name())
+            .map(|(accn, budget)| {
+                let money = budget.money.into_money(&self.currencies);
+                match budget.period.name() {
+                    "monthly" => format!("budget {} {}", accn, money),
+                    period => format!("budget {} {} {}", accn, money, period),
+                }
+            })
+            .join("\n")
+    }
+
+    /// Nearest descendants of `accn` with a budget of their own.
+    fn carve_outs<'a>(&self, accn: AccnEntry<'a>) -> Vec<AccnEntry<'a>> {
+        accn.children()
+            .flat_map(|child| match child.budget() {
+                Some(_) => vec![child],
+                None => self.carve_outs(child),
+            })
+            .collect()
+    }
+
+    /// Budget line of `accn` over its period containing `date`, none if it
+    /// has no budget.
+    pub(crate) fn budget_line<'a>(
+        &'a self,
+        accn: AccnEntry<'a>,
+        date: NaiveDate,
+    ) -> Option<BudgetLine<'a>> {
+        let last = accn.budget()?.period.span(date).end.pred_opt()?;
+        self.budget_line_as_of(accn, Cutoff::end_of(last))
+    }
+
+    /// Budget line of `accn` over its period containing the cutoff, with
+    /// what was spent in it up to the cutoff.
+    pub(crate) fn budget_line_as_of<'a>(
+        &'a self,
+        accn: AccnEntry<'a>,
+        cutoff: Cutoff,
+    ) -> Option<BudgetLine<'a>> {
+        let budget = accn.budget()?;
+        let currency = budget.money.currency();
+        let start = budget.period.span(cutoff.date()).start;
+        let actual = self
+            .postings()
+            .filter(|p| {
+                let txn = p.txn();
+                txn.date() >= start && cutoff.includes(txn.date(), txn.time())
+            })
+            .filter(|p| p.accn().is_descendent_of(accn))
+            .map(|p| p.money().money())
+            .filter(|money| money.currency() == currency)
+            .map(|money| money.amount())
+            .sum();
+
+        let carve_outs = self.carve_outs(accn);
+        let carved = (!carve_outs.is_empty()).then(|| {
+            carve_outs
+                .iter()
+                .filter_map(|child| child.budget())
+                .filter(|child| child.money.currency() == currency)
+                .map(|child| {
+                    child.money.amount() * Decimal::from(child.period.per_year())
+                        / Decimal::from(budget.period.per_year())
+                })
+                .sum()
+        });
+        Some(BudgetLine {
+            accn,
+            budget,
+            start,
+            actual,
+            carved,
+        })
+    }
+
+    /// Budgets `txns`, just added, leave overspent in the periods they fall
+    /// in, each once, with the days left in those periods as of `today`.
+    pub(crate) fn budget_alerts(&self, txns: &[Txn], today: NaiveDate) -> Vec<BudgetAlert<'_>> {
+        let budgeted = txns
+            .iter()
+            .filter(|txn| self.contains_txn(**txn))
+            .flat_map(|txn| {
+                let txn = self.txn(*txn);
+                let date = txn.date();
+                txn.postings()
+                    .filter(|p| p.money().money().amount() > Decimal::ZERO)
+                    .flat_map(|p| std::iter::successors(Some(p.accn()), |accn| accn.parent()))
+                    .filter_map(|accn| {
+                        let budget = accn.budget()?;
+                        Some((accn.id(), budget.period.start(date)))
+                    })
+                    .collect_vec()
+            })
+            .unique();
+        budgeted
+            .filter_map(|(accn, start)| self.budget_line(accn.into_accn(&self.accns), start))
+            .filter(|line| line.remaining() < Decimal::ZERO)
+            .map(|line| {
+                let end = line.budget.period.span(line.start).end;
+                BudgetAlert {
+                    journal: self,
+                    days_left: (end - today.max(line.start)).num_days().max(1) - 1,
+                    line,
+                }
+            })
+            .collect()
+    }
+
+    /// Budgets of `accn` and its descendants, or of every accn, over their
+    /// periods containing `date`.
+    pub(crate) fn budget_report<'a>(
+        &'a self,
+        accn: Option<AccnEntry<'a>>,
+        date: NaiveDate,
+    ) -> BudgetReport<'a> {
+        let lines = self
+            .accns
+            .accns()
+            .filter(|budgeted| accn.is_none_or(|accn| budgeted.is_descendent_of(accn)))
+            .filter_map(|accn| self.budget_line(accn, date))
+            .sorted_by_key(|line| line.accn.abs_name())
+            .collect();
+        BudgetReport {
+            journal: self,
+            lines,
+        }
+    }
+}
+
+impl Display for BudgetReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.lines.is_empty() {
+            return write!(f, "no budgets, add one like `budget expense:food $600`");
+        }
+        let store = &self.journal.currencies;
+        let money = |line: &BudgetLine, amount| {
+            Money::new(amount, line.budget.money.currency())
+                .into_money(store)
+                .to_string()
+        };
+        let mut warnings = String::new();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let name = format!("{}{}", "  ".repeat(line.depth()), line.accn.abs_name());
+            let remaining = match line.remaining() < Decimal::ZERO {
+                true => money(line, line.remaining()).red(),
+                false => money(line, line.remaining()).green(),
+            };
+            write!(
+                f,
+                "{:<36}{:>10} {:>12} / {:>12} {:>12}",
+                name,
+                line.budget.period.label(line.start),
+                money(line, line.actual),
+                money(line, line.budget.money.amount()),
+                remaining
+            )?;
+            if let Some(carved) = line.carved {
+                let rest = line.budget.money.amount() - carved;
+                write!(
+                    f,
+                    "\n{:<36}{}",
+                    "",
+                    format!(
+                        "{} carved out below, {} left",
+                        money(line, carved),
+                        money(line, rest)
+                    )
+                    .dimmed()
+                )?;
+            }
+            if line.over_allocated() {
+                write!(
+                    warnings,
+                    "\n{}: budgets under {} add up to {}, over its {}",
+                    "warning".yellow().bold(),
+                    line.accn.abs_name(),
+                    money(line, line.carved.unwrap_or_default()),
+                    money(line, line.budget.money.amount())
+                )?;
+            }
+        }
+        write!(f, "{}", warnings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"budget expense:food $600
+budget expense:food:dining $200
+budget expense:food:groceries $1800 quarterly
+
+2024-03-02 groceries
+    expense:food:groceries  $150
+    asset:bank
+
+2024-03-05 dinner
+    expense:food:dining  $80
+    asset:bank
+
+2024-03-06 snacks
+    expense:food  $20
+    asset:bank
+
+2024-02-28 dinner
+    expense:food:dining  $500
+    asset:bank"#;
+
+    #[test]
+    fn test_budget_report() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let food = journal.accns().by_name_unique("food").ok().unwrap();
+        let line = journal.budget_line(food, date).unwrap();
+        assert_eq!(line.actual, dec!(250));
+        assert_eq!(line.remaining(), dec!(350));
+        // $200 a month for dining and $600 a month for groceries
+        assert_eq!(line.carved, Some(dec!(800)));
+        assert!(line.over_allocated());
+
+        let report = journal.budget_report(Some(food), date);
+        assert_eq!(report.lines.len(), 3);
+        assert_eq!(report.lines[1].depth(), 1);
+        assert!(report
+            .to_string()
+            .contains("budgets under expense:food add up to $800"));
+
+        let text = journal.to_string();
+        assert!(text.contains("budget expense:food $600\n"));
+        assert!(text.contains("budget expense:food:groceries $1800 quarterly"));
+    }
+
+    #[test]
+    fn test_budget_alerts() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let dining = journal.accns().by_name_unique("dining").ok().unwrap().id();
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        let money = journal.parse_money("$150").unwrap().money();
+        let txn = journal
+            .new_txn(date, "birthday dinner".to_string())
+            .with_posting(dining, Some(money))
+            .with_posting(bank, None::<Money>)
+            .build()
+            .unwrap()
+            .id();
+
+        let alerts = journal.budget_alerts(&[txn], date);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].line.remaining(), dec!(-30));
+        assert_eq!(alerts[0].days_left, 11);
+        assert!(alerts[0].to_string().ends_with(
+            "expense:food:dining is $30 over its $200 budget for 2024-03, 11 days left"
+        ));
+    }
+
+    #[test]
+    fn test_bar() {
+        colored::control::set_override(false);
+        let journal = Journal::from_str(INPUT).unwrap();
+        let dining = journal.accns().by_name_unique("dining").ok().unwrap();
+        let food = journal.accns().by_name_unique("food").ok().unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let line = journal.budget_line(dining, date).unwrap();
+        assert_eq!(line.bar(10), "████░░░░░░  40%");
+        // the dinner of the 5th is after the cutoff
+        let cutoff = Cutoff::end_of(NaiveDate::from_ymd_opt(2024, 3, 4).unwrap());
+        let line = journal.budget_line_as_of(food, cutoff).unwrap();
+        assert_eq!(line.actual, dec!(150));
+        let february = NaiveDate::from_ymd_opt(2024, 2, 10).unwrap();
+        let line = journal.budget_line(dining, february).unwrap();
+        assert_eq!(line.bar(4), "████ 250%");
+    }
+}
@@ -0,0 +1,111 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use chrono::NaiveDate;
+use colored::Colorize;
+
+use crate::valuable::ValuableEntry;
+
+use super::Journal;
+
+/// What the postings to the accns of each class add up to, as printed by
+/// `classes`.
+pub(crate) struct ClassTotals<'a> {
+    totals: BTreeMap<&'a str, ValuableEntry<'a>>,
+}
+
+impl Journal {
+    /// Sum of the postings dated from `since` through `until` to accns of
+    /// each class, an accn counting for the classes of its ancestors too.
+    /// Over all time the sums are balances, so `liquid` is the liquid net
+    /// worth, while over a month `fixed-cost` is what fixed costs took.
+    pub(crate) fn class_totals(
+        &self,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> ClassTotals<'_> {
+        let mut totals: BTreeMap<&str, Vec<_>> = BTreeMap::new();
+        let postings = self.postings().filter(|p| {
+            let date = p.txn().date();
+            since.is_none_or(|since| date >= since) && until.is_none_or(|until| date <= until)
+        });
+        for posting in postings {
+            for class in posting.accn().classes() {
+                totals.entry(class).or_default().push(posting.money());
+            }
+        }
+        ClassTotals {
+            totals: totals
+                .into_iter()
+                .map(|(class, moneys)| (class, moneys.into_iter().sum()))
+                .collect(),
+        }
+    }
+}
+
+impl Display for ClassTotals<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.totals.is_empty() {
+            return write!(
+                f,
+                "no accn has a class, add one like `class asset:bank liquid`"
+            );
+        }
+        for (i, (class, total)) in self.totals.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<30}{:>20}", class.bold(), total.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"class asset:bank liquid
+class asset:bank:savings emergency
+class expense:rent fixed-cost
+
+2024-01-05 salary
+    asset:bank:checking  $3000
+    income:salary
+
+2024-02-01 rent
+    expense:rent  $1200
+    asset:bank:checking
+
+2024-02-03 move to savings
+    asset:bank:savings  $500
+    asset:bank:checking"#;
+
+    #[test]
+    fn test_class_totals() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let savings = journal.accns().by_name_unique("savings").ok().unwrap();
+        assert!(savings.has_class("liquid") && savings.has_class("emergency"));
+
+        let totals = journal.class_totals(None, None).totals;
+        let totals = totals
+            .iter()
+            .map(|(class, total)| (*class, total.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            totals,
+            [
+                ("emergency", "$500".to_string()),
+                ("fixed-cost", "$1200".to_string()),
+                ("liquid", "$1800".to_string()),
+            ]
+        );
+
+        let feb = NaiveDate::from_ymd_opt(2024, 2, 1);
+        let totals = journal.class_totals(feb, None).totals;
+        assert_eq!(totals["liquid"].to_string(), "-$1200");
+
+        let text = journal.to_string();
+        assert!(text.contains("class asset:bank liquid"));
+        assert!(text.contains("class expense:rent fixed-cost"));
+    }
+}
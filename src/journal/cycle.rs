@@ -0,0 +1,205 @@
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use itertools::Itertools;
+use rust_decimal::{prelude::Zero, Decimal};
+
+use crate::{
+    accn::{AccnEntry, BillingCycle},
+    valuable::Valuable,
+};
+
+use super::Journal;
+
+/// How the payment of one statement went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PaymentStatus {
+    /// The statement owed nothing.
+    NothingDue,
+    Paid,
+    /// Not due yet and not paid in full so far.
+    Open,
+    Partial,
+    Missed,
+}
+
+impl PaymentStatus {
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            PaymentStatus::NothingDue => "nothing due",
+            PaymentStatus::Paid => "paid",
+            PaymentStatus::Open => "open",
+            PaymentStatus::Partial => "partial payment",
+            PaymentStatus::Missed => "missed payment",
+        }
+    }
+}
+
+/// One closed statement cycle of an accn with a billing cycle.
+#[derive(Debug, Clone)]
+pub(crate) struct Cycle {
+    pub(crate) closing: NaiveDate,
+    pub(crate) due: NaiveDate,
+    /// Balance at the closing, negative for what is owed.
+    pub(crate) balance: Valuable,
+    /// What was paid into the accn after the closing through the due date.
+    pub(crate) paid: Valuable,
+    pub(crate) status: PaymentStatus,
+}
+
+/// `day` of the month of `date`, or its last day for a shorter month.
+fn day_of(date: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day).rev().find_map(|day| date.with_day(day)).unwrap()
+}
+
+impl BillingCycle {
+    /// Closing date of the statement covering `date`.
+    pub(crate) fn closing_after(self, date: NaiveDate) -> NaiveDate {
+        let closing = day_of(date, self.closing);
+        match closing >= date {
+            true => closing,
+            false => day_of(date + Months::new(1), self.closing),
+        }
+    }
+
+    /// Due date of the statement closing on `closing`.
+    pub(crate) fn due(self, closing: NaiveDate) -> NaiveDate {
+        let due = day_of(closing, self.due);
+        match due > closing {
+            true => due,
+            false => day_of(closing + Months::new(1), self.due),
+        }
+    }
+}
+
+/// Whether `valuable` is below zero in any currency.
+fn owes(valuable: &Valuable) -> bool {
+    valuable
+        .clone()
+        .into_iter()
+        .any(|money| money.amount() < Decimal::ZERO)
+}
+
+impl Journal {
+    /// Every statement cycle of `accn` closed through `today`, from the one
+    /// covering its first posting, none if it has no billing cycle.
+    pub(crate) fn cycles(&self, accn: AccnEntry, today: NaiveDate) -> Vec<Cycle> {
+        let Some(cycle) = accn.cycle() else {
+            return Vec::new();
+        };
+        let postings = self
+            .postings()
+            .filter(|p| p.accn().is_descendent_of(accn))
+            .map(|p| (p.txn().date(), p.money().money()))
+            .sorted_by_key(|(date, _)| *date)
+            .collect_vec();
+        let Some((first, _)) = postings.first() else {
+            return Vec::new();
+        };
+
+        let mut cycles = Vec::new();
+        let mut closing = cycle.closing_after(*first);
+        while closing <= today {
+            let due = cycle.due(closing);
+            let balance: Valuable = postings
+                .iter()
+                .filter(|(date, _)| *date <= closing)
+                .map(|(_, money)| *money)
+                .sum();
+            let paid: Valuable = postings
+                .iter()
+                .filter(|(date, money)| {
+                    *date > closing && *date <= due && money.amount() > Decimal::ZERO
+                })
+                .map(|(_, money)| *money)
+                .sum();
+            let status = match () {
+                _ if !owes(&balance) => PaymentStatus::NothingDue,
+                _ if !owes(&(balance.clone() + paid.clone())) => PaymentStatus::Paid,
+                _ if due >= today => PaymentStatus::Open,
+                _ if !paid.is_zero() => PaymentStatus::Partial,
+                _ => PaymentStatus::Missed,
+            };
+            cycles.push(Cycle {
+                closing,
+                due,
+                balance,
+                paid,
+                status,
+            });
+            closing = cycle.closing_after(closing + Duration::days(1));
+        }
+        cycles
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"cycle liability:visa 25 20
+
+2024-01-10 groceries
+    expense:food  $300
+    liability:visa
+
+2024-02-10 payment
+    liability:visa  $300
+    asset:bank
+
+2024-02-12 flight
+    expense:travel  $800
+    liability:visa
+
+2024-03-15 payment
+    liability:visa  $500
+    asset:bank
+
+2024-03-20 dinner
+    expense:food  $100
+    liability:visa"#;
+
+    #[test]
+    fn test_cycles() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let visa = journal.accns().by_name_unique("visa").ok().unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let cycles = journal.cycles(visa, today);
+        let summary = cycles
+            .iter()
+            .map(|s| (s.closing.to_string(), s.due.to_string(), s.status))
+            .collect_vec();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "2024-01-25".into(),
+                    "2024-02-20".into(),
+                    PaymentStatus::Paid
+                ),
+                (
+                    "2024-02-25".into(),
+                    "2024-03-20".into(),
+                    PaymentStatus::Partial
+                ),
+                (
+                    "2024-03-25".into(),
+                    "2024-04-20".into(),
+                    PaymentStatus::Missed
+                ),
+                (
+                    "2024-04-25".into(),
+                    "2024-05-20".into(),
+                    PaymentStatus::Open
+                ),
+            ]
+        );
+
+        let cycle = visa.cycle().unwrap();
+        let feb = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let short = BillingCycle {
+            closing: 31,
+            ..cycle
+        };
+        assert_eq!(short.closing_after(feb), feb);
+        assert!(journal.to_string().contains("cycle liability:visa 25 20"));
+    }
+}
@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+
+use super::{entry::TxnEntry, Journal};
+
+/// Directives and transactions that differ between two journals, compared
+/// by their serialized form.
+pub(crate) struct TxnDiff<'a> {
+    /// Directive lines, like `rate 2024-01-01 EUR USD 1.1`.
+    pub(crate) removed_directives: Vec<String>,
+    pub(crate) added_directives: Vec<String>,
+    pub(crate) removed: Vec<TxnEntry<'a>>,
+    pub(crate) added: Vec<TxnEntry<'a>>,
+}
+
+impl TxnDiff<'_> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.removed_directives.is_empty()
+            && self.added_directives.is_empty()
+            && self.removed.is_empty()
+            && self.added.is_empty()
+    }
+}
+
+/// Items of `ours` with no equal left in `theirs`, each one of `theirs`
+/// matching only once.
+fn only_in<T: Display>(
+    ours: impl Iterator<Item = T>,
+    theirs: impl Iterator<Item = impl Display>,
+) -> Vec<T> {
+    let mut theirs = theirs.map(|item| item.to_string()).counts();
+    ours.filter(|item| match theirs.get_mut(&item.to_string()) {
+        Some(n) if *n > 0 => {
+            *n -= 1;
+            false
+        }
+        _ => true,
+    })
+    .collect()
+}
+
+impl Journal {
+    fn directive_lines(&self) -> Vec<String> {
+        self.directives()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Directives and transactions of `base` missing from `self` and vice
+    /// versa.
+    pub(crate) fn diff<'a>(&'a self, base: &'a Journal) -> TxnDiff<'a> {
+        let txns = |ours: &'a Journal, theirs: &Journal| {
+            let mut txns = only_in(ours.txns(), theirs.txns());
+            txns.sort_by_key(|txn| txn.date());
+            txns
+        };
+        let (ours, theirs) = (self.directive_lines(), base.directive_lines());
+        TxnDiff {
+            removed_directives: only_in(theirs.iter().cloned(), ours.iter()),
+            added_directives: only_in(ours.iter().cloned(), theirs.iter()),
+            removed: txns(base, self),
+            added: txns(self, base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let base = Journal::from_file("./example/two_txns.coin").unwrap();
+        let mut journal = Journal::from_file("./example/two_txns.coin").unwrap();
+        assert!(journal.diff(&base).is_empty());
+
+        let txn = journal.txns().next().unwrap().id();
+        journal.txn_mut(txn).remove();
+        let diff = journal.diff(&base);
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.added.is_empty());
+
+        // a directive alone is a change too
+        let base = Journal::from_str("rate 2024-01-01 EUR USD 1.1").unwrap();
+        let journal = Journal::from_str("rate 2024-01-01 EUR USD 1.2").unwrap();
+        let diff = journal.diff(&base);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.removed_directives, ["rate 2024-01-01 EUR USD 1.1"]);
+        assert_eq!(diff.added_directives, ["rate 2024-01-01 EUR USD 1.2"]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use anyhow::{bail, Result};
+use itertools::Itertools;
+
+use crate::valuable::ValuableEntry;
+
+use super::{register::QueryType, Journal};
+
+/// Metadata keys declared with `dimension <key>`. Their values group
+/// transactions across the account tree, e.g. `; project: kitchen-reno`
+/// ties expenses of many accounts to one project.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Dimensions {
+    names: Vec<String>,
+}
+
+impl Dimensions {
+    pub(crate) fn declare(&mut self, name: &str) {
+        if !self.contains(name) {
+            self.names.push(name.to_string());
+        }
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+}
+
+impl Display for Dimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.names
+            .iter()
+            .map(|name| format!("dimension {}", name))
+            .join("\n")
+            .fmt(f)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DimensionRow {
+    value: String,
+    count: usize,
+    total: String,
+}
+
+impl Display for DimensionRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<30} {:>5} {:>30}", self.value, self.count, self.total)
+    }
+}
+
+impl Journal {
+    pub(crate) fn dimensions(&self) -> &Dimensions {
+        &self.dimensions
+    }
+
+    fn check_dimension(&self, name: &str) -> Result<()> {
+        if !self.dimensions.contains(name) {
+            bail!(
+                "unknown dimension {}, declare it with `dimension {}`",
+                name,
+                name
+            );
+        }
+        Ok(())
+    }
+
+    /// Total of the postings matching `query` per value of the dimension
+    /// `name`, leaving out txns without a value.
+    pub(crate) fn dimension_totals(
+        &self,
+        name: &str,
+        query: QueryType,
+    ) -> Result<Vec<DimensionRow>> {
+        self.check_dimension(name)?;
+        let mut totals: BTreeMap<&str, (ValuableEntry, usize)> = BTreeMap::new();
+        for p in self.query(query).into_postings() {
+            if let Some(value) = p.txn().meta(name) {
+                let (total, count) = totals.entry(value).or_default();
+                *total += p.money();
+                *count += 1;
+            }
+        }
+        Ok(totals
+            .into_iter()
+            .map(|(value, (total, count))| DimensionRow {
+                value: value.to_string(),
+                count,
+                total: total.to_string(),
+            })
+            .collect())
+    }
+
+    /// Query matching txns whose dimension `name` is `value`.
+    pub(crate) fn dimension_query(&self, name: &str, value: &str) -> Result<QueryType> {
+        self.check_dimension(name)?;
+        Ok(QueryType::Meta(name.to_string(), value.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"dimension project
+
+2024-03-01 tiles
+    ; project: kitchen-reno
+    expense:home:materials  $300
+    asset:bank
+
+2024-03-02 plumber
+    ; project: kitchen-reno
+    expense:services  $200
+    asset:bank
+
+2024-03-03 paint
+    ; project: bedroom
+    expense:home:materials  $80
+    asset:bank
+
+2024-03-04 groceries
+    expense:food  $50
+    asset:bank"#;
+
+    #[test]
+    fn test_dimension_totals() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        assert!(journal.to_string().starts_with("dimension project\n\n"));
+
+        let rows = journal
+            .dimension_totals("project", QueryType::MatchAccn("expense".into()))
+            .unwrap();
+        let rows = rows
+            .iter()
+            .map(|row| (row.value.as_str(), row.count, row.total.as_str()))
+            .collect_vec();
+        assert_eq!(rows, [("bedroom", 1, "$80"), ("kitchen-reno", 2, "$500")]);
+        assert!(journal.dimension_totals("payee", QueryType::All).is_err());
+
+        let query = journal.dimension_query("project", "bedroom").unwrap();
+        assert_eq!(journal.query(query).into_postings().count(), 2);
+    }
+}
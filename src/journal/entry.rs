@@ -1,17 +1,30 @@
 use std::{
+    collections::BTreeMap,
     fmt::{Debug, Display},
     ops::Deref,
 };
 
+use chrono::NaiveTime;
+use colored::Colorize;
 use itertools::Itertools;
 
 use crate::{
     accn::AccnEntry,
+    locale::{self, tr, Label},
+    util::{pad_left, pad_right, width},
     valuable::{MoneyEntry, ValuableEntry},
 };
 
 use super::*;
 
+/// Key of the metadata naming who entered a txn, like `; author: sam`, for
+/// journals a household shares.
+pub(crate) const AUTHOR_META: &str = "author";
+
+/// Key of the metadata giving the time of day of a txn, like `; time: 14:30`,
+/// which orders the txns of a day.
+pub(crate) const TIME_META: &str = "time";
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct PostingEntry<'a> {
     posting: Posting,
@@ -19,12 +32,16 @@ pub(crate) struct PostingEntry<'a> {
 }
 
 impl<'a> PostingEntry<'a> {
+    pub(super) fn id(self) -> Posting {
+        self.posting
+    }
+
     pub(super) fn accn(self) -> AccnEntry<'a> {
         self.data().accn.into_accn(&self.journal.accns)
     }
 
     fn data(self) -> &'a PostingData {
-        &self.journal.txns.postings[&self.posting]
+        &self.journal.txns.postings[self.posting]
     }
 
     pub(super) fn txn(self) -> TxnEntry<'a> {
@@ -34,6 +51,18 @@ impl<'a> PostingEntry<'a> {
     pub(super) fn money(self) -> MoneyEntry<'a> {
         self.data().money.into_money(&self.journal.currencies)
     }
+
+    pub(crate) fn tags(self) -> &'a [String] {
+        &self.data().tags
+    }
+
+    pub(crate) fn timed(self) -> Option<Timed> {
+        self.data().timed
+    }
+
+    pub(super) fn journal(self) -> &'a Journal {
+        self.journal
+    }
 }
 
 impl Posting {
@@ -51,14 +80,49 @@ impl Txn {
     }
 }
 
-impl Display for PostingEntry<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Columns of the accn and the amount of a posting, unless one of a txn is
+/// wider.
+const ACCN_WIDTH: usize = 60;
+const MONEY_WIDTH: usize = 10;
+
+impl PostingEntry<'_> {
+    /// The amount as written, as hours at a rate if it is one.
+    fn money_text(self) -> String {
+        let store = &self.journal.currencies;
+        match self.data().timed {
+            Some(timed) => format!(
+                "{} HRS @ {}",
+                timed.hours.normalize(),
+                timed.rate.fmt(store)
+            ),
+            None => self.data().money.fmt(store),
+        }
+    }
+
+    /// Write the posting with the accn and the amount in columns `widths`
+    /// wide, by the width they show in.
+    fn fmt_aligned(
+        self,
+        f: &mut std::fmt::Formatter<'_>,
+        (accn, money): (usize, usize),
+    ) -> std::fmt::Result {
+        let name = self.accn().to_string();
         write!(
             f,
-            "    {:<60}{:>10}",
-            self.accn(),
-            self.data().money.fmt(&self.journal.currencies)
-        )
+            "    {}{}",
+            pad_right(&name, accn),
+            pad_left(&self.money_text(), money)
+        )?;
+        for tag in self.tags() {
+            write!(f, " #{}", tag)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PostingEntry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_aligned(f, (ACCN_WIDTH, MONEY_WIDTH))
     }
 }
 
@@ -69,19 +133,71 @@ pub(crate) struct TxnEntry<'a> {
 }
 
 impl<'a> TxnEntry<'a> {
-    fn data(&self) -> &TxnData {
+    fn data(&self) -> &'a TxnData {
         &self.journal.txns.txns[&self.txn]
     }
 
-    pub(super) fn date(&self) -> NaiveDate {
+    pub(crate) fn date(&self) -> NaiveDate {
         self.data().date
     }
 
-    pub(super) fn desc(&self) -> &str {
+    /// The narration, without the payee.
+    pub(crate) fn desc(&self) -> &str {
         &self.data().description
     }
 
-    fn postings(&self) -> impl Iterator<Item = PostingEntry<'_>> {
+    pub(crate) fn payee(&self) -> Option<&'a str> {
+        self.data().payee.as_deref()
+    }
+
+    /// The description as written, `payee | narration` if it has a payee.
+    pub(crate) fn title(&self) -> String {
+        match self.payee() {
+            Some(payee) => format!("{} | {}", payee, self.desc()),
+            None => self.desc().to_string(),
+        }
+    }
+
+    /// Whether the payee or the narration contains `search`, ignoring case.
+    pub(crate) fn mentions(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.payee()
+            .into_iter()
+            .chain([self.desc()])
+            .any(|part| part.to_lowercase().contains(&search))
+    }
+
+    /// Value of the first metadata entry with the given key.
+    pub(crate) fn meta(&self, key: &str) -> Option<&'a str> {
+        self.data()
+            .meta
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every metadata entry, in the order written.
+    pub(crate) fn metas(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.data()
+            .meta
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Who entered the txn, from its `author` metadata.
+    pub(crate) fn author(&self) -> Option<&'a str> {
+        self.meta(AUTHOR_META)
+    }
+
+    /// Time of day of the txn, from its `time` metadata, if it is valid.
+    pub(crate) fn time(&self) -> Option<NaiveTime> {
+        let time = self.meta(TIME_META)?.trim();
+        NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .ok()
+    }
+
+    pub(super) fn postings(&self) -> impl Iterator<Item = PostingEntry<'_>> {
         self.data()
             .postings
             .iter()
@@ -103,6 +219,37 @@ impl<'a> TxnEntry<'a> {
         TxnEntryBrief { entry: self }
     }
 
+    pub(crate) fn detailed(self) -> TxnEntryDetailed<'a> {
+        TxnEntryDetailed {
+            entry: self,
+            file: None,
+        }
+    }
+
+    /// Line of the journal file the txn was read from, if it was read.
+    pub(crate) fn line(&self) -> Option<usize> {
+        self.data().line
+    }
+
+    /// What the txn moves, as the sum of its positive postings.
+    pub(crate) fn amount(&self) -> Valuable {
+        self.postings()
+            .map(|p| p.data().money)
+            .filter(|money| money.amount().is_sign_positive())
+            .sum()
+    }
+
+    /// Every tag of any of the postings, without duplicates.
+    pub(crate) fn tags(&self) -> impl Iterator<Item = &'a str> {
+        let journal = self.journal;
+        self.data()
+            .postings
+            .iter()
+            .flat_map(move |posting| posting.into_posting(journal).tags())
+            .map(String::as_str)
+            .unique()
+    }
+
     fn income_statement(&self) -> impl Iterator<Item = PostingEntry<'_>> {
         let inc = self.journal.accns().income();
         let exp = self.journal.accns().expense();
@@ -119,13 +266,26 @@ impl From<TxnEntry<'_>> for Txn {
 
 impl Display for TxnEntry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{} {}\n{}",
-            self.data().date,
-            self.data().description,
-            self.postings().join("\n")
-        )
+        writeln!(f, "{} {}", self.data().date, self.title())?;
+        for (key, value) in &self.data().meta {
+            writeln!(f, "    ; {}: {}", key, value)?;
+        }
+        // wide enough for every posting, with room between accn and amount
+        let accn = self
+            .postings()
+            .map(|posting| width(&posting.accn().to_string()) + 2)
+            .fold(ACCN_WIDTH, usize::max);
+        let money = self
+            .postings()
+            .map(|posting| width(&posting.money_text()))
+            .fold(MONEY_WIDTH, usize::max);
+        for (i, posting) in self.postings().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            posting.fmt_aligned(f, (accn, money))?;
+        }
+        Ok(())
     }
 }
 
@@ -139,14 +299,84 @@ impl Display for TxnEntryBrief<'_> {
         let valuable: ValuableEntry = self.entry.income_statement().map(|p| p.money()).sum();
         write!(
             f,
-            "{} {:<50} {:>20}",
-            txn.data().date,
-            txn.data().description,
-            -valuable
+            "{} {} {}",
+            locale::date(txn.data().date),
+            pad_right(&txn.title(), 50),
+            pad_left(&(-valuable).to_string(), 20)
         )
     }
 }
 
+/// Everything known about a txn, for looking at a single one.
+pub(crate) struct TxnEntryDetailed<'a> {
+    entry: TxnEntry<'a>,
+    file: Option<&'a str>,
+}
+
+impl<'a> TxnEntryDetailed<'a> {
+    /// Name the file the txn's journal was read from.
+    pub(crate) fn in_file(mut self, file: &'a str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Change to the balance of every top level account.
+    fn impact(&self) -> BTreeMap<&'a str, ValuableEntry<'a>> {
+        let mut impact: BTreeMap<&str, ValuableEntry> = BTreeMap::new();
+        for posting in &self.entry.data().postings {
+            let posting = posting.into_posting(self.entry.journal);
+            let top = posting.accn().top_level().name();
+            *impact.entry(top).or_default() += posting.money();
+        }
+        impact
+    }
+}
+
+impl Display for TxnEntryDetailed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let txn = &self.entry;
+        writeln!(
+            f,
+            "{} {}",
+            locale::date(txn.date()).bold(),
+            txn.title().bold()
+        )?;
+        let source = match (self.file, txn.line()) {
+            (Some(file), Some(line)) => format!("{}:{}", file, line),
+            (None, Some(line)) => format!("line {}", line),
+            (_, None) => tr(Label::NotSavedYet).to_string(),
+        };
+        writeln!(f, "  {:<12}{}", tr(Label::Source), source)?;
+        let tags = txn.tags().map(|tag| format!("#{}", tag)).join(" ");
+        if !tags.is_empty() {
+            writeln!(f, "  {:<12}{}", tr(Label::Tags), tags)?;
+        }
+        for (key, value) in &txn.data().meta {
+            writeln!(f, "  {:<12}{}", key, value)?;
+        }
+
+        writeln!(f, "  {}", tr(Label::Postings))?;
+        for posting in txn.postings() {
+            writeln!(
+                f,
+                "    {}{}",
+                pad_right(&posting.accn().abs_name(), 50),
+                pad_left(&posting.money().to_string(), 15)
+            )?;
+        }
+        write!(f, "  {}", tr(Label::Impact))?;
+        for (top, change) in self.impact() {
+            write!(
+                f,
+                "\n    {}{}",
+                pad_right(top, 50),
+                pad_left(&change.to_string(), 15)
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl<'a> Deref for TxnEntryBrief<'a> {
     type Target = TxnEntry<'a>;
     fn deref(&self) -> &Self::Target {
@@ -167,4 +397,60 @@ impl<'a> TxnEntryMut<'a> {
     pub(crate) fn remove(self) {
         self.journal.txns.remove(self.txn);
     }
+
+    /// Set the metadata entry with the given key, replacing the first one.
+    pub(crate) fn set_meta(self, key: &str, value: impl Into<String>) -> Self {
+        let meta = &mut self.journal.txns.txns.get_mut(&self.txn).unwrap().meta;
+        match meta.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.into(),
+            None => meta.push((key.to_string(), value.into())),
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detailed() {
+        let input =
+            "\n2024-03-01 tiles\n    ; project: reno\n    expense:home  $300 #diy\n    asset:bank";
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let txn = journal.txns().next().unwrap();
+        assert_eq!(txn.line(), Some(2));
+
+        colored::control::set_override(false);
+        let detailed = txn.detailed().in_file("home.coin").to_string();
+        assert!(detailed.contains("source      home.coin:2\n"));
+        assert!(detailed.contains("tags        #diy\n"));
+        assert!(detailed.contains("project     reno\n"));
+        assert!(detailed.ends_with(&format!("    {:<50}{:>15}", "expense", "$300")));
+    }
+
+    #[test]
+    fn test_aligned() {
+        let input = "2024-03-01 ramen
+    expense:食費  ¥1200
+    asset:現金";
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let txn = journal.txns().next().unwrap().to_string();
+        let ends = txn.lines().skip(1).map(width).collect_vec();
+        assert_eq!(ends, [74, 74]);
+
+        // a long accn or amount widens the columns of every posting
+        let long = "expense:a-rather-long-accn-name-that-goes-past-sixty-columns-wide";
+        let input = format!(
+            "2024-03-01 fee
+    {}  12345678.001 USD
+    asset:bank",
+            long
+        );
+        let journal = Journal::from_str(&input).unwrap_or_else(|e| panic!("{:#}", e));
+        let txn = journal.txns().next().unwrap().to_string();
+        let lines = txn.lines().skip(1).collect_vec();
+        assert!(lines[0].starts_with(&format!("    {}  ", long)));
+        assert_eq!(width(lines[0]), width(lines[1]));
+    }
 }
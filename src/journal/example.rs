@@ -0,0 +1,140 @@
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use rust_decimal::Decimal;
+
+use super::Journal;
+
+/// Small deterministic generator, so the same seed gives the same journal
+/// on every machine.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next() % 100 < percent
+    }
+
+    /// `base` give or take `spread` percent, in cents.
+    fn around(&mut self, base: i64, spread: i64) -> Decimal {
+        let cents = base * 100;
+        let noise = cents * spread / 100;
+        let noise = match noise {
+            0 => 0,
+            noise => (self.next() % (2 * noise as u64 + 1)) as i64 - noise,
+        };
+        Decimal::new(cents + noise, 2)
+    }
+}
+
+/// Day to day spending: description, accn and a typical amount.
+const SPENDING: [(&str, &str, i64); 6] = [
+    ("groceries", "expense:food:groceries", 45),
+    ("coffee", "expense:food:coffee", 5),
+    ("lunch", "expense:food:dining", 15),
+    ("dinner out", "expense:food:dining", 60),
+    ("train ticket", "expense:transport", 8),
+    ("pharmacy", "expense:health", 20),
+];
+
+impl Journal {
+    /// A made up but plausible journal of `months` months from 2024 on:
+    /// a salary and rent every month, `txns_per_day` purchases a day with
+    /// noisy amounts, a card paid off monthly and a trip now and then.
+    /// The same `seed` always gives the same journal.
+    pub(crate) fn generate_example(seed: u64, months: u32, txns_per_day: usize) -> Journal {
+        let mut rng = SplitMix(seed);
+        let mut out = String::new();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = start + chrono::Months::new(months);
+
+        let mut txn = |date: NaiveDate, desc: &str, accn: &str, amount: Decimal, from: &str| {
+            writeln!(
+                out,
+                "{} {}\n    {}  ${}\n    {}\n",
+                date, desc, accn, amount, from
+            )
+            .unwrap();
+        };
+
+        let mut date = start;
+        let mut card = Decimal::ZERO;
+        while date < end {
+            match date.day() {
+                1 => {
+                    let salary = rng.around(4000, 2);
+                    txn(date, "salary", "asset:bank", salary, "income:salary");
+                }
+                2 => txn(
+                    date,
+                    "rent",
+                    "expense:rent",
+                    Decimal::new(1500, 0),
+                    "asset:bank",
+                ),
+                25 if !card.is_zero() => {
+                    txn(date, "card bill", "liability:card", card, "asset:bank");
+                    card = Decimal::ZERO;
+                }
+                15 if rng.chance(15) => {
+                    let flight = rng.around(350, 40);
+                    txn(date, "flight", "expense:travel", flight, "liability:card");
+                    let hotel = rng.around(120, 30) * Decimal::from(2 + rng.below(4));
+                    txn(date, "hotel", "expense:travel", hotel, "liability:card");
+                    card += flight + hotel;
+                }
+                _ => {}
+            }
+            for _ in 0..txns_per_day {
+                let (desc, accn, base) = SPENDING[rng.below(SPENDING.len())];
+                let amount = rng.around(base, 50);
+                match rng.chance(30) {
+                    true => txn(date, desc, accn, amount, "asset:cash"),
+                    false => {
+                        txn(date, desc, accn, amount, "liability:card");
+                        card += amount;
+                    }
+                }
+            }
+            date += Duration::days(1);
+        }
+
+        Journal::from_str(&out).expect("generated example journal must parse")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate_example() {
+        let journal = Journal::generate_example(7, 3, 2);
+        let text = journal.to_string();
+        assert_eq!(text, Journal::generate_example(7, 3, 2).to_string());
+        assert_ne!(text, Journal::generate_example(8, 3, 2).to_string());
+        // saving and reading back writes the same bytes
+        assert_eq!(text, Journal::from_str(&text).unwrap().to_string());
+
+        // 91 days of 2 purchases, 3 salaries, 3 rents and 2 or 3 card bills
+        let count = journal.txns().count();
+        assert!((190..=196).contains(&count), "{} txns", count);
+        assert!(journal.trial_balance().is_balanced());
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap();
+        assert!(journal
+            .balance(bank)
+            .into_iter()
+            .all(|m| m.amount() > Decimal::ZERO));
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+use anyhow::{bail, Result};
+use chrono::{Duration, NaiveDate};
+use colored::Colorize;
+use rust_decimal::Decimal;
+
+use crate::valuable::{Money, ProviderChain, RateProvider, Valuable};
+
+use super::Journal;
+
+/// Days back the change of each position is measured over.
+const CHANGE_DAYS: i64 = 30;
+
+/// Net position in one currency across the asset and liability accns.
+#[derive(Debug)]
+pub(crate) struct Position {
+    code: String,
+    amount: Decimal,
+    /// Change of the amount over the last 30 days.
+    change: Decimal,
+    /// The amount in the base currency.
+    value: Decimal,
+    /// Change of the value over the last 30 days, rates included.
+    value_change: Decimal,
+}
+
+/// Exposure to every currency held, as printed by `exposure`.
+#[derive(Debug)]
+pub(crate) struct Exposure {
+    date: NaiveDate,
+    base: String,
+    /// Digits values are shown with, the minor units of `base`.
+    units: u32,
+    positions: Vec<Position>,
+}
+
+impl Exposure {
+    /// Net worth in the base currency.
+    pub(crate) fn total(&self) -> Decimal {
+        self.positions.iter().map(|p| p.value).sum()
+    }
+
+    /// Share of the net worth held in `position`.
+    fn share(&self, position: &Position) -> Option<Decimal> {
+        let total = self.total();
+        (!total.is_zero()).then(|| position.value / total)
+    }
+}
+
+impl Journal {
+    /// Net position per currency at the end of `date`, valued in `base` at
+    /// the rates of `date` and compared with 30 days before.
+    pub(crate) fn exposure(
+        &self,
+        base: &str,
+        date: NaiveDate,
+        fallback: &dyn RateProvider,
+    ) -> Result<Exposure> {
+        let Some(currency) = self.currencies.get_by_code(base) else {
+            bail!("code {} not found", base);
+        };
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let before = date - Duration::days(CHANGE_DAYS);
+        let net = |date| {
+            self.balance_at(self.accns.asset(), date)
+                + self.balance_at(self.accns.liability(), date)
+        };
+        let amounts = |valuable: Valuable| -> BTreeMap<&str, Decimal> {
+            valuable
+                .into_iter()
+                .map(|money| (self.currencies.code(money.currency()), money.amount()))
+                .collect()
+        };
+        let (now, then) = (amounts(net(date)), amounts(net(before)));
+        let value = |code: &str, amount: Decimal, date| -> Result<Decimal> {
+            let currency = self.currencies.get_by_code(code).unwrap();
+            let money = Money::new(amount, currency).into_money(&self.currencies);
+            Ok(money.convert_to(base, date, &rates)?.money().amount())
+        };
+
+        let mut positions = Vec::new();
+        let codes: BTreeSet<&str> = now.keys().chain(then.keys()).copied().collect();
+        for code in codes {
+            let amount = now.get(code).copied().unwrap_or_default();
+            let earlier = then.get(code).copied().unwrap_or_default();
+            if amount.is_zero() && earlier.is_zero() {
+                continue;
+            }
+            let value_now = value(code, amount, date)?;
+            positions.push(Position {
+                code: code.to_string(),
+                amount,
+                change: amount - earlier,
+                value: value_now,
+                value_change: value_now - value(code, earlier, before)?,
+            });
+        }
+        positions.sort_by(|a, b| b.value.abs().cmp(&a.value.abs()).then(a.code.cmp(&b.code)));
+        Ok(Exposure {
+            date,
+            base: base.to_string(),
+            units: self.currencies.minor_units(currency),
+            positions,
+        })
+    }
+}
+
+impl Display for Exposure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<6}{:>16}{:>16}{:>16}{:>8}{:>16}",
+            "".bold(),
+            "position".bold(),
+            "30d".bold(),
+            self.base.bold(),
+            "share".bold(),
+            format!("30d {}", self.base).bold()
+        )?;
+        for position in &self.positions {
+            let share = match self.share(position) {
+                Some(share) => format!("{:.1}%", share * Decimal::ONE_HUNDRED),
+                None => "-".to_string(),
+            };
+            writeln!(
+                f,
+                "{:<6}{:>16}{:>16}{:>16}{:>8}{:>16}",
+                position.code,
+                position.amount.to_string(),
+                format!("{:+}", position.change),
+                position.value.round_dp(self.units).to_string(),
+                share,
+                format!("{:+}", position.value_change.round_dp(self.units)),
+            )?;
+        }
+        write!(
+            f,
+            "{:<38}{:>16}  as of {}",
+            "net worth".bold(),
+            self.total().round_dp(self.units).to_string(),
+            self.date
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"currency EUR € prefix
+rate 2024-01-01 EUR USD 1.1
+rate 2024-03-01 EUR USD 1.2
+
+2024-01-05 salary
+    asset:bank  $3000
+    income:salary
+
+2024-02-20 savings abroad
+    asset:eu-bank  €1000
+    income:gift
+
+2024-03-01 card
+    expense:food  $500
+    liability:card"#;
+
+    #[test]
+    fn test_exposure() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let exposure = journal.exposure("USD", date, &journal.rates).unwrap();
+        let positions = exposure
+            .positions
+            .iter()
+            .map(|p| (p.code.as_str(), p.amount, p.change, p.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                ("USD", dec!(2500), dec!(-500), dec!(2500)),
+                ("EUR", dec!(1000), dec!(1000), dec!(1200)),
+            ]
+        );
+        assert_eq!(exposure.total(), dec!(3700));
+        // the euros were not held 30 days ago, so all their value is new
+        assert_eq!(exposure.positions[1].value_change, dec!(1200));
+
+        // values in bitcoin keep their satoshis
+        let rates = "rate 2024-01-01 USD BTC 0.0000234567\nrate 2024-01-01 EUR BTC 0.0000256789\n";
+        let journal = Journal::from_str(&format!("{}{}", rates, INPUT)).unwrap();
+        let exposure = journal.exposure("BTC", date, &journal.rates).unwrap();
+        let text = exposure.to_string();
+        assert!(text.contains("0.05864175"), "{}", text);
+        assert!(text.contains("0.08432065  as of"), "{}", text);
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::valuable::{Money, ProviderChain, RateProvider};
+
+use super::{
+    link::{ID_META, LINK_META},
+    lots::{Booking, Disposal},
+    Journal, Txn, TxnBuilder,
+};
+
+/// Accn realized gains are booked to, a loss as negative income.
+const GAINS: [&str; 2] = ["income", "gains"];
+
+/// Gains realized by the sales of one year, as printed by `gains`.
+#[derive(Debug)]
+pub(crate) struct GainsReport {
+    year: i32,
+    base: String,
+    /// Digits amounts are shown and booked with, the minor units of `base`.
+    units: u32,
+    booking: Booking,
+    /// What each sale took from each lot, in the order sold.
+    disposals: Vec<Disposal>,
+}
+
+impl GainsReport {
+    fn total(&self, long_term: bool) -> Decimal {
+        self.disposals
+            .iter()
+            .filter(|disposal| disposal.is_long_term() == long_term)
+            .map(Disposal::gain)
+            .sum()
+    }
+
+    pub(crate) fn short_term(&self) -> Decimal {
+        self.total(false)
+    }
+
+    pub(crate) fn long_term(&self) -> Decimal {
+        self.total(true)
+    }
+}
+
+impl Journal {
+    /// Gains in `base` of every sale of a commodity in `year`, the lots
+    /// sold picked by `booking`.
+    pub(crate) fn realized_gains(
+        &self,
+        year: i32,
+        base: &str,
+        booking: Booking,
+        fallback: &dyn RateProvider,
+    ) -> Result<GainsReport> {
+        let Some(currency) = self.currencies.get_by_code(base) else {
+            bail!("code {} not found", base);
+        };
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let end = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
+        let mut disposals = Vec::new();
+        for commodity in self.commodities() {
+            let (_, sold) = self.book_lots(commodity, base, end, booking, &rates)?;
+            disposals.extend(sold.into_iter().filter(|d| d.sold.year() == year));
+        }
+        disposals.sort_by_key(|disposal| disposal.sold);
+        Ok(GainsReport {
+            year,
+            base: base.to_string(),
+            units: self.currencies.minor_units(currency),
+            booking,
+            disposals,
+        })
+    }
+
+    /// Book the gain or loss of every sale in the report not booked yet,
+    /// one txn per sale against the trading accn, linked to the sale by
+    /// its id.
+    pub(crate) fn book_gains(&mut self, report: &GainsReport) -> Result<Vec<Txn>> {
+        let base = self.currencies.get_by_code(&report.base).unwrap();
+        let gains = self
+            .accns
+            .root_mut()
+            .or_open_child(GAINS[0])
+            .or_open_child(GAINS[1])
+            .into_ref()
+            .id();
+        let trading = self.trading_accn();
+
+        let mut txns = Vec::new();
+        for sale in report.disposals.iter().map(|d| d.sale).unique() {
+            let gain: Decimal = report
+                .disposals
+                .iter()
+                .filter(|d| d.sale == sale)
+                .map(Disposal::gain)
+                .sum();
+            let gain = gain.round_dp(report.units);
+            if gain.is_zero() {
+                continue;
+            }
+            let id = self.sale_id(sale);
+            let booked = self.txns().any(|txn| {
+                txn.links().any(|link| link == id)
+                    && txn.postings().any(|posting| posting.accn().id() == gains)
+            });
+            if booked {
+                continue;
+            }
+            let sale = self.txn(sale);
+            let mut txn = TxnBuilder::new(sale.date(), format!("gain on {}", sale.title()));
+            txn.with_meta(LINK_META, id)
+                .with_posting(gains, Some(Money::new(-gain, base)))
+                .with_posting(trading, None);
+            txns.push(txn.build(&mut self.txns, &self.currencies)?);
+        }
+        Ok(txns)
+    }
+
+    /// Id of `sale` to link its gain to, given one if it has none.
+    fn sale_id(&mut self, sale: Txn) -> String {
+        if let Some(id) = self.txn(sale).meta(ID_META) {
+            return id.to_string();
+        }
+        let date = self.txn(sale).date().format("%Y%m%d");
+        let id = (1..)
+            .map(|n| match n {
+                1 => format!("sale-{}", date),
+                n => format!("sale-{}-{}", date, n),
+            })
+            .find(|id| !self.txns().any(|txn| txn.meta(ID_META) == Some(id)))
+            .unwrap();
+        self.txn_mut(sale).set_meta(ID_META, &id);
+        id
+    }
+}
+
+impl Display for GainsReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} realized gains in {}, lots booked {}",
+            self.year.to_string().bold(),
+            self.base,
+            self.booking
+        )?;
+        writeln!(
+            f,
+            "{:<12}{:<12}{:<8}{:>12}{:>14}{:>14}{:>14}  {}",
+            "sold".bold(),
+            "bought".bold(),
+            "".bold(),
+            "units".bold(),
+            "proceeds".bold(),
+            "cost".bold(),
+            "gain".bold(),
+            "term".bold()
+        )?;
+        for disposal in &self.disposals {
+            writeln!(
+                f,
+                "{:<12}{:<12}{:<8}{:>12}{:>14}{:>14}{:>14}  {}",
+                disposal.sold.to_string(),
+                disposal.bought.to_string(),
+                disposal.code,
+                disposal.units.normalize().to_string(),
+                disposal.proceeds.round_dp(self.units).to_string(),
+                disposal.cost.round_dp(self.units).to_string(),
+                format!("{:+}", disposal.gain().round_dp(self.units)),
+                match disposal.is_long_term() {
+                    true => "long",
+                    false => "short",
+                }
+            )?;
+        }
+        write!(
+            f,
+            "{:<44}{:>14}\n{:<44}{:>14}",
+            "short term".bold(),
+            format!("{:+}", self.short_term().round_dp(self.units)),
+            "long term".bold(),
+            format!("{:+}", self.long_term().round_dp(self.units)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"currency AAPL
+
+2023-01-05 buy apple
+    asset:broker  10 AAPL
+    asset:bank  -$1000
+    equity:trading
+
+2024-03-01 buy apple
+    asset:broker  10 AAPL
+    asset:bank  -$1500
+    equity:trading
+
+2024-06-01 sell apple
+    ; lot: 2024-03-01
+    asset:broker  -15 AAPL
+    asset:bank  $3000
+    equity:trading"#;
+
+    #[test]
+    fn test_realized_gains() {
+        let mut journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let rates = ProviderChain::default();
+        let gains = |journal: &Journal, booking| {
+            let report = journal
+                .realized_gains(2024, "USD", booking, &rates)
+                .unwrap();
+            (report.short_term(), report.long_term())
+        };
+        // 10 bought in 2023 at $100 and 5 in 2024 at $150, sold at $200
+        assert_eq!(gains(&journal, Booking::Fifo), (dec!(250), dec!(1000)));
+        // 10 bought in 2024 and 5 in 2023
+        assert_eq!(gains(&journal, Booking::Lifo), (dec!(500), dec!(500)));
+        assert_eq!(gains(&journal, Booking::Specific), (dec!(500), dec!(500)));
+        // every unit at the average cost of $125
+        let (short, long) = gains(&journal, Booking::Average);
+        assert_eq!(short + long, dec!(1125));
+        assert!(journal
+            .realized_gains(2023, "USD", Booking::Fifo, &rates)
+            .unwrap()
+            .disposals
+            .is_empty());
+
+        let report = journal
+            .realized_gains(2024, "USD", Booking::Fifo, &rates)
+            .unwrap();
+        let txns = journal.book_gains(&report).unwrap();
+        assert_eq!(txns.len(), 1);
+        let balance = |name: &str| {
+            let accn = journal.accns().by_name_unique(name).ok().unwrap();
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string()
+        };
+        assert_eq!(balance("gains"), "-$1250");
+        assert_eq!(
+            journal.txn(txns[0]).links().collect_vec(),
+            ["sale-20240601"]
+        );
+        assert!(journal.book_gains(&report).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_gains_in_yen() {
+        let input = r#"currency AAPL
+
+2024-01-05 buy apple
+    asset:broker  3 AAPL
+    asset:bank  -1000 JPY
+    equity:trading
+
+2024-06-01 sell apple
+    asset:broker  -1 AAPL
+    asset:bank  500 JPY
+    equity:trading"#;
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let report = journal
+            .realized_gains(2024, "JPY", Booking::Fifo, &ProviderChain::default())
+            .unwrap();
+        let text = report.to_string();
+        assert!(text.contains("+167"), "{}", text);
+        assert!(!text.contains("166.67"), "{}", text);
+    }
+}
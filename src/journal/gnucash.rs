@@ -0,0 +1,698 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use roxmltree::{Document, Node};
+use rusqlite::{Connection, OpenFlags};
+use rust_decimal::Decimal;
+
+use crate::{accn::Accn, valuable::Money};
+
+use super::{Imported, Journal, TxnBuilder};
+
+/// Metadata key keeping the guid of the GnuCash transaction a txn was
+/// imported from, so importing the same book again adds nothing.
+pub(crate) const GNUCASH_META: &str = "gnucash";
+
+/// Accn balancing the commodities of splits in an account of another
+/// commodity than their transaction, like GnuCash's own trading accounts.
+const TRADING: [&str; 2] = ["equity", "trading"];
+
+#[derive(Debug)]
+struct Account {
+    name: String,
+    kind: String,
+    code: Option<String>,
+    parent: Option<String>,
+}
+
+#[derive(Debug)]
+struct Split {
+    account: String,
+    memo: Option<String>,
+    /// Amount in the currency of the transaction.
+    value: Decimal,
+    /// Amount in the commodity of the account.
+    quantity: Decimal,
+}
+
+#[derive(Debug)]
+struct Transaction {
+    guid: String,
+    date: NaiveDate,
+    desc: String,
+    num: Option<String>,
+    code: String,
+    splits: Vec<Split>,
+}
+
+/// The accounts, transactions and prices of a GnuCash book, whichever way
+/// it is stored.
+#[derive(Debug, Default)]
+pub(crate) struct Book {
+    /// Accounts by guid, of the account tree only, not of scheduled
+    /// transactions.
+    accounts: HashMap<String, Account>,
+    transactions: Vec<Transaction>,
+    /// Price of one unit of the first code in the second.
+    prices: Vec<(NaiveDate, String, String, Decimal)>,
+}
+
+/// A commodity's mnemonic as a code, `BRK.B` as `BRKB`.
+pub(super) fn code(mnemonic: &str) -> Result<String> {
+    let code: String = mnemonic.chars().filter(char::is_ascii_alphabetic).collect();
+    match code.is_empty() {
+        true => bail!("commodity {} has no letters to name it by", mnemonic),
+        false => Ok(code.to_uppercase()),
+    }
+}
+
+/// Refuse commodities that would share a code, like `7203.T` and `T`, as
+/// their amounts would be merged into one currency.
+fn check_codes<'a>(mnemonics: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for mnemonic in mnemonics {
+        let code = code(mnemonic)?;
+        match seen.get(&code) {
+            Some(other) if *other != mnemonic => bail!(
+                "commodities {} and {} would both be {}, rename one in GnuCash",
+                other,
+                mnemonic,
+                code
+            ),
+            _ => seen.insert(code, mnemonic),
+        };
+    }
+    Ok(())
+}
+
+/// `name` as an accn name, like `Current Assets` as `current-assets`.
+fn accn_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '_' {
+            true => c,
+            false => '-',
+        })
+        .collect();
+    match name.starts_with(char::is_alphabetic) {
+        true => name,
+        false => format!("gnc-{}", name),
+    }
+}
+
+/// Dates like `2024-03-25 10:59:00 +0000`, or `20240325105900` in older
+/// sqlite books.
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    let date = s.trim().get(..10).unwrap_or(s);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(s.get(..8).unwrap_or(s), "%Y%m%d"))
+        .with_context(|| format!("invalid GnuCash date {}", s))
+}
+
+/// Fractions like `12550/100`.
+fn parse_fraction(s: &str) -> Result<Decimal> {
+    let (num, denom) = s.trim().split_once('/').unwrap_or((s.trim(), "1"));
+    let num: i64 = num
+        .parse()
+        .with_context(|| format!("invalid amount {}", s))?;
+    let denom: i64 = denom
+        .parse()
+        .with_context(|| format!("invalid amount {}", s))?;
+    fraction(num, denom)
+}
+
+fn fraction(num: i64, denom: i64) -> Result<Decimal> {
+    if denom == 0 {
+        bail!("amount {}/0 has no value", num);
+    }
+    Ok((Decimal::from(num) / Decimal::from(denom)).normalize())
+}
+
+fn child<'a>(node: Node<'a, 'a>, name: &str) -> Option<Node<'a, 'a>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
+
+/// Text of the child `name` of `node`, if not blank.
+fn text<'a>(node: Node<'a, 'a>, name: &str) -> Option<&'a str> {
+    let text = child(node, name)?.text()?.trim();
+    Some(text).filter(|text| !text.is_empty())
+}
+
+fn required<'a>(node: Node<'a, 'a>, name: &str) -> Result<&'a str> {
+    text(node, name).ok_or_else(|| anyhow!("{} without a {}", node.tag_name().name(), name))
+}
+
+/// Code of the commodity in the child `name`, from its `id`.
+fn commodity(node: Node, name: &str) -> Result<String> {
+    let commodity = child(node, name).ok_or_else(|| anyhow!("no {}", name))?;
+    code(required(commodity, "id")?)
+}
+
+/// Date of the timestamp in the child `name`.
+fn timestamp(node: Node, name: &str) -> Result<NaiveDate> {
+    let time = child(node, name).ok_or_else(|| anyhow!("no {}", name))?;
+    parse_date(required(time, "date")?)
+}
+
+impl Book {
+    /// Read a book saved as XML, compressed or not, or as sqlite.
+    pub(crate) fn read(path: &Path) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if bytes.starts_with(b"SQLite format 3\0") {
+            let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            return Self::from_sqlite(&db);
+        }
+        let xml = match bytes.starts_with(&[0x1f, 0x8b]) {
+            true => {
+                let mut xml = String::new();
+                GzDecoder::new(bytes.as_slice()).read_to_string(&mut xml)?;
+                xml
+            }
+            false => String::from_utf8(bytes)?,
+        };
+        Self::from_xml(&xml)
+    }
+
+    fn from_xml(xml: &str) -> Result<Self> {
+        let doc = Document::parse(xml).context("not a GnuCash XML book")?;
+        let book = doc
+            .descendants()
+            .find(|n| n.has_tag_name("book"))
+            .unwrap_or(doc.root_element());
+        // commodities are referred to by a space and an id wherever they are
+        check_codes(
+            book.descendants()
+                .filter(|n| child(*n, "space").is_some())
+                .filter_map(|n| text(n, "id")),
+        )?;
+
+        let mut accounts = HashMap::new();
+        for account in book.children().filter(|n| n.has_tag_name("account")) {
+            accounts.insert(
+                required(account, "id")?.to_string(),
+                Account {
+                    name: required(account, "name")?.to_string(),
+                    kind: required(account, "type")?.to_string(),
+                    code: commodity(account, "commodity").ok(),
+                    parent: text(account, "parent").map(str::to_string),
+                },
+            );
+        }
+
+        let mut transactions = Vec::new();
+        for txn in book.children().filter(|n| n.has_tag_name("transaction")) {
+            let guid = required(txn, "id")?;
+            let in_txn = |e: anyhow::Error| e.context(format!("in transaction {}", guid));
+            let splits = child(txn, "splits").into_iter().flat_map(|s| s.children());
+            let splits = splits
+                .filter(|n| n.has_tag_name("split"))
+                .map(|split| {
+                    Ok(Split {
+                        account: required(split, "account")?.to_string(),
+                        memo: text(split, "memo").map(str::to_string),
+                        value: parse_fraction(required(split, "value")?)?,
+                        quantity: parse_fraction(required(split, "quantity")?)?,
+                    })
+                })
+                .collect::<Result<_>>()
+                .map_err(in_txn)?;
+            transactions.push(Transaction {
+                guid: guid.to_string(),
+                date: timestamp(txn, "date-posted").map_err(in_txn)?,
+                desc: text(txn, "description").unwrap_or_default().to_string(),
+                num: text(txn, "num").map(str::to_string),
+                code: commodity(txn, "currency").map_err(in_txn)?,
+                splits,
+            });
+        }
+
+        let prices = child(book, "pricedb")
+            .into_iter()
+            .flat_map(|db| db.children());
+        let prices = prices
+            .filter(|n| n.has_tag_name("price"))
+            .map(|price| {
+                Ok((
+                    timestamp(price, "time")?,
+                    commodity(price, "commodity")?,
+                    commodity(price, "currency")?,
+                    parse_fraction(required(price, "value")?)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            accounts,
+            transactions,
+            prices,
+        }
+        .without_templates())
+    }
+
+    fn from_sqlite(db: &Connection) -> Result<Self> {
+        let mnemonics = db
+            .prepare("SELECT mnemonic FROM commodities")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        check_codes(mnemonics.iter().map(String::as_str))?;
+
+        let mut accounts = HashMap::new();
+        let mut query = db.prepare(
+            "SELECT a.guid, a.name, a.account_type, c.mnemonic, a.parent_guid
+             FROM accounts a LEFT JOIN commodities c ON a.commodity_guid = c.guid",
+        )?;
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            let code = row
+                .get::<_, Option<String>>(3)?
+                .map(|c| code(&c))
+                .transpose();
+            accounts.insert(
+                row.get(0)?,
+                Account {
+                    name: row.get(1)?,
+                    kind: row.get(2)?,
+                    code: code.ok().flatten(),
+                    parent: row.get(4)?,
+                },
+            );
+        }
+
+        let mut splits: HashMap<String, Vec<Split>> = HashMap::new();
+        let mut query = db.prepare(
+            "SELECT tx_guid, account_guid, memo, value_num, value_denom, quantity_num,
+             quantity_denom FROM splits",
+        )?;
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            splits.entry(row.get(0)?).or_default().push(Split {
+                account: row.get(1)?,
+                memo: row.get::<_, Option<String>>(2)?.filter(|m| !m.is_empty()),
+                value: fraction(row.get(3)?, row.get(4)?)?,
+                quantity: fraction(row.get(5)?, row.get(6)?)?,
+            });
+        }
+
+        let mut transactions = Vec::new();
+        let mut query = db.prepare(
+            "SELECT t.guid, t.post_date, t.description, t.num, c.mnemonic
+             FROM transactions t JOIN commodities c ON t.currency_guid = c.guid",
+        )?;
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            let guid: String = row.get(0)?;
+            let date: String = row.get(1)?;
+            transactions.push(Transaction {
+                date: parse_date(&date).with_context(|| format!("in transaction {}", guid))?,
+                desc: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                num: row.get::<_, Option<String>>(3)?.filter(|n| !n.is_empty()),
+                code: code(&row.get::<_, String>(4)?)?,
+                splits: splits.remove(&guid).unwrap_or_default(),
+                guid,
+            });
+        }
+
+        let mut prices = Vec::new();
+        let mut query = db.prepare(
+            "SELECT p.date, c.mnemonic, cur.mnemonic, p.value_num, p.value_denom
+             FROM prices p JOIN commodities c ON p.commodity_guid = c.guid
+             JOIN commodities cur ON p.currency_guid = cur.guid",
+        )?;
+        let mut rows = query.query([])?;
+        while let Some(row) = rows.next()? {
+            prices.push((
+                parse_date(&row.get::<_, String>(0)?)?,
+                code(&row.get::<_, String>(1)?)?,
+                code(&row.get::<_, String>(2)?)?,
+                fraction(row.get(3)?, row.get(4)?)?,
+            ));
+        }
+
+        Ok(Self {
+            accounts,
+            transactions,
+            prices,
+        }
+        .without_templates())
+    }
+
+    /// Accounts from the root of the account tree down, without the root.
+    fn path(&self, guid: &str) -> Option<Vec<&Account>> {
+        let mut path = Vec::new();
+        let mut account = self.accounts.get(guid)?;
+        while account.kind != "ROOT" {
+            path.push(account);
+            account = self.accounts.get(account.parent.as_deref()?)?;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// The book without the accounts and transactions GnuCash keeps for
+    /// scheduled transactions, which live under a root of their own.
+    fn without_templates(mut self) -> Self {
+        let root = self
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.kind == "ROOT")
+            .find(|(_, account)| account.name != "Template Root")
+            .map(|(guid, _)| guid.clone());
+        let in_tree: HashSet<String> = self
+            .accounts
+            .keys()
+            .filter(|guid| {
+                std::iter::successors(Some(guid.as_str()), |guid| {
+                    self.accounts.get(*guid)?.parent.as_deref()
+                })
+                .any(|guid| Some(guid) == root.as_deref())
+            })
+            .cloned()
+            .collect();
+        self.accounts.retain(|guid, _| in_tree.contains(guid));
+        self.transactions
+            .retain(|txn| txn.splits.iter().all(|s| in_tree.contains(&s.account)));
+        self
+    }
+}
+
+/// Root accn of accounts of the GnuCash type `kind`.
+fn root_of(kind: &str) -> &'static str {
+    match kind {
+        "CREDIT" | "LIABILITY" | "PAYABLE" => "liability",
+        "INCOME" => "income",
+        "EXPENSE" => "expense",
+        "EQUITY" | "TRADING" => "equity",
+        _ => "asset",
+    }
+}
+
+impl Journal {
+    /// Accn balancing commodities bought or sold against a currency.
+    pub(super) fn trading_accn(&mut self) -> Accn {
+        self.accns
+            .root_mut()
+            .or_open_child(TRADING[0])
+            .or_open_child(TRADING[1])
+            .into_ref()
+            .id()
+    }
+
+    /// Accn the GnuCash account `guid` maps to, opened if new. A top level
+    /// account named like the root of its type, like `Assets`, is that root.
+    fn gnucash_accn(&mut self, book: &Book, guid: &str) -> Result<Accn> {
+        let path = book
+            .path(guid)
+            .ok_or_else(|| anyhow!("account {} is not in the account tree", guid))?;
+        let top = path
+            .first()
+            .ok_or_else(|| anyhow!("splits in the root account"))?;
+        let root = root_of(&top.kind);
+        let mut accn = self.accns.root_mut().or_open_child(root);
+        let same_as_root = matches!(
+            accn_name(&top.name).as_str(),
+            "assets" | "liabilities" | "income" | "expenses" | "equity"
+        );
+        for account in path.iter().skip(same_as_root as usize) {
+            accn = accn
+                .or_open_child(&accn_name(&account.name))
+                .declare_open(None, account.code.as_deref());
+        }
+        Ok(accn.into_ref().id())
+    }
+
+    /// Import the whole history of a GnuCash book. Its commodities become
+    /// currencies, its prices rates, and its accounts accns under the root
+    /// of their type. A split in an account of another commodity than its
+    /// transaction is balanced through `equity:trading`.
+    pub(crate) fn import_gnucash(&mut self, book: &Book) -> Result<Imported> {
+        self.importing(|journal, import| journal.read_gnucash(book, import))
+    }
+
+    fn read_gnucash(&mut self, book: &Book, import: &mut Imported) -> Result<()> {
+        let codes = book
+            .accounts
+            .values()
+            .filter_map(|account| account.code.as_deref())
+            .chain(book.transactions.iter().map(|txn| txn.code.as_str()))
+            .chain(
+                book.prices
+                    .iter()
+                    .flat_map(|(_, from, to, _)| [from, to].map(String::as_str)),
+            )
+            .collect::<HashSet<_>>();
+        for code in codes {
+            if self.currencies.get_by_code(code).is_none() {
+                self.currencies.declare(code, None, None);
+            }
+        }
+        for (date, from, to, price) in &book.prices {
+            self.rates.insert(*date, from, to, *price);
+        }
+
+        let imported: HashSet<String> = self
+            .txns()
+            .filter_map(|txn| txn.meta(GNUCASH_META).map(str::to_string))
+            .collect();
+        let mut accns = HashMap::new();
+        let mut transactions = book.transactions.iter().collect::<Vec<_>>();
+        transactions.sort_by_key(|txn| txn.date);
+
+        for txn in transactions {
+            if imported.contains(&txn.guid) {
+                import.skipped += 1;
+                continue;
+            }
+            let in_txn = || format!("in GnuCash transaction {}", txn.guid);
+            let currency = self.currencies.get_by_code(&txn.code).unwrap();
+
+            let mut builder = TxnBuilder::new(txn.date, txn.desc.clone());
+            builder.with_meta(GNUCASH_META, &txn.guid);
+            if let Some(num) = &txn.num {
+                builder.with_meta("num", num);
+            }
+            let splits = txn
+                .splits
+                .iter()
+                .filter(|s| !s.value.is_zero() || !s.quantity.is_zero());
+            for split in splits {
+                let accn = match accns.get(&split.account) {
+                    Some(accn) => *accn,
+                    None => {
+                        let accn = self
+                            .gnucash_accn(book, &split.account)
+                            .with_context(in_txn)?;
+                        *accns.entry(split.account.clone()).or_insert(accn)
+                    }
+                };
+                let code = book.accounts[&split.account].code.as_deref();
+                let value = Money::new(split.value, currency);
+                match code.filter(|code| *code != txn.code) {
+                    None => builder.with_posting_combined(accn, Some(value)),
+                    Some(code) => {
+                        let commodity = self.currencies.get_by_code(code).unwrap();
+                        let quantity = Money::new(split.quantity, commodity);
+                        let trading = self.trading_accn();
+                        builder
+                            .with_posting(accn, Some(quantity))
+                            .with_posting_combined(trading, Some(-quantity))
+                            .with_posting_combined(trading, Some(value))
+                    }
+                };
+                if let Some(memo) = &split.memo {
+                    builder.with_meta("memo", memo);
+                }
+            }
+            let txn = builder
+                .build(&mut self.txns, &self.currencies)
+                .with_context(in_txn)?;
+            import.added.push(txn);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BOOK: &str = r#"<?xml version="1.0" encoding="utf-8" ?>
+<gnc-v2 xmlns:gnc="http://www.gnucash.org/XML/gnc" xmlns:act="http://www.gnucash.org/XML/act"
+  xmlns:book="http://www.gnucash.org/XML/book" xmlns:cmdty="http://www.gnucash.org/XML/cmdty"
+  xmlns:price="http://www.gnucash.org/XML/price" xmlns:split="http://www.gnucash.org/XML/split"
+  xmlns:trn="http://www.gnucash.org/XML/trn" xmlns:ts="http://www.gnucash.org/XML/ts">
+<gnc:book version="2.0.0">
+<book:id type="guid">b0</book:id>
+<gnc:pricedb version="1">
+  <price>
+    <price:commodity><cmdty:space>NASDAQ</cmdty:space><cmdty:id>AAPL</cmdty:id></price:commodity>
+    <price:currency><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></price:currency>
+    <price:time><ts:date>2024-03-01 10:59:00 +0000</ts:date></price:time>
+    <price:value>18000/100</price:value>
+  </price>
+</gnc:pricedb>
+<gnc:account version="2.0.0">
+  <act:name>Root Account</act:name><act:id type="guid">r0</act:id><act:type>ROOT</act:type>
+</gnc:account>
+<gnc:account version="2.0.0">
+  <act:name>Assets</act:name><act:id type="guid">a0</act:id><act:type>ASSET</act:type>
+  <act:commodity><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></act:commodity>
+  <act:parent type="guid">r0</act:parent>
+</gnc:account>
+<gnc:account version="2.0.0">
+  <act:name>Checking Account</act:name><act:id type="guid">a1</act:id><act:type>BANK</act:type>
+  <act:commodity><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></act:commodity>
+  <act:parent type="guid">a0</act:parent>
+</gnc:account>
+<gnc:account version="2.0.0">
+  <act:name>Apple</act:name><act:id type="guid">a2</act:id><act:type>STOCK</act:type>
+  <act:commodity><cmdty:space>NASDAQ</cmdty:space><cmdty:id>AAPL</cmdty:id></act:commodity>
+  <act:parent type="guid">a0</act:parent>
+</gnc:account>
+<gnc:account version="2.0.0">
+  <act:name>Salary</act:name><act:id type="guid">i1</act:id><act:type>INCOME</act:type>
+  <act:commodity><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></act:commodity>
+  <act:parent type="guid">r0</act:parent>
+</gnc:account>
+<gnc:transaction version="2.0.0">
+  <trn:id type="guid">t1</trn:id>
+  <trn:currency><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></trn:currency>
+  <trn:date-posted><ts:date>2024-02-28 10:59:00 +0000</ts:date></trn:date-posted>
+  <trn:description>Paycheck</trn:description>
+  <trn:splits>
+    <trn:split><split:id type="guid">s1</split:id><split:value>300000/100</split:value>
+      <split:quantity>300000/100</split:quantity><split:account type="guid">a1</split:account></trn:split>
+    <trn:split><split:id type="guid">s2</split:id><split:value>-300000/100</split:value>
+      <split:quantity>-300000/100</split:quantity><split:account type="guid">i1</split:account></trn:split>
+  </trn:splits>
+</gnc:transaction>
+<gnc:transaction version="2.0.0">
+  <trn:id type="guid">t2</trn:id>
+  <trn:currency><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></trn:currency>
+  <trn:num>1001</trn:num>
+  <trn:date-posted><ts:date>2024-03-01 10:59:00 +0000</ts:date></trn:date-posted>
+  <trn:description>Buy Apple</trn:description>
+  <trn:splits>
+    <trn:split><split:id type="guid">s3</split:id><split:memo>10 shares</split:memo>
+      <split:value>180000/100</split:value><split:quantity>10/1</split:quantity>
+      <split:account type="guid">a2</split:account></trn:split>
+    <trn:split><split:id type="guid">s4</split:id><split:value>-180000/100</split:value>
+      <split:quantity>-180000/100</split:quantity><split:account type="guid">a1</split:account></trn:split>
+  </trn:splits>
+</gnc:transaction>
+</gnc:book>
+</gnc-v2>"#;
+
+    fn balance(journal: &Journal, name: &str) -> (String, String) {
+        let accn = journal.accns().by_name_unique(name).ok().unwrap();
+        (
+            accn.abs_name(),
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string(),
+        )
+    }
+
+    #[test]
+    fn test_import_gnucash_xml() {
+        let book = Book::from_xml(BOOK).unwrap();
+        let mut journal = Journal::from_str("").unwrap();
+        let import = journal.import_gnucash(&book).unwrap();
+        assert_eq!(import.added.len(), 2);
+        assert_eq!(
+            balance(&journal, "checking-account"),
+            ("asset:checking-account".into(), "$1200".into())
+        );
+        assert_eq!(
+            balance(&journal, "apple"),
+            ("asset:apple".into(), "10 AAPL".into())
+        );
+        assert_eq!(
+            balance(&journal, "salary"),
+            ("income:salary".into(), "-$3000".into())
+        );
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(
+            journal.rates.get("AAPL", "USD", date).unwrap(),
+            Decimal::from(180)
+        );
+
+        let mut journal = Journal::from_str(&journal.to_string()).unwrap();
+        let again = journal.import_gnucash(&book).unwrap();
+        assert_eq!((again.added.len(), again.skipped), (0, 2));
+    }
+
+    #[test]
+    fn test_colliding_codes() {
+        let book = BOOK.replacen(
+            "<cmdty:id>AAPL</cmdty:id></act:commodity>",
+            "<cmdty:id>A.APL</cmdty:id></act:commodity>",
+            1,
+        );
+        let err = Book::from_xml(&book).unwrap_err().to_string();
+        assert!(err.contains("AAPL and A.APL would both be AAPL"), "{}", err);
+
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE commodities (guid TEXT, mnemonic TEXT);
+             INSERT INTO commodities VALUES ('c1', '7203.T'), ('c2', 'T');",
+        )
+        .unwrap();
+        let err = Book::from_sqlite(&db).unwrap_err().to_string();
+        assert!(err.contains("7203.T and T would both be T"), "{}", err);
+    }
+
+    #[test]
+    fn test_import_gnucash_sqlite() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE commodities (guid TEXT, mnemonic TEXT);
+             CREATE TABLE accounts (guid TEXT, name TEXT, account_type TEXT,
+                 commodity_guid TEXT, parent_guid TEXT);
+             CREATE TABLE transactions (guid TEXT, currency_guid TEXT, num TEXT,
+                 post_date TEXT, description TEXT);
+             CREATE TABLE splits (guid TEXT, tx_guid TEXT, account_guid TEXT, memo TEXT,
+                 value_num INTEGER, value_denom INTEGER, quantity_num INTEGER,
+                 quantity_denom INTEGER);
+             CREATE TABLE prices (guid TEXT, commodity_guid TEXT, currency_guid TEXT,
+                 date TEXT, value_num INTEGER, value_denom INTEGER);
+             INSERT INTO commodities VALUES ('c1', 'EUR');
+             INSERT INTO accounts VALUES
+                 ('r0', 'Root Account', 'ROOT', NULL, NULL),
+                 ('x0', 'Template Root', 'ROOT', NULL, NULL),
+                 ('x1', 'rent', 'BANK', 'c1', 'x0'),
+                 ('a1', 'Wallet', 'CASH', 'c1', 'r0'),
+                 ('e0', 'Expenses', 'EXPENSE', 'c1', 'r0'),
+                 ('e1', 'Dining', 'EXPENSE', 'c1', 'e0');
+             INSERT INTO transactions VALUES
+                 ('t1', 'c1', '', '2024-01-05 10:59:00', 'Lunch'),
+                 ('t2', 'c1', '', '2024-01-06 10:59:00', 'scheduled rent');
+             INSERT INTO splits VALUES
+                 ('s1', 't1', 'e1', '', 1250, 100, 1250, 100),
+                 ('s2', 't1', 'a1', '', -1250, 100, -1250, 100),
+                 ('s3', 't2', 'x1', '', 0, 1, 0, 1);",
+        )
+        .unwrap();
+        let book = Book::from_sqlite(&db).unwrap();
+        let mut journal = Journal::from_str("").unwrap();
+        let import = journal.import_gnucash(&book).unwrap();
+        assert_eq!(import.added.len(), 1);
+        assert_eq!(
+            balance(&journal, "dining"),
+            ("expense:dining".into(), "€12.5".into())
+        );
+        assert_eq!(
+            balance(&journal, "wallet"),
+            ("asset:wallet".into(), "-€12.5".into())
+        );
+    }
+}
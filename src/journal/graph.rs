@@ -0,0 +1,238 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    str::FromStr,
+};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use crate::valuable::{Currency, Money, Valuable};
+
+use super::Journal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => bail!("unknown graph format {}, expected dot or mermaid", s),
+        }
+    }
+}
+
+/// Money moved between top level accns, like from `income` to `asset`.
+type Flows = BTreeMap<(String, String), Valuable>;
+
+impl Journal {
+    /// Money moved between the top level accns by the txns dated within
+    /// `since..=until`. Within a txn, what the accns giving money lose is
+    /// matched greedily against what the receiving ones gain.
+    pub(crate) fn flows(&self, since: Option<NaiveDate>, until: Option<NaiveDate>) -> Flows {
+        let in_range = |date: NaiveDate| {
+            since.is_none_or(|since| date >= since) && until.is_none_or(|until| date <= until)
+        };
+
+        let mut flows = Flows::new();
+        for txn in self.txns().filter(|txn| in_range(txn.date())) {
+            let mut changes: HashMap<Currency, BTreeMap<&str, Decimal>> = HashMap::new();
+            for posting in txn.postings() {
+                let money = posting.money().money();
+                *changes
+                    .entry(money.currency())
+                    .or_default()
+                    .entry(posting.accn().top_level().name())
+                    .or_default() += money.amount();
+            }
+
+            for (currency, changes) in changes {
+                let (mut sources, mut sinks): (Vec<_>, Vec<_>) = changes
+                    .into_iter()
+                    .filter(|(_, change)| !change.is_zero())
+                    .partition(|(_, change)| change.is_sign_negative());
+                sources
+                    .iter_mut()
+                    .for_each(|(_, change)| *change = -*change);
+
+                let (mut i, mut j) = (0, 0);
+                while i < sources.len() && j < sinks.len() {
+                    let amount = sources[i].1.min(sinks[j].1);
+                    let key = (sources[i].0.to_string(), sinks[j].0.to_string());
+                    *flows.entry(key).or_default() += Money::new(amount, currency);
+                    sources[i].1 -= amount;
+                    sinks[j].1 -= amount;
+                    i += sources[i].1.is_zero() as usize;
+                    j += sinks[j].1.is_zero() as usize;
+                }
+            }
+        }
+        flows
+    }
+
+    /// The accn tree and the flows between top level accns, for rendering
+    /// with graphviz or mermaid.
+    pub(crate) fn graph(
+        &self,
+        format: GraphFormat,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> String {
+        let flows = self.flows(since, until);
+        let accns = self
+            .accns
+            .accns()
+            .filter(|accn| !accn.abs_name().is_empty())
+            .sorted_by_key(|accn| accn.abs_name())
+            .collect_vec();
+
+        // the widest flow of every currency gets the thickest line
+        let mut widest: HashMap<Currency, Decimal> = HashMap::new();
+        for money in flows.values().flat_map(|v| v.clone()) {
+            let widest = widest.entry(money.currency()).or_default();
+            *widest = (*widest).max(money.amount());
+        }
+        let width = |flow: &Valuable| {
+            flow.clone()
+                .into_iter()
+                .filter_map(|money| (money.amount() / widest[&money.currency()]).to_f64())
+                .fold(0.0, f64::max)
+                * 4.0
+                + 1.0
+        };
+        let label = |flow: &Valuable| flow.clone().into_valuable(&self.currencies).to_string();
+
+        let mut out = String::new();
+        match format {
+            GraphFormat::Dot => {
+                writeln!(out, "digraph coinjar {{").unwrap();
+                writeln!(out, "    rankdir=LR;").unwrap();
+                for accn in &accns {
+                    writeln!(
+                        out,
+                        "    \"{}\" [label=\"{}\"];",
+                        accn.abs_name(),
+                        accn.name()
+                    )
+                    .unwrap();
+                }
+                for accn in &accns {
+                    for child in accn.children().sorted_by_key(|child| child.name()) {
+                        writeln!(
+                            out,
+                            "    \"{}\" -> \"{}\" [style=dashed, arrowhead=none];",
+                            accn.abs_name(),
+                            child.abs_name()
+                        )
+                        .unwrap();
+                    }
+                }
+                for ((from, to), flow) in &flows {
+                    writeln!(
+                        out,
+                        "    \"{}\" -> \"{}\" [label=\"{}\", penwidth={:.1}];",
+                        from,
+                        to,
+                        label(flow),
+                        width(flow)
+                    )
+                    .unwrap();
+                }
+                write!(out, "}}").unwrap();
+            }
+            GraphFormat::Mermaid => {
+                // mermaid ids cannot contain colons
+                let id = |name: &str| name.replace(':', "_");
+                writeln!(out, "flowchart LR").unwrap();
+                for accn in &accns {
+                    writeln!(out, "    {}[\"{}\"]", id(&accn.abs_name()), accn.name()).unwrap();
+                }
+                for accn in &accns {
+                    for child in accn.children().sorted_by_key(|child| child.name()) {
+                        writeln!(
+                            out,
+                            "    {} -.- {}",
+                            id(&accn.abs_name()),
+                            id(&child.abs_name())
+                        )
+                        .unwrap();
+                    }
+                }
+                let flows = flows
+                    .iter()
+                    .map(|((from, to), flow)| {
+                        format!("    {} ==>|\"{}\"| {}", id(from), label(flow), id(to))
+                    })
+                    .join("\n");
+                write!(out, "{}", flows).unwrap();
+            }
+        }
+        out.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-05 salary
+    asset:bank  $3000
+    income:salary
+
+2024-01-10 rent
+    expense:rent  $1200
+    asset:bank
+
+2024-01-12 dinner on card
+    expense:food  $50
+    liability:card
+
+2024-02-01 rent
+    expense:rent  $1200
+    asset:bank"#;
+
+    #[test]
+    fn test_flows() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 1, 31);
+        let flows = journal
+            .flows(None, until)
+            .into_iter()
+            .map(|((from, to), flow)| {
+                let flow = flow.into_valuable(journal.currencies()).to_string();
+                (format!("{} -> {}", from, to), flow)
+            })
+            .collect_vec();
+        assert_eq!(
+            flows,
+            [
+                ("asset -> expense".to_string(), "$1200".to_string()),
+                ("income -> asset".to_string(), "$3000".to_string()),
+                ("liability -> expense".to_string(), "$50".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_graph() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let dot = journal.graph(GraphFormat::Dot, None, None);
+        assert!(dot.starts_with("digraph coinjar {"));
+        assert!(dot.contains("\"asset\" -> \"asset:bank\" [style=dashed, arrowhead=none];"));
+        assert!(dot.contains("\"asset\" -> \"expense\" [label=\"$2400\", penwidth=4.2];"));
+
+        let mermaid = journal.graph(GraphFormat::Mermaid, None, None);
+        assert!(mermaid.contains("asset_bank[\"bank\"]"));
+        assert!(mermaid.contains("income ==>|\"$3000\"| asset"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::{BTreeMap, HashSet};
+
+use rust_decimal::Decimal;
+
+use super::{Journal, Txn};
+
+/// Limits on what one command may add at once before it has to be
+/// confirmed, against a bad mapping or generator flooding the journal.
+#[derive(Debug, Clone)]
+pub(crate) struct BulkLimits {
+    /// Txns added at once.
+    pub(crate) max_txns: Option<usize>,
+    /// Postings added at once to any one accn.
+    pub(crate) max_accn_postings: Option<usize>,
+    /// Change of the balance of any one accn, in any one currency.
+    pub(crate) max_change: Option<Decimal>,
+}
+
+impl Default for BulkLimits {
+    fn default() -> Self {
+        Self {
+            max_txns: Some(100),
+            max_accn_postings: None,
+            max_change: None,
+        }
+    }
+}
+
+impl Journal {
+    /// How `txns`, just added in one go, go over `limits`, one line per
+    /// limit and accn.
+    pub(crate) fn exceeded(&self, limits: &BulkLimits, txns: &[Txn]) -> Vec<String> {
+        let mut exceeded = Vec::new();
+        if let Some(max) = limits.max_txns.filter(|max| txns.len() > *max) {
+            exceeded.push(format!("{} txns, over the limit of {}", txns.len(), max));
+        }
+
+        let mut postings: BTreeMap<String, usize> = BTreeMap::new();
+        let mut changes: BTreeMap<(String, String), Decimal> = BTreeMap::new();
+        let added: HashSet<Txn> = txns.iter().copied().collect();
+        for posting in self.postings().filter(|p| added.contains(&p.txn().id())) {
+            let accn = posting.accn().to_string();
+            let money = posting.money();
+            *changes
+                .entry((accn.clone(), money.code().to_string()))
+                .or_default() += money.money().amount();
+            *postings.entry(accn).or_default() += 1;
+        }
+
+        if let Some(max) = limits.max_accn_postings {
+            for (accn, count) in postings.into_iter().filter(|(_, count)| *count > max) {
+                exceeded.push(format!(
+                    "{} postings to {}, over the limit of {}",
+                    count, accn, max
+                ));
+            }
+        }
+        if let Some(max) = limits.max_change {
+            for ((accn, code), change) in changes.into_iter().filter(|(_, c)| c.abs() > max) {
+                exceeded.push(format!(
+                    "{} changes by {} {}, over the limit of {}",
+                    accn, change, code, max
+                ));
+            }
+        }
+        exceeded
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+
+    use crate::valuable::Money;
+
+    use super::*;
+
+    #[test]
+    fn test_exceeded() {
+        let mut journal = Journal::from_str("open asset:bank\nopen expense:rent").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        let rent = journal.accns().by_name_unique("rent").ok().unwrap().id();
+        let money = journal.parse_money("$900").unwrap().money();
+        let txns = (0..3)
+            .map(|_| {
+                let txn = journal.new_txn(date, "rent".to_string());
+                let txn = txn.with_posting(rent, Some(money));
+                txn.with_posting(bank, None::<Money>).build().unwrap().id()
+            })
+            .collect::<Vec<_>>();
+
+        assert!(journal.exceeded(&BulkLimits::default(), &txns).is_empty());
+        let limits = BulkLimits {
+            max_txns: Some(2),
+            max_accn_postings: Some(3),
+            max_change: Some(dec!(2000)),
+        };
+        assert_eq!(
+            journal.exceeded(&limits, &txns),
+            [
+                "3 txns, over the limit of 2",
+                "asset:bank changes by -2700 USD, over the limit of 2000",
+                "expense:rent changes by 2700 USD, over the limit of 2000",
+            ]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    period::{Period, PeriodBucketer},
+    valuable::{ProviderChain, RateProvider, Valuable},
+};
+
+use super::Journal;
+
+/// Shades from no spending to the most, for the cells of the heatmap.
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// Spending per day of the week and of the month, as printed by `heatmap`.
+pub(crate) struct Heatmap {
+    code: String,
+    /// Digits amounts are shown with, the minor units of `code`.
+    units: u32,
+    /// Average spent on each weekday from Monday, over the days in range.
+    weekdays: [Decimal; 7],
+    /// Average spent on each day of the month from the 1st.
+    days: [Decimal; 31],
+}
+
+/// `amount` as a shade relative to `max`.
+fn shade(amount: Decimal, max: Decimal) -> char {
+    if max <= Decimal::ZERO || amount <= Decimal::ZERO {
+        return SHADES[0];
+    }
+    let steps = Decimal::from(SHADES.len() - 1);
+    let i = (amount / max * steps).ceil();
+    SHADES[usize::try_from(i).unwrap_or(0).min(SHADES.len() - 1)]
+}
+
+impl Journal {
+    /// Average spending per weekday and per day of the month from `since`
+    /// through `until`, in `code` at the rates of each day. Spending is what
+    /// is booked to expense accns, and days without any count as zero.
+    pub(crate) fn heatmap(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        code: &str,
+        fallback: &dyn RateProvider,
+    ) -> Result<Heatmap> {
+        let Some(currency) = self.currencies.get_by_code(code) else {
+            bail!("code {} not found", code);
+        };
+        if since > until {
+            bail!("{} is after {}", since, until);
+        }
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let expense = self.accns.expense();
+        let bucketer = PeriodBucketer::new(Period::Daily);
+        let postings = self
+            .postings()
+            .filter(|p| (since..=until).contains(&p.txn().date()))
+            .filter(|p| p.accn().is_descendent_of(expense));
+        let buckets = bucketer.bucket(postings, |p| p.txn().date());
+
+        let (mut weekdays, mut weekday_count) = ([Decimal::ZERO; 7], [0u32; 7]);
+        let (mut days, mut day_count) = ([Decimal::ZERO; 31], [0u32; 31]);
+        for day in bucketer.starts(since, until) {
+            let (weekday, of_month) = (
+                day.weekday().num_days_from_monday() as usize,
+                day.day0() as usize,
+            );
+            weekday_count[weekday] += 1;
+            day_count[of_month] += 1;
+            let Some(postings) = buckets.get(&day) else {
+                continue;
+            };
+            let spent: Valuable = postings.iter().map(|p| p.money().money()).sum();
+            let spent = self.value_in(spent, code, day, &rates)?;
+            weekdays[weekday] += spent;
+            days[of_month] += spent;
+        }
+        let average = |total: Decimal, count: u32| match count {
+            0 => Decimal::ZERO,
+            count => total / Decimal::from(count),
+        };
+        Ok(Heatmap {
+            code: code.to_string(),
+            units: self.currencies.minor_units(currency),
+            weekdays: std::array::from_fn(|i| average(weekdays[i], weekday_count[i])),
+            days: std::array::from_fn(|i| average(days[i], day_count[i])),
+        })
+    }
+}
+
+impl Display for Heatmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let max = self.weekdays.iter().max().copied().unwrap_or_default();
+        writeln!(
+            f,
+            "{}",
+            format!("average per weekday ({})", self.code).bold()
+        )?;
+        for (i, amount) in self.weekdays.iter().enumerate() {
+            let weekday = Weekday::try_from(i as u8).unwrap();
+            let bar = shade(*amount, max).to_string().repeat(4);
+            writeln!(
+                f,
+                "{:<4}{} {:>10}",
+                weekday,
+                bar,
+                amount.round_dp(self.units)
+            )?;
+        }
+
+        let max = self.days.iter().max().copied().unwrap_or_default();
+        writeln!(
+            f,
+            "\n{}",
+            format!("average per day of month ({})", self.code).bold()
+        )?;
+        let weeks = self.days.iter().enumerate().chunks(7);
+        let rows = weeks.into_iter().map(|week| {
+            week.map(|(i, amount)| {
+                format!("{:>2} {}", i + 1, shade(*amount, max).to_string().repeat(2))
+            })
+            .join("  ")
+        });
+        write!(f, "{}", rows.format("\n"))?;
+        let busiest = self.days.iter().position_max().map(|i| i + 1);
+        if let Some(day) = busiest.filter(|_| max > Decimal::ZERO) {
+            write!(
+                f,
+                "\nmost on day {}, {} {} on average",
+                day,
+                max.round_dp(self.units),
+                self.code
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-03-02 bar
+    expense:fun  $60
+    asset:bank
+
+2024-03-09 bar
+    expense:fun  $40
+    asset:bank
+
+2024-03-11 lunch
+    expense:food  $14
+    asset:bank
+
+2024-03-12 salary
+    asset:bank  $3000
+    income:salary"#;
+
+    #[test]
+    fn test_heatmap() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let since = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        let heatmap = journal
+            .heatmap(since, until, "USD", &journal.rates)
+            .unwrap();
+        // two saturdays in range, both at the bar
+        assert_eq!(heatmap.weekdays[5], dec!(50));
+        assert_eq!(heatmap.weekdays[0], dec!(7));
+        assert_eq!(heatmap.weekdays[1], Decimal::ZERO);
+        assert_eq!(heatmap.days[1], dec!(60));
+        assert_eq!(shade(dec!(50), dec!(50)), '█');
+        assert_eq!(shade(dec!(7), dec!(50)), '░');
+        assert!(heatmap
+            .to_string()
+            .ends_with("most on day 2, 60 USD on average"));
+
+        // yen are spent whole
+        let input = INPUT
+            .replace("$60", "1000 JPY")
+            .replace("$40", "1003 JPY")
+            .replace("$14", "7 JPY");
+        let journal = Journal::from_str(&input).unwrap();
+        let heatmap = journal
+            .heatmap(since, until, "JPY", &journal.rates)
+            .unwrap();
+        assert_eq!(heatmap.weekdays[5], dec!(1001.5));
+        let text = heatmap.to_string();
+        assert!(text.contains(" 1002\n"), "{}", text);
+    }
+}
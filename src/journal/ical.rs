@@ -0,0 +1,170 @@
+use std::{collections::HashMap, fmt::Write};
+
+use chrono::{NaiveDate, Utc};
+use itertools::Itertools;
+
+use super::Journal;
+
+/// Longest content line allowed by RFC 5545, in bytes.
+const LINE_LIMIT: usize = 75;
+
+impl Journal {
+    /// Txns dated from `since` on, like installments still to be paid, as an
+    /// iCalendar of all day events so they show up in calendar apps. Events
+    /// are identified by what their txn says, so exporting again updates
+    /// them in place rather than adding them twice.
+    pub(crate) fn ical(&self, since: NaiveDate, until: Option<NaiveDate>) -> String {
+        let stamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+        let txns = self
+            .txns()
+            .filter(|txn| txn.date() >= since && until.is_none_or(|until| txn.date() <= until))
+            .sorted_by_key(|txn| (txn.date(), txn.desc().to_string()));
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//coinjar//coinjar//EN".to_string(),
+        ];
+        // copies of the same txn, numbered after the first
+        let mut copies: HashMap<String, usize> = HashMap::new();
+        for txn in txns {
+            let date = txn.date();
+            let text = txn.to_string();
+            let copy = copies.entry(text.clone()).or_default();
+            let uid = match *copy {
+                0 => format!("{:016x}", stable_hash(&text)),
+                n => format!("{:016x}-{}", stable_hash(&text), n),
+            };
+            *copy += 1;
+            let postings = txn
+                .postings()
+                .map(|p| format!("{} {}", p.accn(), p.money()))
+                .join("\n");
+            lines.extend([
+                "BEGIN:VEVENT".to_string(),
+                format!("UID:{}@coinjar", uid),
+                format!("DTSTAMP:{}", stamp),
+                format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+                format!(
+                    "DTEND;VALUE=DATE:{}",
+                    date.succ_opt().unwrap().format("%Y%m%d")
+                ),
+                format!("SUMMARY:{}", escape(&txn.title())),
+                format!("DESCRIPTION:{}", escape(&postings)),
+                "END:VEVENT".to_string(),
+            ]);
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        let mut out = String::new();
+        for line in lines {
+            write!(out, "{}\r\n", fold(&line)).unwrap();
+        }
+        out
+    }
+}
+
+/// FNV-1a hash of `text`, unlike the std hashers the same from one build
+/// to the next.
+fn stable_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `text` with the characters iCalendar gives a meaning to escaped.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// `line` split into lines of at most `LINE_LIMIT` bytes, each continuation
+/// starting with a space, without splitting any character.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > LINE_LIMIT {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-10 rent
+    expense:rent  $1200
+    asset:bank
+
+2024-02-10 rent
+    expense:rent  $1200
+    asset:bank
+
+2024-02-10 phone, internet
+    expense:phone  $80
+    asset:bank"#;
+
+    #[test]
+    fn test_ical() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let ical = journal.ical(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), None);
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ical.matches("BEGIN:VEVENT").count(), 2);
+        let uids = ical
+            .lines()
+            .filter(|line| line.starts_with("UID:"))
+            .collect_vec();
+        assert_eq!(uids.len(), 2);
+        assert!(uids.iter().all_unique());
+        assert!(ical.contains("DTSTART;VALUE=DATE:20240210\r\n"));
+        assert!(ical.contains("DTEND;VALUE=DATE:20240211\r\n"));
+        assert!(ical.contains("SUMMARY:phone\\, internet\r\n"));
+        assert!(ical.lines().all(|line| line.len() <= LINE_LIMIT));
+    }
+
+    #[test]
+    fn test_uid_stable() {
+        let since = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let uids = |input: &str| {
+            let journal = Journal::from_str(input).unwrap();
+            journal
+                .ical(since, None)
+                .lines()
+                .filter(|line| line.starts_with("UID:"))
+                .map(str::to_string)
+                .sorted()
+                .collect_vec()
+        };
+        // a txn added on the same day leaves the others as they were
+        let more = uids(&format!(
+            "{}\n\n2024-02-10 gym\n    expense:gym  $40\n    asset:bank",
+            INPUT
+        ));
+        let before = uids(INPUT);
+        assert!(before.iter().all(|uid| more.contains(uid)));
+        assert_eq!(stable_hash(""), 0xcbf29ce484222325);
+        assert_eq!(stable_hash("a"), 0xaf63dc4c8601ec8c);
+
+        // a copy of a txn still gets a uid of its own
+        let twice =
+            INPUT.to_string() + "\n\n2024-02-10 rent\n    expense:rent  $1200\n    asset:bank";
+        assert!(uids(&twice).iter().all_unique());
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = "é".repeat(50);
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|line| line.len() <= LINE_LIMIT));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+use colored::Colorize;
+use itertools::Itertools;
+
+use crate::{
+    accn::AccnEntry,
+    locale::{self, tr, Label},
+    valuable::ValuableEntry,
+};
+
+use super::{entry::PostingEntry, Journal};
+
+/// Overview of one accn as printed by `info`.
+pub(crate) struct AccnInfo<'a> {
+    accn: AccnEntry<'a>,
+    postings: usize,
+    activity: Option<(NaiveDate, NaiveDate)>,
+    balance: ValuableEntry<'a>,
+    children: Vec<(AccnEntry<'a>, ValuableEntry<'a>)>,
+}
+
+impl Journal {
+    /// Postings booked to `accn` or any of its descendants.
+    pub(crate) fn postings_under<'a>(
+        &'a self,
+        accn: AccnEntry<'a>,
+    ) -> impl Iterator<Item = PostingEntry<'a>> + 'a {
+        self.postings()
+            .filter(move |p| p.accn().is_descendent_of(accn))
+    }
+
+    /// Dates of the first and the last posting under `accn`.
+    pub(crate) fn activity(&self, accn: AccnEntry) -> Option<(NaiveDate, NaiveDate)> {
+        self.postings_under(accn)
+            .map(|p| p.txn().date())
+            .minmax()
+            .into_option()
+    }
+
+    /// Balance of every direct child of `accn`, by name.
+    pub(crate) fn child_balances<'a>(
+        &'a self,
+        accn: AccnEntry<'a>,
+    ) -> Vec<(AccnEntry<'a>, ValuableEntry<'a>)> {
+        accn.children()
+            .sorted_by_key(|child| child.name())
+            .map(|child| (child, self.postings_under(child).map(|p| p.money()).sum()))
+            .collect()
+    }
+
+    pub(crate) fn accn_info<'a>(&'a self, accn: AccnEntry<'a>) -> AccnInfo<'a> {
+        AccnInfo {
+            accn,
+            postings: self.postings_under(accn).count(),
+            activity: self.activity(accn),
+            balance: self.postings_under(accn).map(|p| p.money()).sum(),
+            children: self.child_balances(accn),
+        }
+    }
+}
+
+impl Display for AccnInfo<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let date = |date: Option<NaiveDate>| date.map_or("-".to_string(), locale::date);
+        writeln!(f, "{}", self.accn.abs_name().bold())?;
+        writeln!(f, "  {:<12}{}", tr(Label::Opened), date(self.accn.opened()))?;
+        writeln!(f, "  {:<12}{}", tr(Label::Closed), date(self.accn.closed()))?;
+        writeln!(
+            f,
+            "  {:<12}{}",
+            tr(Label::Currency),
+            self.accn.currency().unwrap_or("-")
+        )?;
+        writeln!(f, "  {:<12}{}", tr(Label::Postings), self.postings)?;
+        let (first, last) = self.activity.unzip();
+        writeln!(f, "  {:<12}{}", tr(Label::First), date(first))?;
+        writeln!(f, "  {:<12}{}", tr(Label::Last), date(last))?;
+        write!(f, "  {:<12}{}", tr(Label::Balance), self.balance)?;
+        if !self.children.is_empty() {
+            write!(f, "\n  {}", tr(Label::Children))?;
+        }
+        for (child, balance) in &self.children {
+            write!(
+                f,
+                "\n    {:<50}{:>15}",
+                child.abs_name(),
+                balance.to_string()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"open 2024-01-01 asset:bank currency USD
+close 2024-12-31 asset:bank
+
+2024-01-05 salary
+    asset:bank:checking  $3000
+    income:salary
+
+2024-02-01 rent
+    expense:rent  $1200
+    asset:bank:checking
+
+2024-02-03 move to savings
+    asset:bank:savings  $500
+    asset:bank:checking"#;
+
+    #[test]
+    fn test_accn_info() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap();
+
+        let info = journal.accn_info(bank);
+        assert_eq!(info.postings, 4);
+        assert_eq!(
+            info.activity.unwrap(),
+            ("2024-01-05".parse().unwrap(), "2024-02-03".parse().unwrap())
+        );
+        assert_eq!(info.balance.to_string(), "$1800");
+        let children = info
+            .children
+            .iter()
+            .map(|(child, balance)| (child.name(), balance.to_string()))
+            .collect_vec();
+        assert_eq!(
+            children,
+            [
+                ("checking", "$1300".to_string()),
+                ("savings", "$500".to_string())
+            ]
+        );
+
+        let info = info.to_string();
+        assert!(info.contains("opened      2024-01-01"));
+        assert!(info.contains("currency    USD"));
+
+        // declarations are saved with the journal
+        assert!(journal.to_string().starts_with(
+            "open 2024-01-01 asset:bank currency USD\nclose 2024-12-31 asset:bank\n\n"
+        ));
+    }
+
+    #[test]
+    fn test_close_before_open() {
+        let input = "open 2024-06-01 asset:bank\nclose 2024-01-01 asset:bank";
+        assert!(Journal::from_str(input).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::{bail, Result};
+use chrono::{Duration, NaiveDate};
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::{Accn, AccnEntry},
+    locale,
+    period::Period,
+    valuable::{Currency, Money, Valuable},
+};
+
+use super::{
+    cycle::{Cycle, PaymentStatus},
+    Journal, Txn,
+};
+
+/// Key of the metadata marking a txn as the interest of an accn.
+pub(crate) const INTEREST_META: &str = "interest";
+
+/// Interest an accn accrued over one period, not recorded yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Accrual {
+    /// Last day of the period.
+    pub(crate) date: NaiveDate,
+    pub(crate) money: Money,
+}
+
+/// Postings of an accn with the running balance, grouped by its statement
+/// cycles if it has a billing cycle, followed by the interest not recorded
+/// yet.
+pub(crate) struct Statement<'a> {
+    journal: &'a Journal,
+    accn: AccnEntry<'a>,
+    accruals: &'a [Accrual],
+    cycles: Vec<Cycle>,
+}
+
+impl Statement<'_> {
+    fn cycle_row(&self, cycle: &Cycle) -> String {
+        let store = &self.journal.currencies;
+        let owed = cycle.balance.clone().into_valuable(store).to_string();
+        let paid = cycle.paid.clone().into_valuable(store).to_string();
+        let status = match cycle.status {
+            PaymentStatus::Partial | PaymentStatus::Missed => cycle.status.name().red(),
+            PaymentStatus::Open => cycle.status.name().yellow(),
+            _ => cycle.status.name().green(),
+        };
+        format!(
+            "{} statement closed at {}, due {}, paid {}  {}",
+            locale::date(cycle.closing),
+            owed,
+            locale::date(cycle.due),
+            paid,
+            status
+        )
+        .bold()
+        .to_string()
+    }
+}
+
+impl Display for Statement<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let store = &self.journal.currencies;
+        let mut balance = Valuable::default();
+        let postings = self
+            .journal
+            .postings()
+            .filter(|p| p.accn().is_descendent_of(self.accn))
+            .sorted_by_key(|p| p.txn().date());
+        let mut rows = Vec::new();
+        let mut cycles = self.cycles.iter().peekable();
+        for posting in postings {
+            while let Some(cycle) = cycles.next_if(|c| c.closing < posting.txn().date()) {
+                rows.push(self.cycle_row(cycle));
+            }
+            balance += posting.money().money();
+            rows.push(format!(
+                "{} {:<50} {:>12} {:>20}",
+                locale::date(posting.txn().date()),
+                posting.txn().desc(),
+                posting.money().to_string(),
+                balance.clone().into_valuable(store).to_string()
+            ));
+        }
+        rows.extend(cycles.map(|cycle| self.cycle_row(cycle)));
+        for accrual in self.accruals {
+            let note = format!("interest {}", "(not recorded)".yellow());
+            rows.push(format!(
+                "{} {:<50} {:>12}",
+                locale::date(accrual.date),
+                note,
+                accrual.money.into_money(store).to_string()
+            ));
+        }
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+impl Journal {
+    /// Statement of `accn` with the cycles closed through `today`.
+    pub(crate) fn statement<'a>(
+        &'a self,
+        accn: AccnEntry<'a>,
+        accruals: &'a [Accrual],
+        today: NaiveDate,
+    ) -> Statement<'a> {
+        Statement {
+            journal: self,
+            accn,
+            accruals,
+            cycles: self.cycles(accn, today),
+        }
+    }
+
+    /// Last day interest of `accn` has been recorded for.
+    fn accrued_through(&self, accn: AccnEntry) -> Option<NaiveDate> {
+        let name = accn.abs_name();
+        self.txns()
+            .filter(|txn| txn.meta(INTEREST_META) == Some(name.as_str()))
+            .map(|txn| txn.date())
+            .max()
+    }
+
+    /// Simple interest on the balance of `accn` at its declared yearly rate,
+    /// for every whole period from the last one recorded through `until`.
+    /// What someone owes the user grows, and so does what the user owes.
+    pub(crate) fn accruals(
+        &self,
+        accn: AccnEntry,
+        period: Period,
+        until: NaiveDate,
+    ) -> Result<Vec<Accrual>> {
+        let Some(rate) = accn.interest() else {
+            bail!(
+                "{} has no interest rate, declare one with `interest {} <rate>%`",
+                accn,
+                accn.abs_name()
+            );
+        };
+        let postings = self
+            .postings()
+            .filter(|p| p.accn().is_descendent_of(accn))
+            .map(|p| (p.txn().date(), p.money().money()))
+            .sorted_by_key(|(date, _)| *date)
+            .collect_vec();
+        let Some(first) = postings.first().map(|(date, _)| *date) else {
+            return Ok(Vec::new());
+        };
+        let from = match self.accrued_through(accn) {
+            Some(date) => date + Duration::days(1),
+            None => first,
+        };
+
+        let daily = rate / Decimal::from(365);
+        let mut balances: HashMap<Currency, Decimal> = HashMap::new();
+        let mut postings = postings.into_iter().peekable();
+        let mut accruals = Vec::new();
+        let mut start = period.start(from);
+        loop {
+            let end = period.succ(start) - Duration::days(1);
+            if end > until {
+                break;
+            }
+            let mut interest: HashMap<Currency, Decimal> = HashMap::new();
+            for date in start.iter_days().take_while(|date| *date <= end) {
+                while let Some((_, money)) = postings.next_if(|(d, _)| *d <= date) {
+                    *balances.entry(money.currency()).or_default() += money.amount();
+                }
+                if date < from {
+                    continue;
+                }
+                for (currency, balance) in &balances {
+                    *interest.entry(*currency).or_default() += balance * daily;
+                }
+            }
+            for (currency, amount) in interest {
+                let amount = amount.round_dp(self.currencies.minor_units(currency));
+                if !amount.is_zero() {
+                    let money = Money::new(amount, currency);
+                    accruals.push(Accrual { date: end, money });
+                }
+            }
+            start = period.succ(start);
+        }
+        Ok(accruals)
+    }
+
+    /// Record `accruals` of `accn` as txns against `income:interest`, or
+    /// `expense:interest` for what the user owes.
+    pub(crate) fn record_accruals(&mut self, accn: Accn, accruals: &[Accrual]) -> Result<Vec<Txn>> {
+        let (name, abs_name) = {
+            let accn = accn.into_accn(&self.accns);
+            (accn.name().to_string(), accn.abs_name())
+        };
+        let mut txns = Vec::new();
+        for accrual in accruals {
+            let class = match accrual.money.amount().is_sign_positive() {
+                true => "income",
+                false => "expense",
+            };
+            let counter = self
+                .accns
+                .root_mut()
+                .or_open_child(class)
+                .or_open_child("interest")
+                .into_ref()
+                .id();
+            let txn = self
+                .new_txn(accrual.date, format!("interest on {}", name))
+                .with_meta(INTEREST_META, abs_name.clone())
+                .with_posting(accn, Some(accrual.money))
+                .with_posting(counter, None::<Money>)
+                .build()?
+                .id();
+            txns.push(txn);
+        }
+        Ok(txns)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"interest asset:alice 12%
+
+2024-01-01 lent alice
+    asset:alice  $1000
+    asset:bank
+
+2024-01-16 alice paid back half
+    asset:alice  -$500
+    asset:bank"#;
+
+    #[test]
+    fn test_accruals() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let alice = journal.accns().by_name_unique("alice").ok().unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 2, 15).unwrap();
+        let accruals = journal.accruals(alice, Period::Monthly, until).unwrap();
+        // 15 days of $1000 and 16 of $500, at 12% a year
+        assert_eq!(accruals.len(), 1);
+        assert_eq!(
+            accruals[0].date,
+            NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+        );
+        assert_eq!(accruals[0].money.amount(), Decimal::new(756, 2));
+
+        let alice = alice.id();
+        journal.record_accruals(alice, &accruals).unwrap();
+        let alice = alice.into_accn(journal.accns());
+        let until = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let accruals = journal.accruals(alice, Period::Monthly, until).unwrap();
+        assert_eq!(accruals.len(), 1);
+        assert_eq!(accruals[0].date, until);
+
+        let bob = journal.accns().by_name_unique("bank").ok().unwrap();
+        assert!(journal.accruals(bob, Period::Monthly, until).is_err());
+        assert!(journal.to_string().contains("interest asset:alice 12%"));
+    }
+}
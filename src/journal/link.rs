@@ -0,0 +1,182 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+};
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::prelude::Zero;
+
+use crate::valuable::Valuable;
+
+use super::{entry::TxnEntry, reimburse::claim_contact, Journal, Txn};
+
+/// Key of the metadata naming a txn, like `; id: dinner-0312`.
+pub(crate) const ID_META: &str = "id";
+/// Key of the metadata naming the txns a txn belongs with, like a refund
+/// with `; link: dinner-0312`. Several ids are separated by commas.
+pub(crate) const LINK_META: &str = "link";
+/// Tag of postings paid on behalf of someone who is to pay them back.
+pub(crate) const FRONTED_TAG: &str = "fronted";
+
+/// A txn and the txns linking to it, like a purchase and its refund.
+pub(crate) struct Linked<'a> {
+    journal: &'a Journal,
+    id: String,
+    origin: Option<Txn>,
+    links: Vec<Txn>,
+}
+
+impl Linked<'_> {
+    /// What the txns amount to together, per accn.
+    fn net(&self) -> BTreeMap<String, Valuable> {
+        let mut net: BTreeMap<String, Valuable> = BTreeMap::new();
+        for txn in self.origin.iter().chain(&self.links) {
+            for posting in self.journal.txn(*txn).postings() {
+                *net.entry(posting.accn().abs_name()).or_default() += posting.money().money();
+            }
+        }
+        net.retain(|_, balance| !balance.is_zero());
+        net
+    }
+}
+
+impl Display for Linked<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.id)?;
+        match self.origin {
+            Some(origin) => writeln!(f, "  {}", self.journal.txn(origin).brief())?,
+            None => writeln!(f, "  (no txn with id {})", self.id)?,
+        }
+        for txn in &self.links {
+            writeln!(f, "  {}", self.journal.txn(*txn).brief())?;
+        }
+        let net = self.net();
+        if net.is_empty() {
+            return write!(f, "  net: nothing");
+        }
+        write!(f, "  net:")?;
+        for (accn, balance) in net {
+            let balance = balance.into_valuable(&self.journal.currencies);
+            write!(f, "\n    {:<60}{:>10}", accn, balance.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> TxnEntry<'a> {
+    /// Ids of the txns this one links to.
+    pub(crate) fn links(&self) -> impl Iterator<Item = &'a str> {
+        self.meta(LINK_META)
+            .into_iter()
+            .flat_map(|ids| ids.split(','))
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+    }
+}
+
+impl Journal {
+    /// The txn named `id` with the txns linking to it.
+    pub(crate) fn linked(&self, id: &str) -> Linked<'_> {
+        Linked {
+            journal: self,
+            id: id.to_string(),
+            origin: self
+                .txns()
+                .find(|txn| txn.meta(ID_META) == Some(id))
+                .map(Txn::from),
+            links: self
+                .txns()
+                .filter(|txn| txn.links().any(|link| link == id))
+                .sorted_by_key(|txn| txn.date())
+                .map(Txn::from)
+                .collect(),
+        }
+    }
+
+    /// Every group of linked txns, by id.
+    pub(crate) fn link_groups(&self) -> Vec<Linked<'_>> {
+        self.txns()
+            .flat_map(|txn| txn.links().map(str::to_string).collect_vec())
+            .sorted()
+            .dedup()
+            .map(|id| self.linked(&id))
+            .collect()
+    }
+
+    /// Ids some txn links to.
+    pub(super) fn linked_ids(&self) -> HashSet<&str> {
+        self.txns()
+            .flat_map(|txn| txn.links().collect_vec())
+            .collect()
+    }
+
+    /// Fronted or claimed txns dated before `before` that nothing links to
+    /// yet.
+    pub(crate) fn unreimbursed(&self, before: NaiveDate) -> Vec<TxnEntry<'_>> {
+        let linked = self.linked_ids();
+        self.txns()
+            .filter(|txn| txn.date() < before)
+            .filter(|txn| {
+                txn.tags()
+                    .any(|tag| tag == FRONTED_TAG || claim_contact(tag, txn.payee()).is_some())
+            })
+            .filter(|txn| txn.meta(ID_META).is_none_or(|id| !linked.contains(id)))
+            .sorted_by_key(|txn| txn.date())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-03-12 dinner with bob
+    ; id: dinner-0312
+    expense:food  $60 #fronted
+    asset:bank
+
+2024-03-20 bob paid his half
+    ; link: dinner-0312
+    asset:bank  $30
+    expense:food
+
+2024-03-15 concert tickets
+    expense:fun  $80 #fronted
+    asset:bank"#;
+
+    #[test]
+    fn test_linked() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let linked = journal.linked("dinner-0312");
+        assert!(linked.origin.is_some());
+        assert_eq!(linked.links.len(), 1);
+        let net = linked
+            .net()
+            .into_iter()
+            .map(|(accn, balance)| {
+                let balance = balance.into_valuable(journal.currencies()).to_string();
+                (accn, balance)
+            })
+            .collect_vec();
+        assert_eq!(
+            net,
+            [
+                ("asset:bank".to_string(), "-$30".to_string()),
+                ("expense:food".to_string(), "$30".to_string())
+            ]
+        );
+        assert_eq!(journal.link_groups().len(), 1);
+    }
+
+    #[test]
+    fn test_unreimbursed() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 4, 30).unwrap();
+        let open = journal.unreimbursed(before);
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].desc(), "concert tickets");
+        let before = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap();
+        assert!(journal.unreimbursed(before).is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use crate::accn::Accn;
+
+use super::{Journal, Posting, Txn};
+
+/// A rule of how a journal should be kept, checked by `check` and fixed
+/// where it can be by `coinjar fix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Lint {
+    /// An expense paid to no one, its description not `payee | narration`.
+    MissingPayee,
+    /// An accn named other than in lowercase words joined by `-`.
+    AccnNotKebabCase,
+    /// A txn dated after today.
+    FutureTxn,
+    /// A posting of no money.
+    ZeroPosting,
+}
+
+impl Lint {
+    pub(crate) const ALL: [Lint; 4] = [
+        Lint::MissingPayee,
+        Lint::AccnNotKebabCase,
+        Lint::FutureTxn,
+        Lint::ZeroPosting,
+    ];
+
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Lint::MissingPayee => "missing-payee",
+            Lint::AccnNotKebabCase => "accn-not-kebab-case",
+            Lint::FutureTxn => "future-txn",
+            Lint::ZeroPosting => "zero-posting",
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.code().fmt(f)
+    }
+}
+
+/// A change to the journal that makes a finding go away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Fix(FixKind);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FixKind {
+    Rename { accn: Accn, name: String },
+    DropPosting { txn: Txn, posting: Posting },
+}
+
+/// Where a journal breaks a lint.
+#[derive(Debug, Clone)]
+pub(crate) struct Finding {
+    pub(crate) lint: Lint,
+    pub(crate) message: String,
+    /// Line of the txn at fault, if it was read from a file.
+    pub(crate) line: Option<usize>,
+    pub(crate) fix: Option<Fix>,
+}
+
+/// Whether `name` is lowercase words of letters and digits joined by `-`.
+fn is_kebab_case(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('-').all(|word| {
+            !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_alphanumeric() && !c.is_uppercase())
+        })
+}
+
+/// `name` in kebab case, with a word starting at each capital after a
+/// lowercase letter, so `CreditCard` and `credit card` are `credit-card`.
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::new();
+    let mut prev = None;
+    for c in name.chars() {
+        let starts_word =
+            c.is_uppercase() && prev.is_some_and(|p: char| p.is_lowercase() || p.is_numeric());
+        let breaks = !c.is_alphanumeric() || starts_word;
+        if breaks && !kebab.is_empty() && !kebab.ends_with('-') {
+            kebab.push('-');
+        }
+        if c.is_alphanumeric() {
+            kebab.extend(c.to_lowercase());
+        }
+        prev = Some(c);
+    }
+    kebab.trim_end_matches('-').to_string()
+}
+
+impl Journal {
+    /// Everything in the journal breaking a lint, txns dated after `today`
+    /// counting as in the future.
+    pub(crate) fn lint(&self, today: NaiveDate) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let renames = self
+            .accns
+            .accns()
+            .filter(|accn| accn.parent().is_some() && !is_kebab_case(accn.name()))
+            .map(|accn| (accn, kebab_case(accn.name())))
+            .collect_vec();
+        // siblings renamed to the same name would clash with each other
+        let claims = renames
+            .iter()
+            .map(|(accn, name)| (accn.parent().map(|parent| parent.id()), name))
+            .counts();
+        for (accn, name) in renames.iter().cloned() {
+            let taken = claims[&(accn.parent().map(|parent| parent.id()), &name)] > 1
+                || accn
+                    .parent()
+                    .is_some_and(|parent| parent.children().any(|child| child.name() == name));
+            findings.push(Finding {
+                lint: Lint::AccnNotKebabCase,
+                message: format!("accn {} is not named in kebab case", accn),
+                line: None,
+                fix: (!name.is_empty() && !taken).then(|| {
+                    Fix(FixKind::Rename {
+                        accn: accn.id(),
+                        name,
+                    })
+                }),
+            });
+        }
+
+        for txn in self.txns() {
+            let expense = self.accns.expense();
+            let spends = txn
+                .postings()
+                .any(|posting| posting.accn().is_descendent_of(expense));
+            if spends && txn.payee().is_none() {
+                findings.push(Finding {
+                    lint: Lint::MissingPayee,
+                    message: format!("expense {} names no payee", txn.title()),
+                    line: txn.line(),
+                    fix: None,
+                });
+            }
+
+            if txn.date() > today {
+                findings.push(Finding {
+                    lint: Lint::FutureTxn,
+                    message: format!(
+                        "{} is dated {} days from today",
+                        txn.title(),
+                        (txn.date() - today).num_days()
+                    ),
+                    line: txn.line(),
+                    fix: None,
+                });
+            }
+
+            let postings = &self.txns.txns[&txn.id()].postings;
+            let zero = |posting: &Posting| self.txns.postings[*posting].money.amount().is_zero();
+            // dropping them all would leave a txn of less than two postings
+            let kept = postings.iter().filter(|posting| !zero(posting)).count();
+            for posting in postings.iter().filter(|posting| zero(posting)) {
+                let accn = self.txns.postings[*posting].accn.into_accn(&self.accns);
+                findings.push(Finding {
+                    lint: Lint::ZeroPosting,
+                    message: format!("{} posts nothing to {}", txn.title(), accn),
+                    line: txn.line(),
+                    fix: (kept >= 2).then_some(Fix(FixKind::DropPosting {
+                        txn: txn.id(),
+                        posting: *posting,
+                    })),
+                });
+            }
+        }
+        findings
+    }
+
+    /// What applying `fix` changes.
+    pub(crate) fn describe_fix(&self, fix: &Fix) -> String {
+        match &fix.0 {
+            FixKind::Rename { accn, name } => {
+                format!("rename {} to {}", accn.into_accn(&self.accns), name)
+            }
+            FixKind::DropPosting { txn, posting } => {
+                let accn = self.txns.postings[*posting].accn.into_accn(&self.accns);
+                format!(
+                    "drop the posting to {} from {}",
+                    accn,
+                    self.txn(*txn).title()
+                )
+            }
+        }
+    }
+
+    pub(crate) fn apply_fix(&mut self, fix: &Fix) -> Result<()> {
+        match &fix.0 {
+            FixKind::Rename { accn, name } => {
+                accn.into_accn_mut(&mut self.accns).rename(name)?;
+            }
+            FixKind::DropPosting { txn, posting } => {
+                let data = self.txns.txns.get_mut(txn).unwrap();
+                data.postings.retain(|p| p != posting);
+                let date = data.date;
+                self.txns.postings.remove(*posting);
+                self.txns.snapshots.invalidate(date);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-05-02 Blue Bottle | coffee
+    "expense:Eating Out"  $4.8
+    asset:bank
+
+2024-05-03 refund
+    expense:misc  $0
+    asset:bank  $3
+    income:refund
+
+2024-06-10 rent
+    expense:rent  $1500
+    asset:bank"#;
+
+    #[test]
+    fn test_lint() {
+        assert_eq!(kebab_case("CreditCard"), "credit-card");
+        assert_eq!(kebab_case("Eating  Out_2"), "eating-out-2");
+        assert!(is_kebab_case("credit-card2"));
+        assert!(!is_kebab_case("credit--card"));
+
+        let mut journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let findings = journal.lint(today);
+        let lints = findings
+            .iter()
+            .map(|finding| finding.lint)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lints,
+            [
+                Lint::AccnNotKebabCase,
+                Lint::MissingPayee,
+                Lint::ZeroPosting,
+                Lint::MissingPayee,
+                Lint::FutureTxn,
+            ]
+        );
+        assert_eq!(findings[4].line, Some(10));
+
+        let fixes = findings
+            .iter()
+            .filter_map(|finding| finding.fix.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            journal.describe_fix(&fixes[0]),
+            "rename \"expense:Eating Out\" to eating-out"
+        );
+        for fix in &fixes {
+            journal.apply_fix(fix).unwrap();
+        }
+        let fixed = journal.to_string();
+        assert!(fixed.contains("expense:eating-out"));
+        assert!(!fixed.contains("expense:misc"));
+        let lints = journal
+            .lint(today)
+            .into_iter()
+            .map(|finding| finding.lint)
+            .collect::<Vec<_>>();
+        assert_eq!(lints, [Lint::MissingPayee, Lint::FutureTxn]);
+
+        // neither of two names becoming the same one is renamed
+        let input = INPUT.replace("asset:bank", "\"asset:Bank Account\"")
+            + "\n\n2024-05-04 move\n    asset:bank_account  $1\n    \"asset:Bank Account\"";
+        let journal = Journal::from_str(&input).unwrap_or_else(|e| panic!("{:#}", e));
+        let findings = journal.lint(today);
+        let clashing = findings
+            .iter()
+            .filter(|finding| finding.message.contains("asset:"))
+            .collect_vec();
+        assert_eq!(clashing.len(), 2);
+        assert!(clashing.iter().all(|finding| finding.fix.is_none()));
+    }
+}
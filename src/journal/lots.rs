@@ -0,0 +1,302 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Months, NaiveDate};
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::valuable::{Currency, Money, RateProvider};
+
+use super::{Journal, Txn, TxnEntry};
+
+/// Key of the metadata of a sale naming the lots it sells by the day they
+/// were bought, like `; lot: 2024-02-01`. Several days are separated by
+/// commas.
+pub(crate) const LOT_META: &str = "lot";
+
+/// Units of a commodity bought in one txn and still held.
+#[derive(Debug, Clone)]
+pub(crate) struct Lot {
+    pub(crate) txn: Txn,
+    pub(crate) date: NaiveDate,
+    pub(crate) units: Decimal,
+    /// What the units still held cost, in the base currency.
+    pub(crate) cost: Decimal,
+}
+
+/// Which lots a sale takes its units from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum Booking {
+    /// Every lot in proportion, which keeps the average cost.
+    #[default]
+    Average,
+    Fifo,
+    Lifo,
+    /// The lots named by the `lot` metadata of the sale first, then the
+    /// oldest ones.
+    Specific,
+}
+
+impl FromStr for Booking {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "average" => Ok(Booking::Average),
+            "fifo" => Ok(Booking::Fifo),
+            "lifo" => Ok(Booking::Lifo),
+            "specific" => Ok(Booking::Specific),
+            _ => Err(anyhow!("invalid booking: {}", s)),
+        }
+    }
+}
+
+impl Display for Booking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Booking::Average => "average",
+            Booking::Fifo => "fifo",
+            Booking::Lifo => "lifo",
+            Booking::Specific => "specific",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Units of one lot a sale took, amounts in the base currency.
+#[derive(Debug, Clone)]
+pub(crate) struct Disposal {
+    pub(crate) sale: Txn,
+    pub(crate) code: String,
+    pub(crate) bought: NaiveDate,
+    pub(crate) sold: NaiveDate,
+    pub(crate) units: Decimal,
+    /// The share of what the sale brought in for these units.
+    pub(crate) proceeds: Decimal,
+    pub(crate) cost: Decimal,
+}
+
+impl Disposal {
+    pub(crate) fn gain(&self) -> Decimal {
+        self.proceeds - self.cost
+    }
+
+    /// Whether the units were held for more than a year.
+    pub(crate) fn is_long_term(&self) -> bool {
+        self.sold > self.bought + Months::new(12)
+    }
+}
+
+impl Journal {
+    /// Commodities held in the asset accns at some point, currencies outside
+    /// ISO 4217 like shares and coins.
+    pub(crate) fn commodities(&self) -> Vec<Currency> {
+        self.postings()
+            .filter(|posting| posting.accn().is_descendent_of(self.accns.asset()))
+            .map(|posting| posting.money().money().currency())
+            .filter(|currency| self.currencies.name(*currency).is_none())
+            .unique()
+            .sorted_by_key(|currency| self.currencies.code(*currency))
+            .collect()
+    }
+
+    /// The only currency the journal uses apart from commodities, to value
+    /// them in.
+    pub(crate) fn base_code(&self) -> Result<&str> {
+        let codes = self
+            .used_codes()
+            .into_iter()
+            .filter(|code| {
+                let currency = self.currencies.get_by_code(code).unwrap();
+                self.currencies.name(currency).is_some()
+            })
+            .collect_vec();
+        match codes[..] {
+            [code] => Ok(code),
+            [] => bail!("no currency used yet"),
+            _ => bail!("several currencies used, choose one with `in <code>`"),
+        }
+    }
+
+    /// Net change of `commodity` across the asset accns by each txn through
+    /// `date`, in date order. Moves between asset accns cancel out.
+    pub(super) fn commodity_moves(
+        &self,
+        commodity: Currency,
+        date: NaiveDate,
+    ) -> Vec<(TxnEntry<'_>, Decimal)> {
+        self.txns()
+            .filter(|txn| txn.date() <= date)
+            .sorted_by_key(|txn| txn.date())
+            .filter_map(|txn| {
+                let units: Decimal = txn
+                    .postings()
+                    .filter(|posting| posting.accn().is_descendent_of(self.accns.asset()))
+                    .map(|posting| posting.money().money())
+                    .filter(|money| money.currency() == commodity)
+                    .map(|money| money.amount())
+                    .sum();
+                (!units.is_zero()).then_some((txn, units))
+            })
+            .collect()
+    }
+
+    /// What `txn` traded `units` of `commodity` for, in `base`: the money in
+    /// other currencies moving the other way through the asset and
+    /// liability accns, so fees add to a purchase and take from a sale.
+    /// Gifts trade for nothing and count as what the units were worth that
+    /// day.
+    pub(super) fn traded_for(
+        &self,
+        txn: &TxnEntry,
+        commodity: Currency,
+        units: Decimal,
+        base: &str,
+        rates: &impl RateProvider,
+    ) -> Result<Decimal> {
+        let traded: Vec<Money> = txn
+            .postings()
+            .filter(|posting| {
+                let accn = posting.accn();
+                accn.is_descendent_of(self.accns.asset())
+                    || accn.is_descendent_of(self.accns.liability())
+            })
+            .map(|posting| posting.money().money())
+            .filter(|money| {
+                money.currency() != commodity
+                    && money.amount().is_sign_negative() == units.is_sign_positive()
+            })
+            .collect();
+        let worth = match traded.is_empty() {
+            true => vec![Money::new(units.abs(), commodity)],
+            false => traded
+                .into_iter()
+                .map(|money| Money::new(money.amount().abs(), money.currency()))
+                .collect(),
+        };
+        let mut total = Decimal::ZERO;
+        for money in worth {
+            let money = money.into_money(&self.currencies);
+            let converted = money.convert_to(base, txn.date(), rates).with_context(|| {
+                format!("no value in {} for {} of {}", base, money, txn.title())
+            })?;
+            total += converted.money().amount();
+        }
+        Ok(total)
+    }
+
+    /// Lots of `commodity` held at the end of `date` with their cost in
+    /// `base`, and what each sale took from them by `booking`.
+    pub(crate) fn book_lots(
+        &self,
+        commodity: Currency,
+        base: &str,
+        date: NaiveDate,
+        booking: Booking,
+        rates: &impl RateProvider,
+    ) -> Result<(Vec<Lot>, Vec<Disposal>)> {
+        let code = self.currencies.code(commodity);
+        let (mut lots, mut disposals): (Vec<Lot>, Vec<Disposal>) = Default::default();
+        for (txn, units) in self.commodity_moves(commodity, date) {
+            if units.is_sign_positive() {
+                lots.push(Lot {
+                    txn: txn.id(),
+                    date: txn.date(),
+                    units,
+                    cost: self.traded_for(&txn, commodity, units, base, rates)?,
+                });
+                continue;
+            }
+            let (sold, held) = (-units, lots.iter().map(|lot| lot.units).sum::<Decimal>());
+            if sold > held {
+                bail!(
+                    "{} on {} sells {} {} with only {} held",
+                    txn.title(),
+                    txn.date(),
+                    sold,
+                    code,
+                    held
+                );
+            }
+            let taken = take(&lots, &txn, sold, held, booking)?;
+            let proceeds = self.traded_for(&txn, commodity, units, base, rates)?;
+            for (i, taken) in taken {
+                let lot = &mut lots[i];
+                let cost = lot.cost * taken / lot.units;
+                lot.units -= taken;
+                lot.cost -= cost;
+                disposals.push(Disposal {
+                    sale: txn.id(),
+                    code: code.to_string(),
+                    bought: lot.date,
+                    sold: txn.date(),
+                    units: taken,
+                    proceeds: proceeds * taken / sold,
+                    cost,
+                });
+            }
+            lots.retain(|lot| !lot.units.is_zero());
+        }
+        Ok((lots, disposals))
+    }
+
+    /// Lots of `commodity` held at the end of `date`, sales booked by
+    /// `booking`.
+    pub(crate) fn lots(
+        &self,
+        commodity: Currency,
+        base: &str,
+        date: NaiveDate,
+        booking: Booking,
+        rates: &impl RateProvider,
+    ) -> Result<Vec<Lot>> {
+        Ok(self.book_lots(commodity, base, date, booking, rates)?.0)
+    }
+}
+
+/// Units `sale` takes from each of `lots` to sell `sold` of the `held`.
+fn take(
+    lots: &[Lot],
+    sale: &TxnEntry,
+    sold: Decimal,
+    held: Decimal,
+    booking: Booking,
+) -> Result<Vec<(usize, Decimal)>> {
+    let order: Vec<usize> = match booking {
+        Booking::Average if sold == held => {
+            return Ok(lots.iter().map(|lot| lot.units).enumerate().collect())
+        }
+        Booking::Average => {
+            return Ok(lots
+                .iter()
+                .enumerate()
+                .map(|(i, lot)| (i, lot.units * sold / held))
+                .collect())
+        }
+        Booking::Fifo => (0..lots.len()).collect(),
+        Booking::Lifo => (0..lots.len()).rev().collect(),
+        Booking::Specific => {
+            let days: Vec<NaiveDate> = sale
+                .meta(LOT_META)
+                .into_iter()
+                .flat_map(|days| days.split(','))
+                .map(|day| day.trim().parse())
+                .try_collect()
+                .with_context(|| format!("invalid {} of {}", LOT_META, sale.title()))?;
+            let named = days
+                .iter()
+                .flat_map(|day| (0..lots.len()).filter(move |i| lots[*i].date == *day));
+            named.chain(0..lots.len()).unique().collect()
+        }
+    };
+    let mut left = sold;
+    let mut taken = Vec::new();
+    for i in order {
+        if left.is_zero() {
+            break;
+        }
+        let units = lots[i].units.min(left);
+        left -= units;
+        taken.push((i, units));
+    }
+    Ok(taken)
+}
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use crate::{accn::Accn, valuable::Money};
+
+use super::{Journal, Txn};
+
+/// Two one-sided txns that look like both ends of a single transfer, as
+/// imports of two accounts each record their own side of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TransferMatch {
+    /// The txn taking the money out of `from`.
+    pub(crate) out: Txn,
+    /// The txn bringing it into `to`.
+    pub(crate) into: Txn,
+    pub(crate) from: Accn,
+    pub(crate) to: Accn,
+    pub(crate) money: Money,
+}
+
+/// One side of a transfer as an import records it.
+struct Side {
+    txn: Txn,
+    date: NaiveDate,
+    accn: Accn,
+    money: Money,
+    /// Name of the accn the other posting is booked to, the catch-all of
+    /// the import like `uncategorized`.
+    counter: String,
+}
+
+/// The only posting of `txn` to an asset or liability accn, with the
+/// other one booked elsewhere.
+fn one_side(journal: &Journal, txn: Txn) -> Option<Side> {
+    let txn = journal.txn(txn);
+    let (asset, liability) = (journal.accns.asset(), journal.accns.liability());
+    let (sides, others): (Vec<_>, Vec<_>) = txn
+        .postings()
+        .partition(|p| p.accn().is_descendent_of(asset) || p.accn().is_descendent_of(liability));
+    match (&sides[..], &others[..]) {
+        ([side], [other]) => Some(Side {
+            txn: txn.id(),
+            date: txn.date(),
+            accn: side.accn().id(),
+            money: side.money().money(),
+            counter: other.accn().name().to_string(),
+        }),
+        _ => None,
+    }
+}
+
+impl Journal {
+    /// Pairs of one-sided txns moving the same money out of one accn and
+    /// into another at most `window` days apart, with their other sides
+    /// booked to accns of the same name. The closest pairs come first and
+    /// every txn is in one pair at most.
+    pub(crate) fn transfer_matches(&self, window: i64) -> Vec<TransferMatch> {
+        let (outs, intos): (Vec<_>, Vec<_>) = self
+            .txns()
+            .filter_map(|txn| one_side(self, txn.id()))
+            .partition(|side| side.money.amount().is_sign_negative());
+        let apart = |out: &Side, into: &Side| (into.date - out.date).num_days().abs();
+
+        let candidates = outs
+            .iter()
+            .cartesian_product(&intos)
+            .filter(|(out, into)| {
+                out.accn != into.accn
+                    && out.money == -into.money
+                    && out.counter == into.counter
+                    && apart(out, into) <= window
+            })
+            .sorted_by_key(|(out, into)| (apart(out, into), out.date));
+
+        let mut matches: Vec<TransferMatch> = Vec::new();
+        for (out, into) in candidates {
+            let used = |txn: Txn| matches.iter().any(|m| m.out == txn || m.into == txn);
+            if used(out.txn) || used(into.txn) {
+                continue;
+            }
+            matches.push(TransferMatch {
+                out: out.txn,
+                into: into.txn,
+                from: out.accn,
+                to: into.accn,
+                money: into.money,
+            });
+        }
+        matches.sort_by_key(|m| self.txn(m.out).date());
+        matches
+    }
+
+    /// Replace both txns of `matched` with a single transfer dated and
+    /// titled like the one taking the money out. It carries the metadata of
+    /// both, that of the one taking the money out where they share a key.
+    pub(crate) fn merge_transfer(&mut self, matched: &TransferMatch) -> Result<Txn> {
+        let out = self.txn(matched.out);
+        let (date, title) = (out.date(), out.title());
+        let mut meta: Vec<(String, String)> = Vec::new();
+        for (key, value) in out.metas().chain(self.txn(matched.into).metas()) {
+            if meta.iter().all(|(k, _)| k != key) {
+                meta.push((key.to_string(), value.to_string()));
+            }
+        }
+        let txn = meta
+            .into_iter()
+            .fold(self.new_txn(date, title), |txn, (key, value)| {
+                txn.with_meta(key, value)
+            })
+            .with_posting(matched.to, Some(matched.money))
+            .with_posting(matched.from, Some(-matched.money))
+            .build()?
+            .id();
+        self.txns.remove(matched.out);
+        self.txns.remove(matched.into);
+        Ok(txn)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-03-01 transfer to savings
+    ; ref: A1
+    asset:checking  $-500
+    expense:uncategorized
+
+2024-03-03 transfer from checking
+    ; ref: B2
+    ; bank: ally
+    asset:savings  $500
+    income:uncategorized
+
+2024-03-04 lunch
+    expense:food  $500
+    asset:checking
+
+2024-03-20 deposit
+    asset:savings  $500
+    income:uncategorized"#;
+
+    #[test]
+    fn test_transfer_matches() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let matches = journal.transfer_matches(3);
+        assert_eq!(matches.len(), 1);
+        let titles = (
+            journal.txn(matches[0].out).title(),
+            journal.txn(matches[0].into).title(),
+        );
+        assert_eq!(
+            titles,
+            (
+                "transfer to savings".to_string(),
+                "transfer from checking".to_string()
+            )
+        );
+
+        let txn = journal.merge_transfer(&matches[0]).unwrap();
+        assert_eq!(journal.txns().count(), 3);
+        let text = journal.txn(txn).to_string();
+        assert!(text.starts_with("2024-03-01 transfer to savings"));
+        assert!(text.contains("asset:savings") && text.contains("asset:checking"));
+        assert!(!text.contains("uncategorized"));
+        assert_eq!(
+            journal.txn(txn).metas().collect::<Vec<_>>(),
+            [("ref", "A1"), ("bank", "ally")]
+        );
+        assert!(journal.transfer_matches(3).is_empty());
+    }
+}
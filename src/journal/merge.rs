@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use super::{entry::TxnEntry, link::ID_META, note::Note, Journal};
+
+/// A txn as merged: where it sorts, what identifies it across versions of a
+/// journal, and how it is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    date: NaiveDate,
+    /// Its `id` metadata, else its date and title, which an edit keeps
+    /// unless it changes those too.
+    key: String,
+    text: String,
+}
+
+impl From<TxnEntry<'_>> for Version {
+    fn from(txn: TxnEntry<'_>) -> Self {
+        let key = match txn.meta(ID_META) {
+            Some(id) => id.to_string(),
+            None => format!("{} {}", txn.date(), txn.title()),
+        };
+        Self {
+            date: txn.date(),
+            key,
+            text: txn.to_string(),
+        }
+    }
+}
+
+impl From<&Note> for Version {
+    fn from(note: &Note) -> Self {
+        Self {
+            date: note.date,
+            key: format!("{} note", note.date),
+            text: note.to_string(),
+        }
+    }
+}
+
+/// A txn of the base both sides changed, in different ways.
+#[derive(Debug)]
+pub(crate) struct Conflict {
+    pub(crate) base: String,
+    /// What ours has in its place, nothing if it was deleted.
+    pub(crate) ours: Vec<String>,
+    pub(crate) theirs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Resolution {
+    Ours,
+    Theirs,
+    Both,
+}
+
+/// Items of `a` not in `b`, counting duplicates.
+fn minus(a: &[Version], b: &[Version]) -> Vec<Version> {
+    let mut b = b.iter().map(|v| &v.text).counts();
+    a.iter()
+        .filter(|v| match b.get_mut(&v.text) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect()
+}
+
+/// Take the items with `key` out of `versions`.
+fn take_key(versions: &mut Vec<Version>, key: &str) -> Vec<Version> {
+    let (taken, kept) = std::mem::take(versions)
+        .into_iter()
+        .partition(|v| v.key == key);
+    *versions = kept;
+    taken
+}
+
+impl Journal {
+    /// Three-way merge of two journals changed from a common `base` at the
+    /// level of txns and notes: one added, edited or deleted on one side
+    /// only is taken as is, while one both sides edited differently is a
+    /// conflict `resolve` decides on. Directives are merged as lines,
+    /// keeping those either side added, except snapshots, which are taken
+    /// again of the merged txns through the latest month either side had.
+    pub(crate) fn merge(
+        base: &Journal,
+        ours: &Journal,
+        theirs: &Journal,
+        mut resolve: impl FnMut(&Conflict) -> Result<Resolution>,
+    ) -> Result<Journal> {
+        let versions = |journal: &Journal| {
+            let notes = journal.notes.iter().map(Version::from);
+            journal.txns().map(Version::from).chain(notes).collect_vec()
+        };
+        let (base_txns, our_txns, their_txns) = (versions(base), versions(ours), versions(theirs));
+        let (our_removed, mut our_added) =
+            (minus(&base_txns, &our_txns), minus(&our_txns, &base_txns));
+        let (their_removed, mut their_added) = (
+            minus(&base_txns, &their_txns),
+            minus(&their_txns, &base_txns),
+        );
+
+        let mut merged = minus(&minus(&base_txns, &our_removed), &their_removed);
+        for removed in minus(&our_removed, &minus(&our_removed, &their_removed)) {
+            let mut ours = take_key(&mut our_added, &removed.key);
+            let mut theirs = take_key(&mut their_added, &removed.key);
+            ours.sort_by(|a, b| a.text.cmp(&b.text));
+            theirs.sort_by(|a, b| a.text.cmp(&b.text));
+            if ours == theirs {
+                merged.extend(ours);
+                continue;
+            }
+            let conflict = Conflict {
+                base: removed.text,
+                ours: ours.iter().map(|v| v.text.clone()).collect(),
+                theirs: theirs.iter().map(|v| v.text.clone()).collect(),
+            };
+            match resolve(&conflict)? {
+                Resolution::Ours => merged.extend(ours),
+                Resolution::Theirs => merged.extend(theirs),
+                Resolution::Both => merged.extend(minus(&theirs, &ours).into_iter().chain(ours)),
+            }
+        }
+        merged.extend(minus(&their_added, &our_added));
+        merged.extend(our_added);
+
+        // snapshots of either side miss the txns of the other
+        let directives = |journal: &Journal| {
+            journal
+                .directives()
+                .lines()
+                .filter(|line| !line.starts_with("snapshot "))
+                .join("\n")
+        };
+        let base_lines = directives(base);
+        let our_lines = directives(ours);
+        let added = directives(theirs)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .filter(|line| !base_lines.lines().contains(line) && !our_lines.lines().contains(line))
+            .join("\n");
+        let text = format!(
+            "{}\n{}\n\n{}\n",
+            our_lines,
+            added,
+            merged
+                .iter()
+                .sorted_by_key(|v| v.date)
+                .map(|v| &v.text)
+                .join("\n\n")
+        );
+        let mut merged = Journal::from_str(&text).context("merged journal does not parse")?;
+        let snapshotted = [ours, theirs]
+            .iter()
+            .flat_map(|journal| journal.txns.snapshots.dates())
+            .max();
+        if let Some(until) = snapshotted {
+            merged.snapshot_months(until);
+        }
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::bail;
+
+    use super::*;
+
+    const BASE: &str = r#"2024-01-01 lunch
+    expense:food  $12
+    asset:bank
+
+2024-01-02 dinner
+    expense:food  $30
+    asset:bank"#;
+
+    fn texts(journal: &Journal) -> Vec<String> {
+        journal.txns().map(|txn| txn.to_string()).sorted().collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = Journal::from_str(BASE).unwrap();
+        let ours = Journal::from_str(&format!(
+            "{}\n\n2024-01-03 coffee\n    expense:food  $4\n    asset:bank",
+            BASE
+        ))
+        .unwrap();
+        let theirs = Journal::from_str(&BASE.replace("$30", "$32")).unwrap();
+
+        let merged = Journal::merge(&base, &ours, &theirs, |_| bail!("no conflict")).unwrap();
+        assert_eq!(merged.txns().count(), 3);
+        let text = merged.to_string();
+        assert!(text.contains("coffee") && text.contains("$32") && !text.contains("$30"));
+
+        // the same addition on both sides is taken once
+        let merged = Journal::merge(&base, &ours, &ours, |_| bail!("no conflict")).unwrap();
+        assert_eq!(texts(&merged), texts(&ours));
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let base = Journal::from_str(BASE).unwrap();
+        let ours = Journal::from_str(&BASE.replace("$12", "$13")).unwrap();
+        let theirs = Journal::from_str(&BASE.replace("$12", "$14")).unwrap();
+
+        let mut conflicts = 0;
+        let merged = Journal::merge(&base, &ours, &theirs, |conflict| {
+            conflicts += 1;
+            assert!(conflict.base.contains("$12"));
+            assert!(conflict.ours[0].contains("$13") && conflict.theirs[0].contains("$14"));
+            Ok(Resolution::Theirs)
+        })
+        .unwrap();
+        assert_eq!(conflicts, 1);
+        assert_eq!(texts(&merged), texts(&theirs));
+
+        let merged = Journal::merge(&base, &ours, &theirs, |_| Ok(Resolution::Both)).unwrap();
+        assert_eq!(merged.txns().count(), 3);
+    }
+
+    #[test]
+    fn test_merge_notes_snapshots() {
+        let base = Journal::from_str(&format!("2024-01-01\n> New year.\n\n{}", BASE)).unwrap();
+        let mut ours = Journal::from_str(&format!(
+            "{}\n\n2024-02-01 rent\n    expense:rent  $100\n    asset:bank",
+            base
+        ))
+        .unwrap();
+        ours.snapshot_months(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+        let theirs = Journal::from_str(
+            &base
+                .to_string()
+                .replace("New year.", "New year, new budget.")
+                .replace("$30", "$32"),
+        )
+        .unwrap();
+        let theirs =
+            Journal::from_str(&format!("{}\n\n2024-01-02\n> Dinner out.", theirs)).unwrap();
+
+        let merged = Journal::merge(&base, &ours, &theirs, |_| bail!("no conflict")).unwrap();
+        let notes = merged
+            .notes(None, None, None)
+            .map(|note| note.lines[0].clone())
+            .collect_vec();
+        assert_eq!(notes, ["New year, new budget.", "Dinner out."]);
+        // january's snapshot is taken again with their dinner
+        assert!(merged
+            .to_string()
+            .contains("snapshot 2024-01-31 asset:bank -$44"));
+        assert!(merged.stale_snapshots().is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+
+use super::Journal;
+
+/// Free text under a date, each line written after a `>`, like a diary
+/// entry of a trip next to what it cost.
+#[derive(Debug, Clone)]
+pub(crate) struct Note {
+    pub(crate) date: NaiveDate,
+    pub(crate) lines: Vec<String>,
+}
+
+impl Note {
+    pub(crate) fn mentions(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.lines
+            .iter()
+            .any(|line| line.to_lowercase().contains(&search))
+    }
+}
+
+/// Notes of a journal in date order, those of a day in the order written.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Notes {
+    notes: Vec<Note>,
+}
+
+impl Notes {
+    pub(crate) fn push(&mut self, note: Note) {
+        let at = self.notes.partition_point(|n| n.date <= note.date);
+        self.notes.insert(at, note);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.notes.iter()
+    }
+
+    /// Drop the notes dated before `before`.
+    pub(crate) fn remove_before(&mut self, before: NaiveDate) {
+        let at = self.notes.partition_point(|n| n.date < before);
+        self.notes.drain(..at);
+    }
+}
+
+impl Journal {
+    /// Notes dated from `since` through `until` that mention `search`.
+    pub(crate) fn notes<'a>(
+        &'a self,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+        search: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a Note> {
+        self.notes
+            .iter()
+            .filter(move |note| since.is_none_or(|since| note.date >= since))
+            .filter(move |note| until.is_none_or(|until| note.date <= until))
+            .filter(move |note| search.is_none_or(|search| note.mentions(search)))
+    }
+}
+
+impl Display for Note {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.date)?;
+        for line in &self.lines {
+            match line.is_empty() {
+                true => write!(f, "\n>")?,
+                false => write!(f, "\n> {}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use itertools::Itertools;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-07-14
+> Landed in Kyoto; rain all afternoon.
+>
+> Found a ramen place by the station.
+
+2024-07-14 ramen
+    expense:food  ¥1200
+    asset:cash
+
+2024-07-16
+> Day trip to Nara, deer everywhere."#;
+
+    #[test]
+    fn test_notes() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(journal.notes(None, None, None).count(), 2);
+        // written back before the txns of the same day
+        let written = journal.to_string();
+        let (note, _) = INPUT.split_once("\n\n").unwrap();
+        assert!(written.starts_with(&format!("{}\n\n2024-07-14 ramen", note)));
+        assert!(written.ends_with("\n\n2024-07-16\n> Day trip to Nara, deer everywhere."));
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 15).unwrap();
+        let dates = |notes: Vec<&Note>| notes.iter().map(|note| note.date).collect_vec();
+        let later = journal.notes(Some(date), None, None).collect_vec();
+        assert_eq!(
+            dates(later),
+            [NaiveDate::from_ymd_opt(2024, 7, 16).unwrap()]
+        );
+        let ramen = journal.notes(None, None, Some("RAMEN")).collect_vec();
+        assert_eq!(ramen[0].lines[0], "Landed in Kyoto; rain all afternoon.");
+        assert_eq!(ramen[0].lines[1], "");
+        assert!(journal
+            .notes(None, Some(date), Some("nara"))
+            .next()
+            .is_none());
+    }
+}
@@ -1,38 +1,95 @@
-use std::io::Write;
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    io::Write,
+    str::FromStr,
+    time::Instant,
+};
 
-use anyhow::{Context, Ok, Result};
 use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
 
 use pest::{
     iterators::{Pair, Pairs},
     Parser, Span,
 };
 use pest_derive::Parser;
+use tracing::{debug, debug_span, info_span};
 
 use crate::{
-    accn::{AccnEntryMut, AccnTree},
-    journal::{Journal, Txn, TxnBuilder, TxnStore},
-    valuable::{CurrencyStore, Money, MoneyBuilder, MoneyEntry},
+    accn::{Accn, AccnEntryMut, AccnTree, BillingCycle, Budget, Deduction, Sweep},
+    error::{parse_err, CoinError},
+    journal::{
+        dimension::Dimensions,
+        note::{Note, Notes},
+        Journal, Timed, Txn, TxnBuilder, TxnStore,
+    },
+    period::Period,
+    valuable::{CurrencyStore, ExchangeBook, Money, MoneyBuilder, MoneyEntry},
 };
 
 #[derive(Parser)]
 #[grammar = "./parser/coin.pest"]
 pub(crate) struct IdentParser;
 
-fn parse_err(msg: &str, span: Span) -> pest::error::Error<Rule> {
-    use pest::error::{Error, ErrorVariant};
-    Error::new_from_span(
-        ErrorVariant::CustomError {
-            message: msg.to_string(),
-        },
-        span,
-    )
+/// `pair` read as a `T`, for input the grammar accepts but `T` may not, like
+/// the date 2024-02-30.
+fn parse_as<T: FromStr>(pair: &Pair<Rule>) -> Result<T, CoinError>
+where
+    T::Err: Display,
+{
+    pair.as_str()
+        .parse()
+        .map_err(|e: T::Err| parse_err(&e.to_string(), pair.as_span()).into())
 }
 
 struct CoinParser {
     currency_store: CurrencyStore,
+    exchange_book: ExchangeBook,
+    dimensions: Dimensions,
+    notes: Notes,
     accn_tree: AccnTree,
     txn_store: TxnStore,
+    /// Taken in once every txn is read, as reading txns drops snapshots.
+    snapshots: Vec<(NaiveDate, Accn, Money)>,
+    /// Byte offset and line of the last txn read, so the next line number
+    /// counts on from there rather than from the top of the input.
+    last_line: (usize, usize),
+    /// Line of the file the input starts on, past 1 when it was appended.
+    first_line: usize,
+}
+
+/// Where the input a journal was read from ended, to tell whether its file
+/// only grew since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Checkpoint {
+    len: usize,
+    hash: u64,
+    /// Line the input ended on.
+    line: usize,
+}
+
+impl Checkpoint {
+    pub(crate) fn of(input: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        input.hash(&mut hasher);
+        Self {
+            len: input.len(),
+            hash: hasher.finish(),
+            line: input.bytes().filter(|b| *b == b'\n').count() + 1,
+        }
+    }
+
+    /// What was appended to the input of the checkpoint, if `input` starts
+    /// with it unchanged and the rest starts a line of its own.
+    fn appended<'a>(&self, input: &'a str) -> Option<&'a str> {
+        let prefix = input.get(..self.len)?;
+        let appended = &input[self.len..];
+        let on_new_line = prefix.is_empty() || prefix.ends_with('\n') || appended.starts_with('\n');
+        (on_new_line && Checkpoint::of(prefix) == *self).then_some(appended)
+    }
 }
 
 impl CoinParser {
@@ -42,20 +99,68 @@ impl CoinParser {
         let txn_store = TxnStore::default();
         Self {
             currency_store,
+            exchange_book: ExchangeBook::default(),
+            dimensions: Dimensions::default(),
+            notes: Notes::default(),
             accn_tree,
             txn_store,
+            snapshots: Vec::new(),
+            last_line: (0, 1),
+            first_line: 1,
         }
     }
 
-    fn parse_accn(&mut self, pair: Pair<Rule>) -> AccnEntryMut {
+    /// A parser reading on into the stores of `journal`, from `line` of its
+    /// file.
+    fn resume(journal: Journal, line: usize) -> Self {
+        Self {
+            currency_store: journal.currencies,
+            exchange_book: journal.rates,
+            dimensions: journal.dimensions,
+            notes: journal.notes,
+            accn_tree: journal.accns,
+            txn_store: journal.txns,
+            snapshots: Vec::new(),
+            last_line: (0, line),
+            first_line: line,
+        }
+    }
+
+    /// Line `span` starts at, given spans come in the order of the input.
+    fn line_of(&mut self, span: Span) -> usize {
+        let (mut offset, mut line) = self.last_line;
+        if span.start() < offset {
+            (offset, line) = (0, self.first_line);
+        }
+        line += span.get_input()[offset..span.start()]
+            .bytes()
+            .filter(|b| *b == b'\n')
+            .count();
+        self.last_line = (span.start(), line);
+        line
+    }
+
+    fn parse_accn(&mut self, pair: Pair<Rule>) -> AccnEntryMut<'_> {
         let pairs = pair.into_inner();
         pairs.fold(self.accn_tree.root_mut(), |accn, pair| {
-            debug_assert_eq!(pair.as_rule(), Rule::ident);
+            debug_assert!(matches!(pair.as_rule(), Rule::ident | Rule::quoted_ident));
             accn.or_open_child(pair.as_str())
         })
     }
 
-    fn parse_money_builder(pair: Pair<Rule>) -> Result<MoneyBuilder> {
+    /// Accn of a posting, which may only be new if the autocreate policy
+    /// allows it. Accns written in a file count as confirmed.
+    fn parse_posting_accn(&mut self, pair: Pair<Rule>) -> Result<AccnEntryMut<'_>, CoinError> {
+        let span = pair.as_span();
+        let policy = self.accn_tree.autocreate();
+        pair.into_inner()
+            .try_fold(self.accn_tree.root_mut(), |accn, pair| {
+                accn.resolve_child(pair.as_str(), policy, |_| Ok(true))
+            })
+            .map_err(|e| CoinError::at("error parsing account", span, e))
+    }
+
+    fn parse_money_builder(pair: Pair<Rule>) -> Result<MoneyBuilder, CoinError> {
         let pairs = pair.into_inner();
         let mut builder = MoneyBuilder::default();
 
@@ -72,48 +177,276 @@ impl CoinParser {
         Ok(builder)
     }
 
-    fn parse_money(&mut self, pair: Pair<Rule>) -> Result<Money> {
+    fn parse_money(&mut self, pair: Pair<Rule>) -> Result<Money, CoinError> {
         let builder = Self::parse_money_builder(pair)?;
         builder.into_money(&self.currency_store)
     }
 
-    fn parse_txn(&mut self, pair: Pair<Rule>, date: NaiveDate) -> Result<Txn> {
+    fn parse_txn(&mut self, pair: Pair<Rule>, date: NaiveDate) -> Result<Txn, CoinError> {
         let span = pair.as_span();
 
         let mut pairs = pair.into_inner();
-        let desc = pairs.next().unwrap().as_str().to_string();
+        let desc = pairs.next().unwrap().as_str().trim_end().to_string();
         let mut txn = TxnBuilder::new(date, desc);
+        txn.at_line(self.line_of(span));
+
+        for pair in pairs.take_while_ref(|p| p.as_rule() == Rule::meta) {
+            let (key, value) = pair.into_inner().collect_tuple().unwrap();
+            txn.with_meta(key.as_str(), value.as_str().trim_end());
+        }
 
         for posting in pairs {
             let mut pairs = posting.into_inner();
-            let accn = self.parse_accn(pairs.next().unwrap()).as_ref().id();
-            let money = pairs
-                .next()
+            let accn = self
+                .parse_posting_accn(pairs.next().unwrap())?
+                .as_ref()
+                .id();
+            let amount = pairs.take_while_ref(|p| p.as_rule() != Rule::tag).next();
+            let tags = || pairs.map(|p| p.into_inner().as_str().to_string()).collect();
+            if let Some(timed) = amount.clone().filter(|p| p.as_rule() == Rule::timed) {
+                let (hours, rate) = timed.into_inner().collect_tuple().unwrap();
+                let rate_span = rate.as_span();
+                let timed = Timed {
+                    hours: parse_as(&hours)?,
+                    rate: self
+                        .parse_money(rate)
+                        .map_err(|e| CoinError::at("error parsing rate", rate_span, e))?,
+                };
+                txn.with_timed_posting(accn, timed, tags(), &self.currency_store);
+                continue;
+            }
+            let money = amount
                 .map(|p| {
                     self.parse_money(Pair::clone(&p))
-                        .with_context(|| parse_err("error parsing money", p.as_span()))
+                        .map_err(|e| CoinError::at("error parsing money", p.as_span(), e))
                 })
                 .transpose()?;
-            txn.with_posting(accn, money);
+            txn.with_tagged_posting(accn, money, tags());
         }
 
-        txn.build(&mut self.txn_store)
-            .with_context(|| parse_err("error parsing transaction", span))
+        txn.build(&mut self.txn_store, &self.currency_store)
+            .map_err(|e| CoinError::at("error parsing transaction", span, e))
     }
 
-    fn parse_chapter(&mut self, pair: Pair<Rule>) -> Result<()> {
+    fn parse_chapter(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
         let mut pairs = pair.into_inner();
-        let date = pairs.next().unwrap().as_str().parse()?;
+        let date = parse_as(&pairs.next().unwrap())?;
         for pair in pairs {
+            if pair.as_rule() == Rule::note {
+                let lines = pair
+                    .into_inner()
+                    .map(|line| {
+                        let text = line.into_inner().as_str().trim_end();
+                        text.strip_prefix(' ').unwrap_or(text).to_string()
+                    })
+                    .collect();
+                self.notes.push(Note { date, lines });
+                continue;
+            }
             self.parse_txn(pair, date)?;
         }
         Ok(())
     }
 
-    fn parse_journal(mut self, pair: Pairs<Rule>) -> Result<Journal> {
+    fn parse_currency(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let mut pairs = pair.into_inner();
+        let code = pairs.next().unwrap().as_str();
+        let symbol = pairs.next().map(|p| p.as_str());
+        let symbol_first = pairs.next().map(|p| p.as_str() == "prefix");
+        self.currency_store.declare(code, symbol, symbol_first);
+        Ok(())
+    }
+
+    fn parse_symbol(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let span = pair.as_span();
+        let (symbol, code) = pair.into_inner().collect_tuple().unwrap();
+        self.currency_store
+            .prefer(symbol.as_str(), code.as_str())
+            .map_err(|e| CoinError::at("error parsing symbol directive", span, e))
+    }
+
+    fn parse_subunit(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let span = pair.as_span();
+        let mut pairs = pair.into_inner();
+        let code = pairs.next().unwrap().as_str();
+        let name = pairs.next().unwrap().as_str();
+        let exponent = pairs.next().map(|p| parse_as(&p)).transpose()?;
+        self.currency_store
+            .declare_subunit(code, name, exponent)
+            .map_err(|e| CoinError::at("error parsing subunit directive", span, e))
+    }
+
+    fn parse_rate(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let span = pair.as_span();
+        let (date, from, to, rate) = pair.into_inner().collect_tuple().unwrap();
+        for code in [from.as_str(), to.as_str()] {
+            if self.currency_store.get_by_code(code).is_none() {
+                let err = CoinError::UnknownCurrency(code.to_string());
+                return Err(CoinError::at("error parsing rate directive", span, err));
+            }
+        }
+        self.exchange_book.insert(
+            parse_as(&date)?,
+            from.as_str(),
+            to.as_str(),
+            parse_as(&rate)?,
+        );
+        Ok(())
+    }
+
+    fn check_code(&self, code: Pair<Rule>) -> Result<(), CoinError> {
+        if self.currency_store.get_by_code(code.as_str()).is_none() {
+            let err = CoinError::UnknownCurrency(code.as_str().to_string());
+            return Err(CoinError::at("error parsing currency", code.as_span(), err));
+        }
+        Ok(())
+    }
+
+    fn parse_open(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let mut date = None;
+        let mut code = None;
+        let mut report = None;
+        let mut accn = None;
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::date => date = Some(parse_as(&pair)?),
+                Rule::code => {
+                    self.check_code(Pair::clone(&pair))?;
+                    code = Some(pair.as_str());
+                }
+                Rule::report_code => {
+                    let code = pair.into_inner().next().unwrap();
+                    self.check_code(Pair::clone(&code))?;
+                    report = Some(code.as_str());
+                }
+                _ => accn = Some(pair),
+            }
+        }
+        let accn = self.parse_accn(accn.unwrap()).declare_open(date, code);
+        if let Some(report) = report {
+            accn.declare_report(report);
+        }
+        Ok(())
+    }
+
+    fn parse_close(&mut self, pair: Pair<Rule>) -> Result<(), CoinError> {
+        let span = pair.as_span();
+        let (date, accn) = pair.into_inner().collect_tuple().unwrap();
+        let date = parse_as(&date)?;
+        self.parse_accn(accn)
+            .declare_close(date)
+            .map_err(|e| CoinError::at("error parsing close directive", span, e))?;
+        Ok(())
+    }
+
+    fn parse_journal(mut self, pair: Pairs<Rule>) -> Result<Journal, CoinError> {
         for pair in pair {
             match pair.as_rule() {
                 Rule::chapter => self.parse_chapter(pair)?,
+                Rule::currency_directive => self.parse_currency(pair)?,
+                Rule::symbol_directive => self.parse_symbol(pair)?,
+                Rule::subunit_directive => self.parse_subunit(pair)?,
+                Rule::rate_directive => self.parse_rate(pair)?,
+                Rule::open_directive => self.parse_open(pair)?,
+                Rule::close_directive => self.parse_close(pair)?,
+                Rule::interest_directive => {
+                    let (accn, rate) = pair.into_inner().collect_tuple().unwrap();
+                    let rate: Decimal = parse_as(&rate.into_inner().next().unwrap())?;
+                    self.parse_accn(accn)
+                        .declare_interest(rate / Decimal::ONE_HUNDRED);
+                }
+                Rule::class_directive => {
+                    let mut pairs = pair.into_inner();
+                    let mut accn = self.parse_accn(pairs.next().unwrap());
+                    for class in pairs {
+                        accn = accn.declare_class(class.as_str());
+                    }
+                }
+                Rule::tax_directive => {
+                    let (accn, category) = pair.into_inner().collect_tuple().unwrap();
+                    self.parse_accn(accn).declare_tax(category.as_str());
+                }
+                Rule::cycle_directive => {
+                    let span = pair.as_span();
+                    let (accn, closing, due) = pair.into_inner().collect_tuple().unwrap();
+                    let (closing, due) = (parse_as(&closing)?, parse_as(&due)?);
+                    if ![closing, due].iter().all(|day| (1..=31).contains(day)) {
+                        let msg = "days of a cycle must be from 1 to 31";
+                        return Err(parse_err(msg, span).into());
+                    }
+                    self.parse_accn(accn)
+                        .declare_cycle(BillingCycle { closing, due });
+                }
+                Rule::budget_directive => {
+                    let mut pairs = pair.into_inner();
+                    let accn = pairs.next().unwrap();
+                    let money = self.parse_money(pairs.next().unwrap())?;
+                    let period = match pairs.next() {
+                        Some(period) => parse_as(&period)?,
+                        None => Period::Monthly,
+                    };
+                    self.parse_accn(accn)
+                        .declare_budget(Budget { money, period });
+                }
+                Rule::sweep_directive => {
+                    let mut pairs = pair.into_inner();
+                    let accn = pairs.next().unwrap();
+                    let mut next = pairs.next().unwrap();
+                    let mut over = None;
+                    if next.as_rule() != Rule::percent {
+                        over = Some(self.parse_money(next)?);
+                        next = pairs.next().unwrap();
+                    }
+                    let share: Decimal = parse_as(&next.into_inner().next().unwrap())?;
+                    let to = self.parse_accn(pairs.next().unwrap()).into_ref().id();
+                    self.parse_accn(accn).declare_sweep(Sweep {
+                        over,
+                        share: share / Decimal::ONE_HUNDRED,
+                        to,
+                    });
+                }
+                Rule::paycheck_directive => {
+                    let (accn, pair) = pair.into_inner().collect_tuple().unwrap();
+                    // a deposit names just the accn, a deduction also its amount
+                    let mut pairs = pair.into_inner();
+                    let to = self.parse_accn(pairs.next().unwrap()).into_ref().id();
+                    let deduction = match pairs.next() {
+                        None => None,
+                        Some(amount) if amount.as_rule() == Rule::percent => {
+                            let share: Decimal = parse_as(&amount.into_inner().next().unwrap())?;
+                            Some(Deduction::Share(share / Decimal::ONE_HUNDRED))
+                        }
+                        Some(amount) => Some(Deduction::Fixed(self.parse_money(amount)?)),
+                    };
+                    let accn = self.parse_accn(accn);
+                    match deduction {
+                        Some(deduction) => accn.declare_deduction(to, deduction),
+                        None => accn.declare_deposit(to),
+                    };
+                }
+                Rule::snapshot_directive => {
+                    let (date, accn, money) = pair.into_inner().collect_tuple().unwrap();
+                    let date = parse_as(&date)?;
+                    let accn = self.parse_accn(accn).into_ref().id();
+                    let money = self.parse_money(money)?;
+                    self.snapshots.push((date, accn, money));
+                }
+                Rule::autocreate_directive => {
+                    let policy = parse_as(&pair.into_inner().next().unwrap())?;
+                    self.accn_tree.set_autocreate(policy);
+                }
+                Rule::rounding_directive => {
+                    let accn = pair.into_inner().next().unwrap();
+                    self.txn_store.rounding = Some(self.parse_accn(accn).into_ref().id());
+                }
+                Rule::sync_directive => {
+                    let (accn, cursor) = pair.into_inner().collect_tuple().unwrap();
+                    self.parse_accn(accn).declare_sync(cursor.as_str());
+                }
+                Rule::dimension_directive => {
+                    let name = pair.into_inner().next().unwrap().as_str();
+                    self.dimensions.declare(name);
+                }
                 _ => unreachable!(),
             }
         }
@@ -121,35 +454,91 @@ impl CoinParser {
         self.into_journal()
     }
 
-    fn into_journal(self) -> Result<Journal> {
+    fn into_journal(mut self) -> Result<Journal, CoinError> {
+        for (date, accn, money) in self.snapshots {
+            self.txn_store.snapshots.insert(date, accn, money);
+        }
         Ok(Journal::new(
             self.accn_tree,
             self.txn_store,
             self.currency_store,
+            self.exchange_book,
+            self.dimensions,
+            self.notes,
         ))
     }
 }
 
 impl Journal {
-    fn from_str(s: &str) -> Result<Self> {
+    pub(crate) fn from_str(s: &str) -> Result<Self, CoinError> {
+        let _span = debug_span!("parse", bytes = s.len()).entered();
+        let start = Instant::now();
         let parser = CoinParser::new();
         let pairs = IdentParser::parse(Rule::grammar, s)?;
 
-        parser.parse_journal(pairs)
+        let journal = parser.parse_journal(pairs)?;
+        debug!(
+            txns = journal.txns.txns.len(),
+            elapsed = ?start.elapsed(),
+            "parsed journal"
+        );
+        Ok(journal)
     }
 
-    pub(crate) fn from_file(f: &str) -> Result<Self> {
-        let input = std::fs::read_to_string(f)?;
+    pub(crate) fn from_file(f: &str) -> Result<Self, CoinError> {
+        let _span = info_span!("read", file = f).entered();
+        let input = std::fs::read_to_string(f).map_err(|e| CoinError::io(f, e))?;
         Self::from_str(&input)
     }
 
-    pub(crate) fn save_to_file(&self, f: &str) -> Result<()> {
-        let mut file = std::fs::File::create(f)?;
-        file.write_all(self.to_string().as_bytes())?;
-        Ok(())
+    /// Read what was appended to the input of `checkpoint`, if the rest of
+    /// `input` is unchanged, and whether it could. It cannot when anything
+    /// else changed or what was appended would read differently after the
+    /// rest, and the journal is left as it was, as it is after an error.
+    pub(crate) fn read_appended(
+        &mut self,
+        input: &str,
+        checkpoint: &Checkpoint,
+    ) -> Result<bool, CoinError> {
+        let Some(appended) = checkpoint.appended(input) else {
+            return Ok(false);
+        };
+        let _span = debug_span!("parse appended", bytes = appended.len()).entered();
+        // what does not read on its own, like a booking under the last date
+        // of the rest, belongs to the rest
+        let Ok(pairs) = IdentParser::parse(Rule::grammar, appended) else {
+            return Ok(false);
+        };
+        // snapshots are taken in after every txn when read in full, so txns
+        // dated before them would not drop them
+        let earliest = pairs
+            .clone()
+            .filter(|pair| pair.as_rule() == Rule::chapter)
+            .filter_map(|pair| pair.into_inner().next()?.as_str().parse().ok())
+            .min();
+        if earliest.is_some_and(|earliest| self.txns.snapshots.dates().any(|date| date >= earliest))
+        {
+            return Ok(false);
+        }
+
+        let start = Instant::now();
+        // read onto a copy, to keep the journal whole if the rest is wrong
+        *self = CoinParser::resume(self.clone(), checkpoint.line).parse_journal(pairs)?;
+        debug!(
+            txns = self.txns.txns.len(),
+            elapsed = ?start.elapsed(),
+            "parsed appended"
+        );
+        Ok(true)
     }
 
-    pub(crate) fn parse_money(&self, money: &str) -> Result<MoneyEntry> {
+    pub(crate) fn save_to_file(&self, f: &str) -> Result<(), CoinError> {
+        let mut file = std::fs::File::create(f).map_err(|e| CoinError::io(f, e))?;
+        file.write_all(self.to_string().as_bytes())
+            .map_err(|e| CoinError::io(f, e))
+    }
+
+    pub(crate) fn parse_money(&self, money: &str) -> Result<MoneyEntry<'_>, CoinError> {
         let pair = IdentParser::parse(Rule::money, money)?.next().unwrap();
         let money = CoinParser::parse_money_builder(pair)?.into_money(&self.currencies)?;
         Ok(money.into_money(&self.currencies))
@@ -183,7 +572,23 @@ r#"2021-01-01 Opening Balances
     assets:cash:checking                                          $1000.00
     equity:opening-balances                                      -$1000.00"#;
 
-    fn parse_money(money: &str) -> Pairs<Rule> {
+    #[rustfmt::skip]
+const META_INPUT: &str =
+r#"2021-01-01 Lunch
+    ; transfer: abc
+    ;link:x 
+    expense:food  $10
+    asset:cash"#;
+
+    #[rustfmt::skip]
+const META_OUTPUT: &str =
+r#"2021-01-01 Lunch
+    ; transfer: abc
+    ; link: x
+    expense:food                                                       $10
+    asset:cash                                                        -$10"#;
+
+    fn parse_money(money: &str) -> Pairs<'_, Rule> {
         IdentParser::parse(Rule::money_test, money).unwrap_or_else(|e| panic!("{}", e))
     }
 
@@ -244,7 +649,7 @@ r#"2021-01-01 Opening Balances
     }
 
     #[test]
-    fn test_ident() -> Result<()> {
+    fn test_ident() -> Result<(), CoinError> {
         let parser = CoinParser::new();
         let pairs =
             IdentParser::parse(Rule::grammar, JOURNAL_INPUT).unwrap_or_else(|e| panic!("{:#}", e));
@@ -254,4 +659,158 @@ r#"2021-01-01 Opening Balances
         assert_eq!(journal.to_string(), JOURNAL_OUTPUT);
         Ok(())
     }
+
+    #[rustfmt::skip]
+const CURRENCY_INPUT: &str =
+r#"currency JPY ¥
+symbol ¥ JPY
+
+2021-01-01 Sushi
+    expense:food  ¥1200
+    asset:cash  -80 CNY
+    equity:fx"#;
+
+    #[test]
+    fn test_currency_directives() {
+        let journal = Journal::from_str(CURRENCY_INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let output = journal.to_string();
+        assert!(output.starts_with("currency JPY ¥ prefix\nsymbol ¥ JPY\n\n"));
+        assert!(output.contains("-80 CNY"));
+
+        let reparsed = Journal::from_str(&output).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reparsed.to_string(), output);
+    }
+
+    #[test]
+    fn test_line_endings() {
+        for input in [META_INPUT, CURRENCY_INPUT] {
+            let expected = Journal::from_str(input).unwrap().to_string();
+            let windows = input.replace("    ", "\t").replace('\n', " \t\r\n");
+            let windows = format!("\u{FEFF}{}\r\n\r\n", windows);
+            let journal = Journal::from_str(&windows).unwrap_or_else(|e| panic!("{:#}", e));
+            assert_eq!(journal.to_string(), expected);
+        }
+
+        let err = Journal::from_str(
+            "\u{FEFF}2021-01-01 Lunch\r\n\texpense:food  $10\r\n\tasset:cash  $5",
+        )
+        .unwrap_err();
+        assert!(matches!(err, CoinError::Parse { line: 1, .. }));
+    }
+
+    #[test]
+    fn test_unicode_accns() {
+        let input = "open 2021-01-01 \"expense:Groß Einkauf\":食品\n\n2021-01-01 Markt\n    \"expense:Groß Einkauf\":食品  $10\n    asset:Bargeld";
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let accn = journal.accns().by_name_unique("食品").ok().unwrap();
+        assert_eq!(accn.abs_name(), "expense:Groß Einkauf:食品");
+        assert!(accn.is_declared());
+
+        let output = journal.to_string();
+        assert!(output.contains("open 2021-01-01 \"expense:Groß Einkauf:食品\""));
+        let reparsed = Journal::from_str(&output).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reparsed.to_string(), output);
+    }
+
+    #[test]
+    fn test_payee() {
+        let input = "2021-01-01 Acme Corp|  team lunch \n    expense:food  $10 #claim\n    asset:cash\n\n2021-01-02 coffee | \n    expense:food  $3\n    asset:cash";
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let txns = journal.txns().collect_vec();
+        assert_eq!(txns[0].payee(), Some("Acme Corp"));
+        assert_eq!(txns[0].desc(), "team lunch");
+        assert!(txns[0].mentions("acme") && txns[0].mentions("LUNCH"));
+        assert_eq!((txns[1].payee(), txns[1].desc()), (Some("coffee"), ""));
+        assert!(!journal.claims(Some("Acme Corp")).is_empty());
+
+        let output = journal.to_string();
+        assert!(output.contains("2021-01-01 Acme Corp | team lunch\n"));
+        let reparsed = Journal::from_str(&output).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reparsed.to_string(), output);
+
+        let plain = Journal::from_str(JOURNAL_INPUT).unwrap();
+        let txn = plain.txns().next().unwrap();
+        assert_eq!((txn.payee(), txn.desc()), (None, "Opening Balances"));
+    }
+
+    #[test]
+    fn test_ambiguous_symbol() {
+        let input = CURRENCY_INPUT.replace("symbol ¥ JPY\n", "");
+        let err = Journal::from_str(&input).unwrap_err();
+        assert!(format!("{:#}", err).contains("ambiguous between CNY (Yuan Renminbi), JPY (Yen)"));
+    }
+
+    #[rustfmt::skip]
+const SUBUNIT_INPUT: &str =
+r#"subunit BTC sat
+
+2021-01-01 Coffee
+    expense:food  2500 sat
+    asset:wallet  -0.000025 BTC"#;
+
+    #[test]
+    fn test_subunit() {
+        let journal = Journal::from_str(SUBUNIT_INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let output = journal.to_string();
+        assert!(output.starts_with("subunit BTC sat 8\n\n"));
+        assert!(output.contains("-2500 sat"));
+
+        let reparsed = Journal::from_str(&output).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reparsed.to_string(), output);
+    }
+
+    #[test]
+    fn test_posting_tags() {
+        let input = "2024-01-01 lunch\n    expense:food  $12 #work #client-a\n    asset:cash #work";
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let output = journal.to_string();
+        assert!(output.contains("$12 #work #client-a\n"));
+        assert!(output.ends_with("-$12 #work"));
+
+        let reparsed = Journal::from_str(&output).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reparsed.to_string(), output);
+    }
+
+    #[test]
+    fn test_meta() {
+        let journal = Journal::from_str(META_INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(journal.to_string(), META_OUTPUT);
+        let txn = journal.txns().next().unwrap();
+        assert_eq!(txn.meta("link"), Some("x"));
+        assert_eq!(txn.meta("missing"), None);
+    }
+
+    #[test]
+    fn test_read_appended() {
+        let input = "2024-01-01 rent\n    expense:rent  $1500\n    asset:bank\n";
+        let checkpoint = Checkpoint::of(input);
+        let mut journal = Journal::from_str(input).unwrap();
+
+        let grown = format!(
+            "{}\n2024-01-02 lunch\n    expense:food  $12\n    asset:cash\n",
+            input
+        );
+        assert!(journal.read_appended(&grown, &checkpoint).unwrap());
+        let full = Journal::from_str(&grown).unwrap();
+        assert_eq!(journal.to_string(), full.to_string());
+        let lines = |journal: &Journal| journal.txns().map(|txn| txn.line()).collect_vec();
+        assert_eq!(lines(&journal), [Some(1), Some(5)]);
+
+        let mut journal = Journal::from_str(input).unwrap();
+        let changed = grown.replace("$1500", "$1600");
+        assert!(!journal.read_appended(&changed, &checkpoint).unwrap());
+        let booking = format!("{}\nlunch\n    expense:food  $12\n    asset:cash", input);
+        assert!(!journal.read_appended(&booking, &checkpoint).unwrap());
+        assert_eq!(journal.txns().count(), 1);
+
+        let unbalanced = format!(
+            "{}\n2024-01-02 lunch\n    expense:food  $12\n    asset:cash  $-1",
+            input
+        );
+        assert!(journal.read_appended(&unbalanced, &checkpoint).is_err());
+        assert_eq!(
+            journal.to_string(),
+            Journal::from_str(input).unwrap().to_string()
+        );
+    }
 }
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::{Accn, Deduction},
+    valuable::Money,
+};
+
+use super::{Journal, Txn, TxnBuilder};
+
+impl Journal {
+    /// `paycheck` directives of every accn, the deposit before the
+    /// deductions in the order they were declared.
+    pub(super) fn paycheck_directives(&self) -> String {
+        self.accns
+            .accns()
+            .sorted_by_key(|accn| accn.abs_name())
+            .filter_map(|accn| Some((accn, accn.paycheck()?)))
+            .flat_map(|(accn, paycheck)| {
+                let deposit = paycheck
+                    .deposit
+                    .map(|to| format!("paycheck {} to {}", accn, to.into_accn(&self.accns)));
+                let deductions = paycheck.deductions.iter().map(move |(to, deduction)| {
+                    let amount = match deduction {
+                        Deduction::Share(share) => {
+                            format!("{}%", (share * Decimal::ONE_HUNDRED).normalize())
+                        }
+                        Deduction::Fixed(money) => money.into_money(&self.currencies).to_string(),
+                    };
+                    format!(
+                        "paycheck {} deduct {} {}",
+                        accn,
+                        to.into_accn(&self.accns),
+                        amount
+                    )
+                });
+                deposit.into_iter().chain(deductions)
+            })
+            .join("\n")
+    }
+
+    /// Accns with a `paycheck` template.
+    pub(crate) fn paycheck_accns(&self) -> impl Iterator<Item = Accn> + '_ {
+        self.accns
+            .accns()
+            .filter(|accn| accn.paycheck().is_some())
+            .map(|accn| accn.id())
+    }
+
+    /// Record a paycheck of `gross` booked to `income`, broken down by its
+    /// template: every deduction withheld, shares of the gross rounded to
+    /// the currency's minor units, and the rest deposited.
+    pub(crate) fn create_paycheck(
+        &mut self,
+        income: Accn,
+        date: NaiveDate,
+        gross: Money,
+    ) -> Result<Txn> {
+        let accn = income.into_accn(&self.accns);
+        let paycheck = accn
+            .paycheck()
+            .ok_or_else(|| anyhow!("no paycheck template for {}", accn))?;
+        let deposit = paycheck.deposit.ok_or_else(|| {
+            anyhow!(
+                "no accn to deposit paychecks of {} to, add `paycheck {} to <accn>`",
+                accn,
+                accn
+            )
+        })?;
+
+        let units = self.currencies.minor_units(gross.currency());
+        let mut txn = TxnBuilder::new(date, format!("paycheck {}", accn.name()));
+        txn.with_posting(income, Some(-gross));
+        let mut net = gross;
+        for (to, deduction) in &paycheck.deductions {
+            let money = match *deduction {
+                Deduction::Share(share) => {
+                    Money::new((gross.amount() * share).round_dp(units), gross.currency())
+                }
+                Deduction::Fixed(money) => money,
+            };
+            net = net.checked_sub(money)?;
+            txn.with_posting_combined(*to, Some(money));
+        }
+        if net.amount().is_sign_negative() {
+            bail!(
+                "deductions exceed the gross of {}",
+                gross.into_money(&self.currencies)
+            );
+        }
+        txn.with_posting(deposit, None);
+        Ok(txn.build(&mut self.txns, &self.currencies)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = "paycheck income:salary to asset:bank
+paycheck income:salary deduct expense:tax:federal 22%
+paycheck income:salary deduct asset:retirement 6%
+paycheck income:salary deduct expense:insurance $180.50";
+
+    #[test]
+    fn test_paycheck() {
+        let mut journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(journal.paycheck_directives(), INPUT);
+
+        let income = journal.paycheck_accns().exactly_one().ok().unwrap();
+        let gross = journal.parse_money("$5000").unwrap().money();
+        let date = NaiveDate::from_ymd_opt(2024, 5, 31).unwrap();
+        let txn = journal.create_paycheck(income, date, gross).unwrap();
+        let balance = |name: &str| {
+            let accn = journal.accns().by_name_unique(name).ok().unwrap();
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string()
+        };
+        assert_eq!(balance("federal"), "$1100.00");
+        assert_eq!(balance("retirement"), "$300.00");
+        assert_eq!(balance("insurance"), "$180.50");
+        assert_eq!(balance("bank"), "$3419.50");
+        assert_eq!(journal.txn(txn).title(), "paycheck salary");
+
+        let gross = journal.parse_money("$100").unwrap().money();
+        assert!(journal.create_paycheck(income, date, gross).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::valuable::{ProviderChain, RateProvider, Valuable};
+
+use super::{entry::TxnEntry, Journal};
+
+/// Who a txn paid, its payee or else its narration, with the store numbers
+/// and reference codes imports leave in it dropped and the case folded,
+/// so `STARBUCKS #1234` and `Starbucks` are the same.
+pub(crate) fn payee_of(txn: &TxnEntry) -> String {
+    let name = txn.payee().unwrap_or(txn.desc());
+    let words = name
+        .split_whitespace()
+        .filter(|word| !word.starts_with('#') && !word.chars().any(|c| c.is_ascii_digit()))
+        .map(|word| word.to_lowercase())
+        .collect_vec();
+    match words.is_empty() {
+        true => name.trim().to_lowercase(),
+        false => words.join(" "),
+    }
+}
+
+/// Spending at one payee.
+#[derive(Debug)]
+pub(crate) struct PayeeRow {
+    pub(crate) name: String,
+    pub(crate) total: Decimal,
+    pub(crate) visits: usize,
+}
+
+impl PayeeRow {
+    /// Spending per visit.
+    pub(crate) fn average(&self) -> Decimal {
+        self.total / Decimal::from(self.visits)
+    }
+}
+
+/// Payees spent the most at, as printed by `payees top`.
+pub(crate) struct TopPayees {
+    code: String,
+    /// Digits amounts are shown with, the minor units of `code`.
+    units: u32,
+    rows: Vec<PayeeRow>,
+}
+
+impl Journal {
+    /// The `n` payees the most was spent at from `since` through `until`,
+    /// in `code` at the rates of each txn's date. Spending is what a txn
+    /// books to expense accns.
+    pub(crate) fn top_payees(
+        &self,
+        n: usize,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+        code: &str,
+        fallback: &dyn RateProvider,
+    ) -> Result<TopPayees> {
+        let Some(currency) = self.currencies.get_by_code(code) else {
+            bail!("code {} not found", code);
+        };
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let expense = self.accns.expense();
+        let mut payees: HashMap<String, PayeeRow> = HashMap::new();
+        let txns = self
+            .txns()
+            .filter(|txn| since.is_none_or(|since| txn.date() >= since))
+            .filter(|txn| until.is_none_or(|until| txn.date() <= until));
+        for txn in txns {
+            let spent: Valuable = txn
+                .postings()
+                .filter(|p| p.accn().is_descendent_of(expense))
+                .map(|p| p.money().money())
+                .sum();
+            let spent = self.value_in(spent, code, txn.date(), &rates)?;
+            if spent <= Decimal::ZERO {
+                continue;
+            }
+            let name = payee_of(&txn);
+            let row = payees.entry(name.clone()).or_insert(PayeeRow {
+                name,
+                total: Decimal::ZERO,
+                visits: 0,
+            });
+            row.total += spent;
+            row.visits += 1;
+        }
+        let rows = payees
+            .into_values()
+            .sorted_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)))
+            .take(n)
+            .collect();
+        Ok(TopPayees {
+            code: code.to_string(),
+            units: self.currencies.minor_units(currency),
+            rows,
+        })
+    }
+}
+
+impl Display for TopPayees {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            format!(
+                "{:<4}{:<36}{:>14}{:>8}{:>14}",
+                "", "payee", "total", "visits", "average"
+            )
+            .bold()
+        )?;
+        for (i, row) in self.rows.iter().enumerate() {
+            write!(
+                f,
+                "\n{:<4}{:<36}{:>14}{:>8}{:>14}",
+                i + 1,
+                row.name,
+                format!("{} {}", row.total.round_dp(self.units), self.code),
+                row.visits,
+                format!("{} {}", row.average().round_dp(self.units), self.code),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-03-01 STARBUCKS #1234
+    expense:food:coffee  $6
+    asset:bank
+
+2024-03-02 Starbucks
+    expense:food:coffee  $4
+    asset:bank
+
+2024-03-03 landlord | march rent
+    expense:home:rent  $900
+    asset:bank
+
+2024-03-04 salary
+    asset:bank  $3000
+    income:salary
+
+2024-04-01 starbucks 0042
+    expense:food:coffee  $5
+    asset:bank"#;
+
+    #[test]
+    fn test_top_payees() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 3, 31);
+        let top = journal
+            .top_payees(10, None, until, "USD", &journal.rates)
+            .unwrap();
+        let rows = top
+            .rows
+            .iter()
+            .map(|row| (row.name.as_str(), row.total, row.visits))
+            .collect_vec();
+        assert_eq!(
+            rows,
+            [("landlord", dec!(900), 1), ("starbucks", dec!(10), 2)]
+        );
+        assert_eq!(top.rows[1].average(), dec!(5));
+
+        let top = journal
+            .top_payees(1, None, None, "USD", &journal.rates)
+            .unwrap();
+        assert_eq!(top.rows.len(), 1);
+
+        // yen are spent whole
+        let input = "2024-03-01 sushi\n    expense:food  300 JPY\n    asset:bank\n\n".repeat(2)
+            + "2024-03-02 sushi\n    expense:food  400 JPY\n    asset:bank";
+        let journal = Journal::from_str(&input).unwrap();
+        let top = journal
+            .top_payees(1, None, None, "JPY", &journal.rates)
+            .unwrap();
+        let text = top.to_string();
+        assert!(text.contains("333 JPY"), "{}", text);
+        assert!(!text.contains("333.33"), "{}", text);
+    }
+}
@@ -0,0 +1,222 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use itertools::Itertools;
+
+use crate::valuable::ValuableEntry;
+
+use super::{entry::PostingEntry, register::PostingQuery};
+
+/// What the rows or the columns of a pivot table group postings by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PivotKey {
+    /// The accn cut to its first parts, like `expense:food` for depth 2.
+    Accn(usize),
+    Month,
+    Payee,
+    /// Every tag of the posting, so one posting may count in several.
+    Tag,
+    Currency,
+}
+
+impl FromStr for PivotKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, depth) = match s.split_once(':') {
+            Some((key, depth)) => (key, Some(depth.parse()?)),
+            None => (s, None),
+        };
+        match (key, depth) {
+            ("accn", Some(0)) => Err(anyhow!("accn depth must be at least 1")),
+            ("accn", depth) => Ok(PivotKey::Accn(depth.unwrap_or(usize::MAX))),
+            ("month", None) => Ok(PivotKey::Month),
+            ("payee", None) => Ok(PivotKey::Payee),
+            ("tag", None) => Ok(PivotKey::Tag),
+            ("currency", None) => Ok(PivotKey::Currency),
+            _ => Err(anyhow!(
+                "cannot pivot by {}, expected accn[:depth], month, payee, tag or currency",
+                s
+            )),
+        }
+    }
+}
+
+impl PivotKey {
+    /// Labels of the groups `posting` falls in.
+    fn labels(self, posting: PostingEntry) -> Vec<String> {
+        match self {
+            PivotKey::Accn(depth) => {
+                vec![posting.accn().abs_name().split(':').take(depth).join(":")]
+            }
+            PivotKey::Month => vec![posting.txn().date().format("%Y-%m").to_string()],
+            PivotKey::Payee => vec![posting.txn().payee().unwrap_or("-").to_string()],
+            PivotKey::Tag if posting.tags().is_empty() => vec!["-".to_string()],
+            PivotKey::Tag => posting
+                .tags()
+                .iter()
+                .map(|tag| format!("#{}", tag))
+                .collect(),
+            PivotKey::Currency => vec![posting.money().code().to_string()],
+        }
+    }
+}
+
+/// Postings cross-tabulated by two keys, with the totals of every row and
+/// column.
+pub(crate) struct Pivot<'a> {
+    rows: Vec<String>,
+    cols: Vec<String>,
+    /// Postings of every row and column.
+    cells: BTreeMap<(String, String), Vec<PostingEntry<'a>>>,
+}
+
+impl<'a> PostingQuery<'a> {
+    pub(crate) fn into_pivot(self, rows: PivotKey, cols: PivotKey) -> Pivot<'a> {
+        let mut cells: BTreeMap<(String, String), Vec<_>> = BTreeMap::new();
+        for posting in self.into_postings() {
+            for row in rows.labels(posting) {
+                for col in cols.labels(posting) {
+                    cells.entry((row.clone(), col)).or_default().push(posting);
+                }
+            }
+        }
+        Pivot {
+            rows: cells.keys().map(|(row, _)| row.clone()).unique().collect(),
+            cols: cells
+                .keys()
+                .map(|(_, col)| col.clone())
+                .sorted()
+                .dedup()
+                .collect(),
+            cells,
+        }
+    }
+}
+
+impl<'a> Pivot<'a> {
+    /// Total of the cells whose row and column pass `filter`.
+    fn total(&self, filter: impl Fn(&str, &str) -> bool) -> ValuableEntry<'a> {
+        self.cells
+            .iter()
+            .filter(|((row, col), _)| filter(row, col))
+            .flat_map(|(_, postings)| postings.iter().map(|p| p.money()))
+            .sum()
+    }
+
+    fn row_total(&self, row: &str) -> ValuableEntry<'a> {
+        self.total(|r, _| r == row)
+    }
+
+    fn col_total(&self, col: &str) -> ValuableEntry<'a> {
+        self.total(|_, c| c == col)
+    }
+}
+
+impl Display for Pivot<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.cells.is_empty() {
+            return write!(f, "no postings to pivot");
+        }
+        let total = "total".to_string();
+        let cell = |row: &str, col: &str| match self.cells.contains_key(&(row.into(), col.into())) {
+            true => self.total(|r, c| r == row && c == col).to_string(),
+            false => String::new(),
+        };
+
+        // every row as its label, its cells and its total
+        let mut table = vec![std::iter::once(String::new())
+            .chain(self.cols.iter().cloned())
+            .chain([total.clone()])
+            .collect_vec()];
+        for row in &self.rows {
+            table.push(
+                std::iter::once(row.clone())
+                    .chain(self.cols.iter().map(|col| cell(row, col)))
+                    .chain([self.row_total(row).to_string()])
+                    .collect(),
+            );
+        }
+        let grand = self.total(|_, _| true);
+        table.push(
+            std::iter::once(total)
+                .chain(self.cols.iter().map(|col| self.col_total(col).to_string()))
+                .chain([grand.to_string()])
+                .collect(),
+        );
+
+        let widths = (0..table[0].len())
+            .map(|i| {
+                table
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .max()
+                    .unwrap()
+            })
+            .collect_vec();
+        let last = table.len() - 1;
+        for (i, row) in table.iter().enumerate() {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(j, (text, width))| match j {
+                    0 => format!("{:<width$}", text, width = width),
+                    _ => format!("{:>width$}", text, width = width),
+                })
+                .join("  ");
+            match i == 0 || i == last {
+                true => write!(f, "{}", line.bold())?,
+                false => write!(f, "{}", line)?,
+            }
+            if i < last {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::journal::{register::QueryType, Journal};
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-03-01 landlord | rent
+    expense:home:rent  $900
+    asset:bank
+
+2024-03-02 lunch
+    expense:food:dining  $12 #work
+    asset:bank
+
+2024-04-02 market | groceries
+    expense:food:groceries  $80
+    asset:bank
+
+2024-04-05 dinner
+    expense:food:dining  $30
+    asset:bank"#;
+
+    #[test]
+    fn test_pivot() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let rows: PivotKey = "accn:2".parse().unwrap();
+        let pivot = journal
+            .query(QueryType::MatchAccn("expense".into()))
+            .into_pivot(rows, PivotKey::Month);
+        assert_eq!(pivot.rows, ["expense:food", "expense:home"]);
+        assert_eq!(pivot.cols, ["2024-03", "2024-04"]);
+        assert_eq!(pivot.row_total("expense:food").to_string(), "$122");
+        assert_eq!(pivot.col_total("2024-04").to_string(), "$110");
+
+        let pivot = journal
+            .query(QueryType::MatchAccn("expense".into()))
+            .into_pivot(PivotKey::Payee, PivotKey::Tag);
+        assert_eq!(pivot.rows, ["-", "landlord", "market"]);
+        assert_eq!(pivot.cols, ["#work", "-"]);
+        assert!("week".parse::<PivotKey>().is_err());
+    }
+}
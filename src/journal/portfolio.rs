@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+
+use crate::valuable::{ProviderChain, RateProvider};
+
+use super::{lots::Booking, Journal};
+
+/// One commodity held, amounts in the base currency.
+#[derive(Debug)]
+pub(crate) struct Holding {
+    code: String,
+    units: Decimal,
+    /// What the units held cost.
+    cost: Decimal,
+    /// Latest price of one unit.
+    price: Decimal,
+}
+
+impl Holding {
+    pub(crate) fn avg_cost(&self) -> Decimal {
+        self.cost / self.units
+    }
+
+    pub(crate) fn value(&self) -> Decimal {
+        self.units * self.price
+    }
+
+    /// Gain or loss not realized by a sale yet.
+    pub(crate) fn unrealized(&self) -> Decimal {
+        self.value() - self.cost
+    }
+}
+
+/// Every commodity held, as printed by `portfolio`.
+#[derive(Debug)]
+pub(crate) struct Portfolio {
+    date: NaiveDate,
+    base: String,
+    /// Digits amounts are shown with, the minor units of `base`.
+    units: u32,
+    holdings: Vec<Holding>,
+}
+
+impl Portfolio {
+    pub(crate) fn cost(&self) -> Decimal {
+        self.holdings.iter().map(|h| h.cost).sum()
+    }
+
+    pub(crate) fn value(&self) -> Decimal {
+        self.holdings.iter().map(Holding::value).sum()
+    }
+
+    /// Share of the portfolio's value in `holding`.
+    fn weight(&self, holding: &Holding) -> Option<Decimal> {
+        let value = self.value();
+        (!value.is_zero()).then(|| holding.value() / value)
+    }
+}
+
+impl Journal {
+    /// Commodities held in the asset accns at the end of `date`, at their
+    /// average cost and the latest price known by `date`, in `base`.
+    pub(crate) fn portfolio(
+        &self,
+        base: &str,
+        date: NaiveDate,
+        fallback: &dyn RateProvider,
+    ) -> Result<Portfolio> {
+        let Some(currency) = self.currencies.get_by_code(base) else {
+            bail!("code {} not found", base);
+        };
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let mut holdings = Vec::new();
+        for commodity in self.commodities() {
+            let lots = self.lots(commodity, base, date, Booking::Average, &rates)?;
+            let units: Decimal = lots.iter().map(|lot| lot.units).sum();
+            if units.is_zero() {
+                continue;
+            }
+            let code = self.currencies.code(commodity);
+            holdings.push(Holding {
+                code: code.to_string(),
+                units: units.normalize(),
+                cost: lots.iter().map(|lot| lot.cost).sum(),
+                price: rates.rate(code, base, date)?,
+            });
+        }
+        holdings.sort_by(|a, b| b.value().cmp(&a.value()).then(a.code.cmp(&b.code)));
+        Ok(Portfolio {
+            date,
+            base: base.to_string(),
+            units: self.currencies.minor_units(currency),
+            holdings,
+        })
+    }
+}
+
+impl Display for Portfolio {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<8}{:>12}{:>12}{:>12}{:>14}{:>14}{:>8}",
+            "".bold(),
+            "units".bold(),
+            "avg cost".bold(),
+            "price".bold(),
+            format!("value {}", self.base).bold(),
+            "unrealized".bold(),
+            "weight".bold()
+        )?;
+        for holding in &self.holdings {
+            let weight = match self.weight(holding) {
+                Some(weight) => format!("{:.1}%", weight * Decimal::ONE_HUNDRED),
+                None => "-".to_string(),
+            };
+            writeln!(
+                f,
+                "{:<8}{:>12}{:>12}{:>12}{:>14}{:>14}{:>8}",
+                holding.code,
+                holding.units.to_string(),
+                holding.avg_cost().round_dp(self.units).to_string(),
+                holding.price.round_dp(self.units).to_string(),
+                holding.value().round_dp(self.units).to_string(),
+                format!("{:+}", holding.unrealized().round_dp(self.units)),
+                weight,
+            )?;
+        }
+        write!(
+            f,
+            "{:<44}{:>14}{:>14}  cost {}  as of {}",
+            "total".bold(),
+            self.value().round_dp(self.units).to_string(),
+            format!("{:+}", (self.value() - self.cost()).round_dp(self.units)),
+            self.cost().round_dp(self.units),
+            self.date
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"currency AAPL
+rate 2024-05-01 BTC USD 55000
+rate 2024-06-01 AAPL USD 200
+
+2024-01-05 salary
+    asset:bank  $10000
+    income:salary
+
+2024-02-01 buy apple
+    asset:broker  10 AAPL
+    asset:bank  $-1500
+    equity:trading
+
+2024-03-01 buy apple
+    asset:broker  10 AAPL
+    asset:bank  $-1700
+    expense:fees  $10
+    asset:bank  $-10
+    equity:trading
+
+2024-04-01 move to another broker
+    asset:broker  -5 AAPL
+    asset:ira  5 AAPL
+
+2024-05-01 sell apple
+    asset:ira  -5 AAPL
+    asset:bank  $950
+    equity:trading
+
+2024-05-02 gift
+    asset:wallet  0.1 BTC
+    income:gift"#;
+
+    #[test]
+    fn test_portfolio() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let date = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let portfolio = journal
+            .portfolio("USD", date, &ProviderChain::default())
+            .unwrap();
+        let [btc, apple] = &portfolio.holdings[..] else {
+            panic!("{:?}", portfolio.holdings);
+        };
+        assert_eq!(apple.code, "AAPL");
+        assert_eq!(apple.units, dec!(15));
+        assert_eq!(apple.avg_cost(), dec!(160.50));
+        assert_eq!(apple.value(), dec!(3000));
+        assert_eq!(apple.unrealized(), dec!(592.50));
+
+        // a gift costs what it was worth that day
+        assert_eq!(btc.code, "BTC");
+        assert_eq!(btc.cost, dec!(5500));
+        assert_eq!(btc.value(), dec!(5500));
+        assert_eq!(portfolio.value(), dec!(8500));
+        assert_eq!(portfolio.weight(btc).unwrap().round_dp(3), dec!(0.647));
+
+        // valued in bitcoin, down to the satoshi
+        let rates = "rate 2024-01-01 USD BTC 0.0000181818\nrate 2024-06-01 AAPL BTC 0.0036363636\n";
+        let input = INPUT.replacen('\n', &format!("\n{}", rates), 1);
+        let journal = Journal::from_str(&input).unwrap_or_else(|e| panic!("{:#}", e));
+        let portfolio = journal
+            .portfolio("BTC", date, &ProviderChain::default())
+            .unwrap();
+        let text = portfolio.to_string();
+        assert!(text.contains("0.05454545   +0.01077277"), "{}", text);
+    }
+}
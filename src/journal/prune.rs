@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Result};
+
+use crate::accn::{Accn, AccnEntry};
+
+use super::Journal;
+
+impl Journal {
+    /// Accns a directive or snapshot names, as the subject of a directive
+    /// or where it sends money, which saving writes back.
+    fn directive_accns(&self) -> HashSet<Accn> {
+        let mut used: HashSet<Accn> = self.txns.snapshots.accns().collect();
+        used.extend(self.txns.rounding);
+        for accn in self.accns.accns() {
+            let directed = accn.budget().is_some()
+                || accn.interest().is_some()
+                || accn.cycle().is_some()
+                || accn.own_tax_category().is_some()
+                || accn.own_report_currency().is_some()
+                || !accn.own_classes().is_empty()
+                || !accn.sweeps().is_empty()
+                || accn.paycheck().is_some();
+            if directed {
+                used.insert(accn.id());
+            }
+            used.extend(accn.sweeps().iter().map(|sweep| sweep.to));
+            if let Some(paycheck) = accn.paycheck() {
+                used.extend(paycheck.deposit);
+                used.extend(paycheck.deductions.iter().map(|(to, _)| *to));
+            }
+        }
+        used
+    }
+
+    /// Remove `accn` from the tree, refusing while postings are booked to it
+    /// or a directive names it.
+    pub(crate) fn remove_accn(&mut self, accn: Accn) -> Result<()> {
+        let used = self.postings().filter(|p| p.accn().id() == accn).count();
+        if used > 0 {
+            let name = accn.into_accn(&self.accns);
+            bail!("{} still has {} postings", name, used);
+        }
+        if self.directive_accns().contains(&accn) {
+            bail!("a directive still names {}", accn.into_accn(&self.accns));
+        }
+        self.accns.remove(accn)
+    }
+
+    /// Accns with no postings, named by no directive or snapshot and with
+    /// nothing but such accns below them, deepest first so each can be removed in turn.
+    pub(crate) fn prunable(&self) -> Vec<Accn> {
+        fn collect(accn: AccnEntry, used: &HashSet<Accn>, out: &mut Vec<Accn>) -> bool {
+            let mut empty = true;
+            for child in accn.children() {
+                empty &= collect(child, used, out);
+            }
+            let keep = used.contains(&accn.id()) || accn.is_declared() || accn.closed().is_some();
+            let prunable = empty && !keep;
+            if prunable {
+                out.push(accn.id());
+            }
+            prunable
+        }
+
+        let mut used: HashSet<Accn> = self.postings().map(|p| p.accn().id()).collect();
+        used.extend(self.directive_accns());
+        let mut out = Vec::new();
+        for top in self.accns.root().children() {
+            for accn in top.children() {
+                collect(accn, &used, &mut out);
+            }
+        }
+        out
+    }
+
+    /// Remove every prunable accn, returning their names.
+    pub(crate) fn prune(&mut self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for accn in self.prunable() {
+            names.push(accn.into_accn(&self.accns).abs_name());
+            self.remove_accn(accn)?;
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"open 2024-01-01 asset:savings
+
+2024-01-01 lunch
+    expense:food:dining  $12
+    asset:bank"#;
+
+    #[test]
+    fn test_prune() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        for path in ["expense:food:takeout", "expense:old:misc:stuff"] {
+            path.split(':')
+                .fold(journal.accns_mut().root_mut(), |accn, name| {
+                    accn.or_open_child(name)
+                });
+        }
+        let dining = journal.accns().by_name_unique("dining").ok().unwrap().id();
+        assert!(journal.remove_accn(dining).is_err());
+        let asset = journal.accns().asset().id();
+        assert!(journal.remove_accn(asset).is_err());
+
+        let pruned = journal.prune().unwrap();
+        assert_eq!(
+            pruned,
+            [
+                "expense:food:takeout",
+                "expense:old:misc:stuff",
+                "expense:old:misc",
+                "expense:old"
+            ]
+        );
+        assert!(journal.accns().by_name_unique("savings").is_ok());
+        assert!(journal.prunable().is_empty());
+    }
+
+    #[test]
+    fn test_prune_directive_accns() {
+        let input = r#"rounding equity:rounding
+sweep income:salary 20% to asset:savings
+paycheck income:salary to asset:checking
+budget expense:travel $300
+snapshot 2024-01-31 asset:cash $40
+
+2024-01-02 salary
+    income:salary  -$1000
+    asset:checking"#;
+        let mut journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        journal
+            .accns_mut()
+            .root_mut()
+            .or_open_child("expense")
+            .or_open_child("unused");
+        assert_eq!(journal.prune().unwrap(), ["expense:unused"]);
+        let rounding = journal
+            .accns()
+            .by_name_unique("rounding")
+            .ok()
+            .unwrap()
+            .id();
+        assert!(journal.remove_accn(rounding).is_err());
+
+        let saved = journal.to_string();
+        let reread = Journal::from_str(&saved).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reread.to_string(), saved);
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::Accn,
+    valuable::{Currency, Money},
+};
+
+use super::{Imported, Journal, TxnBuilder};
+
+/// Metadata key identifying a txn imported from QIF, so importing an
+/// overlapping export again skips what is already there.
+pub(crate) const QIF_META: &str = "qif";
+
+/// One `^` terminated record of a QIF file, the split lines in order.
+#[derive(Debug, Default)]
+struct Record {
+    date: Option<NaiveDate>,
+    amount: Option<Decimal>,
+    payee: Option<String>,
+    memo: Option<String>,
+    category: Option<String>,
+    /// Category and amount of every `S`/`$` pair.
+    splits: Vec<(String, Option<Decimal>)>,
+}
+
+/// `name` as an accn name, like `Dining Out` as `dining-out`.
+fn accn_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '_' {
+            true => c,
+            false => '-',
+        })
+        .collect();
+    match name.starts_with(char::is_alphabetic) {
+        true => name,
+        false => format!("qif-{}", name),
+    }
+}
+
+/// Dates like `03/25/2024`, `3/25'24` or `2024-03-25`, month first.
+fn parse_date(s: &str) -> Result<NaiveDate> {
+    let s = s.trim();
+    if let Ok(date) = s.parse() {
+        return Ok(date);
+    }
+    let parts = s
+        .split(['/', '\'', '-'])
+        .map(|part| part.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid QIF date {}", s))?;
+    let [month, day, year] = parts[..] else {
+        bail!("invalid QIF date {}", s);
+    };
+    let year = if year < 100 { year + 2000 } else { year };
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+        .ok_or_else(|| anyhow!("invalid QIF date {}", s))
+}
+
+fn parse_amount(s: &str) -> Result<Decimal> {
+    let amount = s.trim().replace(',', "");
+    amount
+        .parse()
+        .with_context(|| format!("invalid QIF amount {}", s))
+}
+
+impl Journal {
+    /// Accn the QIF account `name` of `kind`, like `Bank` or `CCard`, maps
+    /// to, opened if new.
+    fn qif_account(&mut self, name: &str, kind: &str) -> Accn {
+        let root = match kind {
+            "CCard" | "Oth L" => "liability",
+            _ => "asset",
+        };
+        self.accns
+            .root_mut()
+            .or_open_child(root)
+            .or_open_child(&accn_name(name))
+            .declare_open(None, None)
+            .into_ref()
+            .id()
+    }
+
+    /// Accn of a QIF category like `Food:Groceries`, under income or expense
+    /// as the category list says or else as `amount`, what it is booked,
+    /// suggests. `[Savings]` names the account of a transfer instead.
+    fn qif_category(
+        &mut self,
+        category: &str,
+        amount: Decimal,
+        incomes: &HashSet<String>,
+        accounts: &HashMap<String, Accn>,
+    ) -> Accn {
+        if let Some(account) = category.strip_prefix('[').and_then(|c| c.strip_suffix(']')) {
+            return match accounts.get(account) {
+                Some(accn) => *accn,
+                None => self.qif_account(account, "Bank"),
+            };
+        }
+        // a class after a slash is not part of the category
+        let category = category.split('/').next().unwrap();
+        let income = match incomes.contains(category) {
+            true => true,
+            false => amount < Decimal::ZERO,
+        };
+        let mut accn =
+            self.accns
+                .root_mut()
+                .or_open_child(if income { "income" } else { "expense" });
+        for part in category.split(':').filter(|part| !part.trim().is_empty()) {
+            accn = accn.or_open_child(&accn_name(part));
+        }
+        accn.declare_open(None, None).into_ref().id()
+    }
+
+    /// Import the txns of a QIF file, in `code`. Records before any
+    /// `!Account` header go to `account`. Categories become income and
+    /// expense accns, splits postings of their own, and a transfer between
+    /// two accounts of the file is imported once.
+    pub(crate) fn import_qif(
+        &mut self,
+        input: &str,
+        account: Option<Accn>,
+        code: &str,
+    ) -> Result<Imported> {
+        self.importing(|journal, import| journal.read_qif(input, account, code, import))
+    }
+
+    fn read_qif(
+        &mut self,
+        input: &str,
+        account: Option<Accn>,
+        code: &str,
+        import: &mut Imported,
+    ) -> Result<()> {
+        let currency = self
+            .currencies
+            .get_by_code(code)
+            .ok_or_else(|| anyhow!("code {} not found", code))?;
+        let imported: HashSet<String> = self
+            .txns()
+            .filter_map(|txn| txn.meta(QIF_META).map(str::to_string))
+            .collect();
+
+        let (mut section, mut record) = (String::new(), Record::default());
+        let (mut header, mut current) = (Vec::<(char, String)>::new(), account);
+        let mut accounts: HashMap<String, Accn> = HashMap::new();
+        let mut incomes = HashSet::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('!') {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (field, value) = line.split_at(line.chars().next().unwrap().len_utf8());
+            let value = value.trim().to_string();
+            let in_record = |e: anyhow::Error| e.context(format!("in QIF line {}", i + 1));
+
+            match section.as_str() {
+                "Account" => match field {
+                    "^" => {
+                        let field = |c: char| header.iter().find(|(f, _)| *f == c);
+                        let (_, name) =
+                            field('N').ok_or_else(|| anyhow!("account without a name"))?;
+                        let kind = field('T').map_or("Bank", |(_, kind)| kind.as_str());
+                        let accn = self.qif_account(name, kind);
+                        accounts.insert(name.clone(), accn);
+                        current = Some(accn);
+                        header.clear();
+                    }
+                    _ => header.push((field.chars().next().unwrap(), value)),
+                },
+                "Type:Cat" => match field {
+                    "N" => header = vec![('N', value)],
+                    "I" => incomes.extend(header.drain(..).map(|(_, name)| name)),
+                    _ => {}
+                },
+                section if section.starts_with("Type:") => match field {
+                    "D" => record.date = Some(parse_date(&value).map_err(in_record)?),
+                    "T" | "U" => record.amount = Some(parse_amount(&value).map_err(in_record)?),
+                    "P" => record.payee = Some(value),
+                    "M" => record.memo = Some(value),
+                    "L" => record.category = Some(value),
+                    "S" => record.splits.push((value, None)),
+                    "$" => {
+                        let amount = parse_amount(&value).map_err(in_record)?;
+                        if let Some(split) = record.splits.last_mut() {
+                            split.1 = Some(amount);
+                        }
+                    }
+                    "^" => {
+                        let record = std::mem::take(&mut record);
+                        let account = current.ok_or_else(|| {
+                            anyhow!("no account to import QIF line {} into", i + 1)
+                        })?;
+                        let txn = self
+                            .qif_txn(record, account, currency, &accounts, &incomes, &mut seen)
+                            .map_err(in_record)?;
+                        match txn {
+                            Some(txn) if imported.contains(&txn.0) => import.skipped += 1,
+                            Some((key, builder)) => {
+                                let mut builder = builder;
+                                builder.with_meta(QIF_META, key);
+                                import
+                                    .added
+                                    .push(builder.build(&mut self.txns, &self.currencies)?);
+                            }
+                            None => {}
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Txn of `record`, keyed by what identifies it across imports. None
+    /// for the second half of a transfer already seen in the file.
+    fn qif_txn(
+        &mut self,
+        record: Record,
+        account: Accn,
+        currency: Currency,
+        accounts: &HashMap<String, Accn>,
+        incomes: &HashSet<String>,
+        seen: &mut HashMap<String, usize>,
+    ) -> Result<Option<(String, TxnBuilder)>> {
+        let date = record
+            .date
+            .ok_or_else(|| anyhow!("record without a date"))?;
+        let amount = record
+            .amount
+            .ok_or_else(|| anyhow!("record without an amount"))?;
+        let mut postings = vec![(account, amount)];
+        match record.splits.is_empty() {
+            true => {
+                let category = record.category.as_deref().unwrap_or("Uncategorized");
+                postings.push((
+                    self.qif_category(category, -amount, incomes, accounts),
+                    -amount,
+                ));
+            }
+            false => {
+                for (category, split) in &record.splits {
+                    let split =
+                        split.ok_or_else(|| anyhow!("split {} without an amount", category))?;
+                    postings.push((
+                        self.qif_category(category, -split, incomes, accounts),
+                        -split,
+                    ));
+                }
+            }
+        }
+
+        // both halves of a transfer share the key, so the second is dropped
+        let sides = postings
+            .iter()
+            .map(|(accn, amount)| format!("{}{}", accn.into_accn(&self.accns), amount.abs()))
+            .sorted()
+            .join(" ");
+        let is_transfer = postings.len() == 2 && accounts.values().contains(&postings[1].0);
+        let key = format!("{} {}", date, sides);
+        let n = seen.entry(key.clone()).or_default();
+        *n += 1;
+        if is_transfer && *n == 2 {
+            return Ok(None);
+        }
+        let key = match *n {
+            1 => key,
+            n => format!("{} #{}", key, n),
+        };
+
+        let desc = match (record.payee, record.memo) {
+            (Some(payee), Some(memo)) => format!("{} | {}", payee, memo),
+            (Some(desc), None) | (None, Some(desc)) => desc,
+            (None, None) => "qif import".to_string(),
+        };
+        let mut txn = TxnBuilder::new(date, desc);
+        for (accn, amount) in postings {
+            txn.with_posting(accn, Some(Money::new(amount, currency)));
+        }
+        Ok(Some((key, txn)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const QIF: &str = r#"!Type:Cat
+NPaycheck
+I
+^
+!Account
+NChecking
+TBank
+^
+!Type:Bank
+D03/01/2024
+T2,500.00
+PAcme
+LPaycheck
+^
+D03/05'24
+T-45.10
+PGrocer
+MWeekly shop
+LFood:Groceries
+^
+D03/06/2024
+T-100.00
+PMarket
+SFood:Groceries
+$-60.00
+SHousehold
+EDish soap
+$-40.00
+^
+D03/08/2024
+T-300.00
+L[Savings]
+^
+!Account
+NSavings
+TBank
+^
+!Type:Bank
+D03/08/2024
+T300.00
+L[Checking]
+^"#;
+
+    #[test]
+    fn test_import_qif() {
+        let mut journal = Journal::from_str("").unwrap();
+        let import = journal.import_qif(QIF, None, "USD").unwrap();
+        assert_eq!(import.added.len(), 4);
+        let accn = |journal: &Journal, name: &str| {
+            let accn = journal.accns().by_name_unique(name).ok().unwrap();
+            (
+                accn.abs_name(),
+                journal
+                    .balance(accn)
+                    .into_valuable(journal.currencies())
+                    .to_string(),
+            )
+        };
+        assert_eq!(
+            accn(&journal, "checking"),
+            ("asset:checking".into(), "$2054.90".into())
+        );
+        assert_eq!(
+            accn(&journal, "paycheck"),
+            ("income:paycheck".into(), "-$2500.00".into())
+        );
+        assert_eq!(
+            accn(&journal, "groceries"),
+            ("expense:food:groceries".into(), "$105.10".into())
+        );
+        assert_eq!(
+            accn(&journal, "household"),
+            ("expense:household".into(), "$40.00".into())
+        );
+        // the transfer is in the file twice, once per account
+        assert_eq!(
+            accn(&journal, "savings"),
+            ("asset:savings".into(), "$300.00".into())
+        );
+
+        let mut journal = Journal::from_str(&journal.to_string()).unwrap();
+        let again = journal.import_qif(QIF, None, "USD").unwrap();
+        assert_eq!((again.added.len(), again.skipped), (0, 4));
+    }
+}
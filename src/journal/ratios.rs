@@ -0,0 +1,219 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use chrono::{Duration, NaiveDate};
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::AccnEntry,
+    period::Period,
+    valuable::{ProviderChain, RateProvider, Valuable},
+};
+
+use super::Journal;
+
+/// Financial ratios of one period, amounts in a single currency.
+#[derive(Debug)]
+pub(crate) struct Ratios {
+    start: NaiveDate,
+    period: Period,
+    income: Decimal,
+    expense: Decimal,
+    /// Expense per direct child of `expense`, largest first.
+    categories: Vec<(String, Decimal)>,
+    assets: Decimal,
+    /// What is owed, as a positive amount.
+    debt: Decimal,
+    liquid: Decimal,
+    /// Length of the period in months, for the runway.
+    months: Decimal,
+}
+
+impl Ratios {
+    /// Share of the income not spent.
+    pub(crate) fn savings_rate(&self) -> Option<Decimal> {
+        ratio(self.income - self.expense, self.income)
+    }
+
+    pub(crate) fn debt_to_asset(&self) -> Option<Decimal> {
+        ratio(self.debt, self.assets)
+    }
+
+    /// Months the liquid assets last at the spending of the period.
+    pub(crate) fn runway(&self) -> Option<Decimal> {
+        ratio(self.liquid, self.expense / self.months)
+    }
+}
+
+fn ratio(part: Decimal, whole: Decimal) -> Option<Decimal> {
+    (!whole.is_zero()).then(|| part / whole)
+}
+
+fn percent(ratio: Option<Decimal>) -> String {
+    match ratio {
+        Some(ratio) => format!("{:.1}%", ratio * Decimal::ONE_HUNDRED),
+        None => "-".to_string(),
+    }
+}
+
+impl Display for Ratios {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let runway = match self.runway() {
+            Some(months) => format!("{:.1} months", months),
+            None => "-".to_string(),
+        };
+        write!(
+            f,
+            "{:<10} savings {:>7}  debt/assets {:>7}  runway {}",
+            self.period.label(self.start),
+            percent(self.savings_rate()),
+            percent(self.debt_to_asset()),
+            runway
+        )?;
+        for (category, amount) in &self.categories {
+            write!(
+                f,
+                "\n    {:<30} {:>7}",
+                category,
+                percent(ratio(*amount, self.expense))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Journal {
+    /// Sum of `valuable` in `code` at the rates of `date`.
+    pub(super) fn value_in(
+        &self,
+        valuable: Valuable,
+        code: &str,
+        date: NaiveDate,
+        rates: &impl RateProvider,
+    ) -> Result<Decimal> {
+        valuable
+            .into_iter()
+            .map(|money| {
+                let money = money.into_money(&self.currencies);
+                Ok(money.convert_to(code, date, rates)?.money().amount())
+            })
+            .sum()
+    }
+
+    /// Ratios of every period from the one containing `since` through the
+    /// one containing `until`, in `code`. Balances are taken at the end of
+    /// each period and `liquid` is the accn counted for the runway.
+    pub(crate) fn ratios(
+        &self,
+        period: Period,
+        since: NaiveDate,
+        until: NaiveDate,
+        code: &str,
+        liquid: AccnEntry,
+        fallback: &dyn RateProvider,
+    ) -> Result<Vec<Ratios>> {
+        if self.currencies.get_by_code(code).is_none() {
+            bail!("code {} not found", code);
+        }
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let (inc, exp) = (self.accns.income(), self.accns.expense());
+        let (asset, liability) = (self.accns.asset(), self.accns.liability());
+        let month = Decimal::new(365, 0) / Decimal::new(12, 0);
+
+        let mut all = Vec::new();
+        let mut start = period.start(since);
+        while start <= until {
+            let next = period.succ(start);
+            let end = next - Duration::days(1);
+            let within = |accn: AccnEntry| -> Valuable {
+                self.postings()
+                    .filter(|p| (start..next).contains(&p.txn().date()))
+                    .filter(|p| p.accn().is_descendent_of(accn))
+                    .map(|p| p.money().money())
+                    .sum()
+            };
+            let value = |valuable| self.value_in(valuable, code, end, &rates);
+
+            let mut categories = Vec::new();
+            for category in exp.children() {
+                let amount = value(within(category))?;
+                if !amount.is_zero() {
+                    categories.push((category.name().to_string(), amount));
+                }
+            }
+            categories.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+
+            all.push(Ratios {
+                start,
+                period,
+                income: -value(within(inc))?,
+                expense: value(within(exp))?,
+                categories,
+                assets: value(self.balance_at(asset, end))?,
+                debt: -value(self.balance_at(liability, end))?,
+                liquid: value(self.balance_at(liquid, end))?,
+                months: Decimal::from((next - start).num_days()) / month,
+            });
+            start = next;
+        }
+        Ok(all)
+    }
+
+    /// The only currency the journal uses, for reports that need one.
+    pub(crate) fn sole_code(&self) -> Result<&str> {
+        match self.used_codes().into_iter().collect_vec()[..] {
+            [code] => Ok(code),
+            [] => bail!("no currency used yet"),
+            _ => bail!("several currencies used, choose one with `in <code>`"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-01 salary
+    asset:bank  $4000
+    income:salary
+
+2024-01-02 rent
+    expense:rent  $1500
+    asset:bank
+
+2024-01-03 groceries
+    expense:food  $500
+    liability:card
+
+2024-02-01 salary
+    asset:bank  $4000
+    income:salary"#;
+
+    #[test]
+    fn test_ratios() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let jan = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap();
+        let code = journal.sole_code().unwrap();
+        let ratios = journal
+            .ratios(Period::Monthly, jan, jan, code, bank, &journal.rates)
+            .unwrap();
+        assert_eq!(ratios.len(), 1);
+        let jan = &ratios[0];
+        assert_eq!(jan.savings_rate(), Some(dec!(0.5)));
+        assert_eq!(jan.debt_to_asset(), Some(dec!(0.2)));
+        assert_eq!(
+            jan.categories,
+            [
+                ("rent".to_string(), dec!(1500)),
+                ("food".to_string(), dec!(500))
+            ]
+        );
+        // $2500 in the bank at $2000 per 31 days
+        let runway = jan.runway().unwrap();
+        assert!((runway - dec!(1.27)).abs() < dec!(0.01));
+    }
+}
@@ -0,0 +1,145 @@
+use itertools::Itertools;
+
+use crate::accn::Accn;
+
+use super::{register::QueryType, Journal, Txn};
+
+/// A posting moved to another accn, kept to move it back on undo.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Move {
+    pub(crate) txn: Txn,
+    /// Position of the posting in its txn.
+    posting: usize,
+    from: Accn,
+    to: Accn,
+}
+
+impl Journal {
+    /// Postings matching `query` of expense accns other than `to`, to move
+    /// to `to`, by date.
+    pub(crate) fn reclass_moves(&self, query: QueryType, to: Accn) -> Vec<Move> {
+        self.query(query)
+            .into_postings()
+            .filter(|posting| {
+                let accn = posting.accn();
+                accn.is_descendent_of(self.accns.expense()) && accn.id() != to
+            })
+            .map(|posting| {
+                let txn = posting.txn().id();
+                Move {
+                    txn,
+                    posting: self.txns.txns[&txn]
+                        .postings
+                        .iter()
+                        .position(|p| *p == posting.id())
+                        .unwrap(),
+                    from: posting.accn().id(),
+                    to,
+                }
+            })
+            .sorted_by_key(|move_| (self.txn(move_.txn).date(), move_.txn, move_.posting))
+            .collect()
+    }
+
+    /// Line of `reclass` showing what `move_` changes.
+    pub(crate) fn describe_move(&self, move_: &Move) -> String {
+        let txn = self.txn(move_.txn);
+        let posting = txn.postings().nth(move_.posting).unwrap();
+        format!(
+            "{} {:<30} {} -> {} {:>12}",
+            txn.date(),
+            txn.title(),
+            move_.from.into_accn(&self.accns).abs_name(),
+            move_.to.into_accn(&self.accns).abs_name(),
+            posting.money().to_string()
+        )
+    }
+
+    pub(crate) fn apply_moves(&mut self, moves: &[Move]) {
+        for move_ in moves {
+            self.set_posting_accn(move_.txn, move_.posting, move_.to);
+        }
+    }
+
+    /// Move the postings back where `moves` took them from, the last first,
+    /// skipping txns no longer in the journal.
+    pub(crate) fn revert_moves(&mut self, moves: &[Move]) {
+        for move_ in moves.iter().rev() {
+            if self.contains_txn(move_.txn) {
+                self.set_posting_accn(move_.txn, move_.posting, move_.from);
+            }
+        }
+    }
+
+    fn set_posting_accn(&mut self, txn: Txn, posting: usize, accn: Accn) {
+        let data = &self.txns.txns[&txn];
+        let (date, posting) = (data.date, data.postings[posting]);
+        self.txns.postings[posting].accn = accn;
+        self.txns.snapshots.invalidate(date);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use regex::RegexBuilder;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-05-02 STARBUCKS COFFEE 1234
+    expense:misc  $4.8
+    asset:bank
+
+2024-05-03 Blue Bottle | coffee
+    expense:misc  $5.5
+    expense:snacks  $3
+    asset:bank
+
+2024-05-04 groceries
+    expense:misc  $62
+    asset:bank"#;
+
+    #[test]
+    fn test_reclass() {
+        let mut journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let coffee = journal
+            .accns_mut()
+            .root_mut()
+            .or_open_child("expense")
+            .or_open_child("coffee")
+            .into_ref()
+            .id();
+        let query = || {
+            QueryType::Desc(
+                RegexBuilder::new("coffee")
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap(),
+            )
+        };
+        let moves = journal.reclass_moves(query(), coffee);
+        // the bank postings stay where they are
+        assert_eq!(moves.len(), 3);
+        assert_eq!(
+            journal.describe_move(&moves[0]),
+            "2024-05-02 STARBUCKS COFFEE 1234          expense:misc -> expense:coffee         $4.8"
+        );
+        let snacks = QueryType::And(vec![query(), QueryType::MatchAccn("snacks".into())]);
+        assert_eq!(journal.reclass_moves(snacks, coffee).len(), 1);
+
+        let balance = |journal: &Journal, name: &str| {
+            let accn = journal.accns().by_name_unique(name).ok().unwrap();
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string()
+        };
+        journal.apply_moves(&moves);
+        assert_eq!(balance(&journal, "coffee"), "$13.3");
+        assert_eq!(balance(&journal, "misc"), "$62");
+        assert!(journal.reclass_moves(query(), coffee).is_empty());
+
+        journal.revert_moves(&moves);
+        assert_eq!(balance(&journal, "coffee"), "0");
+        assert_eq!(balance(&journal, "snacks"), "$3");
+    }
+}
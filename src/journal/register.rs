@@ -1,9 +1,18 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
+use anyhow::anyhow;
 use chrono::NaiveDate;
 use itertools::Itertools;
+use regex::Regex;
 
-use crate::valuable::ValuableEntry;
+use rust_decimal::Decimal;
+use tracing::debug;
+
+use crate::{
+    locale,
+    period::PeriodBucketer,
+    valuable::{Money, ValuableEntry},
+};
 
 use super::{entry::PostingEntry, Journal};
 
@@ -28,7 +37,8 @@ impl<'a> PostingQuery<'a> {
                 *bal += p.money();
                 RegisterRow {
                     date: p.txn().date(),
-                    desc: p.txn().desc().to_string(),
+                    desc: p.txn().title(),
+                    author: p.txn().author().map(str::to_string),
                     accn: p.accn().to_string(),
                     change: p.money().to_string(),
                     total: bal.to_string(),
@@ -38,6 +48,100 @@ impl<'a> PostingQuery<'a> {
     }
 }
 
+impl<'a> PostingQuery<'a> {
+    /// Rows of the register with what flows in and out of the accns in
+    /// columns of their own, each with its running total.
+    pub(crate) fn into_side_regs(self) -> impl Iterator<Item = SideRow> + 'a {
+        let totals = (ValuableEntry::default(), ValuableEntry::default());
+        self.postings
+            .sorted_by_key(|p| p.txn().date())
+            .scan(totals, |(inflow, outflow), p| {
+                let (mut money_in, mut money_out) = (String::new(), String::new());
+                match Side::of(&p) {
+                    Some(Side::Debit) => {
+                        *inflow += p.money();
+                        money_in = p.money().to_string();
+                    }
+                    Some(Side::Credit) => {
+                        let money = (-p.money().money()).into_money(p.journal().currencies());
+                        money_out = money.to_string();
+                        *outflow += money;
+                    }
+                    None => {}
+                }
+                SideRow {
+                    date: p.txn().date(),
+                    desc: p.txn().title(),
+                    accn: p.accn().to_string(),
+                    inflow: money_in,
+                    outflow: money_out,
+                    total_in: inflow.to_string(),
+                    total_out: outflow.to_string(),
+                }
+                .into()
+            })
+    }
+}
+
+impl<'a> PostingQuery<'a> {
+    pub(super) fn into_postings(self) -> impl Iterator<Item = PostingEntry<'a>> + 'a {
+        self.postings
+    }
+
+    pub(super) fn into_buckets(
+        self,
+        bucketer: PeriodBucketer,
+    ) -> BTreeMap<NaiveDate, Vec<PostingEntry<'a>>> {
+        bucketer.bucket(self.postings, |p| p.txn().date())
+    }
+
+    /// Total of the postings per account in every period, instead of one row
+    /// per posting.
+    pub(crate) fn into_period_regs(
+        self,
+        bucketer: PeriodBucketer,
+    ) -> impl Iterator<Item = PeriodRow> + 'a {
+        let period = bucketer.period();
+        self.into_buckets(bucketer)
+            .into_iter()
+            .flat_map(move |(start, postings)| {
+                let mut totals: BTreeMap<String, (ValuableEntry, usize)> = BTreeMap::new();
+                for p in postings {
+                    let (total, count) = totals.entry(p.accn().to_string()).or_default();
+                    *total += p.money();
+                    *count += 1;
+                }
+                totals
+                    .into_iter()
+                    .map(move |(accn, (total, count))| PeriodRow {
+                        period: period.label(start),
+                        accn,
+                        count,
+                        total: total.to_string(),
+                    })
+            })
+    }
+}
+
+impl<'a> PostingQuery<'a> {
+    /// Total of the postings carrying each tag, across all accounts.
+    pub(crate) fn into_tag_totals(self) -> impl Iterator<Item = TagRow> + 'a {
+        let mut totals: BTreeMap<&str, (ValuableEntry, usize)> = BTreeMap::new();
+        for p in self.postings {
+            for tag in p.tags() {
+                let (total, count) = totals.entry(tag).or_default();
+                *total += p.money();
+                *count += 1;
+            }
+        }
+        totals.into_iter().map(|(tag, (total, count))| TagRow {
+            tag: tag.to_string(),
+            count,
+            total: total.to_string(),
+        })
+    }
+}
+
 impl<'a, I> From<I> for PostingQuery<'a>
 where
     I: PostingIterator<'a>,
@@ -51,6 +155,7 @@ where
 pub(crate) struct RegisterRow {
     date: NaiveDate,
     desc: String,
+    author: Option<String>,
     accn: String,
     change: String,
     total: String,
@@ -60,9 +165,10 @@ impl Display for RegisterRow {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{:<15} {:<40} {:<30} {:>10} {:>30}",
-            self.date.format("%Y/%m/%d"),
+            "{:<15} {:<40} {:<10} {:<30} {:>10} {:>30}",
+            locale::date(self.date),
             self.desc,
+            self.author.as_deref().unwrap_or(""),
             self.accn,
             self.change,
             self.total,
@@ -70,15 +176,168 @@ impl Display for RegisterRow {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct SideRow {
+    date: NaiveDate,
+    desc: String,
+    accn: String,
+    inflow: String,
+    /// What flows out, as a positive amount.
+    outflow: String,
+    total_in: String,
+    total_out: String,
+}
+
+impl Display for SideRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<15} {:<40} {:<30} {:>10} {:>10} {:>20} {:>20}",
+            locale::date(self.date),
+            self.desc,
+            self.accn,
+            self.inflow,
+            self.outflow,
+            self.total_in,
+            self.total_out,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PeriodRow {
+    period: String,
+    accn: String,
+    count: usize,
+    total: String,
+}
+
+impl Display for PeriodRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<15} {:<40} {:>5} {:>30}",
+            self.period, self.accn, self.count, self.total,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TagRow {
+    tag: String,
+    count: usize,
+    total: String,
+}
+
+impl Display for TagRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<30} {:>5} {:>30}",
+            format!("#{}", self.tag),
+            self.count,
+            self.total
+        )
+    }
+}
+
+/// Which way a posting moves money: a debit adds to its accn, a credit
+/// takes from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+    Debit,
+    Credit,
+}
+
+impl Side {
+    /// Side of `posting`, none for a posting of no money.
+    fn of(posting: &PostingEntry) -> Option<Self> {
+        let amount = posting.money().money().amount();
+        match amount.is_zero() {
+            true => None,
+            false if amount.is_sign_positive() => Some(Side::Debit),
+            false => Some(Side::Credit),
+        }
+    }
+}
+
+impl FromStr for Side {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "debit" | ">" => Ok(Side::Debit),
+            "credit" | "<" => Ok(Side::Credit),
+            _ => Err(anyhow!("invalid side: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub(crate) enum QueryType {
     #[default]
     All,
     MatchAccn(String),
+    /// Postings whose absolute amount is larger than the given money.
+    AmountAbove(Money),
+    /// Postings whose absolute amount is smaller than the given money.
+    AmountBelow(Money),
+    CurrencyIs(String),
+    HasTag(String),
+    /// Postings of txns whose metadata `key` has the given value.
+    Meta(String, String),
+    /// Postings of txns entered by the given author.
+    Author(String),
+    /// Postings of txns whose title matches the regex.
+    Desc(Regex),
+    /// Postings on the given side, like `amount > 0` for debits.
+    Side(Side),
+    /// Postings matching every one of the queries.
+    And(Vec<QueryType>),
+}
+
+impl QueryType {
+    fn matches(&self, posting: &PostingEntry) -> bool {
+        match self {
+            QueryType::All => true,
+            QueryType::MatchAccn(s) => posting.accn().abs_name().contains(s),
+            QueryType::AmountAbove(limit) => {
+                abs_amount(posting, *limit).is_some_and(|amount| amount > limit.amount())
+            }
+            QueryType::AmountBelow(limit) => {
+                abs_amount(posting, *limit).is_some_and(|amount| amount < limit.amount())
+            }
+            QueryType::CurrencyIs(code) => posting.money().code().eq_ignore_ascii_case(code),
+            QueryType::HasTag(tag) => posting.tags().contains(tag),
+            QueryType::Meta(key, value) => posting.txn().meta(key) == Some(value),
+            QueryType::Author(name) => posting
+                .txn()
+                .author()
+                .is_some_and(|author| author.eq_ignore_ascii_case(name)),
+            QueryType::Desc(regex) => regex.is_match(&posting.txn().title()),
+            QueryType::Side(side) => Side::of(posting) == Some(*side),
+            QueryType::And(queries) => queries.iter().all(|q| q.matches(posting)),
+        }
+    }
+}
+
+/// Absolute amount of `posting` in the currency of `limit`, converted at
+/// the journal's rate of the txn date when the currencies differ.
+fn abs_amount(posting: &PostingEntry, limit: Money) -> Option<Decimal> {
+    let money = posting.money();
+    if money.money().eq_currency(&limit) {
+        return Some(money.money().amount().abs());
+    }
+    let journal = posting.journal();
+    let code = journal.currencies().code(limit.currency());
+    money
+        .convert_to(code, posting.txn().date(), journal.rates())
+        .ok()
+        .map(|money| money.money().amount().abs())
 }
 
 impl Journal {
-    pub(crate) fn query(&self, query: QueryType) -> PostingQuery {
+    pub(crate) fn query(&self, query: QueryType) -> PostingQuery<'_> {
+        debug!(?query, "querying postings");
         match query {
             QueryType::All => self
                 .txns
@@ -86,10 +345,116 @@ impl Journal {
                 .keys()
                 .map(|p| p.into_posting(self))
                 .into(),
-            QueryType::MatchAccn(s) => self
-                .postings()
-                .filter(move |p| p.accn().abs_name().contains(&s))
-                .into(),
+            query => self.postings().filter(move |p| query.matches(p)).into(),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"rate 2024-03-01 EUR USD 1.5
+
+2024-03-01 rent
+    expense:rent  $900
+    asset:bank
+
+2024-03-02 lunch
+    expense:food  $12
+    asset:bank
+
+2024-03-03 dinner in paris
+    expense:food  €80
+    asset:bank"#;
+
+    fn count(journal: &Journal, queries: Vec<QueryType>) -> usize {
+        journal.query(QueryType::And(queries)).postings.count()
+    }
+
+    #[test]
+    fn test_tag_totals() {
+        let input = INPUT
+            .replace("$12", "$12 #work")
+            .replace("€80", "€80 #work #trip");
+        let journal = Journal::from_str(&input).unwrap_or_else(|e| panic!("{:#}", e));
+        let rows = journal
+            .query(QueryType::All)
+            .into_tag_totals()
+            .map(|row| (row.tag, row.count, row.total))
+            .collect_vec();
+        assert_eq!(
+            rows,
+            [
+                ("trip".to_string(), 1, "€80".to_string()),
+                ("work".to_string(), 2, "€80, $12".to_string())
+            ]
+        );
+        assert_eq!(count(&journal, vec![QueryType::HasTag("trip".into())]), 1);
+    }
+
+    #[test]
+    fn test_author() {
+        let input = INPUT
+            .replace("lunch\n", "lunch\n    ; author: sam\n")
+            .replace("paris\n", "paris\n    ; author: alex\n");
+        let journal = Journal::from_str(&input).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(count(&journal, vec![QueryType::Author("Sam".into())]), 2);
+        let rows = journal
+            .query(QueryType::Author("alex".into()))
+            .into_regs()
+            .collect_vec();
+        assert_eq!(rows[0].author.as_deref(), Some("alex"));
+        assert!(journal.to_string().contains("; author: sam"));
+    }
+
+    #[test]
+    fn test_amount_filters() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let money = |s| journal.parse_money(s).unwrap().money();
+        let expense = || QueryType::MatchAccn("expense".into());
+
+        assert_eq!(count(&journal, vec![expense()]), 3);
+        // €80 is $120 at the journal's rate
+        assert_eq!(
+            count(
+                &journal,
+                vec![expense(), QueryType::AmountAbove(money("$100"))]
+            ),
+            2
+        );
+        assert_eq!(
+            count(&journal, vec![QueryType::AmountBelow(money("$100"))]),
+            2
+        );
+        assert_eq!(
+            count(&journal, vec![QueryType::CurrencyIs("eur".into())]),
+            2
+        );
+    }
+
+    #[test]
+    fn test_sides() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let bank = || QueryType::MatchAccn("bank".into());
+        let side = |s: &str| QueryType::Side(s.parse().unwrap());
+        assert_eq!(count(&journal, vec![side("debit")]), 3);
+        assert_eq!(count(&journal, vec![bank(), side("<")]), 3);
+        assert_eq!(count(&journal, vec![bank(), side(">")]), 0);
+        assert!("both".parse::<Side>().is_err());
+
+        let rows = journal
+            .query(QueryType::MatchAccn("food".into()))
+            .into_side_regs()
+            .collect_vec();
+        assert_eq!(
+            (rows[1].inflow.as_str(), rows[1].outflow.as_str()),
+            ("€80", "")
+        );
+        assert_eq!(rows[1].total_in, "€80, $12");
+        let rows = journal.query(bank()).into_side_regs().collect_vec();
+        assert_eq!(rows[0].outflow, "$900");
+        assert_eq!(rows[2].total_out, "€80, $912");
+        assert_eq!(rows[2].total_in, "0");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::{
+    accn::Accn,
+    valuable::{Money, Valuable},
+};
+
+use super::{
+    link::{ID_META, LINK_META},
+    Journal, Txn,
+};
+
+/// Prefix of the tags marking postings someone is to pay back, like
+/// `#claim:acme` for an expense the employer acme reimburses.
+const CLAIM_TAG: &str = "claim:";
+
+/// Who `tag` claims a posting from, if it is a claim. A bare `#claim`
+/// claims from the payee of the txn.
+pub(super) fn claim_contact<'a>(tag: &'a str, payee: Option<&'a str>) -> Option<&'a str> {
+    match tag {
+        "claim" => payee,
+        _ => tag
+            .strip_prefix(CLAIM_TAG)
+            .filter(|contact| !contact.is_empty()),
+    }
+}
+
+/// A claimed posting not reimbursed yet.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Claim {
+    txn: Txn,
+    accn: Accn,
+    money: Money,
+}
+
+/// Open claims grouped per contact, for handing in.
+pub(crate) struct ClaimReport<'a> {
+    journal: &'a Journal,
+    claims: BTreeMap<String, Vec<Claim>>,
+}
+
+impl ClaimReport<'_> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.claims.is_empty()
+    }
+}
+
+impl Display for ClaimReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let store = &self.journal.currencies;
+        let reports = self.claims.iter().map(|(contact, claims)| {
+            let mut lines = vec![contact.to_string()];
+            for claim in claims {
+                let txn = self.journal.txn(claim.txn);
+                lines.push(format!(
+                    "  {} {:<40} {:<30} {:>12}",
+                    txn.date(),
+                    txn.title(),
+                    claim.accn.into_accn(&self.journal.accns),
+                    claim.money.into_money(store).to_string()
+                ));
+            }
+            let total: Valuable = claims.iter().map(|claim| claim.money).sum();
+            let total = total.into_valuable(store).to_string();
+            lines.push(format!("  {:>97}", format!("total {}", total)));
+            lines.join("\n")
+        });
+        write!(f, "{}", reports.format("\n\n"))
+    }
+}
+
+impl Journal {
+    /// Claimed postings of txns nothing links to yet, per contact, oldest
+    /// first.
+    fn open_claims(&self) -> BTreeMap<String, Vec<Claim>> {
+        let linked = self.linked_ids();
+        let mut claims: BTreeMap<String, Vec<Claim>> = BTreeMap::new();
+        let postings = self
+            .postings()
+            .filter(|p| p.txn().meta(ID_META).is_none_or(|id| !linked.contains(id)))
+            .sorted_by_key(|p| p.txn().date());
+        for posting in postings {
+            let payee = posting.txn().payee();
+            for contact in posting
+                .tags()
+                .iter()
+                .filter_map(|tag| claim_contact(tag, payee))
+            {
+                claims.entry(contact.to_string()).or_default().push(Claim {
+                    txn: posting.txn().id(),
+                    accn: posting.accn().id(),
+                    money: posting.money().money(),
+                });
+            }
+        }
+        claims
+    }
+
+    /// Open claims of `contact`, or of everyone.
+    pub(crate) fn claims(&self, contact: Option<&str>) -> ClaimReport<'_> {
+        let mut claims = self.open_claims();
+        claims.retain(|name, _| contact.is_none_or(|contact| contact == name));
+        ClaimReport {
+            journal: self,
+            claims,
+        }
+    }
+
+    /// Record the payment of every open claim of `contact` into `to`, taking
+    /// the claimed amounts back out of the accns they were booked to. The
+    /// payment links to the claimed txns, which get ids if they had none.
+    pub(crate) fn reimburse(&mut self, contact: &str, date: NaiveDate, to: Accn) -> Result<Txn> {
+        let Some(claims) = self.open_claims().remove(contact) else {
+            bail!("no open claims from {}", contact);
+        };
+
+        let mut ids = Vec::new();
+        for txn in claims.iter().map(|claim| claim.txn).unique() {
+            let id = match self.txn(txn).meta(ID_META) {
+                Some(id) => id.to_string(),
+                None => {
+                    let id = format!("claim-{}", &Uuid::new_v4().simple().to_string()[..8]);
+                    self.txn_mut(txn).set_meta(ID_META, &id);
+                    id
+                }
+            };
+            ids.push(id);
+        }
+
+        let mut txn = self
+            .new_txn(date, format!("reimbursement from {}", contact))
+            .with_meta(LINK_META, ids.join(", "));
+        for claim in &claims {
+            txn = txn
+                .with_posting_combined(to, Some(claim.money))
+                .with_posting_combined(claim.accn, Some(-claim.money));
+        }
+        Ok(txn.build()?.id())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-05-02 flight to the conference
+    expense:travel  $400 #claim:acme
+    asset:bank
+
+2024-05-03 hotel
+    expense:travel  $250 #claim:acme
+    asset:bank
+
+2024-05-04 taxi home
+    expense:travel  $30
+    asset:bank
+
+2024-05-10 concert tickets for bob
+    expense:fun  $80 #claim:bob
+    asset:bank"#;
+
+    #[test]
+    fn test_reimburse() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        assert_eq!(journal.claims(None).claims.len(), 2);
+        assert_eq!(journal.claims(Some("acme")).claims["acme"].len(), 2);
+
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let txn = journal.reimburse("acme", date, bank).unwrap();
+        assert_eq!(journal.txn(txn).links().count(), 2);
+        assert!(journal.claims(Some("acme")).is_empty());
+        assert!(journal.reimburse("acme", date, bank).is_err());
+
+        // only the taxi is left as travel expense
+        let travel = journal.accns().by_name_unique("travel").ok().unwrap();
+        let balance = journal.balance(travel);
+        assert_eq!(
+            balance.into_valuable(journal.currencies()).to_string(),
+            "$30"
+        );
+        // bob still owes the tickets
+        assert_eq!(journal.unreimbursed(date).len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::accn::Accn;
+
+use super::Journal;
+
+impl Journal {
+    /// Accn taking the residuals of txns below the precision of their
+    /// currencies, if the journal has one.
+    pub(crate) fn rounding_accn(&self) -> Option<Accn> {
+        self.txns.rounding
+    }
+
+    /// Book residuals to `accn` from now on, or fail on them again.
+    pub(crate) fn set_rounding_accn(&mut self, accn: Option<Accn>) {
+        self.txns.rounding = accn;
+    }
+
+    pub(super) fn rounding_directive(&self) -> String {
+        match self.txns.rounding {
+            Some(accn) => format!("rounding {}", accn.into_accn(&self.accns)),
+            None => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"rounding equity:rounding
+
+2024-03-01 dinner for three
+    expense:food  $33.3333
+    asset:bank  -$33.33
+
+2024-03-02 fuel
+    expense:car  $41.126
+    asset:bank"#;
+
+    #[test]
+    fn test_rounding() {
+        let journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(journal.rounding_directive(), "rounding equity:rounding");
+        let balance = |name: &str| {
+            let accn = journal.accns().by_name_unique(name).ok().unwrap();
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string()
+        };
+        assert_eq!(balance("bank"), "-$74.46");
+        assert_eq!(balance("rounding"), "$0.0007");
+
+        // a residual of a cent or more is still an error
+        let input = INPUT.replace("-$33.33", "-$33.32");
+        assert!(Journal::from_str(&input).is_err());
+        // and so is any residual without a rounding accn
+        let input = INPUT.replace("rounding equity:rounding", "");
+        assert!(Journal::from_str(&input).is_err());
+    }
+}
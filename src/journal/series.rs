@@ -0,0 +1,147 @@
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use crate::{
+    period::{Period, PeriodBucketer},
+    valuable::{CurrencyStore, Valuable},
+};
+
+use super::register::PostingQuery;
+
+/// Totals per period in chronological order, without gaps between the first
+/// and the last period.
+#[derive(Debug, Clone)]
+pub(crate) struct PeriodSeries {
+    period: Period,
+    points: Vec<(NaiveDate, Valuable)>,
+}
+
+impl PeriodSeries {
+    pub(crate) fn period(&self) -> Period {
+        self.period
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub(crate) fn points(&self) -> impl Iterator<Item = (NaiveDate, &Valuable)> {
+        self.points.iter().map(|(date, v)| (*date, v))
+    }
+
+    pub(crate) fn total(&self) -> Valuable {
+        self.points.iter().map(|(_, v)| v.clone()).sum()
+    }
+
+    /// Average total per period over the whole series.
+    pub(crate) fn average(&self, store: &CurrencyStore) -> Valuable {
+        self.total().div_round(self.len(), store)
+    }
+
+    /// Trailing average over `window` periods, starting at the first period
+    /// with a full window behind it.
+    pub(crate) fn moving_average(&self, window: usize, store: &CurrencyStore) -> PeriodSeries {
+        let points = match window {
+            0 => Vec::new(),
+            _ => self
+                .points
+                .windows(window)
+                .map(|w| {
+                    let sum: Valuable = w.iter().map(|(_, v)| v.clone()).sum();
+                    (w[window - 1].0, sum.div_round(window, store))
+                })
+                .collect_vec(),
+        };
+
+        PeriodSeries {
+            period: self.period,
+            points,
+        }
+    }
+}
+
+impl<'a> PostingQuery<'a> {
+    /// Sum the postings of every period, filling periods without postings
+    /// with zero.
+    pub(crate) fn into_series(self, bucketer: PeriodBucketer) -> PeriodSeries {
+        let mut buckets = self.into_buckets(bucketer);
+        let points = match (buckets.keys().next(), buckets.keys().last()) {
+            (Some(first), Some(last)) => bucketer
+                .starts(*first, *last)
+                .collect_vec()
+                .into_iter()
+                .map(|start| {
+                    let total = buckets
+                        .remove(&start)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|p| p.money().money())
+                        .sum();
+                    (start, total)
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        PeriodSeries {
+            period: bucketer.period(),
+            points,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{journal::register::QueryType, journal::Journal, period::Period};
+
+    use super::*;
+
+    #[rustfmt::skip]
+const JOURNAL: &str =
+r#"2024-01-05 lunch
+    expense:food  $30
+    asset:cash
+
+2024-03-05 lunch
+    expense:food  $60
+    asset:cash
+
+2024-04-05 lunch
+    expense:food  $90
+    asset:cash"#;
+
+    fn show(journal: &Journal, series: &PeriodSeries) -> Vec<String> {
+        series
+            .points()
+            .map(|(_, v)| v.clone().into_valuable(journal.currencies()).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_series() {
+        let journal = Journal::from_str(JOURNAL).unwrap();
+        let series = journal
+            .query(QueryType::MatchAccn("food".into()))
+            .into_series(PeriodBucketer::new(Period::Monthly));
+
+        assert_eq!(show(&journal, &series), ["$30", "0", "$60", "$90"]);
+        let store = journal.currencies();
+        let average = series.average(store).into_valuable(store);
+        assert_eq!(average.to_string(), "$45");
+
+        let moving = series.moving_average(3, store);
+        assert_eq!(show(&journal, &moving), ["$30", "$50"]);
+        assert!(series.moving_average(5, store).is_empty());
+
+        // yen have no minor units to round to
+        let yen: Valuable = [journal.parse_money("1000 JPY").unwrap().money()]
+            .into_iter()
+            .sum();
+        let third = yen.div_round(3, store).into_valuable(store);
+        assert_eq!(third.to_string(), "333 JPY");
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::prelude::Zero;
+
+use crate::{
+    accn::{Accn, AccnEntry, Budget},
+    valuable::{Money, Valuable},
+};
+
+use super::{
+    entry::TIME_META,
+    interest::INTEREST_META,
+    link::{ID_META, LINK_META},
+    lots::LOT_META,
+    Journal, Timed, TxnBuilder,
+};
+
+/// Metadata a shared txn keeps, what it means to the books rather than who
+/// entered it or where it was imported from.
+const SHARED_META: [&str; 5] = [TIME_META, LOT_META, ID_META, LINK_META, INTEREST_META];
+
+impl Journal {
+    /// Copy of `accn` in `other`, created with its name, its directives and
+    /// those of its ancestors.
+    fn copy_accn(&self, other: &mut Journal, accn: AccnEntry) -> Result<Accn> {
+        let mut path = std::iter::successors(Some(accn), |accn| accn.parent()).collect_vec();
+        path.pop(); // root
+        let mut copy = other.accns.root_mut();
+        for accn in path.into_iter().rev() {
+            copy = copy.or_open_child(accn.name());
+            if accn.is_declared() {
+                copy = copy.declare_open(accn.opened(), accn.currency());
+            }
+            if let Some(date) = accn.closed() {
+                copy = copy.declare_close(date)?;
+            }
+            if let Some(code) = accn.own_report_currency() {
+                copy = copy.declare_report(code);
+            }
+            if let Some(rate) = accn.interest() {
+                copy = copy.declare_interest(rate);
+            }
+            if let Some(category) = accn.own_tax_category() {
+                copy = copy.declare_tax(category);
+            }
+            if let Some(cycle) = accn.cycle() {
+                copy = copy.declare_cycle(cycle);
+            }
+            if let Some(budget) = accn.budget() {
+                let money = budget.money.rebase(&self.currencies, &other.currencies)?;
+                copy = copy.declare_budget(Budget { money, ..budget });
+            }
+            for class in accn.own_classes() {
+                copy = copy.declare_class(class);
+            }
+        }
+        Ok(copy.into_ref().id())
+    }
+
+    /// A standalone journal of only what was booked to `accns` and their
+    /// descendants, to share without showing the other accns. Txns are kept
+    /// from `since` on, those before it summed up in opening balances
+    /// against `equity:opening`, and only those with `contact` as payee if
+    /// given. What a txn booked elsewhere is lumped into `equity:private`,
+    /// and only the metadata in `SHARED_META` is kept.
+    pub(crate) fn share(
+        &self,
+        accns: &[AccnEntry],
+        contact: Option<&str>,
+        since: Option<NaiveDate>,
+    ) -> Result<Journal> {
+        let groups = [self.currencies.to_string(), self.rates.to_string()];
+        let text = groups.iter().filter(|group| !group.is_empty()).join("\n\n");
+        let mut shared = Journal::from_str(&text)?;
+        let equity = |shared: &mut Journal, name: &str| {
+            let accn = shared.accns.root_mut().or_open_child("equity");
+            let accn = accn.or_open_child(name).declare_open(None, None);
+            accn.into_ref().id()
+        };
+
+        let kept = |accn: AccnEntry| accns.iter().any(|a| accn.is_descendent_of(*a));
+        let mut copies: BTreeMap<Accn, Accn> = BTreeMap::new();
+        for accn in self.accns.accns().filter(|accn| kept(*accn)) {
+            copies.insert(accn.id(), self.copy_accn(&mut shared, accn)?);
+        }
+
+        let before = |date: NaiveDate| since.is_some_and(|since| date < since);
+        let mut openings: BTreeMap<Accn, Valuable> = BTreeMap::new();
+        for posting in self.postings().filter(|p| before(p.txn().date())) {
+            if let Some(copy) = copies.get(&posting.accn().id()) {
+                *openings.entry(*copy).or_default() += posting.money().money();
+            }
+        }
+        if let Some(since) = since.filter(|_| !openings.is_empty()) {
+            let opening = equity(&mut shared, "opening");
+            let date = since.pred_opt().unwrap_or(since);
+            let mut txn = TxnBuilder::new(date, "opening balance".to_string());
+            for (accn, balance) in openings {
+                for money in balance {
+                    txn.with_posting(
+                        accn,
+                        Some(money.rebase(&self.currencies, &shared.currencies)?),
+                    );
+                }
+            }
+            txn.with_posting(opening, None);
+            txn.build(&mut shared.txns, &shared.currencies)?;
+        }
+
+        let txns = self.txns().filter(|txn| !before(txn.date())).filter(|txn| {
+            contact.is_none_or(|contact| {
+                txn.payee()
+                    .is_some_and(|payee| payee.eq_ignore_ascii_case(contact))
+            })
+        });
+        for txn in txns {
+            let postings = txn
+                .postings()
+                .filter(|p| copies.contains_key(&p.accn().id()))
+                .collect_vec();
+            if postings.is_empty() {
+                continue;
+            }
+            let mut copy = TxnBuilder::new(txn.date(), txn.title());
+            for (key, value) in txn.metas().filter(|(key, _)| SHARED_META.contains(key)) {
+                copy.with_meta(key, value);
+            }
+            let rebase = |money: Money| money.rebase(&self.currencies, &shared.currencies);
+            for posting in &postings {
+                let accn = copies[&posting.accn().id()];
+                let tags = posting.tags().to_vec();
+                match posting.timed() {
+                    Some(timed) => {
+                        let rate = rebase(timed.rate)?;
+                        copy.with_timed_posting(
+                            accn,
+                            Timed { rate, ..timed },
+                            tags,
+                            &shared.currencies,
+                        )
+                    }
+                    None => {
+                        copy.with_tagged_posting(accn, Some(rebase(posting.money().money())?), tags)
+                    }
+                };
+            }
+            if !copy.inbalance().is_zero() {
+                let private = equity(&mut shared, "private");
+                copy.with_posting(private, None);
+            }
+            copy.build(&mut shared.txns, &shared.currencies)?;
+        }
+        Ok(shared)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"open asset:joint report USD
+class asset:joint shared
+
+2024-01-05 salary
+    asset:joint  $2000
+    asset:mine  $1000
+    income:salary
+
+2024-02-01 Landlord | rent
+    ; time: 09:00
+    ; author: alex
+    expense:rent  $1500
+    asset:joint  -$1500 #home
+
+2024-02-10 Grocer | weekly shop
+    expense:food  $80
+    asset:joint
+
+2024-02-11 gift for sam
+    expense:gifts  $50
+    asset:mine"#;
+
+    #[test]
+    fn test_share() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let joint = journal.accns().by_name_unique("joint").ok().unwrap();
+        let since = NaiveDate::from_ymd_opt(2024, 2, 1);
+        let shared = journal.share(&[joint], None, since).unwrap();
+        let text = shared.to_string();
+        assert!(!text.contains("mine") && !text.contains("salary") && !text.contains("gift"));
+        assert!(text.contains("class asset:joint shared"));
+        assert_eq!(shared.txns().count(), 3);
+        assert!(text.contains("#home"));
+        assert!(text.contains("open asset:joint report USD"));
+        assert!(text.contains("; time: 09:00") && !text.contains("alex"));
+
+        let shared = Journal::from_str(&text).unwrap();
+        let copy = shared.accns().by_name_unique("joint").ok().unwrap();
+        let balance = |journal: &Journal, accn| {
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string()
+        };
+        assert_eq!(balance(&shared, copy), balance(&journal, joint));
+        let private = shared.accns().by_name_unique("private").ok().unwrap();
+        assert_eq!(balance(&shared, private), "$1580");
+
+        let shared = journal.share(&[joint], Some("grocer"), None).unwrap();
+        assert_eq!(shared.txns().count(), 1);
+        assert!(shared.to_string().contains("Grocer | weekly shop"));
+    }
+}
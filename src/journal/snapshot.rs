@@ -0,0 +1,488 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Write},
+};
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+use itertools::Itertools;
+use rust_decimal::prelude::Zero;
+
+use crate::{
+    accn::{Accn, AccnEntry},
+    valuable::{Money, ProviderChain, RateProvider, Valuable},
+};
+
+use super::Journal;
+
+/// Balances of the accns at the end of some days, so balances of later days
+/// only add up the postings after the nearest one.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Snapshots {
+    /// Sum of the postings booked to each accn itself, not its descendants,
+    /// through the end of the day.
+    days: BTreeMap<NaiveDate, HashMap<Accn, Valuable>>,
+}
+
+impl Snapshots {
+    pub(crate) fn insert(&mut self, date: NaiveDate, accn: Accn, money: Money) {
+        *self.days.entry(date).or_default().entry(accn).or_default() += money;
+    }
+
+    /// Forget the snapshots a txn dated `date` changes.
+    pub(super) fn invalidate(&mut self, date: NaiveDate) {
+        self.days.split_off(&date);
+    }
+
+    /// Accns with a balance in any snapshot.
+    pub(super) fn accns(&self) -> impl Iterator<Item = Accn> + '_ {
+        self.days
+            .values()
+            .flat_map(|balances| balances.keys().copied())
+    }
+
+    pub(crate) fn dates(&self) -> impl Iterator<Item = NaiveDate> + '_ {
+        self.days.keys().copied()
+    }
+
+    /// Nearest snapshot at or before `date`.
+    fn nearest(&self, date: NaiveDate) -> Option<(NaiveDate, &HashMap<Accn, Valuable>)> {
+        self.days
+            .range(..=date)
+            .next_back()
+            .map(|(date, balances)| (*date, balances))
+    }
+}
+
+/// Moment balances are taken at: the start or end of a day, or a time of
+/// it that splits the txns of the day by their `time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Cutoff {
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+    /// Whether txns right at the cutoff are booked by it.
+    inclusive: bool,
+}
+
+impl Cutoff {
+    pub(crate) fn new(date: NaiveDate, time: Option<NaiveTime>, inclusive: bool) -> Self {
+        Self {
+            date,
+            time,
+            inclusive,
+        }
+    }
+
+    /// End of `date`, after every txn of the day.
+    pub(crate) fn end_of(date: NaiveDate) -> Self {
+        Self::new(date, None, true)
+    }
+
+    pub(crate) fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Whether a txn on `date` at `time` is booked by the cutoff. Txns
+    /// without a time come first in their day.
+    pub(super) fn includes(&self, date: NaiveDate, time: Option<NaiveTime>) -> bool {
+        if date != self.date {
+            return date < self.date;
+        }
+        match self.time {
+            None => self.inclusive,
+            Some(cut) => {
+                let time = time.unwrap_or(NaiveTime::MIN);
+                time < cut || (self.inclusive && time == cut)
+            }
+        }
+    }
+
+    /// Last day booked in full, whose snapshot the balances can start from.
+    fn last_full_day(&self) -> Option<NaiveDate> {
+        match (self.time, self.inclusive) {
+            (None, true) => Some(self.date),
+            _ => self.date.pred_opt(),
+        }
+    }
+}
+
+impl Display for Cutoff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = match self.inclusive {
+            true => "through",
+            false => "before",
+        };
+        match self.time {
+            Some(time) => write!(f, "{} {} {}", side, self.date, time.format("%H:%M")),
+            None => write!(f, "{} {}", side, self.date),
+        }
+    }
+}
+
+impl Journal {
+    /// Balance of `accn` and its descendants at the end of `date`, starting
+    /// from the nearest snapshot.
+    pub(crate) fn balance_at(&self, accn: AccnEntry, date: NaiveDate) -> Valuable {
+        self.balance_as_of(accn, Cutoff::end_of(date))
+    }
+
+    /// Balance of `accn` and its descendants at `cutoff`.
+    pub(crate) fn balance_as_of(&self, accn: AccnEntry, cutoff: Cutoff) -> Valuable {
+        self.roll_up(self.own_balances_as_of(cutoff))
+            .remove(&accn.id())
+            .unwrap_or_default()
+    }
+
+    /// Balance of every accn with its descendants at `cutoff`, leaving out
+    /// those at zero, by name.
+    pub(crate) fn balances_as_of(&self, cutoff: Cutoff) -> Vec<(AccnEntry<'_>, Valuable)> {
+        self.rolled_up(self.own_balances_as_of(cutoff))
+    }
+
+    /// Like [`Journal::balances_as_of`], with the balances of accns that
+    /// report in a currency converted to it at the rates of the cutoff day
+    /// before they are rolled up, so the ancestors add up what is reported.
+    pub(crate) fn reported_balances_as_of(
+        &self,
+        cutoff: Cutoff,
+        fallback: &dyn RateProvider,
+    ) -> Result<Vec<(AccnEntry<'_>, Valuable)>> {
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let mut own = self.own_balances_as_of(cutoff);
+        for (accn, balance) in own.iter_mut() {
+            let accn = accn.into_accn(&self.accns);
+            let Some(code) = accn.report_currency() else {
+                continue;
+            };
+            let mut reported = Valuable::default();
+            for money in std::mem::take(balance) {
+                let money = money.into_money(&self.currencies);
+                reported += match money.code() == code {
+                    true => money.money(),
+                    false => money
+                        .convert_to(code, cutoff.date, &rates)
+                        .with_context(|| format!("cannot report {} in {}", accn, code))?
+                        .money(),
+                };
+            }
+            *balance = reported;
+        }
+        Ok(self.rolled_up(own))
+    }
+
+    /// Own balances added up to every ancestor.
+    fn roll_up(&self, own: HashMap<Accn, Valuable>) -> HashMap<Accn, Valuable> {
+        let mut balances: HashMap<Accn, Valuable> = HashMap::new();
+        for (accn, balance) in own {
+            let mut accn = Some(accn.into_accn(&self.accns));
+            while let Some(entry) = accn {
+                *balances.entry(entry.id()).or_default() += balance.clone();
+                accn = entry.parent();
+            }
+        }
+        balances
+    }
+
+    /// Own balances added up to every ancestor but the root, leaving out
+    /// those at zero, by name.
+    fn rolled_up(&self, own: HashMap<Accn, Valuable>) -> Vec<(AccnEntry<'_>, Valuable)> {
+        self.roll_up(own)
+            .into_iter()
+            .filter(|(accn, balance)| {
+                accn.into_accn(&self.accns).parent().is_some() && !balance.is_zero()
+            })
+            .map(|(accn, balance)| (accn.into_accn(&self.accns), balance))
+            .sorted_by_key(|(accn, _)| accn.abs_name())
+            .collect()
+    }
+
+    /// Own balances of the accns at `cutoff`, from the nearest snapshot of a
+    /// day the cutoff books in full.
+    fn own_balances_as_of(&self, cutoff: Cutoff) -> HashMap<Accn, Valuable> {
+        let nearest = cutoff
+            .last_full_day()
+            .and_then(|day| self.txns.snapshots.nearest(day));
+        let (from, mut balances) = match nearest {
+            Some((from, balances)) => (Some(from), balances.clone()),
+            None => (None, HashMap::new()),
+        };
+        for posting in self.postings().filter(|p| {
+            let txn = p.txn();
+            from.is_none_or(|from| txn.date() > from) && cutoff.includes(txn.date(), txn.time())
+        }) {
+            *balances.entry(posting.accn().id()).or_default() += posting.money().money();
+        }
+        balances
+    }
+
+    /// Own balances of every accn at the end of `date`, replaying every
+    /// posting.
+    fn replay(&self, date: NaiveDate) -> HashMap<Accn, Valuable> {
+        let mut balances: HashMap<Accn, Valuable> = HashMap::new();
+        for posting in self.postings().filter(|p| p.txn().date() <= date) {
+            *balances.entry(posting.accn().id()).or_default() += posting.money().money();
+        }
+        balances.retain(|_, balance| !balance.is_zero());
+        balances
+    }
+
+    /// Snapshot the end of every month from the first txn through `until`
+    /// that has none yet, returning the days snapshotted.
+    pub(crate) fn snapshot_months(&mut self, until: NaiveDate) -> Vec<NaiveDate> {
+        let Some(first) = self.txns().map(|txn| txn.date()).min() else {
+            return Vec::new();
+        };
+        let mut ends = Vec::new();
+        let mut month = first.with_day(1).unwrap();
+        loop {
+            let next = month + chrono::Months::new(1);
+            let end = next - Duration::days(1);
+            if end > until {
+                break;
+            }
+            if !self.txns.snapshots.days.contains_key(&end) {
+                ends.push(end);
+            }
+            month = next;
+        }
+
+        for end in &ends {
+            let balances = self.replay(*end);
+            self.txns.snapshots.days.insert(*end, balances);
+        }
+        ends
+    }
+
+    /// Days whose snapshot disagrees with the postings, like after editing
+    /// the file by hand.
+    pub(crate) fn stale_snapshots(&self) -> Vec<NaiveDate> {
+        let moneys = |balance: &Valuable| {
+            balance
+                .sorted(&self.currencies)
+                .into_iter()
+                .filter(|money| !money.amount().is_zero())
+                .collect_vec()
+        };
+        self.txns
+            .snapshots
+            .days
+            .iter()
+            .filter(|(date, balances)| {
+                let replayed = self.replay(**date);
+                let accns = balances.keys().chain(replayed.keys()).unique();
+                accns.into_iter().any(|accn| {
+                    let snapshot = balances.get(accn).map(moneys).unwrap_or_default();
+                    let replayed = replayed.get(accn).map(moneys).unwrap_or_default();
+                    snapshot != replayed
+                })
+            })
+            .map(|(date, _)| *date)
+            .collect()
+    }
+
+    /// Snapshot directives, one line per accn and currency.
+    pub(super) fn snapshot_directives(&self) -> String {
+        let mut out = String::new();
+        for (date, balances) in &self.txns.snapshots.days {
+            let balances = balances
+                .iter()
+                .map(|(accn, balance)| (accn.into_accn(&self.accns).to_string(), balance))
+                .sorted_by(|(a, _), (b, _)| a.cmp(b));
+            for (accn, balance) in balances {
+                for money in balance.sorted(&self.currencies) {
+                    let money = money.into_money(&self.currencies);
+                    writeln!(out, "snapshot {} {} {}", date, accn, money).unwrap();
+                }
+            }
+        }
+        out.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-01 salary
+    asset:bank  $4000
+    income:salary
+
+2024-01-02 rent
+    expense:rent  $1500
+    asset:bank
+
+2024-02-01 salary
+    asset:bank  $4000
+    income:salary
+
+2024-03-02 rent
+    expense:rent  $1500
+    asset:bank"#;
+
+    #[test]
+    fn test_snapshots() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let until = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let days = journal.snapshot_months(until);
+        assert_eq!(days.len(), 2);
+        assert!(journal.snapshot_months(until).is_empty());
+
+        let show = |journal: &Journal, date| {
+            let asset = journal.accns().asset();
+            let balance = journal.balance_at(asset, date);
+            balance.into_valuable(journal.currencies()).to_string()
+        };
+        assert_eq!(show(&journal, days[0]), "$2500");
+        assert_eq!(show(&journal, until), "$5000");
+
+        let text = journal.to_string();
+        assert!(text.contains("snapshot 2024-01-31 asset:bank $2500"));
+        let reread = Journal::from_str(&text).unwrap();
+        assert_eq!(reread.txns.snapshots.dates().count(), 2);
+        assert!(reread.stale_snapshots().is_empty());
+
+        // a txn in february drops the february snapshot
+        let bank = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        let rent = journal.accns().by_name_unique("rent").ok().unwrap().id();
+        let money = journal.parse_money("$10").unwrap().money();
+        journal
+            .new_txn(NaiveDate::from_ymd_opt(2024, 2, 10).unwrap(), "fee".into())
+            .with_posting(rent, Some(money))
+            .with_posting(bank, None::<Money>)
+            .build()
+            .unwrap();
+        assert_eq!(journal.txns.snapshots.dates().collect_vec(), [days[0]]);
+        assert_eq!(show(&journal, until), "$4990");
+
+        let edited = text.replace("asset:bank $2500", "asset:bank $2400");
+        let edited = Journal::from_str(&edited).unwrap();
+        assert_eq!(edited.stale_snapshots(), [days[0]]);
+    }
+
+    #[test]
+    fn test_quoted_snapshot() {
+        let input = r#"2024-01-01 salary
+    "asset:Main Bank"  $4000
+    income:salary"#;
+        let mut journal = Journal::from_str(input).unwrap();
+        journal.snapshot_months(NaiveDate::from_ymd_opt(2024, 2, 15).unwrap());
+        let text = journal.to_string();
+        assert!(text.contains(r#"snapshot 2024-01-31 "asset:Main Bank" $4000"#));
+        let reread = Journal::from_str(&text).unwrap_or_else(|e| panic!("{:#}", e));
+        assert_eq!(reread.txns.snapshots.dates().count(), 1);
+        assert!(reread.stale_snapshots().is_empty());
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let input = r#"2024-02-29 salary
+    asset:bank  $4000
+    income:salary
+
+2024-03-31 lunch
+    ; time: 12:30
+    expense:food  $20
+    asset:bank
+
+2024-03-31 groceries
+    ; time: 18:00
+    expense:food  $80
+    asset:bank
+
+2024-03-31 refund
+    asset:bank  $5
+    income:misc"#;
+        let mut journal = Journal::from_str(input).unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        let at = |time: Option<&str>, inclusive| {
+            let time = time.map(|time| NaiveTime::parse_from_str(time, "%H:%M").unwrap());
+            Cutoff::new(day, time, inclusive)
+        };
+        // the lines of `bal`
+        let report = |journal: &Journal, cutoff| {
+            journal
+                .balances_as_of(cutoff)
+                .into_iter()
+                .map(|(accn, balance)| {
+                    let balance = balance.into_valuable(journal.currencies());
+                    format!("{} {}", accn.abs_name(), balance)
+                })
+                .collect_vec()
+        };
+        let asset = |journal: &Journal, cutoff| report(journal, cutoff).remove(0);
+        assert_eq!(asset(&journal, at(None, true)), "asset $3905");
+        assert_eq!(asset(&journal, at(None, false)), "asset $4000");
+        // the untimed refund comes first in the day
+        assert_eq!(asset(&journal, at(Some("12:30"), false)), "asset $4005");
+        assert_eq!(asset(&journal, at(Some("12:30"), true)), "asset $3985");
+        assert_eq!(asset(&journal, at(Some("17:59"), true)), "asset $3985");
+
+        // a snapshot of the day is only used once the whole day is in
+        assert_eq!(journal.snapshot_months(day).len(), 2);
+        assert_eq!(asset(&journal, at(Some("12:30"), true)), "asset $3985");
+        assert_eq!(asset(&journal, at(None, false)), "asset $4000");
+        let asset_accn = journal.accns().asset();
+        let balance = journal.balance_as_of(asset_accn, at(Some("12:30"), true));
+        assert_eq!(
+            balance.into_valuable(journal.currencies()).to_string(),
+            "$3985"
+        );
+        assert_eq!(
+            report(&journal, at(Some("18:00"), true)),
+            [
+                "asset $3905",
+                "asset:bank $3905",
+                "expense $100",
+                "expense:food $100",
+                "income -$4005",
+                "income:misc -$5",
+                "income:salary -$4000"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reported() {
+        let input = r#"rate 2024-04-01 JPY USD 0.0066
+open asset:jp-bank currency JPY report USD
+
+2024-04-01 salary
+    asset:jp-bank:savings  300000 JPY
+    income:salary
+
+2024-04-02 rent
+    expense:rent  $1500
+    asset:us-bank  -$1500"#;
+        let journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        assert!(journal
+            .to_string()
+            .contains("open asset:jp-bank currency JPY report USD"));
+
+        let cutoff = Cutoff::end_of(NaiveDate::from_ymd_opt(2024, 4, 30).unwrap());
+        let show = |balances: Vec<(AccnEntry, Valuable)>| {
+            balances
+                .into_iter()
+                .map(|(accn, balance)| {
+                    let balance = balance.into_valuable(journal.currencies());
+                    format!("{} {}", accn.abs_name(), balance)
+                })
+                .collect_vec()
+        };
+        let reported = journal
+            .reported_balances_as_of(cutoff, &ProviderChain::default())
+            .unwrap();
+        let reported = show(reported);
+        assert_eq!(
+            reported[..3],
+            [
+                "asset $480.00",
+                "asset:jp-bank $1980.00",
+                "asset:jp-bank:savings $1980.00"
+            ]
+        );
+        assert_eq!(reported[6], "income -300000 JPY");
+        // the amounts booked stay as they are
+        let native = show(journal.balances_as_of(cutoff));
+        assert!(native.contains(&"asset:jp-bank:savings 300000 JPY".to_string()));
+    }
+}
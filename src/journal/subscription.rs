@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+use chrono::NaiveDate;
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{accn::AccnEntry, locale, valuable::Money};
+
+use super::Journal;
+
+/// How often a subscription charges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Cadence {
+    Monthly,
+    Annual,
+}
+
+impl Cadence {
+    /// Cadence whose charges are `days` apart, give or take a few days.
+    fn of(days: i64) -> Option<Self> {
+        match days {
+            26..=35 => Some(Cadence::Monthly),
+            350..=380 => Some(Cadence::Annual),
+            _ => None,
+        }
+    }
+
+    /// Charges it takes to see the cadence, a monthly one could be chance
+    /// with only two.
+    fn min_charges(self) -> usize {
+        match self {
+            Cadence::Monthly => 3,
+            Cadence::Annual => 2,
+        }
+    }
+
+    fn per_year(self) -> Decimal {
+        match self {
+            Cadence::Monthly => Decimal::from(12),
+            Cadence::Annual => Decimal::ONE,
+        }
+    }
+}
+
+impl Display for Cadence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cadence::Monthly => "monthly",
+            Cadence::Annual => "annual",
+        }
+        .fmt(f)
+    }
+}
+
+/// Charges of the same desc to the same expense accn, repeating at a
+/// steady cadence.
+pub(crate) struct Subscription<'a> {
+    journal: &'a Journal,
+    desc: String,
+    accn: AccnEntry<'a>,
+    cadence: Cadence,
+    last: NaiveDate,
+    amount: Money,
+    /// What was charged before the latest price, if the price went up.
+    increased_from: Option<Money>,
+}
+
+impl<'a> Subscription<'a> {
+    pub(crate) fn accn(&self) -> AccnEntry<'a> {
+        self.accn
+    }
+
+    pub(crate) fn annual_cost(&self) -> Money {
+        Money::new(
+            self.amount.amount() * self.cadence.per_year(),
+            self.amount.currency(),
+        )
+    }
+
+    pub(crate) fn increased(&self) -> bool {
+        self.increased_from.is_some()
+    }
+}
+
+impl Display for Subscription<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let store = &self.journal.currencies;
+        write!(
+            f,
+            "{:<30} {:<30} {:<8} {:>12} {:>12}/yr  {}",
+            self.desc,
+            self.accn,
+            self.cadence,
+            self.amount.into_money(store).to_string(),
+            self.annual_cost().into_money(store).to_string(),
+            locale::date(self.last)
+        )?;
+        if let Some(from) = self.increased_from {
+            let note = format!("up from {}", from.into_money(store));
+            write!(f, "  {}", note.yellow())?;
+        }
+        Ok(())
+    }
+}
+
+impl Journal {
+    /// Expenses charged again and again at a monthly or annual cadence,
+    /// costliest per year first. Prices may change between charges, but not
+    /// by more than half again.
+    pub(crate) fn subscriptions(&self) -> Vec<Subscription<'_>> {
+        let expense = self.accns().expense();
+        let groups = self
+            .postings()
+            .filter(|p| p.accn().is_descendent_of(expense))
+            .filter(|p| p.money().money().amount().is_sign_positive())
+            .into_group_map_by(|p| {
+                // the payee names the merchant better than a narration that
+                // may change from charge to charge
+                let txn = p.txn();
+                let desc = txn.payee().unwrap_or(txn.desc()).trim().to_lowercase();
+                (desc, p.accn().id(), p.money().money().currency())
+            });
+
+        groups
+            .into_values()
+            .filter_map(|postings| {
+                let charges = postings
+                    .iter()
+                    .map(|p| (p.txn().date(), p.money().money()))
+                    .sorted_by_key(|(date, _)| *date)
+                    .collect_vec();
+                let (first, second) = (charges.first()?, charges.get(1)?);
+                let cadence = Cadence::of((second.0 - first.0).num_days())?;
+                let steady = charges.iter().tuple_windows().all(|((d0, m0), (d1, m1))| {
+                    let (lo, hi) = (m0.amount().min(m1.amount()), m0.amount().max(m1.amount()));
+                    Cadence::of((*d1 - *d0).num_days()) == Some(cadence)
+                        && hi <= lo * Decimal::new(15, 1)
+                });
+                if !steady || charges.len() < cadence.min_charges() {
+                    return None;
+                }
+
+                let (last, amount) = charges[charges.len() - 1];
+                let (_, previous) = charges[charges.len() - 2];
+                Some(Subscription {
+                    journal: self,
+                    desc: postings[0].txn().title(),
+                    accn: postings[0].accn(),
+                    cadence,
+                    last,
+                    amount,
+                    increased_from: (amount.amount() > previous.amount()).then_some(previous),
+                })
+            })
+            .sorted_by(|a, b| {
+                let cost = |s: &Subscription| s.annual_cost().amount();
+                cost(b).cmp(&cost(a)).then_with(|| a.desc.cmp(&b.desc))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-03 streaming
+    expense:media  $10
+    asset:bank
+
+2024-02-03 streaming
+    expense:media  $10
+    asset:bank
+
+2024-03-04 streaming
+    expense:media  $12
+    asset:bank
+
+2024-01-15 domain
+    expense:web  $15
+    asset:bank
+
+2025-01-14 domain
+    expense:web  $15
+    asset:bank
+
+2024-01-05 groceries
+    expense:food  $80
+    asset:bank
+
+2024-01-20 groceries
+    expense:food  $60
+    asset:bank
+
+2024-02-05 groceries
+    expense:food  $75
+    asset:bank
+
+2024-03-05 gym
+    expense:gym  $30
+    asset:bank
+
+2024-04-05 gym
+    expense:gym  $30
+    asset:bank"#;
+
+    #[test]
+    fn test_subscriptions() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let subs = journal.subscriptions();
+        let found = subs
+            .iter()
+            .map(|s| (s.desc.as_str(), s.cadence, s.increased()))
+            .collect_vec();
+        assert_eq!(
+            found,
+            [
+                ("streaming", Cadence::Monthly, true),
+                ("domain", Cadence::Annual, false)
+            ]
+        );
+        assert_eq!(subs[0].annual_cost().amount(), Decimal::from(144));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{
+    accn::Accn,
+    valuable::{Money, Valuable},
+};
+
+use super::{Journal, Txn};
+
+/// A transfer a `sweep` rule asks for after a txn booked enough to its accn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SweepProposal {
+    /// Txn that triggered the rule.
+    pub(crate) txn: Txn,
+    pub(crate) date: NaiveDate,
+    /// Accn the txn brought the money into.
+    pub(crate) from: Accn,
+    pub(crate) to: Accn,
+    pub(crate) money: Money,
+    /// What the txn booked to the accn of the rule.
+    pub(crate) of: Money,
+}
+
+/// A sweep proposal with what it needs to be printed.
+pub(crate) struct SweepDisplay<'a> {
+    journal: &'a Journal,
+    proposal: SweepProposal,
+}
+
+impl Display for SweepDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let journal = self.journal;
+        let proposal = &self.proposal;
+        write!(
+            f,
+            "sweep {} from {} to {}, out of {} on {}",
+            proposal.money.into_money(&journal.currencies),
+            proposal.from.into_accn(&journal.accns),
+            proposal.to.into_accn(&journal.accns),
+            proposal.of.into_money(&journal.currencies),
+            journal.txn(proposal.txn).title()
+        )
+    }
+}
+
+impl SweepProposal {
+    pub(crate) fn display(self, journal: &Journal) -> SweepDisplay<'_> {
+        SweepDisplay {
+            journal,
+            proposal: self,
+        }
+    }
+}
+
+impl Journal {
+    /// `sweep` directives of every accn, in the order they were declared.
+    pub(super) fn sweep_directives(&self) -> String {
+        self.accns
+            .accns()
+            .sorted_by_key(|accn| accn.abs_name())
+            .flat_map(|accn| accn.sweeps().iter().map(move |sweep| (accn, sweep)))
+            .map(|(accn, sweep)| {
+                let over = match sweep.over {
+                    Some(over) => format!(" > {}", over.into_money(&self.currencies)),
+                    None => String::new(),
+                };
+                format!(
+                    "sweep {}{} {}% to {}",
+                    accn,
+                    over,
+                    (sweep.share * Decimal::ONE_HUNDRED).normalize(),
+                    sweep.to.into_accn(&self.accns)
+                )
+            })
+            .join("\n")
+    }
+
+    /// Transfers the `sweep` rules ask for after `txns`, just added. A rule
+    /// applies to what a txn books to its accn and descendants, income
+    /// counted as positive, when that is over its threshold in the same
+    /// currency. The share is taken from the asset the txn brought it into.
+    pub(crate) fn sweep_proposals(&self, txns: &[Txn]) -> Vec<SweepProposal> {
+        let mut proposals = Vec::new();
+        for txn in txns.iter().filter(|txn| self.contains_txn(**txn)) {
+            let txn = self.txn(*txn);
+            for accn in self.accns.accns().filter(|accn| !accn.sweeps().is_empty()) {
+                let booked: Valuable = txn
+                    .postings()
+                    .filter(|p| p.accn().is_descendent_of(accn))
+                    .map(|p| -p.money().money())
+                    .sum();
+                for of in booked.into_iter().filter(|m| m.amount() > Decimal::ZERO) {
+                    let from = txn.postings().find(|p| {
+                        let money = p.money().money();
+                        p.accn().is_descendent_of(self.accns.asset())
+                            && money.currency() == of.currency()
+                            && money.amount() > Decimal::ZERO
+                    });
+                    let Some(from) = from else {
+                        continue;
+                    };
+                    let sweeps = accn.sweeps().iter().filter(|sweep| match sweep.over {
+                        Some(over) => {
+                            over.currency() == of.currency() && of.amount() > over.amount()
+                        }
+                        None => true,
+                    });
+                    for sweep in sweeps {
+                        let units = self.currencies.minor_units(of.currency());
+                        let amount = (of.amount() * sweep.share).round_dp(units).normalize();
+                        proposals.push(SweepProposal {
+                            txn: txn.id(),
+                            date: txn.date(),
+                            from: from.accn().id(),
+                            to: sweep.to,
+                            money: Money::new(amount, of.currency()),
+                            of,
+                        });
+                    }
+                }
+            }
+        }
+        proposals
+    }
+
+    /// Book `proposal` as a txn of its own on the date of the txn that
+    /// triggered it.
+    pub(crate) fn create_sweep(&mut self, proposal: &SweepProposal) -> Result<Txn> {
+        let desc = format!("sweep {}", self.txn(proposal.txn).title());
+        let txn = self
+            .new_txn(proposal.date, desc)
+            .with_posting(proposal.to, Some(proposal.money))
+            .with_posting(proposal.from, Some(-proposal.money))
+            .build()?;
+        Ok(txn.id())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"open asset:savings
+sweep income:salary > $3000 20% to asset:savings
+sweep income:bonus 50% to asset:savings
+
+2024-03-01 salary
+    asset:bank  $3500
+    income:salary
+
+2024-03-15 salary
+    asset:bank  $2000
+    income:salary
+
+2024-03-20 lunch
+    expense:food  $12
+    asset:bank"#;
+
+    #[test]
+    fn test_sweep_proposals() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let txns = journal.txns().map(|txn| txn.id()).collect_vec();
+        let proposals = journal.sweep_proposals(&txns);
+        assert_eq!(proposals.len(), 1);
+        let proposal = proposals[0];
+        assert_eq!(proposal.money.amount(), dec!(700));
+        assert_eq!(
+            proposal.display(&journal).to_string(),
+            "sweep $700 from asset:bank to asset:savings, out of $3500 on salary"
+        );
+
+        let txn = journal.create_sweep(&proposal).unwrap();
+        let savings = journal.accns().by_name_unique("savings").ok().unwrap();
+        assert_eq!(journal.txn(txn).title(), "sweep salary");
+        assert_eq!(
+            journal
+                .balance(savings)
+                .into_iter()
+                .next()
+                .unwrap()
+                .amount(),
+            dec!(700)
+        );
+        // moving money between assets triggers no other rule
+        assert!(journal.sweep_proposals(&[txn]).is_empty());
+    }
+
+    #[test]
+    fn test_sweep_directives() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let text = journal.to_string();
+        assert!(text.contains("sweep income:bonus 50% to asset:savings\n"));
+        assert!(text.contains("sweep income:salary > $3000 20% to asset:savings\n"));
+        assert_eq!(Journal::from_str(&text).unwrap().to_string(), text);
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use rust_decimal::Decimal;
+
+use crate::valuable::{ProviderChain, RateProvider};
+
+use super::{entry::PostingEntry, Journal};
+
+/// Whether a tax category adds to the taxable income or takes from it,
+/// decided by the root of the accns mapped to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum TaxKind {
+    Income,
+    Deduction,
+}
+
+/// One category of a tax report with what went into it.
+pub(crate) struct TaxCategory<'a> {
+    name: String,
+    kind: TaxKind,
+    /// Sum of the postings in the currency of the report, income positive.
+    total: Decimal,
+    postings: Vec<(PostingEntry<'a>, Decimal)>,
+}
+
+/// Estimated tax of one year as printed by `tax`.
+pub(crate) struct TaxReport<'a> {
+    year: i32,
+    code: String,
+    /// Digits the estimates are rounded to, the minor units of `code`.
+    units: u32,
+    /// Tax on the taxable income, like `0.24` for 24%, none to leave out
+    /// the estimate.
+    rate: Option<Decimal>,
+    categories: Vec<TaxCategory<'a>>,
+}
+
+impl TaxReport<'_> {
+    fn sum(&self, kind: TaxKind) -> Decimal {
+        self.categories
+            .iter()
+            .filter(|category| category.kind == kind)
+            .map(|category| category.total)
+            .sum()
+    }
+
+    pub(crate) fn income(&self) -> Decimal {
+        self.sum(TaxKind::Income)
+    }
+
+    pub(crate) fn deductions(&self) -> Decimal {
+        self.sum(TaxKind::Deduction)
+    }
+
+    /// Income less deductions, never below zero.
+    pub(crate) fn taxable(&self) -> Decimal {
+        (self.income() - self.deductions()).max(Decimal::ZERO)
+    }
+
+    pub(crate) fn estimate(&self) -> Option<Decimal> {
+        Some((self.taxable() * self.rate?).round_dp(self.units))
+    }
+
+    /// Estimated payments due for the year, the estimate spread evenly over
+    /// the usual quarterly due dates with the last one in the next year.
+    pub(crate) fn quarterly(&self) -> Vec<(NaiveDate, Decimal)> {
+        let Some(estimate) = self.estimate() else {
+            return Vec::new();
+        };
+        let quarter = (estimate / Decimal::from(4)).round_dp(self.units);
+        let dues = [
+            (self.year, 4),
+            (self.year, 6),
+            (self.year, 9),
+            (self.year + 1, 1),
+        ];
+        dues.into_iter()
+            .enumerate()
+            .map(|(i, (year, month))| {
+                let date = NaiveDate::from_ymd_opt(year, month, 15).unwrap();
+                // the last payment takes what rounding left over
+                match i {
+                    3 => (date, estimate - quarter * Decimal::from(3)),
+                    _ => (date, quarter),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Journal {
+    /// Tax report of `year` in `code` from the accns mapped to tax
+    /// categories by `tax` directives. Postings are converted at the rates
+    /// of their dates, taking those the journal lacks from `fallback`.
+    pub(crate) fn tax_report(
+        &self,
+        year: i32,
+        code: &str,
+        rate: Option<Decimal>,
+        fallback: &dyn RateProvider,
+    ) -> Result<TaxReport<'_>> {
+        let Some(currency) = self.currencies.get_by_code(code) else {
+            bail!("code {} not found", code);
+        };
+        let rates = ProviderChain::default().with(&self.rates).with(fallback);
+        let income = self.accns.income();
+
+        let mut categories: Vec<TaxCategory> = Vec::new();
+        let postings = self.postings().filter(|p| p.txn().date().year() == year);
+        for posting in postings {
+            let Some(name) = posting.accn().tax_category() else {
+                continue;
+            };
+            let kind = match posting.accn().is_descendent_of(income) {
+                true => TaxKind::Income,
+                false => TaxKind::Deduction,
+            };
+            let amount = posting
+                .money()
+                .convert_to(code, posting.txn().date(), &rates)?
+                .money()
+                .amount();
+            let amount = match kind {
+                TaxKind::Income => -amount,
+                TaxKind::Deduction => amount,
+            };
+            let category = match categories
+                .iter_mut()
+                .position(|c| c.name == name && c.kind == kind)
+            {
+                Some(i) => &mut categories[i],
+                None => {
+                    categories.push(TaxCategory {
+                        name: name.to_string(),
+                        kind,
+                        total: Decimal::ZERO,
+                        postings: Vec::new(),
+                    });
+                    categories.last_mut().unwrap()
+                }
+            };
+            category.total += amount;
+            category.postings.push((posting, amount));
+        }
+        categories.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+
+        Ok(TaxReport {
+            year,
+            code: code.to_string(),
+            units: self.currencies.minor_units(currency),
+            rate,
+            categories,
+        })
+    }
+}
+
+impl Display for TaxReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let row = |f: &mut std::fmt::Formatter<'_>, label: &str, amount: Decimal| {
+            writeln!(f, "{:<40}{:>16} {}", label, amount, self.code)
+        };
+        writeln!(f, "{}", format!("tax year {}", self.year).bold())?;
+        for kind in [TaxKind::Income, TaxKind::Deduction] {
+            for category in self.categories.iter().filter(|c| c.kind == kind) {
+                let label = match kind {
+                    TaxKind::Income => format!("{} (income)", category.name),
+                    TaxKind::Deduction => format!("{} (deduction)", category.name),
+                };
+                row(f, &label.bold().to_string(), category.total)?;
+                for (posting, amount) in &category.postings {
+                    let txn = posting.txn();
+                    let label = format!("  {} {}", txn.date(), txn.title());
+                    writeln!(
+                        f,
+                        "{:<40}{:>16} {}  {}",
+                        label,
+                        amount,
+                        self.code,
+                        posting.accn().to_string().dimmed()
+                    )?;
+                }
+            }
+        }
+        writeln!(f)?;
+        row(f, "taxable income", self.income())?;
+        row(f, "deductions", self.deductions())?;
+        row(f, &"taxable".bold().to_string(), self.taxable())?;
+        match (self.rate, self.estimate()) {
+            (Some(rate), Some(estimate)) => {
+                let label = format!(
+                    "estimated tax at {}%",
+                    (rate * Decimal::ONE_HUNDRED).normalize()
+                );
+                row(f, &label, estimate)?;
+                for (date, amount) in self.quarterly() {
+                    row(f, &format!("  due {}", date), amount)?;
+                }
+                Ok(())
+            }
+            _ => write!(
+                f,
+                "give a rate with --rate or `set tax-rate` for an estimate"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"tax income:salary wages
+tax expense:charity charitable
+tax expense:health medical
+
+2023-12-20 salary
+    asset:bank  $3000
+    income:salary
+
+2024-01-05 salary
+    asset:bank  $5000
+    income:salary
+
+2024-02-05 salary
+    asset:bank  $5000
+    income:salary
+
+2024-03-01 donation
+    expense:charity:redcross  $1000
+    asset:bank
+
+2024-03-02 groceries
+    expense:food  $200
+    asset:bank"#;
+
+    #[test]
+    fn test_tax_report() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let redcross = journal.accns().by_name_unique("redcross").ok().unwrap();
+        assert_eq!(redcross.tax_category(), Some("charitable"));
+
+        let report = journal
+            .tax_report(2024, "USD", Some(dec!(0.25)), &journal.rates)
+            .unwrap();
+        assert_eq!(report.income(), dec!(10000));
+        assert_eq!(report.deductions(), dec!(1000));
+        assert_eq!(report.estimate(), Some(dec!(2250)));
+        let quarterly = report.quarterly();
+        assert_eq!(quarterly.len(), 4);
+        assert_eq!(quarterly[0].1, dec!(562.50));
+        assert_eq!(
+            quarterly[3].0,
+            NaiveDate::from_ymd_opt(2025, 1, 15).unwrap()
+        );
+        assert_eq!(report.categories[0].postings.len(), 2);
+
+        assert!(journal
+            .to_string()
+            .contains("tax expense:charity charitable"));
+    }
+
+    #[test]
+    fn test_tax_in_yen() {
+        let input = r#"tax income:salary wages
+
+2024-01-25 salary
+    asset:bank  10007 JPY
+    income:salary"#;
+        let journal = Journal::from_str(input).unwrap();
+        let report = journal
+            .tax_report(2024, "JPY", Some(dec!(0.1)), &journal.rates)
+            .unwrap();
+        assert_eq!(report.estimate(), Some(dec!(1001)));
+        let quarterly = report.quarterly();
+        assert_eq!(quarterly[0].1, dec!(250));
+        assert_eq!(quarterly[3].1, dec!(251));
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+use crate::util::fold;
+
+use super::{Journal, Txn, TxnBuilder};
+
+/// Titles of past txns with the latest txn of each, the latest first, to
+/// find one alike to a new description.
+#[derive(Debug, Default)]
+pub(crate) struct DescIndex {
+    entries: Vec<DescEntry>,
+}
+
+#[derive(Debug)]
+struct DescEntry {
+    title: String,
+    folded: String,
+    txn: Txn,
+}
+
+impl DescEntry {
+    /// How well `typed`, folded, matches: the title starting with it, every
+    /// typed word starting a word of the title, or its letters appearing in
+    /// order.
+    fn score(&self, typed: &str) -> Option<u8> {
+        let words = self.folded.split_whitespace().collect_vec();
+        if self.folded.starts_with(typed) {
+            Some(3)
+        } else if typed
+            .split_whitespace()
+            .all(|part| words.iter().any(|word| word.starts_with(part)))
+        {
+            Some(2)
+        } else {
+            let mut chars = self.folded.chars();
+            typed
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .all(|c| chars.any(|t| t == c))
+                .then_some(1)
+        }
+    }
+}
+
+impl DescIndex {
+    /// Latest txn whose title matches `typed` best, if any does.
+    pub(crate) fn suggest(&self, typed: &str) -> Option<Txn> {
+        let typed = fold(typed.trim());
+        if typed.is_empty() {
+            return None;
+        }
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((entry.score(&typed)?, entry)))
+            // the first of the best, as the latest come first
+            .rev()
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, entry)| entry.txn)
+    }
+
+    /// Rest of the latest title starting with `typed`, to offer as a hint
+    /// while typing.
+    pub(crate) fn complete(&self, typed: &str) -> Option<&str> {
+        let typed = typed.to_lowercase();
+        self.entries
+            .iter()
+            .map(|entry| entry.title.as_str())
+            .find(|title| {
+                title.len() > typed.len()
+                    && title.is_char_boundary(typed.len())
+                    && title[..typed.len()].to_lowercase() == typed
+            })
+            .map(|title| &title[typed.len()..])
+    }
+}
+
+impl Journal {
+    pub(crate) fn desc_index(&self) -> DescIndex {
+        let entries = self
+            .txns()
+            .sorted_by_key(|txn| txn.date())
+            .rev()
+            .unique_by(|txn| fold(&txn.title()))
+            .map(|txn| DescEntry {
+                title: txn.title(),
+                folded: fold(&txn.title()),
+                txn: txn.id(),
+            })
+            .collect();
+        DescIndex { entries }
+    }
+
+    /// A txn on `date` with the postings and amounts of `template`.
+    pub(crate) fn create_from_template(
+        &mut self,
+        template: Txn,
+        date: NaiveDate,
+        desc: String,
+    ) -> Result<Txn> {
+        let mut txn = TxnBuilder::new(date, desc);
+        for posting in self.txn(template).postings() {
+            let (accn, tags) = (posting.accn().id(), posting.tags().to_vec());
+            match posting.timed() {
+                Some(timed) => txn.with_timed_posting(accn, timed, tags, &self.currencies),
+                None => txn.with_tagged_posting(accn, Some(posting.money().money()), tags),
+            };
+        }
+        Ok(txn.build(&mut self.txns, &self.currencies)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-03 Blue Bottle | coffee
+    expense:coffee  $4.5
+    asset:cash
+
+2024-02-10 Blue Bottle | coffee
+    expense:coffee  $5.25
+    asset:cash
+
+2024-02-11 rent
+    expense:rent  $1500
+    asset:bank"#;
+
+    #[test]
+    fn test_template() {
+        let mut journal = Journal::from_str(INPUT).unwrap_or_else(|e| panic!("{:#}", e));
+        let index = journal.desc_index();
+        assert_eq!(index.complete("blue"), Some(" Bottle | coffee"));
+        assert_eq!(index.complete("rent"), None);
+        assert!(index.suggest("groceries").is_none());
+
+        let coffee = index.suggest("bottle coffee").unwrap();
+        assert_eq!(journal.txn(coffee).date().to_string(), "2024-02-10");
+        assert_eq!(index.suggest("rnt"), index.suggest("rent"));
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let txn = journal
+            .create_from_template(coffee, date, "Blue Bottle | coffee".into())
+            .unwrap();
+        let txn = journal.txn(txn);
+        assert_eq!(txn.payee(), Some("Blue Bottle"));
+        let accn = journal.accns().by_name_unique("coffee").ok().unwrap();
+        assert_eq!(
+            journal
+                .balance(accn)
+                .into_valuable(journal.currencies())
+                .to_string(),
+            "$15.00"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use chrono::NaiveDate;
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use crate::{locale, valuable::ValuableEntry};
+
+use super::{entry::PostingEntry, Journal};
+
+/// Hours booked for each client, as printed by `timesheet`.
+pub(crate) struct Timesheet<'a> {
+    journal: &'a Journal,
+    /// Timed postings by client, the payee of their txn or else the name
+    /// of their accn.
+    clients: BTreeMap<String, Vec<PostingEntry<'a>>>,
+}
+
+impl Timesheet<'_> {
+    pub(crate) fn hours(&self, client: &str) -> Decimal {
+        self.clients
+            .get(client)
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.timed())
+            .map(|timed| timed.hours.abs())
+            .sum()
+    }
+}
+
+impl Journal {
+    /// Postings written as hours at a rate, dated from `since` through
+    /// `until` and billed to clients whose name contains `client`.
+    pub(crate) fn timesheet(
+        &self,
+        client: Option<&str>,
+        since: Option<NaiveDate>,
+        until: Option<NaiveDate>,
+    ) -> Timesheet<'_> {
+        let client = client.map(str::to_lowercase);
+        let mut clients: BTreeMap<String, Vec<_>> = BTreeMap::new();
+        let postings = self
+            .postings()
+            .filter(|p| p.timed().is_some())
+            .filter(|p| since.is_none_or(|since| p.txn().date() >= since))
+            .filter(|p| until.is_none_or(|until| p.txn().date() <= until))
+            .sorted_by_key(|p| p.txn().date());
+        for posting in postings {
+            let name = match posting.txn().payee() {
+                Some(payee) => payee.to_string(),
+                None => posting.accn().name().to_string(),
+            };
+            if client
+                .as_ref()
+                .is_some_and(|client| !name.to_lowercase().contains(client))
+            {
+                continue;
+            }
+            clients.entry(name).or_default().push(posting);
+        }
+        Timesheet {
+            journal: self,
+            clients,
+        }
+    }
+}
+
+impl Display for Timesheet<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.clients.is_empty() {
+            return write!(
+                f,
+                "no hours, book some like `income:consulting -3.5 HRS @ $120`"
+            );
+        }
+        for (i, (client, postings)) in self.clients.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            // income is negative, what the client owes is not
+            let total: ValuableEntry = postings.iter().map(|p| p.money()).sum();
+            write!(
+                f,
+                "{:<52}{:>8} HRS {:>14}",
+                client.bold(),
+                self.hours(client).normalize(),
+                (-total).to_string()
+            )?;
+            for posting in postings {
+                let timed = posting.timed().unwrap();
+                let txn = posting.txn();
+                let store = &self.journal.currencies;
+                let rate = timed.rate.into_money(store);
+                let amount = (-posting.money().money()).into_money(store);
+                write!(
+                    f,
+                    "\n  {} {:<38}{:>8} HRS @ {:<8}{:>10}",
+                    locale::date(txn.date()),
+                    txn.desc(),
+                    timed.hours.abs().normalize(),
+                    rate.to_string(),
+                    amount.to_string()
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-05-02 acme | api review
+    income:consulting  -3.5 HRS @ $120
+    asset:receivable:acme
+
+2024-05-03 acme | deployment
+    income:consulting  -2 HRS @ $120 #onsite
+    asset:receivable:acme
+
+2024-05-03 globex | workshop
+    income:consulting  -4 HRS @ $150
+    asset:receivable:globex"#;
+
+    #[test]
+    fn test_timesheet() {
+        let journal = Journal::from_str(INPUT).unwrap();
+        let receivable = journal.accns().by_name_unique("receivable").ok().unwrap();
+        let owed = journal
+            .balance(receivable)
+            .into_valuable(journal.currencies());
+        assert_eq!(owed.to_string(), "$1260");
+
+        let timesheet = journal.timesheet(None, None, None);
+        assert_eq!(timesheet.clients.len(), 2);
+        assert_eq!(timesheet.hours("acme"), dec!(5.5));
+        let acme = journal.timesheet(Some("ACM"), None, None);
+        assert_eq!(acme.clients.keys().collect_vec(), ["acme"]);
+
+        let text = journal.to_string();
+        assert!(text.contains("-3.5 HRS @ $120"));
+        assert!(text.contains("-2 HRS @ $120 #onsite"));
+        assert_eq!(Journal::from_str(&text).unwrap().to_string(), text);
+
+        // yen are booked whole
+        let input = INPUT.replace("-4 HRS @ $150", "-0.3 HRS @ 1001 JPY");
+        let journal = Journal::from_str(&input).unwrap();
+        let globex = journal.accns().by_name_unique("globex").ok().unwrap();
+        let owed = journal.balance(globex).into_valuable(journal.currencies());
+        assert_eq!(owed.to_string(), "300 JPY");
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use colored::Colorize;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use super::{Journal, Txn};
+
+/// Debits and credits of every accn, with whatever breaks double entry.
+pub(crate) struct TrialBalance<'a> {
+    journal: &'a Journal,
+    /// Total debits and credits per accn and currency code.
+    rows: BTreeMap<(String, String), (Decimal, Decimal)>,
+    /// Txns whose postings do not add up to zero.
+    unbalanced: Vec<Txn>,
+    /// Postings of txns that do not exist, or missing postings of txns.
+    dangling: usize,
+}
+
+impl TrialBalance<'_> {
+    /// Total debits and credits per currency code.
+    fn totals(&self) -> BTreeMap<&str, (Decimal, Decimal)> {
+        let mut totals: BTreeMap<&str, (Decimal, Decimal)> = BTreeMap::new();
+        for ((_, code), (debit, credit)) in &self.rows {
+            let total = totals.entry(code).or_default();
+            total.0 += debit;
+            total.1 += credit;
+        }
+        totals
+    }
+
+    /// Whether every currency sums to zero and nothing is inconsistent.
+    pub(crate) fn is_balanced(&self) -> bool {
+        self.unbalanced.is_empty()
+            && self.dangling == 0
+            && self
+                .totals()
+                .values()
+                .all(|(debit, credit)| debit == credit)
+    }
+}
+
+impl Display for TrialBalance<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:<50} {:>6} {:>15} {:>15}", "", "", "debit", "credit")?;
+        for ((accn, code), (debit, credit)) in &self.rows {
+            writeln!(f, "{:<50} {:>6} {:>15} {:>15}", accn, code, debit, credit)?;
+        }
+        for (code, (debit, credit)) in self.totals() {
+            let line = format!("{:<50} {:>6} {:>15} {:>15}", "total", code, debit, credit);
+            writeln!(f, "{}", line.bold())?;
+            if debit != credit {
+                let msg = format!("{} is off by {}", code, debit - credit);
+                writeln!(f, "{}", msg.red())?;
+            }
+        }
+        for txn in &self.unbalanced {
+            let msg = format!("unbalanced txn:\n{}", self.journal.txn(*txn));
+            writeln!(f, "{}", msg.red())?;
+        }
+        if self.dangling > 0 {
+            let msg = format!("{} postings not matching their txns", self.dangling);
+            writeln!(f, "{}", msg.red())?;
+        }
+        match self.is_balanced() {
+            true => write!(f, "{}", "balanced".green()),
+            false => write!(f, "{}", "not balanced".red().bold()),
+        }
+    }
+}
+
+impl Journal {
+    /// Debits and credits of every accn, checking the postings of every
+    /// currency and every txn add up to zero.
+    pub(crate) fn trial_balance(&self) -> TrialBalance<'_> {
+        let mut rows: BTreeMap<(String, String), (Decimal, Decimal)> = BTreeMap::new();
+        for posting in self.txns.postings.values() {
+            let code = self.currencies.code(posting.money.currency());
+            let accn = posting.accn.into_accn(&self.accns).abs_name();
+            let row = rows.entry((accn, code.to_string())).or_default();
+            match posting.money.amount().is_sign_negative() {
+                true => row.1 -= posting.money.amount(),
+                false => row.0 += posting.money.amount(),
+            }
+        }
+
+        let mut dangling = self
+            .txns
+            .postings
+            .iter()
+            .filter(|(id, posting)| {
+                self.txns
+                    .txns
+                    .get(&posting.txn)
+                    .is_none_or(|txn| !txn.postings.contains(id))
+            })
+            .count();
+        dangling += self
+            .txns
+            .txns
+            .values()
+            .flat_map(|txn| &txn.postings)
+            .filter(|posting| !self.txns.postings.contains_key(**posting))
+            .count();
+
+        let unbalanced = self
+            .txns()
+            .filter(|txn| {
+                let postings = txn.postings().map(|p| p.money().money()).collect_vec();
+                postings
+                    .iter()
+                    .into_group_map_by(|money| money.currency())
+                    .values()
+                    .any(|moneys| !moneys.iter().map(|m| m.amount()).sum::<Decimal>().is_zero())
+            })
+            .sorted_by_key(|txn| txn.date())
+            .map(Txn::from)
+            .collect();
+
+        TrialBalance {
+            journal: self,
+            rows,
+            unbalanced,
+            dangling,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::valuable::Money;
+
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-01 salary
+    asset:bank  $4000
+    income:salary
+
+2024-01-02 rent
+    expense:rent  $1500
+    asset:bank
+
+2024-01-03 sushi
+    expense:food  ¥3000
+    asset:cash"#;
+
+    #[test]
+    fn test_trial_balance() {
+        let mut journal = Journal::from_str(INPUT).unwrap();
+        let trial = journal.trial_balance();
+        assert!(trial.is_balanced());
+        let bank = &trial.rows[&("asset:bank".to_string(), "USD".to_string())];
+        assert_eq!(*bank, (Decimal::from(4000), Decimal::from(1500)));
+        assert_eq!(trial.totals().len(), 2);
+
+        // break a posting behind the back of the txn
+        let posting = journal.txns.postings.values_mut().next().unwrap();
+        posting.money = Money::new(
+            posting.money.amount() + Decimal::ONE,
+            posting.money.currency(),
+        );
+        let trial = journal.trial_balance();
+        assert!(!trial.is_balanced());
+        assert_eq!(trial.unbalanced.len(), 1);
+    }
+}
@@ -0,0 +1,37 @@
+#![allow(dead_code)]
+#![feature(try_blocks)]
+#![feature(impl_trait_in_assoc_type)]
+#![feature(trait_alias)]
+
+mod accn;
+#[doc(hidden)]
+pub mod bench;
+mod error;
+pub mod highlight;
+mod journal;
+mod locale;
+mod period;
+mod valuable;
+mod workspace;
+
+mod repl;
+#[cfg(test)]
+mod tests;
+mod util;
+
+pub use error::CoinError;
+pub use journal::Journal;
+pub use repl::{
+    plugin::{Handler, Registry},
+    ReplState,
+};
+
+pub fn run() {
+    run_with(Registry::builtin());
+}
+
+/// Run the REPL, falling back to `commands` for input the grammar does not
+/// know.
+pub fn run_with(commands: Registry) {
+    repl::repl(commands);
+}
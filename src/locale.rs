@@ -0,0 +1,217 @@
+use std::{fmt::Display, str::FromStr, sync::RwLock};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+
+/// Language of the labels and dates shown to the user. Journal files are
+/// never localized, so they read the same everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    De,
+    Fr,
+    Ja,
+}
+
+static CURRENT: RwLock<Locale> = RwLock::new(Locale::En);
+
+/// Locale of the session.
+pub(crate) fn current() -> Locale {
+    *CURRENT.read().unwrap()
+}
+
+pub(crate) fn set(locale: Locale) {
+    *CURRENT.write().unwrap() = locale;
+}
+
+/// `label` in the locale of the session.
+pub(crate) fn tr(label: Label) -> &'static str {
+    current().text(label)
+}
+
+/// `date` formatted for the locale of the session.
+pub(crate) fn date(date: NaiveDate) -> String {
+    current().format_date(date)
+}
+
+/// Locale named by the environment like `LANG=de_DE.UTF-8`, if supported.
+pub(crate) fn from_env() -> Option<Locale> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| value.parse().ok())
+}
+
+/// Strings shown to the user that have translations.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Label {
+    Error,
+    Info,
+    Total,
+    Opened,
+    Closed,
+    Currency,
+    Postings,
+    First,
+    Last,
+    Balance,
+    Children,
+    Source,
+    Tags,
+    Impact,
+    NotSavedYet,
+    SelectToShow,
+    SelectToDelete,
+    EnterDesc,
+    EnterAmount,
+    CreateAccn,
+    NoUnsavedChanges,
+    NoProblemsFound,
+    NoAnomaliesFound,
+}
+
+impl Locale {
+    pub(crate) fn text(self, label: Label) -> &'static str {
+        let [en, de, fr, ja] = match label {
+            Label::Error => ["error", "Fehler", "erreur", "エラー"],
+            Label::Info => ["info", "Info", "info", "情報"],
+            Label::Total => ["total", "Summe", "total", "合計"],
+            Label::Opened => ["opened", "eröffnet", "ouvert", "開設"],
+            Label::Closed => ["closed", "geschlossen", "fermé", "閉鎖"],
+            Label::Currency => ["currency", "Währung", "devise", "通貨"],
+            Label::Postings => ["postings", "Buchungen", "écritures", "仕訳"],
+            Label::First => ["first", "erste", "première", "初回"],
+            Label::Last => ["last", "letzte", "dernière", "最終"],
+            Label::Balance => ["balance", "Saldo", "solde", "残高"],
+            Label::Children => ["children", "Unterkonten", "sous-comptes", "子勘定"],
+            Label::Source => ["source", "Quelle", "source", "出典"],
+            Label::Tags => ["tags", "Tags", "étiquettes", "タグ"],
+            Label::Impact => ["impact", "Auswirkung", "impact", "影響"],
+            Label::NotSavedYet => [
+                "not saved yet",
+                "noch nicht gespeichert",
+                "pas encore enregistré",
+                "未保存",
+            ],
+            Label::SelectToShow => [
+                "select to show",
+                "zum Anzeigen auswählen",
+                "choisir à afficher",
+                "表示する取引を選択",
+            ],
+            Label::SelectToDelete => [
+                "select to delete",
+                "zum Löschen auswählen",
+                "choisir à supprimer",
+                "削除する取引を選択",
+            ],
+            Label::EnterDesc => [
+                "enter desc: ",
+                "Beschreibung eingeben: ",
+                "saisir la description : ",
+                "説明を入力: ",
+            ],
+            Label::EnterAmount => ["amount? ", "Betrag? ", "montant ? ", "金額? "],
+            Label::CreateAccn => [
+                "create accn",
+                "Konto anlegen",
+                "créer le compte",
+                "勘定を作成",
+            ],
+            Label::NoUnsavedChanges => [
+                "no unsaved changes",
+                "keine ungespeicherten Änderungen",
+                "aucune modification non enregistrée",
+                "未保存の変更はありません",
+            ],
+            Label::NoProblemsFound => [
+                "no problems found",
+                "keine Probleme gefunden",
+                "aucun problème trouvé",
+                "問題は見つかりません",
+            ],
+            Label::NoAnomaliesFound => [
+                "no anomalies found",
+                "keine Auffälligkeiten gefunden",
+                "aucune anomalie trouvée",
+                "異常は見つかりません",
+            ],
+        };
+        match self {
+            Locale::En => en,
+            Locale::De => de,
+            Locale::Fr => fr,
+            Locale::Ja => ja,
+        }
+    }
+
+    pub(crate) fn format_date(self, date: NaiveDate) -> String {
+        let format = match self {
+            // as journal files write them
+            Locale::En => "%Y-%m-%d",
+            Locale::De => "%d.%m.%Y",
+            Locale::Fr => "%d/%m/%Y",
+            Locale::Ja => "%Y年%m月%d日",
+        };
+        date.format(format).to_string()
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    /// Takes a language like `de` or a POSIX locale like `de_DE.UTF-8`.
+    fn from_str(s: &str) -> Result<Self> {
+        let language = s.split(['_', '-', '.']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            "en" | "c" | "posix" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            "ja" => Ok(Locale::Ja),
+            _ => bail!("unsupported locale {}, expected en, de, fr or ja", s),
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::En => "en",
+            Locale::De => "de",
+            Locale::Fr => "fr",
+            Locale::Ja => "ja",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!("de_DE.UTF-8".parse::<Locale>().unwrap(), Locale::De);
+        assert_eq!("fr-CA".parse::<Locale>().unwrap(), Locale::Fr);
+        assert_eq!("C".parse::<Locale>().unwrap(), Locale::En);
+        assert!("xx".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        assert_eq!(Locale::En.format_date(date), "2024-03-15");
+        assert_eq!(Locale::De.format_date(date), "15.03.2024");
+        assert_eq!(Locale::Ja.format_date(date), "2024年03月15日");
+        assert_eq!(Locale::De.text(Label::Balance), "Saldo");
+    }
+
+    #[test]
+    fn test_en_dates_read_back() {
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        let shown = Locale::En.format_date(date);
+        assert_eq!(shown.parse::<NaiveDate>().unwrap(), date);
+    }
+}
@@ -0,0 +1,250 @@
+use std::{collections::BTreeMap, ops::Range, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Period {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Period {
+    /// First day of the period containing `date`.
+    pub(crate) fn start(self, date: NaiveDate) -> NaiveDate {
+        let first_of_month = |month| NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap();
+        match self {
+            Period::Daily => date,
+            Period::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Monthly => first_of_month(date.month()),
+            Period::Quarterly => first_of_month((date.month() - 1) / 3 * 3 + 1),
+            Period::Yearly => first_of_month(1),
+        }
+    }
+
+    /// First day of the period following the one starting at `start`.
+    pub(crate) fn succ(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Daily => start + Duration::days(1),
+            Period::Weekly => start + Duration::days(7),
+            Period::Monthly => start + Months::new(1),
+            Period::Quarterly => start + Months::new(3),
+            Period::Yearly => start + Months::new(12),
+        }
+    }
+
+    /// Days of the period containing `date`, from its first day up to the
+    /// first day of the next.
+    pub(crate) fn span(self, date: NaiveDate) -> Range<NaiveDate> {
+        let start = self.start(date);
+        start..self.succ(start)
+    }
+
+    /// Same day `n` periods after `date`, clamped to the end of shorter
+    /// months, or an error past the last date there is.
+    pub(crate) fn advance(self, date: NaiveDate, n: u32) -> Result<NaiveDate> {
+        let days = |per: i64| date.checked_add_signed(Duration::days(per * n as i64));
+        let months = |per: u32| {
+            per.checked_mul(n)
+                .and_then(|months| date.checked_add_months(Months::new(months)))
+        };
+        match self {
+            Period::Daily => days(1),
+            Period::Weekly => days(7),
+            Period::Monthly => months(1),
+            Period::Quarterly => months(3),
+            Period::Yearly => months(12),
+        }
+        .ok_or_else(|| anyhow!("no date {} {} periods after {}", n, self.name(), date))
+    }
+
+    /// Name of the period as directives and commands write it.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Period::Daily => "daily",
+            Period::Weekly => "weekly",
+            Period::Monthly => "monthly",
+            Period::Quarterly => "quarterly",
+            Period::Yearly => "yearly",
+        }
+    }
+
+    /// How many of the period make a year, for comparing amounts given
+    /// over different periods.
+    pub(crate) fn per_year(self) -> u32 {
+        match self {
+            Period::Daily => 365,
+            Period::Weekly => 52,
+            Period::Monthly => 12,
+            Period::Quarterly => 4,
+            Period::Yearly => 1,
+        }
+    }
+
+    /// Human readable name of the period starting at `start`.
+    pub(crate) fn label(self, start: NaiveDate) -> String {
+        match self {
+            Period::Daily => start.format("%Y-%m-%d").to_string(),
+            Period::Weekly => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Monthly => start.format("%Y-%m").to_string(),
+            Period::Quarterly => format!("{}-Q{}", start.year(), (start.month() - 1) / 3 + 1),
+            Period::Yearly => start.year().to_string(),
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" | "day" | "days" | "d" => Ok(Period::Daily),
+            "weekly" | "week" | "weeks" | "w" => Ok(Period::Weekly),
+            "monthly" | "month" | "months" | "m" => Ok(Period::Monthly),
+            "quarterly" | "quarter" | "quarters" | "q" => Ok(Period::Quarterly),
+            "yearly" | "year" | "years" | "y" => Ok(Period::Yearly),
+            _ => Err(anyhow!("invalid period: {}", s)),
+        }
+    }
+}
+
+/// A number of consecutive periods, written like `3m` or `6w`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Window {
+    pub(crate) period: Period,
+    pub(crate) len: usize,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            period: Period::Monthly,
+            len: 3,
+        }
+    }
+}
+
+impl FromStr for Window {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| anyhow!("invalid window {}, expected e.g. 3m", s))?;
+        let (len, period) = s.split_at(split);
+        let len = match len {
+            "" => 1,
+            len => len.parse()?,
+        };
+        if len == 0 {
+            return Err(anyhow!("window must span at least one period"));
+        }
+        Ok(Self {
+            period: period.parse()?,
+            len,
+        })
+    }
+}
+
+/// Groups dated items into the periods they fall in, shared by every report
+/// that aggregates over time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PeriodBucketer {
+    period: Period,
+}
+
+impl PeriodBucketer {
+    pub(crate) fn new(period: Period) -> Self {
+        Self { period }
+    }
+
+    pub(crate) fn period(&self) -> Period {
+        self.period
+    }
+
+    /// Bucket `items` by the start of their period, in chronological order.
+    pub(crate) fn bucket<T>(
+        &self,
+        items: impl IntoIterator<Item = T>,
+        date: impl Fn(&T) -> NaiveDate,
+    ) -> BTreeMap<NaiveDate, Vec<T>> {
+        let mut buckets: BTreeMap<NaiveDate, Vec<T>> = BTreeMap::new();
+        for item in items {
+            let start = self.period.start(date(&item));
+            buckets.entry(start).or_default().push(item);
+        }
+        buckets
+    }
+
+    /// Start of every period from the one containing `from` up to the one
+    /// containing `to`, including empty ones.
+    pub(crate) fn starts(&self, from: NaiveDate, to: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+        let period = self.period;
+        let end = period.start(to);
+        std::iter::successors(Some(period.start(from)), move |start| {
+            Some(period.succ(*start))
+        })
+        .take_while(move |start| *start <= end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_start() {
+        let d = date("2024-05-15"); // a Wednesday
+        assert_eq!(Period::Daily.start(d), d);
+        assert_eq!(Period::Weekly.start(d), date("2024-05-13"));
+        assert_eq!(Period::Monthly.start(d), date("2024-05-01"));
+        assert_eq!(Period::Quarterly.start(d), date("2024-04-01"));
+        assert_eq!(Period::Yearly.start(d), date("2024-01-01"));
+    }
+
+    #[test]
+    fn test_advance() {
+        let d = date("2024-01-31");
+        assert_eq!(Period::Monthly.advance(d, 1).unwrap(), date("2024-02-29"));
+        assert_eq!(Period::Weekly.advance(d, 2).unwrap(), date("2024-02-14"));
+        assert!(Period::Daily.advance(NaiveDate::MAX, 1).is_err());
+        assert!(Period::Yearly.advance(d, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(Period::Weekly.label(date("2024-01-01")), "2024-W01");
+        assert_eq!(Period::Monthly.label(date("2024-03-01")), "2024-03");
+        assert_eq!(Period::Quarterly.label(date("2024-10-01")), "2024-Q4");
+    }
+
+    #[test]
+    fn test_window() {
+        let window: Window = "3m".parse().unwrap();
+        assert_eq!(window.period, Period::Monthly);
+        assert_eq!(window.len, 3);
+        assert_eq!("w".parse::<Window>().unwrap().len, 1);
+        assert!("0m".parse::<Window>().is_err());
+        assert!("3".parse::<Window>().is_err());
+    }
+
+    #[test]
+    fn test_bucket() {
+        let bucketer = PeriodBucketer::new(Period::Monthly);
+        let dates = ["2024-01-31", "2024-01-01", "2024-03-02"].map(date);
+        let buckets = bucketer.bucket(dates, |d| *d);
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[&date("2024-01-01")].len(), 2);
+
+        let starts = bucketer.starts(date("2024-01-31"), date("2024-03-02"));
+        assert_eq!(starts.count(), 3);
+    }
+}
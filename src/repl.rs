@@ -1,72 +1,375 @@
+#[cfg(feature = "bank")]
+mod bank;
+mod check;
+mod complete;
 mod date;
+mod discover;
+mod init;
+mod log;
+mod merge;
+pub(crate) mod plugin;
+mod quick;
+mod receipt;
+mod serve;
+mod shell;
 mod split;
+mod summary;
+#[cfg(feature = "sync")]
+mod sync;
+mod transfer;
 mod util;
 
-use std::fmt::Display;
+use std::{fmt::Display, path::Path};
 
 use anyhow::{anyhow, bail, Context, Result};
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate, NaiveTime};
 use colored::Colorize;
-use inquire::Select;
+use inquire::{Confirm, Select};
 use itertools::Itertools;
-use pest::Parser;
-use rustyline::{config::Configurer, error::ReadlineError};
+use pest::{iterators::Pair, Parser};
+use regex::RegexBuilder;
+use rust_decimal::Decimal;
+use rustyline::{config::Configurer, error::ReadlineError, history::DefaultHistory};
 
 use crate::{
+    accn::AutoCreate,
     journal::{
+        anomaly::{AnomalyDetector, Method},
+        budget::BAR_WIDTH,
+        entry::AUTHOR_META,
+        gnucash::Book,
+        graph::GraphFormat,
+        guard::BulkLimits,
+        lots::Booking,
         parser::{IdentParser, Rule},
+        reclass::Move,
         register::QueryType,
+        snapshot::Cutoff,
         Journal, Txn,
     },
+    locale::{self, tr, Label, Locale},
+    period::{Period, PeriodBucketer, Window},
     util::NotEmpty,
+    valuable::{HttpPolicy, Money, ProviderChain, RateCache, RateSource, Valuable},
+    workspace::Workspace,
 };
 
-use self::{date::DateArg, util::fuzzy_create_accn};
+use self::{
+    complete::CmdCompleter,
+    date::DateArg,
+    summary::BulkSummary,
+    util::{diff_lines, find_accn, find_or_create_accn, fuzzy_create_accn},
+};
+
+/// What one save wrote, for `undo` to take back.
+struct Write {
+    added: Vec<Txn>,
+    moved: Vec<Move>,
+}
 
-struct ReplState {
+/// What a REPL session keeps between commands, handed to registered ones.
+pub struct ReplState {
     date: NaiveDate,
-    file: String,
+    dry_run: bool,
+    /// Whether lines like `12.5 coffee` record purchases right away.
+    quick: bool,
+    /// Accn quick purchases are paid from.
+    quick_source: Option<String>,
+    /// Days a fronted expense may wait for its reimbursement before `check`
+    /// flags it.
+    fronted_days: i64,
+    /// Which lints `check` reports, and how severe they are.
+    lints: check::LintConfig,
+    /// Rate `tax` estimates the tax with when none is given.
+    tax_rate: Option<Decimal>,
+    /// Who new txns are recorded as entered by.
+    author: Option<String>,
+    anomalies: AnomalyDetector,
+    /// Rates missing from a journal, fetched in the background.
+    rates: RateCache,
     new_txns: Vec<Txn>,
     del_txns: usize,
+    /// Postings `reclass` moved since the last save.
+    moved: Vec<Move>,
+    /// Files `archive` writes and their content, once the journal they were
+    /// archived from is saved.
+    archives: Vec<(String, String)>,
+    /// What the last command adding txns in bulk did.
+    last_bulk: Option<BulkSummary>,
+    /// How much one command may add before it asks to keep it.
+    limits: BulkLimits,
 
-    history_writes: Vec<Vec<Txn>>,
+    history_writes: Vec<Write>,
+    /// Commands added outside the grammar.
+    commands: plugin::Registry,
 }
 
 impl ReplState {
-    fn inspect(&self) {
+    fn inspect(&self, workspace: &Workspace) {
         println!("date: {}", self.date);
-        println!("file: {}", self.file);
+        println!("file: {}", workspace.active_file());
+        if workspace.is_read_only() {
+            println!("{}", "read-only".yellow());
+        }
+        if self.dry_run {
+            println!("{}", "dry-run".yellow());
+        }
+        if workspace.in_sandbox() {
+            println!("{}", "sandbox".yellow());
+        }
+        println!("autocreate: {}", workspace.active().accns().autocreate());
+        println!("locale: {}", locale::current());
         println!(
-            "changes not saved {}[+] {}[-]",
+            "journals: {}",
+            workspace
+                .names()
+                .map(|name| match name == workspace.active_name() {
+                    true => format!("{}", name.bold()),
+                    false => name.to_string(),
+                })
+                .join(", ")
+        );
+        println!(
+            "changes not saved {}[+] {}[-] {}[~]",
             self.new_txns.len(),
-            self.del_txns
+            self.del_txns,
+            self.moved.len()
         );
+        let limit = |limit: Option<String>| limit.unwrap_or_else(|| "off".to_string());
+        println!(
+            "limits: max-txns {}, max-accn-postings {}, max-change {}",
+            limit(self.limits.max_txns.map(|n| n.to_string())),
+            limit(self.limits.max_accn_postings.map(|n| n.to_string())),
+            limit(self.limits.max_change.map(|n| n.to_string())),
+        );
+        if let Some(summary) = &self.last_bulk {
+            println!("last bulk operation {}", summary);
+        }
+    }
+
+    /// Print the summary of a bulk operation and keep it for `inspect`.
+    fn bulk_done(&mut self, summary: BulkSummary) {
+        println!("{}", summary);
+        self.last_bulk = Some(summary);
     }
 }
 
 #[derive(Debug, clap::Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    file: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Journal files to open as a workspace, the first one starts active.
+    /// Without any, the one of $COINJAR_FILE, of the config file or the
+    /// nearest .coin file up from the current directory is opened
+    files: Vec<String>,
+
+    /// Journal file to open, before any other given
+    #[arg(long, short)]
+    file: Option<String>,
+
+    /// Open every journal read-only, disabling commands that change them
+    #[arg(long)]
+    read_only: bool,
+
+    /// Create missing journal files empty instead of asking about them
+    #[arg(long)]
+    create: bool,
+
+    /// Only show the transactions commands would add or remove
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Exchange rate providers asked in order when a journal lacks a rate:
+    /// jsdelivr, ecb, coingecko, bundled or csv:<file>. The bundled monthly
+    /// averages need the bundled-rates feature
+    #[arg(long = "rates", value_name = "SOURCE")]
+    rates: Vec<RateSource>,
+
+    /// Seconds a request for a rate may take before it is tried again
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    rates_timeout: u64,
+
+    /// Language of labels and dates: en, de, fr or ja. Defaults to the
+    /// one of LANG
+    #[arg(long)]
+    locale: Option<Locale>,
+
+    /// Name recorded as the author of the txns entered, for journals shared
+    /// by a household. Defaults to the `user` of the config file
+    #[arg(long)]
+    user: Option<String>,
+
+    /// Log what coinjar does to stderr, -vv and -vvv for more. COINJAR_LOG
+    /// takes a filter like `coinjar::journal=debug` instead
+    #[arg(long, short, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Log as JSON lines, for long-running modes like serve
+    #[arg(long, global = true)]
+    log_json: bool,
 }
 
-pub(crate) fn repl() {
+#[derive(Debug, clap::Subcommand)]
+enum Command {
+    /// Create a new journal, asking for its currency and opening balances
+    Init {
+        /// File to write, main.coin if not given
+        file: Option<String>,
+    },
+    /// Explore a generated example journal, read-only
+    Demo {
+        /// Seed of the generator, the same seed gives the same journal
+        #[arg(long, default_value_t = 1)]
+        seed: u64,
+
+        /// Months of txns to generate
+        #[arg(long, default_value_t = 12)]
+        months: u32,
+    },
+    /// Print the completion script of a shell, source it to complete
+    /// coinjar commands
+    Completions { shell: clap_complete::Shell },
+    /// Check journals without opening the REPL, failing if one does not
+    /// load
+    Check {
+        /// Journals to check, the discovered one if none given
+        files: Vec<String>,
+
+        #[arg(long, value_enum, default_value_t)]
+        format: check::Format,
+    },
+    /// Apply the fixes of the lints `check` reports to journals, writing
+    /// them back
+    Fix {
+        /// Journals to fix, the discovered one if none given
+        files: Vec<String>,
+
+        /// Print the fixes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the semantic tokens of a journal as JSON, for editors to
+    /// highlight it
+    Highlight { file: String },
+    /// Merge the txns of two copies of a journal changed since a common
+    /// base, asking which side to keep where both changed the same txn.
+    /// The result is written to ours
+    Merge {
+        ours: String,
+        theirs: String,
+
+        /// The version both copies started from
+        #[arg(long)]
+        base: String,
+    },
+    /// Sync a journal with its remote copy, merging the txns if both
+    /// changed since the last sync
+    #[cfg(feature = "sync")]
+    Sync {
+        /// Journal to sync, the discovered one if not given
+        file: Option<String>,
+
+        /// A path, webdav+https://host/file, s3://bucket/file or
+        /// ssh://host/path. Defaults to `remote` of the config file
+        #[arg(long)]
+        remote: Option<String>,
+    },
+    /// Serve the metrics of a journal at /metrics for Prometheus, like its
+    /// txns and the balance of each top-level accn
+    Serve {
+        /// Journal to serve, the discovered one if not given
+        file: Option<String>,
+
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:9400")]
+        addr: String,
+
+        /// Currency the balances are given in, the one the journal uses if
+        /// not given
+        #[arg(long)]
+        base: Option<String>,
+    },
+    /// List the accn names of a journal, one per line, for shell completion
+    Accns {
+        /// Journal to read, the discovered one if not given
+        file: Option<String>,
+    },
+}
+
+pub(crate) fn repl(commands: plugin::Registry) {
     let history_path = "/tmp/coinjar.history";
 
-    let (args, mut journal) = parse_args().unwrap_or_else(|e| exit_gracefully(e));
-    let mut rl = rustyline::DefaultEditor::new().unwrap_or_else(|e| exit_gracefully(e));
+    let (args, mut workspace) = parse_args().unwrap_or_else(|e| exit_gracefully(e));
+    recover(&mut workspace).unwrap_or_else(|e| exit_gracefully(e));
+    let mut rl = rustyline::Editor::<CmdCompleter, DefaultHistory>::new()
+        .unwrap_or_else(|e| exit_gracefully(e));
+    rl.set_helper(Some(CmdCompleter::default()));
+    locale::set(args.locale.or_else(locale::from_env).unwrap_or_default());
     rl.load_history(history_path).ok();
     rl.set_auto_add_history(true);
+    let policy = HttpPolicy {
+        timeout: std::time::Duration::from_secs(args.rates_timeout),
+        ..HttpPolicy::default()
+    };
+    let mut rates = ProviderChain::default();
+    for source in args.rates {
+        rates.push(
+            source
+                .into_provider(policy)
+                .unwrap_or_else(|e| exit_gracefully(e)),
+        );
+    }
+    let date = Local::now().date_naive();
+    let rates = RateCache::spawn(rates);
+    // fetch every pair of currencies the journals use before any report asks
+    let codes = workspace
+        .journals()
+        .flat_map(|(_, journal)| journal.used_codes())
+        .sorted()
+        .dedup()
+        .collect_vec();
+    for (from, to) in codes.iter().tuple_combinations() {
+        rates.prefetch(from, to, date);
+    }
+
+    let author = match args.user {
+        Some(user) => Some(user),
+        None => discover::config("user").unwrap_or_else(|e| exit_gracefully(e)),
+    };
     let mut state = ReplState {
-        date: Local::now().date_naive(),
-        file: args.file.clone(),
+        date,
+        dry_run: args.dry_run,
+        quick: false,
+        quick_source: None,
+        fronted_days: 30,
+        lints: check::LintConfig::from_config().unwrap_or_else(|e| exit_gracefully(e)),
+        tax_rate: None,
+        author,
+        anomalies: AnomalyDetector::default(),
+        rates,
         new_txns: Vec::new(),
         del_txns: 0,
+        moved: Vec::new(),
+        archives: Vec::new(),
+        last_bulk: None,
+        limits: BulkLimits::default(),
         history_writes: Vec::new(),
+        commands,
     };
 
     loop {
+        if let Some(completer) = rl.helper_mut() {
+            completer.update(&workspace);
+        }
         let ret: Result<()> = try {
-            let input = rl.readline("coinjar> ");
+            let prompt = match (state.quick, workspace.in_sandbox()) {
+                (true, _) => "quick> ",
+                (false, true) => "sandbox> ",
+                (false, false) => "coinjar> ",
+            };
+            let input = rl.readline(prompt);
             let input = match input {
                 Err(ReadlineError::Interrupted) => continue,
                 Err(ReadlineError::Eof) => {
@@ -74,24 +377,55 @@ pub(crate) fn repl() {
                         .unwrap_or_else(|e| exit_gracefully(e));
                     return;
                 }
-                input => input?,
+                input => input.map_err(anyhow::Error::from)?,
             };
 
-            interact(&input, &mut journal, &mut state)?;
+            let done = interact(&input, &mut workspace, &mut state);
+            for stale in state.rates.take_stale() {
+                println!("{}: {}", "warning".yellow().bold(), stale);
+            }
+            done?;
         };
 
-        ret.with_context(|| format!("{}", "error".red().bold()))
+        ret.with_context(|| format!("{}", tr(Label::Error).red().bold()))
             .unwrap_or_else(|e| eprintln!("{:#}", e));
     }
 }
 
-fn interact(input: &str, journal: &mut Journal, state: &mut ReplState) -> Result<()> {
-    let pair = IdentParser::parse(Rule::cmd, input)
-        .with_context(|| "Failed to parse cmd".to_string())?
-        .next()
-        .unwrap();
+fn interact(input: &str, workspace: &mut Workspace, state: &mut ReplState) -> Result<()> {
+    if state.quick {
+        if let Ok(mut pairs) = IdentParser::parse(Rule::quick_entry, input) {
+            let pair = pairs.next().unwrap();
+            check_writable(workspace, &pair)?;
+            let pairs = pair.into_inner();
+            let txn = quick::quick(workspace.active_mut(), pairs, state)?;
+            record(workspace, state, vec![txn]);
+            return autosave(workspace, state);
+        }
+    }
 
-    match pair.as_rule() {
+    let pair = match IdentParser::parse(Rule::cmd, input) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(e) => {
+            let Some((command, args)) = state.commands.resolve(input) else {
+                return Err(e).with_context(|| "Failed to parse cmd".to_string());
+            };
+            if command.mutating && workspace.is_read_only() {
+                bail!("{} is read-only", workspace.active_name());
+            }
+            (command.run)(workspace.active_mut(), state, args)?;
+            return match command.mutating {
+                true => autosave(workspace, state),
+                false => Ok(()),
+            };
+        }
+    };
+
+    check_writable(workspace, &pair)?;
+    let mutating = is_mutating(&pair);
+
+    let rule = pair.as_rule();
+    match rule {
         Rule::date_cmd => {
             let date_arg = pair.into_inner().next();
             if let Some(d) = date_arg
@@ -104,21 +438,318 @@ fn interact(input: &str, journal: &mut Journal, state: &mut ReplState) -> Result
         }
         Rule::split => {
             let pairs = pair.into_inner();
-            let txn = split::split(journal, pairs, state)?;
-            println!("{}", txn);
-            state.new_txns.push(txn.into());
+            let before = BulkSummary::accns(workspace.active());
+            let txns = split::split(workspace.active_mut(), pairs, state)?;
+            guard(workspace, state, "split", &txns)?;
+            let summary = BulkSummary::new("split", workspace.active(), &txns, 0, &before);
+            let bulk = txns.len() > 1;
+            record(workspace, state, txns);
+            if bulk {
+                state.bulk_done(summary);
+            }
         }
         Rule::reg => {
-            let matcher = pair.into_inner().next();
-            let query = matcher
-                .map(|m| QueryType::MatchAccn(m.as_str().into()))
-                .unwrap_or_default();
-            println!("{}", journal.query(query).into_regs().join("\n"));
+            let journal = workspace.active();
+            let mut queries = Vec::new();
+            let mut period = None;
+            let mut sides = false;
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::sides => sides = true,
+                    Rule::period_opt => {
+                        period = Some(pair.into_inner().as_str().parse::<Period>()?)
+                    }
+                    _ => queries.push(query_filter(journal, pair)?),
+                }
+            }
+            let query = journal.query(QueryType::And(queries));
+            match period {
+                Some(period) => println!(
+                    "{}",
+                    query
+                        .into_period_regs(PeriodBucketer::new(period))
+                        .join("\n")
+                ),
+                None if sides => println!("{}", query.into_side_regs().join("\n")),
+                None => println!("{}", query.into_regs().join("\n")),
+            }
+        }
+        Rule::tags => {
+            let query = match pair.into_inner().next() {
+                Some(matcher) => QueryType::MatchAccn(matcher.as_str().into()),
+                None => QueryType::All,
+            };
+            let rows = workspace.active().query(query).into_tag_totals();
+            println!("{}", rows.format("\n"));
+        }
+        Rule::dim => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str();
+            // txns balance out, so only the expense side is meaningful by default
+            let matcher = pairs.next().map_or("expense", |p| p.as_str());
+            let rows = workspace
+                .active()
+                .dimension_totals(name, QueryType::MatchAccn(matcher.into()))?;
+            println!("{}", rows.iter().join("\n"));
+        }
+        Rule::show => {
+            let journal = workspace.active();
+            // txns are numbered in date order, like reg lists them
+            let txns = journal
+                .txns()
+                .sorted_by_key(|txn| (txn.date(), txn.desc().to_string()))
+                .collect_vec();
+            let target = pair.into_inner().next().unwrap();
+            let txn = match target.as_rule() {
+                Rule::show_index => {
+                    let index: usize = target.as_str().parse()?;
+                    txns.into_iter()
+                        .nth(index.wrapping_sub(1))
+                        .ok_or_else(|| anyhow!("no transaction number {}", index))?
+                }
+                _ => {
+                    let search = target.as_str().trim().to_lowercase();
+                    let found = txns
+                        .into_iter()
+                        .filter(|txn| txn.mentions(&search))
+                        .map(|txn| txn.brief())
+                        .collect_vec();
+                    match found.len() {
+                        0 => bail!("no transaction matching {}", search),
+                        1 => found.into_iter().next().unwrap().id().into_txn(journal),
+                        _ => Select::new(tr(Label::SelectToShow), found)
+                            .prompt()?
+                            .id()
+                            .into_txn(journal),
+                    }
+                }
+            };
+            println!("{}", txn.detailed().in_file(workspace.active_file()));
+        }
+        Rule::info => {
+            let journal = workspace.active();
+            let matcher = pair.into_inner().next().unwrap().as_str();
+            let accn = find_accn(journal, matcher)?;
+            println!("{}", journal.accn_info(accn));
+        }
+        Rule::statement => {
+            let mut pairs = pair.into_inner();
+            let matcher = pairs.next().unwrap().as_str();
+            let (mut period, mut accrue) = (Period::Monthly, false);
+            for pair in pairs {
+                match pair.as_rule() {
+                    Rule::period_opt => period = pair.into_inner().as_str().parse()?,
+                    _ => accrue = true,
+                }
+            }
+
+            let journal = workspace.active();
+            let accn = find_accn(journal, matcher)?;
+            // accruing asks for a rate, showing does without one
+            let accruals = match (accn.interest(), accrue) {
+                (None, false) => Vec::new(),
+                _ => journal.accruals(accn, period, state.date)?,
+            };
+            if !accrue {
+                println!("{}", journal.statement(accn, &accruals, state.date));
+                return Ok(());
+            }
+            let accn = accn.id();
+            let before = BulkSummary::accns(workspace.active());
+            let txns = workspace.active_mut().record_accruals(accn, &accruals)?;
+            guard(workspace, state, "accrue", &txns)?;
+            let summary = BulkSummary::new("accrue", workspace.active(), &txns, 0, &before);
+            record(workspace, state, txns);
+            state.bulk_done(summary);
+        }
+        Rule::import => {
+            let import = pair.into_inner().next().unwrap();
+            let kind = import.as_rule();
+            let mut pairs = import.into_inner();
+            // the file imported, or the accn a bank's txns go to
+            let source = pairs.next().unwrap().as_str();
+            let read = || {
+                std::fs::read_to_string(source)
+                    .with_context(|| format!("Failed to read {}", source))
+            };
+            let before = BulkSummary::accns(workspace.active());
+            let journal = workspace.active_mut();
+            let import = match kind {
+                Rule::import_receipts => receipt::import(journal, &read()?, state)?,
+                Rule::import_bank => {
+                    let accn = find_accn(journal, source)?.id();
+                    let account = pairs.next().map(|pair| pair.as_str());
+                    #[cfg(feature = "bank")]
+                    {
+                        let cursor = accn.into_accn(journal.accns()).sync_cursor();
+                        let cursor = cursor.map(str::to_string);
+                        let synced =
+                            bank::Plaid::from_config()?.pull(account, cursor.as_deref())?;
+                        bank::import(journal, accn, synced)?
+                    }
+                    #[cfg(not(feature = "bank"))]
+                    {
+                        let _ = (accn, account);
+                        bail!("coinjar was built without the bank feature");
+                    }
+                }
+                Rule::import_qif => {
+                    let (mut account, mut code) = (None, None);
+                    for pair in pairs {
+                        match pair.as_rule() {
+                            Rule::matcher => {
+                                account = Some(find_accn(journal, pair.as_str())?.id())
+                            }
+                            _ => code = Some(pair.as_str().to_string()),
+                        }
+                    }
+                    let code = match code {
+                        Some(code) => code,
+                        None => journal.sole_code()?.to_string(),
+                    };
+                    journal.import_qif(&read()?, account, &code)?
+                }
+                Rule::import_gnucash => journal.import_gnucash(&Book::read(Path::new(source))?)?,
+                Rule::import_beancount => journal.import_beancount(&read()?)?,
+                _ => unreachable!(),
+            };
+            for warning in &import.warnings {
+                println!("{}: {}", "warning".yellow().bold(), warning);
+            }
+            guard(workspace, state, "import", &import.added)?;
+            let summary = BulkSummary::new(
+                "import",
+                workspace.active(),
+                &import.added,
+                import.skipped,
+                &before,
+            );
+            record(workspace, state, import.added);
+            state.bulk_done(summary);
+        }
+        Rule::archive => {
+            let mut pairs = pair.into_inner();
+            let before: NaiveDate = pairs.next().unwrap().as_str().parse()?;
+            let file = match pairs.next() {
+                Some(file) => file.as_str().to_string(),
+                None => {
+                    let path = std::path::Path::new(workspace.active_file());
+                    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                    let name = format!("{}-before-{}.coin", stem, before);
+                    path.with_file_name(name).to_string_lossy().into_owned()
+                }
+            };
+            if std::path::Path::new(&file).exists()
+                || state.archives.iter().any(|(f, _)| *f == file)
+            {
+                bail!("{} already exists", file);
+            }
+
+            let journal = workspace.active_mut();
+            if state.dry_run {
+                let count = journal.txns().filter(|txn| txn.date() < before).count();
+                println!("dry-run: would archive {} txns to {}", count, file);
+                return Ok(());
+            }
+            let archive = journal.archive(before)?;
+            println!(
+                "archived {} txns, replaced by {} opening balances, save to shrink {} and write {}",
+                archive.archived.len(),
+                archive.openings.len(),
+                workspace.active_file(),
+                file
+            );
+            state.archives.push((file, archive.history));
+            state.del_txns += archive.archived.len();
+            state.new_txns.retain(|txn| !archive.archived.contains(txn));
+            state.new_txns.extend(archive.openings);
+        }
+        Rule::export => {
+            let export = pair.into_inner().next().unwrap();
+            let kind = export.as_rule();
+            let (mut format, mut since, mut until, mut file) =
+                (GraphFormat::default(), None, None, None);
+            let (mut matchers, mut contact) = (Vec::new(), None);
+            for pair in export.into_inner() {
+                match pair.as_rule() {
+                    Rule::graph_format => format = pair.as_str().parse()?,
+                    Rule::since => since = Some(pair.into_inner().as_str().parse()?),
+                    Rule::until => until = Some(pair.into_inner().as_str().parse()?),
+                    Rule::file_path => file = Some(pair.as_str()),
+                    Rule::matcher => matchers.push(pair.as_str()),
+                    Rule::contact_name => contact = Some(pair.as_str()),
+                    _ => unreachable!(),
+                }
+            }
+            let journal = workspace.active();
+            let out = match kind {
+                Rule::export_graph => format!("{}\n", journal.graph(format, since, until)),
+                // upcoming txns unless asked otherwise
+                Rule::export_ical => journal.ical(since.unwrap_or(state.date), until),
+                Rule::export_journal => {
+                    let accns: Vec<_> = matchers
+                        .into_iter()
+                        .map(|matcher| find_accn(journal, matcher))
+                        .try_collect()?;
+                    journal.share(&accns, contact, since)?.to_string()
+                }
+                _ => unreachable!(),
+            };
+            match file {
+                Some(file) => {
+                    std::fs::write(file, out)?;
+                    println!("exported to {}", file);
+                }
+                None => print!("{}", out),
+            }
+        }
+        Rule::quick => {
+            state.quick = !state.quick;
+            match state.quick {
+                true => {
+                    println!("quick mode, enter purchases like `12.5 coffee`, `quick` to leave")
+                }
+                false => println!("quick mode off"),
+            }
+        }
+        Rule::bal => {
+            let (mut date, mut time, mut inclusive) = (state.date, None, true);
+            let mut native = false;
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::at_cutoff => {
+                        let mut pairs = pair.into_inner();
+                        date = pairs.next().unwrap().as_str().parse()?;
+                        time = pairs
+                            .next()
+                            .map(|time| NaiveTime::parse_from_str(time.as_str(), "%H:%M"))
+                            .transpose()?;
+                    }
+                    Rule::native => native = true,
+                    _ => inclusive = false,
+                }
+            }
+            let journal = workspace.active();
+            let cutoff = Cutoff::new(date, time, inclusive);
+            println!("{}", format!("balances {}", cutoff).bold());
+            let balances = match native {
+                true => journal.balances_as_of(cutoff),
+                false => journal.reported_balances_as_of(cutoff, &state.rates)?,
+            };
+            for (accn, balance) in balances {
+                let balance = balance.into_valuable(journal.currencies());
+                print!("{:<50}{:>15}", accn.abs_name(), balance.to_string());
+                match journal.budget_line_as_of(accn, cutoff) {
+                    Some(line) => println!("  {}", line.bar(BAR_WIDTH)),
+                    None => println!(),
+                }
+            }
         }
         Rule::accn_cmd => {
-            println!("{}", journal.accns());
+            println!("{}", workspace.active().accns());
         }
         Rule::open => {
+            let journal = workspace.active_mut();
             let matcher = pair.into_inner().next().unwrap().as_str();
             journal
                 .accns()
@@ -130,59 +761,1092 @@ fn interact(input: &str, journal: &mut Journal, state: &mut ReplState) -> Result
                         e.map(|accn| accn.abs_name()).join("\n")
                     )
                 })?;
-            let accn = fuzzy_create_accn(journal, matcher)?;
+            // opening is the explicit way to create accns, whatever the policy
+            let accn = fuzzy_create_accn(journal, matcher, AutoCreate::Auto)?
+                .declare_open(Some(state.date), None);
             println!("created accn: {}", accn.as_ref().abs_name());
         }
         Rule::save => {
-            journal.save_to_file(&state.file)?;
-            println!("saved {} txns to {}", state.new_txns.len(), state.file);
-            if state.new_txns.is_empty() {
+            if workspace.in_sandbox() {
+                bail!("commit or discard the sandbox before saving");
+            }
+            if state.dry_run {
+                println!("dry-run: nothing written");
+                return Ok(());
+            }
+            workspace.save()?;
+            workspace.clear_recovery()?;
+            // only now the txns are gone from the journal file
+            while let Some((file, history)) = state.archives.first() {
+                std::fs::write(file, history)
+                    .with_context(|| format!("Failed to write archive: {}", file))?;
+                println!("wrote archive {}", file);
+                state.archives.remove(0);
+            }
+            println!(
+                "saved {} txns to {}",
+                state.new_txns.len(),
+                workspace.names().join(", ")
+            );
+            if state.new_txns.is_empty() && state.moved.is_empty() {
                 return Ok(());
             }
             state.del_txns = 0;
-            state
-                .history_writes
-                .push(std::mem::take(&mut state.new_txns));
+            state.history_writes.push(Write {
+                added: std::mem::take(&mut state.new_txns),
+                moved: std::mem::take(&mut state.moved),
+            });
         }
         Rule::undo => {
+            if workspace.in_sandbox() {
+                bail!("commit or discard the sandbox before undoing saved txns");
+            }
             let history = state
                 .history_writes
-                .pop()
+                .last()
                 .ok_or_else(|| anyhow!("no history to undo"))?;
-            println!("undo {} txns", history.len());
-            for txn in history {
-                journal.txn_mut(txn).remove()
+            if state.dry_run {
+                for txn in history
+                    .added
+                    .iter()
+                    .filter_map(|txn| workspace.find_txn(*txn))
+                {
+                    println!("{}", diff_lines('-', txn));
+                }
+                return Ok(());
+            }
+            let history = state.history_writes.pop().unwrap();
+            println!("undo {} txns", history.added.len());
+            for txn in history.added {
+                workspace.remove_txn(txn);
+            }
+            if !history.moved.is_empty() {
+                println!("move back {} postings", history.moved.len());
+                for (_, journal) in workspace.journals_mut() {
+                    journal.revert_moves(&history.moved);
+                }
             }
-            journal.save_to_file(&state.file)?;
+            workspace.save()?;
         }
         Rule::del => {
+            let journal = workspace.active_mut();
             let txns: Vec<_> = journal.txns().map(|t| t.brief()).collect();
             if txns.is_empty() {
                 bail!("no transaction left to delete")
             }
-            let prompt = format!("{}", "select to delete".red());
+            let prompt = format!("{}", tr(Label::SelectToDelete).red());
             let txn = Select::new(&prompt, txns).prompt()?.id();
+            if state.dry_run {
+                println!("{}", diff_lines('-', journal.txn(txn)));
+                return Ok(());
+            }
 
             state.del_txns += 1;
             state.new_txns.retain(|t| *t != txn);
             txn.into_mut(journal).remove();
         }
-        Rule::inspect => state.inspect(),
+        Rule::inspect => state.inspect(workspace),
+        Rule::use_cmd => {
+            if let Some(name) = pair.into_inner().next() {
+                workspace.switch(name.as_str())?;
+            }
+            println!("using {}", workspace.active_name());
+        }
+        Rule::networth => {
+            let code = pair.into_inner().next().map(|p| p.as_str());
+            for (name, journal) in workspace.journals() {
+                let worth = match code {
+                    Some(code) => journal.net_worth_in(code, state.date, &state.rates)?,
+                    None => journal.net_worth(),
+                };
+                let worth = worth.into_valuable(journal.currencies());
+                println!("{:<30} {:>30}", name, worth.to_string());
+            }
+            let total = match code {
+                Some(code) => workspace.net_worth_in(code, state.date, &state.rates)?,
+                None => workspace.net_worth()?,
+            };
+            let total = total.to_string();
+            println!("{:<30} {:>30}", tr(Label::Total).bold(), total);
+        }
+        Rule::ratios => {
+            let journal = workspace.active();
+            let (mut period, mut code, mut liquid) = (Period::Monthly, None, None);
+            let mut since = state.date - chrono::Months::new(11);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::period_opt => period = pair.into_inner().as_str().parse()?,
+                    Rule::since => since = pair.into_inner().as_str().parse()?,
+                    Rule::code => code = Some(pair.as_str()),
+                    _ => liquid = Some(find_accn(journal, pair.into_inner().as_str())?),
+                }
+            }
+            let code = match code {
+                Some(code) => code,
+                None => journal.sole_code()?,
+            };
+            let liquid = liquid.unwrap_or(journal.accns().asset());
+            let ratios = journal.ratios(period, since, state.date, code, liquid, &state.rates)?;
+            for ratios in ratios {
+                println!("{}", ratios);
+            }
+        }
+        Rule::transfer => {
+            let (out, into) = transfer::transfer(workspace, pair.into_inner(), state)?;
+            record(workspace, state, vec![out, into]);
+        }
+        Rule::check => {
+            let diagnostics = check::check(workspace, state.date, state.fronted_days, &state.lints);
+            let format = match pair.into_inner().next().map(|format| format.as_str()) {
+                Some("json") => check::Format::Json,
+                _ => check::Format::Text,
+            };
+            match format {
+                check::Format::Json => println!("{}", check::to_json(&diagnostics)),
+                check::Format::Text if diagnostics.is_empty() => {
+                    println!("{}", tr(Label::NoProblemsFound))
+                }
+                check::Format::Text => diagnostics.iter().for_each(|d| println!("{}", d)),
+            }
+        }
+        Rule::trial_balance => println!("{}", workspace.active().trial_balance()),
+        Rule::snapshot => {
+            if state.dry_run {
+                println!(
+                    "dry-run: would snapshot the month ends through {}",
+                    state.date
+                );
+                return Ok(());
+            }
+            let days = workspace.active_mut().snapshot_months(state.date);
+            match days.last() {
+                Some(last) => println!("snapshotted {} month ends through {}", days.len(), last),
+                None => println!("every month end is snapshotted already"),
+            }
+        }
+        Rule::prune => {
+            let journal = workspace.active_mut();
+            if state.dry_run {
+                for accn in journal.prunable() {
+                    println!("dry-run: would remove {}", accn.into_accn(journal.accns()));
+                }
+                return Ok(());
+            }
+            let names = journal.prune()?;
+            match names.is_empty() {
+                true => println!("no unused accns"),
+                false => println!("removed {}", names.join(", ")),
+            }
+        }
+        Rule::linked => {
+            let journal = workspace.active();
+            let groups = match pair.into_inner().next() {
+                Some(id) => vec![journal.linked(id.as_str())],
+                None => journal.link_groups(),
+            };
+            if groups.is_empty() {
+                println!("no linked txns, link one with `; link: <id>`");
+            }
+            for group in groups {
+                println!("{}\n", group);
+            }
+        }
+        Rule::claims => {
+            let contact = pair.into_inner().next().map(|c| c.as_str());
+            let claims = workspace.active().claims(contact);
+            match claims.is_empty() {
+                true => println!("no open claims, claim a posting with a #claim:<contact> tag"),
+                false => println!("{}", claims),
+            }
+        }
+        Rule::reimburse => {
+            let mut pairs = pair.into_inner();
+            let contact = pairs.next().unwrap().as_str();
+            let to = pairs.next().unwrap().as_str();
+            let journal = workspace.active_mut();
+            let to = find_accn(journal, to)?.id();
+            let txn = journal.reimburse(contact, state.date, to)?;
+            record(workspace, state, vec![txn]);
+        }
+        Rule::diff => {
+            let mut clean = true;
+            for (file, journal) in workspace.files() {
+                let base = Journal::from_file(file)
+                    .with_context(|| format!("Failed to open journal file: {}", file))?;
+                let diff = journal.diff(&base);
+                if diff.is_empty() {
+                    continue;
+                }
+                clean = false;
+                println!("{}", format!("--- {}\n+++ {} (unsaved)", file, file).bold());
+                for directive in &diff.removed_directives {
+                    println!("{}", diff_lines('-', directive));
+                }
+                for directive in &diff.added_directives {
+                    println!("{}", diff_lines('+', directive));
+                }
+                if !(diff.removed_directives.is_empty() && diff.added_directives.is_empty()) {
+                    println!();
+                }
+                for txn in diff.removed {
+                    println!("{}\n", diff_lines('-', txn));
+                }
+                for txn in diff.added {
+                    println!("{}\n", diff_lines('+', txn));
+                }
+            }
+            if clean {
+                println!("{}", tr(Label::NoUnsavedChanges));
+            }
+        }
+        Rule::avg => {
+            let mut pairs = pair.into_inner();
+            let accn = pairs.next().unwrap().as_str();
+            let window = pairs
+                .next()
+                .map(|w| w.as_str().parse::<Window>())
+                .transpose()?
+                .unwrap_or_default();
+
+            let journal = workspace.active();
+            let series = journal
+                .query(QueryType::MatchAccn(accn.into()))
+                .into_series(PeriodBucketer::new(window.period));
+            let moving = series.moving_average(window.len, journal.currencies());
+            let fmt = |v: &Valuable| v.clone().into_valuable(journal.currencies()).to_string();
+
+            for (start, total) in series.points() {
+                let avg = moving
+                    .points()
+                    .find(|(date, _)| *date == start)
+                    .map(|(_, v)| fmt(v))
+                    .unwrap_or_default();
+                println!(
+                    "{:<15} {:>30} {:>30}",
+                    window.period.label(start),
+                    fmt(total),
+                    avg
+                );
+            }
+            println!(
+                "{} {} over {} periods, moving average over {}",
+                "average".bold(),
+                fmt(&series.average(journal.currencies())),
+                series.len(),
+                window.len
+            );
+        }
+        Rule::anomalies => match pair.into_inner().next() {
+            Some(pair) if pair.as_rule() == Rule::anomaly_threshold => {
+                let mut pairs = pair.into_inner();
+                let accn = pairs.next().unwrap().as_str();
+                let method = pairs.next().unwrap().as_str();
+                let k = pairs.next().unwrap().as_str().parse()?;
+                let method = match method {
+                    "iqr" => Method::Iqr(k),
+                    _ => Method::ZScore(k),
+                };
+                state.anomalies.with_method(accn, method);
+                println!("{}: {}", accn, method);
+            }
+            matcher => {
+                let matcher = matcher.map(|m| m.as_str()).unwrap_or_default();
+                let anomalies = workspace.active().anomalies(&state.anomalies);
+                let anomalies = anomalies
+                    .iter()
+                    .filter(|a| a.accn().abs_name().contains(matcher))
+                    .collect_vec();
+                if anomalies.is_empty() {
+                    println!("{}", tr(Label::NoAnomaliesFound));
+                }
+                for anomaly in anomalies {
+                    println!("{}", anomaly);
+                }
+            }
+        },
+        Rule::subscriptions => {
+            let matcher = pair
+                .into_inner()
+                .next()
+                .map(|m| m.as_str())
+                .unwrap_or_default();
+            let subs = workspace.active().subscriptions();
+            let subs = subs
+                .iter()
+                .filter(|s| s.accn().abs_name().contains(matcher))
+                .collect_vec();
+            if subs.is_empty() {
+                println!("no subscriptions found");
+            }
+            for sub in subs {
+                println!("{}", sub);
+            }
+        }
+        Rule::notes => {
+            let (mut since, mut until, mut search) = (None, None, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::since => since = Some(pair.into_inner().as_str().parse()?),
+                    Rule::until => until = Some(pair.into_inner().as_str().parse()?),
+                    _ => search = Some(pair.as_str().trim()),
+                }
+            }
+            let notes = workspace.active().notes(since, until, search).collect_vec();
+            if notes.is_empty() {
+                println!("no notes found");
+            }
+            for note in notes {
+                println!("{}\n", note);
+            }
+        }
+        Rule::classes => {
+            let (mut since, mut until) = (None, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::since => since = Some(pair.into_inner().as_str().parse()?),
+                    _ => until = Some(pair.into_inner().as_str().parse()?),
+                }
+            }
+            println!("{}", workspace.active().class_totals(since, until));
+        }
+        Rule::tax => {
+            let journal = workspace.active();
+            let (mut year, mut code, mut rate) = (state.date.year(), None, state.tax_rate);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::tax_year => year = pair.as_str().parse()?,
+                    Rule::code => code = Some(pair.as_str()),
+                    _ => rate = Some(parse_percent(pair.as_str())?),
+                }
+            }
+            let code = match code {
+                Some(code) => code,
+                None => journal.sole_code()?,
+            };
+            println!("{}", journal.tax_report(year, code, rate, &state.rates)?);
+        }
+        Rule::transfers => {
+            let window = match pair.into_inner().next() {
+                Some(days) => days.as_str().parse()?,
+                None => 3,
+            };
+            let matches = workspace.active().transfer_matches(window);
+            if matches.is_empty() {
+                println!("no one-sided txns to pair up");
+            }
+            for matched in matches {
+                let journal = workspace.active();
+                println!(
+                    "{}\n{}",
+                    journal.txn(matched.out),
+                    journal.txn(matched.into)
+                );
+                if state.dry_run {
+                    println!("dry-run: would merge into one transfer");
+                    continue;
+                }
+                let merge = Confirm::new("merge into one transfer?")
+                    .with_default(true)
+                    .prompt()?;
+                if !merge {
+                    continue;
+                }
+                let journal = workspace.active_mut();
+                let txn = journal.merge_transfer(&matched)?;
+                state
+                    .new_txns
+                    .retain(|t| *t != matched.out && *t != matched.into);
+                state.del_txns += 2;
+                record(workspace, state, vec![txn]);
+            }
+        }
+        Rule::sandbox => match pair.into_inner().next().map(|p| p.as_str()) {
+            None => {
+                if !state.new_txns.is_empty()
+                    || state.del_txns > 0
+                    || !state.moved.is_empty()
+                    || !state.archives.is_empty()
+                {
+                    bail!("save the unsaved changes before opening a sandbox");
+                }
+                workspace.open_sandbox()?;
+                println!(
+                    "sandbox of {} open, `sandbox commit` keeps what changes in it, `sandbox discard` drops it",
+                    workspace.active_name()
+                );
+            }
+            Some("commit") => {
+                let name = workspace.commit_sandbox()?;
+                println!("sandbox committed to {}, unsaved", name);
+                autosave(workspace, state)?;
+            }
+            _ => {
+                let name = workspace.discard_sandbox()?;
+                println!("sandbox of {} discarded", name);
+                state.new_txns.clear();
+                state.del_txns = 0;
+                state.moved.clear();
+                state.archives.clear();
+            }
+        },
+        Rule::paycheck => {
+            let journal = workspace.active_mut();
+            let (mut date, mut gross, mut matcher) = (state.date, None, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::date => date = pair.as_str().parse()?,
+                    Rule::matcher => matcher = Some(pair.as_str()),
+                    _ => gross = Some(journal.parse_money(pair.as_str())?.money()),
+                }
+            }
+            let income =
+                match matcher {
+                    Some(matcher) => find_accn(journal, matcher)?.id(),
+                    None => journal
+                        .paycheck_accns()
+                        .exactly_one()
+                        .map_err(|accns| match accns.count() {
+                            0 => anyhow!("no paycheck template, add `paycheck` directives"),
+                            _ => anyhow!("several paycheck templates, name the income accn"),
+                        })?,
+                };
+            let txn = journal.create_paycheck(income, date, gross.unwrap())?;
+            record(workspace, state, vec![txn]);
+        }
+        Rule::add => {
+            let journal = workspace.active_mut();
+            let typed = pair.into_inner().next().unwrap().as_str().trim();
+            let template = journal
+                .desc_index()
+                .suggest(typed)
+                .ok_or_else(|| anyhow!("no past txn like {}, enter it with split", typed))?;
+            // a description typed in part, like the hint offers, is the template's
+            let title = journal.txn(template).title();
+            let desc = match title.to_lowercase().starts_with(&typed.to_lowercase()) {
+                true => title,
+                false => typed.to_string(),
+            };
+            let txn = journal.create_from_template(template, state.date, desc)?;
+            println!("{}", journal.txn(txn));
+            let keep = Confirm::new("record this txn?").with_default(true).prompt();
+            if !matches!(keep, Ok(true)) {
+                journal.txn_mut(txn).remove();
+                return keep.map(|_| ()).map_err(Into::into);
+            }
+            record(workspace, state, vec![txn]);
+        }
+        Rule::reclass => {
+            let mut pairs = pair.into_inner().collect_vec();
+            let to = pairs.pop().unwrap().as_str();
+            let queries = pairs
+                .into_iter()
+                .map(|pair| query_filter(workspace.active(), pair))
+                .collect::<Result<_>>()?;
+            let journal = workspace.active_mut();
+            let to = find_or_create_accn(journal, to)?.id();
+            let moves = journal.reclass_moves(QueryType::And(queries), to);
+            if moves.is_empty() {
+                bail!("no expense postings match the query");
+            }
+            for move_ in &moves {
+                println!("{}", journal.describe_move(move_));
+            }
+            if state.dry_run {
+                println!("dry-run: would move {} postings", moves.len());
+                return Ok(());
+            }
+            let keep = Confirm::new(&format!("move {} postings?", moves.len()))
+                .with_default(true)
+                .prompt()?;
+            if !keep {
+                return Ok(());
+            }
+            journal.apply_moves(&moves);
+            state.moved.extend(moves);
+            autosave(workspace, state)?;
+        }
+        Rule::heatmap => {
+            let journal = workspace.active();
+            let (mut since, mut until, mut code) =
+                (state.date - chrono::Months::new(12), state.date, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::since => since = pair.into_inner().as_str().parse()?,
+                    Rule::until => until = pair.into_inner().as_str().parse()?,
+                    _ => code = Some(pair.as_str()),
+                }
+            }
+            let code = match code {
+                Some(code) => code,
+                None => journal.sole_code()?,
+            };
+            println!("{}", journal.heatmap(since, until, code, &state.rates)?);
+        }
+        Rule::payees => {
+            let journal = workspace.active();
+            let (mut n, mut since, mut until, mut code) = (10, None, None, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::nat => n = pair.as_str().parse()?,
+                    Rule::since => since = Some(pair.into_inner().as_str().parse()?),
+                    Rule::until => until = Some(pair.into_inner().as_str().parse()?),
+                    _ => code = Some(pair.as_str()),
+                }
+            }
+            let code = match code {
+                Some(code) => code,
+                None => journal.sole_code()?,
+            };
+            println!(
+                "{}",
+                journal.top_payees(n, since, until, code, &state.rates)?
+            );
+        }
+        Rule::pivot => {
+            let mut pairs = pair.into_inner();
+            let rows = pairs.next().unwrap().as_str().parse()?;
+            let cols = pairs.next().unwrap().as_str().parse()?;
+            // txns balance out, so only the expense side is meaningful by default
+            let matcher = pairs.next().map_or("expense", |p| p.as_str());
+            let query = workspace
+                .active()
+                .query(QueryType::MatchAccn(matcher.into()));
+            println!("{}", query.into_pivot(rows, cols));
+        }
+        Rule::timesheet => {
+            let (mut client, mut since, mut until) = (None, None, None);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::since => since = Some(pair.into_inner().as_str().parse()?),
+                    Rule::until => until = Some(pair.into_inner().as_str().parse()?),
+                    _ => client = Some(pair.as_str()),
+                }
+            }
+            print!("{}", workspace.active().timesheet(client, since, until));
+        }
+        Rule::budget => {
+            let journal = workspace.active();
+            let accn = match pair.into_inner().next() {
+                Some(accn) => Some(find_accn(journal, accn.as_str())?),
+                None => None,
+            };
+            println!("{}", journal.budget_report(accn, state.date));
+        }
+        Rule::exposure => {
+            let journal = workspace.active();
+            let code = match pair.into_inner().next() {
+                Some(code) => code.as_str(),
+                None => journal.sole_code()?,
+            };
+            println!("{}", journal.exposure(code, state.date, &state.rates)?);
+        }
+        Rule::portfolio => {
+            let journal = workspace.active();
+            let code = match pair.into_inner().next() {
+                Some(code) => code.as_str(),
+                None => journal.base_code()?,
+            };
+            println!("{}", journal.portfolio(code, state.date, &state.rates)?);
+        }
+        Rule::gains => {
+            let journal = workspace.active();
+            let (mut year, mut code) = (state.date.year(), None);
+            let (mut booking, mut book) = (Booking::default(), false);
+            for pair in pair.into_inner() {
+                match pair.as_rule() {
+                    Rule::tax_year => year = pair.as_str().parse()?,
+                    Rule::lot_booking => booking = pair.as_str().parse()?,
+                    Rule::code => code = Some(pair.as_str()),
+                    _ => book = true,
+                }
+            }
+            let code = match code {
+                Some(code) => code,
+                None => journal.base_code()?,
+            };
+            let report = journal.realized_gains(year, code, booking, &state.rates)?;
+            println!("{}", report);
+            if !book {
+                return Ok(());
+            }
+            let txns = workspace.active_mut().book_gains(&report)?;
+            guard(workspace, state, "gains", &txns)?;
+            println!("booked the gains of {} sales", txns.len());
+            record(workspace, state, txns);
+        }
+        Rule::set_cmd => {
+            let mut pairs = pair.into_inner();
+            let name = pairs.next().unwrap().as_str();
+            let value = pairs.next().map(|v| v.as_str());
+            match name {
+                "dry-run" => state.dry_run = parse_switch(value)?,
+                "autocreate" => {
+                    let policy = value
+                        .ok_or_else(|| anyhow!("expected auto, confirm or strict"))?
+                        .parse()?;
+                    workspace.active_mut().accns_mut().set_autocreate(policy);
+                }
+                "rounding" => {
+                    let journal = workspace.active_mut();
+                    let accn = match value {
+                        Some(matcher) => Some(find_or_create_accn(journal, matcher)?.id()),
+                        None => None,
+                    };
+                    journal.set_rounding_accn(accn);
+                }
+                "source" => state.quick_source = value.map(str::to_string),
+                "fronted-days" => {
+                    let value = value.ok_or_else(|| anyhow!("expected a number of days"))?;
+                    state.fronted_days = value.parse()?;
+                }
+                "locale" => {
+                    let value = value.ok_or_else(|| anyhow!("expected en, de, fr or ja"))?;
+                    locale::set(value.parse()?);
+                }
+                "tax-rate" => state.tax_rate = value.map(parse_percent).transpose()?,
+                "user" => state.author = value.map(str::to_string),
+                "max-txns" => state.limits.max_txns = parse_limit(value)?,
+                "max-accn-postings" => state.limits.max_accn_postings = parse_limit(value)?,
+                "max-change" => state.limits.max_change = parse_limit(value)?,
+                _ => bail!("unknown option {}", name),
+            }
+        }
         _ => unreachable!("unexpected rule: {:?}", pair.as_rule()),
     };
 
+    match mutating && rule != Rule::save {
+        true => autosave(workspace, state),
+        false => Ok(()),
+    }
+}
+
+/// A rate written as a percentage like `24%` or `24`.
+fn parse_percent(value: &str) -> Result<Decimal> {
+    let percent: Decimal = value.trim_end_matches('%').parse()?;
+    Ok(percent / Decimal::ONE_HUNDRED)
+}
+
+/// Keep the unsaved changes in the recovery files after every command
+/// changing a journal.
+fn autosave(workspace: &Workspace, state: &ReplState) -> Result<()> {
+    match state.dry_run {
+        true => Ok(()),
+        false => workspace.autosave(),
+    }
+}
+
+/// Offer to restore or discard what a previous session left unsaved.
+fn recover(workspace: &mut Workspace) -> Result<()> {
+    for name in workspace.recoverable() {
+        let restore = Confirm::new(&format!(
+            "{} has unsaved changes from a previous session, restore them?",
+            name
+        ))
+        .with_default(true)
+        .prompt()?;
+        match restore {
+            true => workspace.restore(&name)?,
+            false => workspace.discard_recovery(&name)?,
+        }
+    }
     Ok(())
 }
 
-fn parse_args() -> Result<(Args, Journal)> {
-    let args = <Args as clap::Parser>::parse();
-    let journal = Journal::from_file(&args.file)
-        .with_context(|| format!("Failed to open journal file: {}", args.file))?;
+/// Record `author` as the one who entered `txn`, if `journal` holds it and
+/// it does not name someone already.
+fn sign(journal: &mut Journal, txn: Txn, author: Option<&str>) {
+    if !journal.contains_txn(txn) {
+        return;
+    }
+    if let Some(author) = author.filter(|_| journal.txn(txn).author().is_none()) {
+        journal.txn_mut(txn).set_meta(AUTHOR_META, author);
+    }
+}
+
+/// Sign txns just added by a command, print them with the budgets they
+/// overspend, and remember them as unsaved, or roll them back again when in
+/// dry-run mode. Transfers `sweep` rules ask for are then offered one by one.
+fn record(workspace: &mut Workspace, state: &mut ReplState, txns: Vec<Txn>) {
+    for (_, journal) in workspace.journals_mut() {
+        for txn in &txns {
+            sign(journal, *txn, state.author.as_deref());
+        }
+    }
+    for txn in txns.iter().filter_map(|txn| workspace.find_txn(*txn)) {
+        match state.dry_run {
+            true => println!("{}", diff_lines('+', txn)),
+            false => println!("{}", txn),
+        }
+    }
+    for alert in workspace.active().budget_alerts(&txns, state.date) {
+        println!("{}", alert);
+    }
+    // described before a dry run rolls back the txns they come from
+    let journal = workspace.active();
+    let sweeps = journal
+        .sweep_proposals(&txns)
+        .into_iter()
+        .map(|proposal| (proposal, proposal.display(journal).to_string()))
+        .collect_vec();
+
+    match state.dry_run {
+        true => txns.into_iter().for_each(|txn| workspace.remove_txn(txn)),
+        false => state.new_txns.extend(txns),
+    }
+    for (proposal, prompt) in sweeps {
+        if state.dry_run {
+            println!("dry-run: would {}", prompt);
+            continue;
+        }
+        let confirmed = Confirm::new(&format!("{}?", prompt))
+            .with_default(true)
+            .prompt()
+            .unwrap_or(false);
+        if !confirmed {
+            continue;
+        }
+        let journal = workspace.active_mut();
+        match journal.create_sweep(&proposal) {
+            Ok(txn) => {
+                sign(journal, txn, state.author.as_deref());
+                println!("{}", journal.txn(txn));
+                state.new_txns.push(txn);
+            }
+            Err(err) => eprintln!("{}: {:#}", tr(Label::Error).red().bold(), err),
+        }
+    }
+}
 
-    Ok((args, journal))
+/// Parse the value of a limit, `off` lifts it.
+fn parse_limit<T: std::str::FromStr>(value: Option<&str>) -> Result<Option<T>>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match value.ok_or_else(|| anyhow!("expected a limit or off"))? {
+        "off" => Ok(None),
+        value => Ok(Some(value.parse()?)),
+    }
+}
+
+/// Ask to keep `txns` just added by `op` if they go over the limits, and
+/// roll them back if not, or if the answer cannot be read.
+fn guard(workspace: &mut Workspace, state: &ReplState, op: &str, txns: &[Txn]) -> Result<()> {
+    let exceeded = workspace.active().exceeded(&state.limits, txns);
+    if exceeded.is_empty() {
+        return Ok(());
+    }
+    for line in &exceeded {
+        println!("{}: {} would add {}", "warning".yellow().bold(), op, line);
+    }
+    if state.dry_run {
+        return Ok(());
+    }
+    let keep = Confirm::new(&format!("keep the {} txns of {}?", txns.len(), op))
+        .with_default(false)
+        .prompt()
+        .unwrap_or(false);
+    if !keep {
+        txns.iter().for_each(|txn| workspace.remove_txn(*txn));
+        bail!("{} rolled back", op);
+    }
+    Ok(())
+}
+
+/// Parse the value of an on/off option, a missing value turns it on.
+fn parse_switch(value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("on" | "true" | "yes") => Ok(true),
+        Some("off" | "false" | "no") => Ok(false),
+        Some(value) => bail!("expected on or off, got {}", value),
+    }
+}
+
+/// Commands that change a journal or its file.
+fn is_mutating(pair: &Pair<'_, Rule>) -> bool {
+    let has = |rule| pair.clone().into_inner().any(|pair| pair.as_rule() == rule);
+    match pair.as_rule() {
+        Rule::set_cmd => matches!(
+            pair.clone().into_inner().next().map(|name| name.as_str()),
+            Some("autocreate" | "rounding")
+        ),
+        Rule::statement => has(Rule::accrue),
+        Rule::gains => has(Rule::book_gains),
+        rule => matches!(
+            rule,
+            Rule::quick_entry
+                | Rule::split
+                | Rule::del
+                | Rule::open
+                | Rule::save
+                | Rule::undo
+                | Rule::transfer
+                | Rule::archive
+                | Rule::import
+                | Rule::paycheck
+                | Rule::add
+                | Rule::reclass
+                | Rule::reimburse
+                | Rule::snapshot
+                | Rule::prune
+                | Rule::transfers
+        ),
+    }
+}
+
+/// The query of a filter of `reg` or `reclass`, like `--above $50`.
+fn query_filter(journal: &Journal, pair: Pair<Rule>) -> Result<QueryType> {
+    let money = |pair: Pair<Rule>| -> Result<Money> {
+        let money = pair.into_inner().next().unwrap().as_str();
+        Ok(journal.parse_money(money)?.money())
+    };
+    let query = match pair.as_rule() {
+        Rule::reg_matcher => QueryType::MatchAccn(pair.as_str().into()),
+        Rule::posting_side | Rule::amount_sign => {
+            QueryType::Side(pair.into_inner().next().unwrap().as_str().parse()?)
+        }
+        Rule::amount_above => QueryType::AmountAbove(money(pair)?),
+        Rule::amount_below => QueryType::AmountBelow(money(pair)?),
+        Rule::currency_is => QueryType::CurrencyIs(pair.into_inner().as_str().into()),
+        Rule::author_is => QueryType::Author(pair.into_inner().as_str().into()),
+        Rule::desc_matches => {
+            let pattern = pair.into_inner().as_str();
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("invalid pattern {}", pattern))?;
+            QueryType::Desc(regex)
+        }
+        Rule::tag => QueryType::HasTag(pair.into_inner().as_str().into()),
+        Rule::dimension_filter => {
+            let (name, value) = pair.into_inner().collect_tuple().unwrap();
+            journal.dimension_query(name.as_str(), value.as_str())?
+        }
+        _ => unreachable!("unexpected rule: {:?}", pair.as_rule()),
+    };
+    Ok(query)
+}
+
+/// Fail before a command changing the active journal runs if it is
+/// read-only.
+fn check_writable(workspace: &Workspace, pair: &Pair<'_, Rule>) -> Result<()> {
+    if is_mutating(pair) && workspace.is_read_only() {
+        bail!(
+            "{} is read-only, {} is disabled",
+            workspace.active_name(),
+            format!("{:?}", pair.as_rule()).blue()
+        );
+    }
+    Ok(())
+}
+
+fn parse_args() -> Result<(Args, Workspace)> {
+    let args = <Args as clap::Parser>::parse();
+    log::init(args.verbose, args.log_json)?;
+    match args.command {
+        Some(Command::Init { file }) => {
+            init::init(file)?;
+            std::process::exit(0);
+        }
+        Some(Command::Demo { seed, months }) => {
+            let journal = Journal::generate_example(seed, months, 3);
+            return Ok((args, Workspace::demo("demo", journal)));
+        }
+        Some(Command::Completions { shell }) => {
+            shell::completions(shell, &mut std::io::stdout())?;
+            std::process::exit(0);
+        }
+        Some(Command::Check { ref files, format }) => {
+            let mut files = files.clone();
+            if files.is_empty() {
+                files.push(discover::Discovery::from_env()?.discover()?);
+            }
+            let mut diagnostics = files
+                .iter()
+                .filter_map(|file| {
+                    let err = Journal::from_file(file).err()?;
+                    Some(check::Diagnostic::load_error(file, &err))
+                })
+                .collect_vec();
+            if diagnostics.is_empty() {
+                let workspace = Workspace::open(files.iter().map(String::as_str))?;
+                let lints = check::LintConfig::from_config()?;
+                diagnostics = check::check(&workspace, Local::now().date_naive(), 30, &lints);
+            }
+            match format {
+                check::Format::Json => println!("{}", check::to_json(&diagnostics)),
+                check::Format::Text => diagnostics.iter().for_each(|d| println!("{}", d)),
+            }
+            let failed = diagnostics
+                .iter()
+                .any(|d| d.severity == check::Severity::Error);
+            std::process::exit(failed as i32);
+        }
+        Some(Command::Fix { ref files, dry_run }) => {
+            let mut files = files.clone();
+            if files.is_empty() {
+                files.push(discover::Discovery::from_env()?.discover()?);
+            }
+            let lints = check::LintConfig::from_config()?;
+            let today = Local::now().date_naive();
+            let mut failed = false;
+            for file in &files {
+                let mut journal = Journal::from_file(file)?;
+                let fixes = journal
+                    .lint(today)
+                    .into_iter()
+                    .filter(|finding| lints.severity(finding.lint).is_some())
+                    .filter_map(|finding| finding.fix)
+                    .collect_vec();
+                if fixes.is_empty() {
+                    println!("{}: nothing to fix", file);
+                    continue;
+                }
+                let mut applied = true;
+                for fix in &fixes {
+                    let desc = journal.describe_fix(fix);
+                    match journal.apply_fix(fix) {
+                        Ok(()) => println!("{}: {}", file, desc),
+                        Err(e) => {
+                            eprintln!("{}: cannot {}: {:#}", file, desc, e);
+                            applied = false;
+                        }
+                    }
+                }
+                // a journal fixed halfway is left as it was
+                match (applied, dry_run) {
+                    (false, _) => {
+                        eprintln!("{}: not saved, nothing was fixed", file);
+                        failed = true;
+                    }
+                    (true, false) => journal.save_to_file(file)?,
+                    (true, true) => {}
+                }
+            }
+            std::process::exit(failed as i32);
+        }
+        Some(Command::Highlight { ref file }) => {
+            let input = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to open journal file: {}", file))?;
+            let tokens = crate::highlight::highlight(&input)?;
+            println!("{}", crate::highlight::to_json(&tokens));
+            std::process::exit(0);
+        }
+        Some(Command::Merge {
+            ref ours,
+            ref theirs,
+            ref base,
+        }) => {
+            merge::merge_files(ours, theirs, base)?;
+            std::process::exit(0);
+        }
+        #[cfg(feature = "sync")]
+        Some(Command::Sync {
+            ref file,
+            ref remote,
+        }) => {
+            let file = match file {
+                Some(file) => file.clone(),
+                None => discover::Discovery::from_env()?.discover()?,
+            };
+            let remote: sync::Remote = match remote {
+                Some(remote) => remote.clone(),
+                None => discover::config("remote")?
+                    .ok_or_else(|| anyhow!("no remote given, nor one in the config file"))?,
+            }
+            .parse()?;
+            let outcome = match sync::sync(&file, &remote, merge::ask)? {
+                sync::Outcome::UpToDate => "up to date",
+                sync::Outcome::Pushed => "pushed",
+                sync::Outcome::Pulled => "pulled",
+                sync::Outcome::Merged => "merged and pushed",
+            };
+            println!("{}: {}", file, outcome);
+            std::process::exit(0);
+        }
+        Some(Command::Serve {
+            ref file,
+            ref addr,
+            ref base,
+        }) => {
+            let file = match file {
+                Some(file) => file.clone(),
+                None => discover::Discovery::from_env()?.discover()?,
+            };
+            serve::serve(&file, addr, base.as_deref())?;
+            std::process::exit(0);
+        }
+        Some(Command::Accns { ref file }) => {
+            let file = match file {
+                Some(file) => file.clone(),
+                None => discover::Discovery::from_env()?.discover()?,
+            };
+            shell::accns(&Journal::from_file(&file)?, &mut std::io::stdout())?;
+            std::process::exit(0);
+        }
+        None => {}
+    }
+    let mut files = args.file.iter().chain(&args.files).cloned().collect_vec();
+    if files.is_empty() {
+        files.push(discover::Discovery::from_env()?.discover()?);
+    }
+    init::bootstrap(&files, args.create)?;
+    let mut workspace = Workspace::open(files.iter().map(String::as_str))?;
+    if args.read_only {
+        workspace.set_read_only();
+    }
+    Ok((args, workspace))
 }
 
 fn exit_gracefully(e: impl Display) -> ! {
-    eprintln!("{}: {:#}", "error".red().bold(), e);
+    eprintln!("{}: {:#}", tr(Label::Error).red().bold(), e);
     std::process::exit(1)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_mutating() {
+        let mutating = |input: &str| {
+            let rule = match input.starts_with(|c: char| c.is_ascii_digit()) {
+                true => Rule::quick_entry,
+                false => Rule::cmd,
+            };
+            let pair = IdentParser::parse(rule, input).unwrap().next().unwrap();
+            is_mutating(&pair)
+        };
+        assert!(mutating("set rounding expense:rounding"));
+        assert!(mutating("set autocreate strict"));
+        assert!(!mutating("set dry-run on"));
+        assert!(mutating("statement liability:card --accrue"));
+        assert!(!mutating("statement liability:card"));
+        assert!(mutating("gains 2024 --book"));
+        assert!(!mutating("gains 2024"));
+        assert!(mutating("12.5 coffee"));
+        assert!(!mutating("bal"));
+    }
+
+    #[test]
+    fn test_reclass_query() {
+        let journal = Journal::from_str("").unwrap();
+        let pair = IdentParser::parse(
+            Rule::cmd,
+            "reclass where --desc coffee misc to expense:coffee",
+        )
+        .unwrap()
+        .next()
+        .unwrap();
+        let mut pairs = pair.into_inner().collect_vec();
+        assert_eq!(pairs.pop().unwrap().as_str(), "expense:coffee");
+        let queries = pairs
+            .into_iter()
+            .map(|pair| format!("{:?}", query_filter(&journal, pair).unwrap()))
+            .collect_vec();
+        assert_eq!(
+            queries,
+            [r#"Desc(Regex("coffee"))"#, r#"MatchAccn("misc")"#]
+        );
+        assert!(IdentParser::parse(Rule::cmd, "reclass where to expense:coffee").is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    accn::Accn,
+    journal::{Imported, Txn},
+    valuable::Money,
+};
+
+use super::{discover, quick::expense_accn, util::find_or_create_accn, *};
+
+/// Metadata key keeping the bank's id of a txn, the FITID of its statement
+/// line, so pulling the same days again adds nothing.
+const FITID_META: &str = "fitid";
+
+/// Credentials of a Plaid item, a login at one bank, from the `plaid_*`
+/// entries of the config file.
+pub(super) struct Plaid {
+    url: String,
+    client_id: String,
+    secret: String,
+    access_token: String,
+}
+
+/// A posted transaction of the bank, not yet a txn.
+#[derive(Debug)]
+pub(super) struct BankTxn {
+    id: String,
+    date: NaiveDate,
+    name: String,
+    merchant: Option<String>,
+    /// Money leaving the account, negative for money coming in.
+    amount: Decimal,
+    code: String,
+    /// Plaid's detailed category, like `FOOD_AND_DRINK_GROCERIES`.
+    category: Option<String>,
+}
+
+/// What changed at the bank since a cursor: the transactions it posted,
+/// the ones it corrected and the ids of the ones it took back.
+#[derive(Debug, Default)]
+pub(super) struct Synced {
+    added: Vec<BankTxn>,
+    modified: Vec<BankTxn>,
+    removed: Vec<String>,
+    /// Where the next pull starts.
+    cursor: Option<String>,
+}
+
+impl Plaid {
+    pub(super) fn from_config() -> Result<Self> {
+        let entry = |key: &str| {
+            discover::config(key)?
+                .ok_or_else(|| anyhow!("no {} in the config file to reach the bank with", key))
+        };
+        let env = discover::config("plaid_env")?.unwrap_or("sandbox".to_string());
+        Ok(Self {
+            url: format!("https://{}.plaid.com", env),
+            client_id: entry("plaid_client_id")?,
+            secret: entry("plaid_secret")?,
+            access_token: entry("plaid_access_token")?,
+        })
+    }
+
+    /// What changed at the bank since `cursor`, everything without one, for
+    /// the whole item or its `account` only.
+    pub(super) fn pull(&self, account: Option<&str>, cursor: Option<&str>) -> Result<Synced> {
+        let client = reqwest::blocking::Client::new();
+        let mut synced = Synced {
+            cursor: cursor.map(str::to_string),
+            ..Default::default()
+        };
+        loop {
+            let mut request = json!({
+                "client_id": self.client_id,
+                "secret": self.secret,
+                "access_token": self.access_token,
+                "cursor": synced.cursor,
+            });
+            if let Some(account) = account {
+                request["options"] = json!({ "account_id": account });
+            }
+            let response: Value = client
+                .post(format!("{}/transactions/sync", self.url))
+                .json(&request)
+                .send()?
+                .error_for_status()
+                .context("the bank refused to sync")?
+                .json()?;
+            let page = parse_sync(&response)?;
+            synced.added.extend(page.added);
+            synced.modified.extend(page.modified);
+            synced.removed.extend(page.removed);
+            synced.cursor = page.cursor.or(synced.cursor);
+            if !response["has_more"].as_bool().unwrap_or(false) {
+                return Ok(synced);
+            }
+        }
+    }
+}
+
+/// What a `/transactions/sync` response says changed.
+fn parse_sync(response: &Value) -> Result<Synced> {
+    let removed = response["removed"]
+        .as_array()
+        .ok_or_else(|| anyhow!("no removed transactions in the bank's response"))?
+        .iter()
+        .map(|txn| {
+            txn["transaction_id"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("removed transaction without transaction_id"))
+        })
+        .collect::<Result<_>>()?;
+    Ok(Synced {
+        added: parse_txns(&response["added"])?,
+        modified: parse_txns(&response["modified"])?,
+        removed,
+        cursor: response["next_cursor"].as_str().map(str::to_string),
+    })
+}
+
+/// The posted ones of `txns`, pending ones come again once posted.
+fn parse_txns(txns: &Value) -> Result<Vec<BankTxn>> {
+    txns.as_array()
+        .ok_or_else(|| anyhow!("no transactions in the bank's response"))?
+        .iter()
+        .filter(|txn| !txn["pending"].as_bool().unwrap_or(false))
+        .map(|txn| {
+            let field = |key: &str| {
+                txn[key]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("transaction without {}", key))
+            };
+            Ok(BankTxn {
+                id: field("transaction_id")?.to_string(),
+                date: field("date")?.parse()?,
+                name: field("name")?.to_string(),
+                merchant: txn["merchant_name"].as_str().map(str::to_string),
+                amount: Decimal::from_str(&txn["amount"].to_string())
+                    .context("transaction without an amount")?,
+                code: field("iso_currency_code")?.to_string(),
+                category: txn["personal_finance_category"]["detailed"]
+                    .as_str()
+                    .map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+impl BankTxn {
+    fn desc(&self) -> String {
+        match &self.merchant {
+            Some(merchant) => format!("{} | {}", merchant, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Words of the category below its group, `FOOD_AND_DRINK_GROCERIES`
+    /// as `groceries` and `INCOME_WAGES` as `wages`.
+    fn category(&self) -> String {
+        let category = self.category.as_deref().unwrap_or("");
+        let groups = [
+            "FOOD_AND_DRINK_",
+            "INCOME_",
+            "TRANSFER_IN_",
+            "TRANSFER_OUT_",
+        ];
+        let category = groups
+            .iter()
+            .find_map(|group| category.strip_prefix(group))
+            .unwrap_or(category);
+        category.replace('_', " ").to_lowercase()
+    }
+
+    /// Draft txn of the bank's transaction in `accn`, against an expense
+    /// or income of its category.
+    fn draft(&self, journal: &mut Journal, accn: Accn) -> Result<Txn> {
+        let currency = journal
+            .currencies()
+            .get_by_code(&self.code)
+            .ok_or_else(|| anyhow!("code {} not found", self.code))?;
+        let other = match self.amount.is_sign_positive() {
+            true => expense_accn(journal, &format!("{} {}", self.category(), self.desc()))?,
+            false => {
+                let source = self.category();
+                let source = source.split_whitespace().next().unwrap_or("bank");
+                find_or_create_accn(journal, &format!("income:{}", source))?.id()
+            }
+        };
+        let txn = journal
+            .new_txn(self.date, self.desc())
+            .with_meta(FITID_META, &self.id)
+            .with_posting(accn, Some(Money::new(-self.amount, currency)))
+            .with_posting(other, None::<Money>)
+            .build()?;
+        Ok(txn.id())
+    }
+}
+
+/// The txn recording the bank's transaction `id`.
+fn by_fitid(journal: &Journal, id: &str) -> Option<Txn> {
+    journal
+        .txns()
+        .find(|txn| txn.meta(FITID_META) == Some(id))
+        .map(|txn| txn.id())
+}
+
+/// Record what changed at the bank in `accn`, asking to keep each draft.
+/// Txns the bank took back are removed and ones it corrected are drafted
+/// again, replacing the old txn once kept. Ones pulled before are skipped
+/// by their FITID. A failed prompt ends the import with the txns kept
+/// until then, and leaves the cursor of `accn` so the rest comes again.
+pub(super) fn import(journal: &mut Journal, accn: Accn, synced: Synced) -> Result<Imported> {
+    let mut import = Imported::default();
+    for id in &synced.removed {
+        if let Some(txn) = by_fitid(journal, id) {
+            let title = journal.txn(txn).title();
+            import
+                .warnings
+                .push(format!("removed {}, the bank took it back", title));
+            journal.txn_mut(txn).remove();
+        }
+    }
+    let pulled = (synced.modified.iter().map(|pulled| (pulled, true)))
+        .chain(synced.added.iter().map(|pulled| (pulled, false)));
+    for (pulled, modified) in pulled {
+        let old = by_fitid(journal, &pulled.id);
+        if old.is_some() && !modified {
+            import.skipped += 1;
+            continue;
+        }
+        let txn = pulled.draft(journal, accn)?;
+        println!("{}", journal.txn(txn));
+        let keep = Confirm::new("record this txn?").with_default(true).prompt();
+        match keep {
+            Ok(true) => {
+                if let Some(old) = old {
+                    journal.txn_mut(old).remove();
+                }
+                import.added.push(txn);
+            }
+            Ok(false) => journal.txn_mut(txn).remove(),
+            // keep the txns confirmed so far
+            Err(e) => {
+                journal.txn_mut(txn).remove();
+                warn!("stopped importing bank txns: {}", e);
+                return Ok(import);
+            }
+        }
+    }
+    if let Some(cursor) = &synced.cursor {
+        accn.into_accn_mut(journal.accns_mut()).declare_sync(cursor);
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_txns() {
+        let response = json!({
+            "added": [
+                {
+                    "transaction_id": "lPNjeW1nR6CDn5okmGQ6hEpMo4lLNoSrzqDje",
+                    "date": "2024-03-04",
+                    "name": "WHOLEFDS 10234",
+                    "merchant_name": "Whole Foods",
+                    "amount": 72.1,
+                    "iso_currency_code": "USD",
+                    "pending": false,
+                    "personal_finance_category": {
+                        "primary": "FOOD_AND_DRINK",
+                        "detailed": "FOOD_AND_DRINK_GROCERIES"
+                    }
+                },
+                {
+                    "transaction_id": "pending-1",
+                    "date": "2024-03-05",
+                    "name": "UBER TRIP",
+                    "amount": 12.5,
+                    "iso_currency_code": "USD",
+                    "pending": true
+                },
+                {
+                    "transaction_id": "x5WPK1Ee9kcE3xLRGeo4uAZXlmNVLQiMqdB3",
+                    "date": "2024-03-01",
+                    "name": "ACME PAYROLL",
+                    "merchant_name": null,
+                    "amount": -2500,
+                    "iso_currency_code": "USD",
+                    "pending": false,
+                    "personal_finance_category": {
+                        "primary": "INCOME",
+                        "detailed": "INCOME_WAGES"
+                    }
+                }
+            ],
+            "modified": [],
+            "removed": [],
+            "next_cursor": "c1",
+            "has_more": false
+        });
+        let txns = parse_sync(&response).unwrap().added;
+        assert_eq!(txns.len(), 2);
+        assert_eq!(txns[0].amount, dec!(72.1));
+        assert_eq!(txns[0].desc(), "Whole Foods | WHOLEFDS 10234");
+        assert_eq!(txns[0].category(), "groceries");
+        assert_eq!(txns[1].amount, dec!(-2500));
+        assert_eq!(txns[1].desc(), "ACME PAYROLL");
+        assert_eq!(txns[1].category(), "wages");
+    }
+
+    #[test]
+    fn test_import_removed() {
+        let input = r#"sync asset:bank c1
+
+2024-03-01 ACME PAYROLL
+    ; fitid: x5WPK1Ee9kcE3xLRGeo4uAZXlmNVLQiMqdB3
+    asset:bank  $2500
+    income:wages"#;
+        let mut journal = Journal::from_str(input).unwrap_or_else(|e| panic!("{:#}", e));
+        let accn = journal.accns().by_name_unique("bank").ok().unwrap().id();
+        assert_eq!(accn.into_accn(journal.accns()).sync_cursor(), Some("c1"));
+        let response = json!({
+            "added": [],
+            "modified": [],
+            "removed": [{ "transaction_id": "x5WPK1Ee9kcE3xLRGeo4uAZXlmNVLQiMqdB3" }],
+            "next_cursor": "c2",
+            "has_more": false
+        });
+        let synced = parse_sync(&response).unwrap();
+        let import = import(&mut journal, accn, synced).unwrap();
+        assert_eq!(import.warnings.len(), 1);
+        assert_eq!(journal.txns().count(), 0);
+        assert!(journal.to_string().contains("sync asset:bank c2"));
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, fmt::Display};
+
+use anyhow::{bail, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use serde_json::{json, Value};
+
+use crate::{error::CoinError, journal::lint::Lint, workspace::Workspace};
+
+use super::discover;
+
+/// How `check` reports what it finds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub(super) enum Format {
+    /// Warnings for people to read.
+    #[default]
+    Text,
+    /// A JSON array of diagnostics for editors and CI.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+/// Something `check` found wrong with a journal.
+#[derive(Debug, Clone)]
+pub(super) struct Diagnostic {
+    /// Short stable name of the problem, like `orphaned-transfer`.
+    code: &'static str,
+    pub(super) severity: Severity,
+    file: String,
+    line: Option<usize>,
+    col: Option<usize>,
+    message: String,
+    /// The txn it is about, printed below the message.
+    detail: Option<String>,
+}
+
+impl Diagnostic {
+    fn warning(code: &'static str, file: &str, message: String) -> Self {
+        Self {
+            code,
+            severity: Severity::Warning,
+            file: file.to_string(),
+            line: None,
+            col: None,
+            message,
+            detail: None,
+        }
+    }
+
+    /// The error that kept `file` from loading.
+    pub(super) fn load_error(file: &str, err: &CoinError) -> Self {
+        let (line, col) = match err {
+            CoinError::Parse { line, col, .. } => (Some(*line), Some(*col)),
+            _ => (None, None),
+        };
+        // the cause reads better than the rendered line when there is one
+        let message = match err {
+            CoinError::Parse {
+                source: Some(source),
+                ..
+            } => format!("{:#}", source),
+            err => format!("{:#}", err),
+        };
+        Self {
+            code: err.code(),
+            severity: Severity::Error,
+            file: file.to_string(),
+            line,
+            col,
+            message,
+            detail: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "code": self.code,
+            "severity": self.severity.name(),
+            "file": self.file,
+            "line": self.line,
+            "col": self.col,
+            "message": self.message,
+        })
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => self.severity.name().yellow().bold(),
+            Severity::Error => self.severity.name().red().bold(),
+        };
+        write!(f, "{}: {}", severity, self.message)?;
+        match &self.detail {
+            Some(detail) => write!(f, "\n{}\n", detail),
+            None => Ok(()),
+        }
+    }
+}
+
+/// How severe breaking each lint is, `None` for the lints turned off.
+#[derive(Debug, Clone)]
+pub(super) struct LintConfig {
+    severities: HashMap<Lint, Option<Severity>>,
+}
+
+impl Default for LintConfig {
+    /// Every lint a warning, except a missing payee, which few journals
+    /// bother to name.
+    fn default() -> Self {
+        let severities = Lint::ALL
+            .into_iter()
+            .map(|lint| match lint {
+                Lint::MissingPayee => (lint, None),
+                _ => (lint, Some(Severity::Warning)),
+            })
+            .collect();
+        Self { severities }
+    }
+}
+
+impl LintConfig {
+    /// The defaults, overridden by `lint.<code> = off|warning|error` lines
+    /// of the user's config file.
+    pub(super) fn from_config() -> Result<Self> {
+        let mut config = Self::default();
+        for lint in Lint::ALL {
+            let severity = match discover::config(&format!("lint.{}", lint))?.as_deref() {
+                None => continue,
+                Some("off") => None,
+                Some("warning") => Some(Severity::Warning),
+                Some("error") => Some(Severity::Error),
+                Some(other) => bail!(
+                    "invalid severity of lint.{}: {}, expected off, warning or error",
+                    lint,
+                    other
+                ),
+            };
+            config.severities.insert(lint, severity);
+        }
+        Ok(config)
+    }
+
+    pub(super) fn severity(&self, lint: Lint) -> Option<Severity> {
+        self.severities[&lint]
+    }
+}
+
+/// Problems of the journals in `workspace` as of `date`, where fronted
+/// expenses may wait `fronted_days` for their reimbursement and `lints`
+/// tells which lints to report.
+pub(super) fn check(
+    workspace: &Workspace,
+    date: NaiveDate,
+    fronted_days: i64,
+    lints: &LintConfig,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for (name, journal) in workspace.journals() {
+        let file = workspace.file(name).unwrap_or(name);
+        for finding in journal.lint(date) {
+            let Some(severity) = lints.severity(finding.lint) else {
+                continue;
+            };
+            diagnostics.push(Diagnostic {
+                severity,
+                line: finding.line,
+                detail: finding
+                    .fix
+                    .map(|fix| format!("fix: {}", journal.describe_fix(&fix))),
+                ..Diagnostic::warning(finding.lint.code(), file, finding.message)
+            });
+        }
+    }
+
+    for (name, txn) in workspace.orphaned_transfers() {
+        let file = workspace.file(name).unwrap_or(name);
+        diagnostics.push(Diagnostic {
+            line: txn.line(),
+            detail: Some(txn.to_string()),
+            ..Diagnostic::warning(
+                "orphaned-transfer",
+                file,
+                format!("transfer in {} has no counterpart in the workspace", name),
+            )
+        });
+    }
+
+    let before = date - chrono::Duration::days(fronted_days);
+    for txn in workspace.active().unreimbursed(before) {
+        diagnostics.push(Diagnostic {
+            line: txn.line(),
+            detail: Some(txn.to_string()),
+            ..Diagnostic::warning(
+                "unreimbursed",
+                workspace.active_file(),
+                format!(
+                    "fronted {} days ago, nothing links to it as reimbursement",
+                    (date - txn.date()).num_days()
+                ),
+            )
+        });
+    }
+
+    for day in workspace.active().stale_snapshots() {
+        diagnostics.push(Diagnostic::warning(
+            "stale-snapshot",
+            workspace.active_file(),
+            format!(
+                "snapshot of {} disagrees with the txns, drop its lines and run `snapshot`",
+                day
+            ),
+        ));
+    }
+    diagnostics
+}
+
+/// `diagnostics` as a JSON array.
+pub(super) fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let diagnostics: Vec<Value> = diagnostics.iter().map(Diagnostic::to_json).collect();
+    serde_json::to_string_pretty(&diagnostics).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::journal::Journal;
+
+    use super::*;
+
+    #[test]
+    fn test_check_json() {
+        let workspace = Workspace::open(["./example/simple.coin"]).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut diagnostics = check(&workspace, date, 30, &LintConfig::default());
+        assert!(diagnostics.is_empty());
+
+        let unbalanced = "2024-01-01 lunch\n    expense:food  $12\n    asset:bank  $-10";
+        let err = Journal::from_str(unbalanced).unwrap_err();
+        diagnostics.push(Diagnostic::load_error("lunch.coin", &err));
+        let json: Value = serde_json::from_str(&to_json(&diagnostics)).unwrap();
+        assert_eq!(json[0]["code"], "unbalanced");
+        assert_eq!(json[0]["severity"], "error");
+        assert_eq!(json[0]["file"], "lunch.coin");
+        assert_eq!(json[0]["line"], 1);
+        assert_eq!(json[0]["message"], "transaction not balanced, off by 2 USD");
+    }
+}
@@ -0,0 +1,207 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use itertools::Itertools;
+use pest::{
+    error::{ErrorVariant, InputLocation},
+    Parser,
+};
+use pest_meta::{ast::Expr, parser};
+use rustyline::{
+    completion::Completer, highlight::Highlighter, hint::Hinter, validate::Validator, Context,
+    Helper,
+};
+
+use crate::{
+    journal::{
+        parser::{IdentParser, Rule},
+        template::DescIndex,
+    },
+    workspace::Workspace,
+};
+
+/// Words each rule of the command grammar starts with, e.g. `networth` and
+/// `nw` for `networth`, read from the grammar itself so they never drift.
+static KEYWORDS: LazyLock<HashMap<String, Vec<String>>> = LazyLock::new(|| {
+    let grammar = include_str!("../parser/coin.pest");
+    let pairs = parser::parse(parser::Rule::grammar_rules, grammar).expect("grammar parses");
+    parser::consume_rules(pairs)
+        .expect("grammar is valid")
+        .into_iter()
+        .map(|rule| (rule.name, leading_words(&rule.expr).0))
+        .collect()
+});
+
+/// Literals `expr` can start with, and whether it can match nothing at all.
+fn leading_words(expr: &Expr) -> (Vec<String>, bool) {
+    match expr {
+        Expr::Str(word) | Expr::Insens(word) => (vec![word.clone()], false),
+        Expr::Seq(first, rest) => {
+            let (mut words, nullable) = leading_words(first);
+            if !nullable {
+                return (words, false);
+            }
+            let (rest, nullable) = leading_words(rest);
+            words.extend(rest);
+            (words, nullable)
+        }
+        Expr::Choice(left, right) => {
+            let (mut words, left) = leading_words(left);
+            let (rest, right) = leading_words(right);
+            words.extend(rest);
+            (words, left || right)
+        }
+        Expr::Opt(expr) | Expr::Rep(expr) => (leading_words(expr).0, true),
+        Expr::RepOnce(expr) | Expr::Push(expr) => leading_words(expr),
+        Expr::PosPred(_) | Expr::NegPred(_) => (Vec::new(), true),
+        // other rules are completed by what they stand for, not by their words
+        _ => (Vec::new(), false),
+    }
+}
+
+/// Rules the command grammar expects at byte `pos` of `line`.
+fn expected(line: &str, pos: usize) -> Vec<Rule> {
+    // no argument starts with a line break, so parsing stops right at `pos`
+    let input = format!("{}\n", &line[..pos]);
+    match IdentParser::parse(Rule::cmd, &input) {
+        Err(e) => match (e.variant, e.location) {
+            (ErrorVariant::ParsingError { positives, .. }, InputLocation::Pos(at)) if at == pos => {
+                positives
+            }
+            _ => Vec::new(),
+        },
+        // free text like a fuzzy date takes the line break too
+        Ok(pairs) => pairs
+            .flatten()
+            .filter(|pair| pair.as_span().start() == pos)
+            .map(|pair| pair.as_rule())
+            .collect(),
+    }
+}
+
+/// Completes the word under the cursor with whatever the grammar accepts
+/// there: command keywords and options, or names from the workspace.
+#[derive(Default)]
+pub(super) struct CmdCompleter {
+    accns: Vec<String>,
+    codes: Vec<String>,
+    tags: Vec<String>,
+    dimensions: Vec<String>,
+    journals: Vec<String>,
+    descs: DescIndex,
+}
+
+impl CmdCompleter {
+    /// Refresh the names offered from the workspace.
+    pub(super) fn update(&mut self, workspace: &Workspace) {
+        let journal = workspace.active();
+        // people like `alice` are accns too, so leaf names are offered as well
+        self.accns = journal
+            .accns()
+            .accns()
+            .flat_map(|accn| [accn.abs_name(), accn.name().to_string()])
+            .filter(|name| !name.is_empty() && name != "root")
+            .sorted()
+            .dedup()
+            .collect();
+        self.codes = journal.currencies().codes().map(str::to_string).collect();
+        self.tags = journal
+            .postings()
+            .flat_map(|p| p.tags())
+            .map(|tag| format!("#{}", tag))
+            .sorted()
+            .dedup()
+            .collect();
+        self.dimensions = journal.dimensions().names().map(str::to_string).collect();
+        self.journals = workspace.names().map(str::to_string).collect();
+        self.descs = journal.desc_index();
+    }
+
+    fn candidates(&self, rule: Rule) -> Vec<String> {
+        match rule {
+            Rule::accn | Rule::ident => self.accns.clone(),
+            Rule::code => self.codes.clone(),
+            Rule::tag => self.tags.clone(),
+            Rule::meta_key => self.dimensions.clone(),
+            Rule::journal_name => self.journals.clone(),
+            Rule::fuzzy_date => ["today", "yesterday", "tomorrow"].map(String::from).into(),
+            rule => KEYWORDS
+                .get(&format!("{:?}", rule))
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Completer for CmdCompleter {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(' ').map_or(0, |i| i + 1);
+        let word = line[start..pos].to_lowercase();
+        let candidates = expected(line, start)
+            .into_iter()
+            .flat_map(|rule| self.candidates(rule))
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .sorted()
+            .dedup()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for CmdCompleter {
+    type Hint = String;
+
+    /// The rest of the latest description starting like the one of an
+    /// `add`, taken with the right arrow key.
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        let typed = line.strip_prefix("add ")?.trim_start();
+        self.descs.complete(typed).map(str::to_string)
+    }
+}
+
+impl Highlighter for CmdCompleter {}
+
+impl Validator for CmdCompleter {}
+
+impl Helper for CmdCompleter {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn complete(line: &str) -> Vec<String> {
+        let completer = CmdCompleter {
+            codes: vec!["JPY".into(), "USD".into()],
+            accns: vec!["asset".into(), "asset:cash".into(), "cash".into()],
+            ..Default::default()
+        };
+        expected(line, line.len())
+            .into_iter()
+            .flat_map(|rule| completer.candidates(rule))
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
+    #[test]
+    fn test_keywords() {
+        let cmds = complete("");
+        for cmd in ["split", "reg", "networth", "nw", "date", "open"] {
+            assert!(cmds.iter().any(|c| c == cmd), "{} not offered", cmd);
+        }
+        assert!(complete("reg ").contains(&"--period".to_string()));
+        assert_eq!(complete("split $5 "), ["by", "from", "over", "to"]);
+        assert_eq!(complete("nw in "), ["JPY", "USD"]);
+        assert_eq!(complete("info "), ["asset", "asset:cash", "cash"]);
+        assert_eq!(complete("date "), ["today", "tomorrow", "yesterday"]);
+    }
+}
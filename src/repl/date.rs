@@ -16,7 +16,7 @@ impl DateArg {
 
         try {
             let date = format!("{}-{}", today.year(), s);
-            let mut date = NaiveDate::parse_from_str(&date, &fmt)?;
+            let mut date = NaiveDate::parse_from_str(&date, &fmt).map_err(anyhow::Error::from)?;
             if date > today {
                 let s = format!("{}-{}", today.year() - 1, s);
                 date = NaiveDate::parse_from_str(&s, &fmt).unwrap();
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+
+/// Environment variable naming the journal to open when none is given.
+const FILE_VAR: &str = "COINJAR_FILE";
+
+/// Where to look for a journal when none is given on the command line.
+pub(super) struct Discovery {
+    /// Value of `$COINJAR_FILE`.
+    env: Option<String>,
+    /// The config file, which may name a journal in a `file = ...` line.
+    config: Option<PathBuf>,
+    /// Directory to search upward from for a `.coin` file.
+    cwd: PathBuf,
+}
+
+impl Discovery {
+    pub(super) fn from_env() -> Result<Self> {
+        Ok(Self {
+            env: std::env::var(FILE_VAR).ok().filter(|file| !file.is_empty()),
+            config: config_path(),
+            cwd: std::env::current_dir().context("Failed to read the current directory")?,
+        })
+    }
+
+    /// The journal to open, from `$COINJAR_FILE`, the config file or the
+    /// nearest directory holding a `.coin` file, in that order.
+    pub(super) fn discover(&self) -> Result<String> {
+        let mut searched = Vec::new();
+
+        match &self.env {
+            Some(file) => return Ok(file.clone()),
+            None => searched.push(format!("${}", FILE_VAR)),
+        }
+
+        if let Some(config) = &self.config {
+            if let Some(file) = config_file(config)? {
+                return Ok(file);
+            }
+            searched.push(config.display().to_string());
+        }
+
+        for dir in self.cwd.ancestors() {
+            if let Some(file) = coin_file(dir)? {
+                return Ok(file.display().to_string());
+            }
+            searched.push(dir.join("*.coin").display().to_string());
+        }
+
+        bail!(
+            "no journal file given or found, searched:\n  {}",
+            searched.join("\n  ")
+        )
+    }
+}
+
+/// `$XDG_CONFIG_HOME/coinjar/config`, or under `~/.config` without it.
+fn config_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("coinjar").join("config"))
+}
+
+/// The journal named by the `file = ...` line of `config`, relative to the
+/// directory of the config file.
+fn config_file(config: &Path) -> Result<Option<String>> {
+    let file = config_entry(config, "file")?;
+    Ok(file.map(|file| match config.parent() {
+        Some(dir) => dir.join(file).display().to_string(),
+        None => file,
+    }))
+}
+
+/// Value of the `key = ...` line of the `config` file, if both exist.
+fn config_entry(config: &Path, key: &str) -> Result<Option<String>> {
+    if !config.exists() {
+        return Ok(None);
+    }
+    let input = std::fs::read_to_string(config)
+        .with_context(|| format!("Failed to read config file: {}", config.display()))?;
+    Ok(input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string()))
+}
+
+/// Value of `key` in the user's config file.
+pub(super) fn config(key: &str) -> Result<Option<String>> {
+    match config_path() {
+        Some(path) => config_entry(&path, key),
+        None => Ok(None),
+    }
+}
+
+/// Whether `path` is an archive `archive` wrote, named like
+/// `main-before-2023-01-01.coin`.
+fn is_archive(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.rsplit_once("-before-"))
+        .is_some_and(|(_, date)| date.parse::<NaiveDate>().is_ok())
+}
+
+/// The `.coin` file in `dir`, leaving out archives. Of several, it is
+/// `main.coin`, else which one is ambiguous.
+fn coin_file(dir: &Path) -> Result<Option<PathBuf>> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(None);
+    };
+    let files: Vec<PathBuf> = entries
+        .map_ok(|entry| entry.path())
+        .filter_ok(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "coin"))
+        .filter_ok(|path| !is_archive(path))
+        .try_collect()
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    if let Some(main) = files
+        .iter()
+        .find(|path| path.file_name().is_some_and(|name| name == "main.coin"))
+    {
+        return Ok(Some(main.clone()));
+    }
+    match files.len() {
+        0 | 1 => Ok(files.into_iter().next()),
+        _ => bail!(
+            "several journals in {}, name one or call it main.coin: {}",
+            dir.display(),
+            files
+                .iter()
+                .filter_map(|path| path.file_name())
+                .map(|name| name.to_string_lossy())
+                .sorted()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_discover() {
+        let discovery = Discovery {
+            env: Some("env.coin".to_string()),
+            config: None,
+            cwd: PathBuf::from("./example"),
+        };
+        assert_eq!(discovery.discover().unwrap(), "env.coin");
+
+        let discovery = Discovery {
+            env: None,
+            config: Some(PathBuf::from("./example/missing-config")),
+            cwd: PathBuf::from("./example"),
+        };
+        let err = discovery.discover().unwrap_err().to_string();
+        assert!(err.contains("several journals in ./example") && err.contains("simple.coin"));
+
+        let dir = std::env::temp_dir().join(format!("coinjar-discover-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        let discovery = Discovery {
+            env: None,
+            config: Some(dir.join("config")),
+            cwd: dir.join("nested"),
+        };
+        // .coin files above the temp dir would be found first
+        if let Err(err) = discovery.discover() {
+            let err = err.to_string();
+            assert!(
+                err.starts_with("several journals")
+                    || err.contains("$COINJAR_FILE") && err.contains("nested/*.coin")
+            );
+        }
+
+        // an archive next to the journal is not a candidate
+        std::fs::write(dir.join("books.coin"), "").unwrap();
+        std::fs::write(dir.join("books-before-2023-01-01.coin"), "").unwrap();
+        assert_eq!(coin_file(&dir).unwrap(), Some(dir.join("books.coin")));
+        std::fs::write(dir.join("trip.coin"), "").unwrap();
+        assert!(coin_file(&dir).is_err());
+        std::fs::write(dir.join("main.coin"), "").unwrap();
+        assert_eq!(coin_file(&dir).unwrap(), Some(dir.join("main.coin")));
+
+        std::fs::write(dir.join("config"), "# journal\nfile = books.coin\n").unwrap();
+        assert_eq!(
+            discovery.discover().unwrap(),
+            dir.join("books.coin").display().to_string()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
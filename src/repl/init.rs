@@ -0,0 +1,249 @@
+use inquire::{validator::Validation, CustomType, Text};
+use rust_decimal::Decimal;
+
+use crate::journal::parser::{IdentParser, Rule};
+
+use super::*;
+
+/// An accn of a new journal with what it holds, or owes, to begin with.
+struct Opening {
+    /// Like `asset:bank`.
+    accn: String,
+    /// Positive for what it holds, negative for what it owes.
+    amount: Decimal,
+}
+
+/// Whether the grammar reads all of `input` as `rule`.
+fn parses_as(rule: Rule, input: &str) -> bool {
+    IdentParser::parse(rule, input)
+        .is_ok_and(|mut pairs| pairs.next().is_some_and(|pair| pair.as_str() == input))
+}
+
+/// Whether `name` makes an accn the journal can be read back with, each
+/// part quoted when saved if it needs to be.
+fn is_accn_name(name: &str) -> bool {
+    name.split(':')
+        .all(|part| part == part.trim() && parses_as(Rule::quoted_ident, part))
+}
+
+/// Fail unless commands like `use` and `transfer` can name the journal
+/// kept in `file`, which they know by its file name.
+fn check_journal_name(file: &str) -> Result<()> {
+    let path = std::path::Path::new(file);
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    if !parses_as(Rule::journal_name, name) {
+        bail!(
+            "journal name {:?} may only have letters, digits, -, _ and .",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// A journal in `code` whose accns are opened on `date`, brought to their
+/// balances by one txn against `equity:opening`.
+fn opening_journal(code: &str, date: NaiveDate, openings: &[Opening]) -> Result<Journal> {
+    let mut journal = Journal::from_str("")?;
+    let Some(currency) = journal.currencies().get_by_code(code) else {
+        bail!("code {} not found", code);
+    };
+    if let Some(opening) = openings.iter().find(|opening| !is_accn_name(&opening.accn)) {
+        bail!("{:?} is not an accn name", opening.accn);
+    }
+
+    let mut accns = Vec::new();
+    for opening in openings {
+        let accn = opening
+            .accn
+            .split(':')
+            .fold(journal.accns_mut().root_mut(), |accn, name| {
+                accn.or_open_child(name)
+            })
+            .declare_open(Some(date), Some(code))
+            .into_ref()
+            .id();
+        accns.push((accn, opening.amount));
+    }
+    if accns.is_empty() {
+        return Ok(journal);
+    }
+
+    let equity = journal
+        .accns_mut()
+        .root_mut()
+        .or_open_child("equity")
+        .or_open_child("opening")
+        .declare_open(Some(date), Some(code))
+        .into_ref()
+        .id();
+    let mut txn = journal.new_txn(date, "opening balances".to_string());
+    for (accn, amount) in accns {
+        txn = txn.with_posting(accn, Some(Money::new(amount, currency)));
+    }
+    txn.with_posting(equity, None::<Money>).build()?;
+    Ok(journal)
+}
+
+/// Ask for a file to write a new journal to, which must not exist yet.
+pub(super) fn init(file: Option<String>) -> Result<()> {
+    let file = match file {
+        Some(file) => file,
+        None => Text::new("journal file:")
+            .with_default("main.coin")
+            .prompt()?,
+    };
+    if std::path::Path::new(&file).exists() {
+        bail!("{} already exists", file);
+    }
+    check_journal_name(&file)?;
+    create(&file)?;
+    println!("created {}, start with `coinjar {}`", file, file);
+    Ok(())
+}
+
+/// Offer to create each missing journal of `files`, asking for its currency
+/// and opening balances, or create empty ones without asking when `quiet`.
+pub(super) fn bootstrap(files: &[String], quiet: bool) -> Result<()> {
+    for file in files
+        .iter()
+        .filter(|file| !std::path::Path::new(file).exists())
+    {
+        check_journal_name(file)?;
+        if quiet {
+            Journal::from_str("")?.save_to_file(file)?;
+        } else if Confirm::new(&format!("{} does not exist, create it?", file))
+            .with_default(true)
+            .prompt()?
+        {
+            create(file)?;
+        } else {
+            bail!("{} does not exist", file);
+        }
+        println!("created {}", file);
+    }
+    Ok(())
+}
+
+/// Ask for the currency, accns and balances of a new journal and write it
+/// to `file`.
+fn create(file: &str) -> Result<()> {
+    let code = Text::new("base currency:")
+        .with_default("USD")
+        .prompt()?
+        .to_uppercase();
+    let today = Local::now().date_naive();
+    let date = CustomType::<NaiveDate>::new("opening date:")
+        .with_default(today)
+        .with_error_message("expected a date like 2024-01-31")
+        .prompt()?;
+
+    let mut openings = Vec::new();
+    loop {
+        let class = Select::new("add an accn:", vec!["asset", "liability", "done"]).prompt()?;
+        if class == "done" {
+            break;
+        }
+        let name = Text::new(&format!(
+            "name, like {}:",
+            match class {
+                "asset" => "bank or cash",
+                _ => "card or loan",
+            }
+        ))
+        .with_validator(|name: &str| {
+            Ok(match is_accn_name(name.trim()) {
+                true => Validation::Valid,
+                false => {
+                    Validation::Invalid("not an accn name, leave out \" and empty parts".into())
+                }
+            })
+        })
+        .prompt()?;
+        let prompt = match class {
+            "asset" => "balance:",
+            _ => "amount owed:",
+        };
+        let amount = CustomType::<Decimal>::new(prompt)
+            .with_error_message("expected a number")
+            .prompt()?;
+        let amount = match class {
+            "asset" => amount,
+            _ => -amount,
+        };
+        openings.push(Opening {
+            accn: format!("{}:{}", class, name.trim()),
+            amount,
+        });
+    }
+
+    let journal = opening_journal(&code, date, &openings)?;
+    journal.save_to_file(file)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opening_journal() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let openings = [
+            Opening {
+                accn: "asset:bank".into(),
+                amount: Decimal::from(2500),
+            },
+            Opening {
+                accn: "liability:card".into(),
+                amount: Decimal::from(-300),
+            },
+        ];
+        let journal = opening_journal("EUR", date, &openings).unwrap();
+        let text = journal.to_string();
+        assert!(text.contains("open 2024-01-01 asset:bank currency EUR"));
+        assert!(text.contains("open 2024-01-01 equity:opening currency EUR"));
+
+        let journal = Journal::from_str(&text).unwrap();
+        let worth = journal.net_worth().into_valuable(journal.currencies());
+        assert_eq!(worth.to_string(), "€2200");
+        assert!(opening_journal("XXQ", date, &openings).is_err());
+    }
+
+    #[test]
+    fn test_names() {
+        assert!(is_accn_name("asset:Groß Einkauf"));
+        assert!(!is_accn_name("asset:"));
+        assert!(!is_accn_name("asset:\"bank\""));
+        assert!(!is_accn_name("asset: bank"));
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let openings = [Opening {
+            accn: "asset::bank".into(),
+            amount: Decimal::from(10),
+        }];
+        assert!(opening_journal("USD", date, &openings).is_err());
+
+        // a name with spaces is quoted, so it reads back
+        let openings = [Opening {
+            accn: "asset:my bank".into(),
+            amount: Decimal::from(10),
+        }];
+        let text = opening_journal("USD", date, &openings).unwrap().to_string();
+        let journal = Journal::from_str(&text).unwrap_or_else(|e| panic!("{:#}", e));
+        assert!(journal.accns().by_name_unique("my bank").is_ok());
+
+        assert!(check_journal_name("books/main-2024.coin").is_ok());
+        assert!(check_journal_name("my books.coin").is_err());
+        assert!(check_journal_name("#1.coin").is_err());
+    }
+
+    #[test]
+    fn test_bootstrap_quiet() {
+        let dir = std::env::temp_dir().join(format!("coinjar-bootstrap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("new.coin").display().to_string();
+        bootstrap(std::slice::from_ref(&file), true).unwrap();
+        let journal = Journal::from_file(&file).unwrap();
+        assert_eq!(journal.txns().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
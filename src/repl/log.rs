@@ -0,0 +1,46 @@
+use anyhow::{anyhow, Context, Result};
+use tracing_subscriber::EnvFilter;
+
+/// Variable holding a filter like `coinjar=debug`, which wins over `-v`.
+const LOG_ENV: &str = "COINJAR_LOG";
+
+/// Filter of `-v` given `verbose` times: warnings only without it.
+fn filter(verbose: u8) -> &'static str {
+    match verbose {
+        0 => "warn",
+        1 => "warn,coinjar=info",
+        2 => "warn,coinjar=debug",
+        _ => "coinjar=trace,debug",
+    }
+}
+
+/// Log to stderr, as text or JSON lines.
+pub(super) fn init(verbose: u8, json: bool) -> Result<()> {
+    let filter = match std::env::var(LOG_ENV) {
+        Ok(filter) => EnvFilter::try_new(&filter)
+            .with_context(|| format!("invalid {}: {}", LOG_ENV, filter))?,
+        Err(_) => EnvFilter::new(filter(verbose)),
+    };
+    let logger = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    match json {
+        true => logger.json().try_init(),
+        false => logger.try_init(),
+    }
+    .map_err(|e| anyhow!(e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        for verbose in 0..4 {
+            assert!(EnvFilter::try_new(filter(verbose)).is_ok());
+        }
+        assert_eq!(filter(0), "warn");
+        assert_eq!(filter(5), filter(3));
+    }
+}
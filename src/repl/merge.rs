@@ -0,0 +1,37 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use inquire::Select;
+
+use crate::journal::{
+    merge::{Conflict, Resolution},
+    Journal,
+};
+
+/// Merge `theirs` into `ours`, both changed from `base`, asking which side
+/// to keep where they conflict, and write the result to `ours`.
+pub(super) fn merge_files(ours: &str, theirs: &str, base: &str) -> Result<()> {
+    let open = |file: &str| {
+        Journal::from_file(file).with_context(|| format!("Failed to open journal file: {}", file))
+    };
+    let merged = Journal::merge(&open(base)?, &open(ours)?, &open(theirs)?, ask)?;
+    merged.save_to_file(ours)?;
+    println!("merged {} into {}", theirs, ours);
+    Ok(())
+}
+
+/// Show both sides of `conflict` and ask which to keep.
+pub(super) fn ask(conflict: &Conflict) -> Result<Resolution> {
+    let side = |txns: &[String]| match txns.is_empty() {
+        true => "deleted".italic().to_string(),
+        false => txns.join("\n\n"),
+    };
+    println!("{}\n{}\n", "base".bold(), conflict.base);
+    println!("{}\n{}\n", "ours".green().bold(), side(&conflict.ours));
+    println!("{}\n{}\n", "theirs".blue().bold(), side(&conflict.theirs));
+    let choice = Select::new("keep", vec!["ours", "theirs", "both"]).prompt()?;
+    Ok(match choice {
+        "ours" => Resolution::Ours,
+        "theirs" => Resolution::Theirs,
+        _ => Resolution::Both,
+    })
+}
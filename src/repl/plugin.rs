@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+
+use crate::journal::Journal;
+
+use super::ReplState;
+
+/// A command added outside the grammar, given the active journal, the REPL
+/// state and whatever follows its name on the line.
+pub type Handler = fn(&mut Journal, &mut ReplState, &str) -> Result<()>;
+
+#[derive(Clone, Copy)]
+pub(super) struct Command {
+    pub(super) run: Handler,
+    /// Whether it changes the journal, so it is refused on read-only ones.
+    pub(super) mutating: bool,
+}
+
+/// Commands `interact` falls back to for input the grammar does not know,
+/// keyed by their name, the first word of the input. Embedders add theirs
+/// and start the REPL with [`crate::run_with`].
+#[derive(Default)]
+pub struct Registry {
+    commands: BTreeMap<String, Command>,
+}
+
+impl Registry {
+    /// The commands compiled into coinjar.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register("version", version, false);
+        registry
+    }
+
+    /// Add the command `name`, replacing any registered before.
+    pub fn register(&mut self, name: &str, run: Handler, mutating: bool) {
+        self.commands
+            .insert(name.to_string(), Command { run, mutating });
+    }
+
+    /// The command `input` invokes and the arguments given to it.
+    pub(super) fn resolve<'a>(&self, input: &'a str) -> Option<(Command, &'a str)> {
+        let input = input.trim();
+        let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+        self.commands
+            .get(name)
+            .map(|command| (*command, args.trim()))
+    }
+}
+
+fn version(_: &mut Journal, _: &mut ReplState, _: &str) -> Result<()> {
+    println!("coinjar {}", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::bail;
+
+    use super::*;
+
+    fn echo(_: &mut Journal, _: &mut ReplState, args: &str) -> Result<()> {
+        if args.is_empty() {
+            bail!("nothing to echo");
+        }
+        println!("{}", args);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut registry = Registry::builtin();
+        registry.register("echo", echo, false);
+        let (command, args) = registry.resolve("  echo hello  world ").unwrap();
+        assert_eq!(args, "hello  world");
+        assert!(!command.mutating);
+        assert_eq!(registry.resolve("version").unwrap().1, "");
+        assert!(registry.resolve("unknown 1").is_none());
+    }
+}
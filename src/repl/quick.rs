@@ -0,0 +1,116 @@
+use pest::iterators::Pairs;
+
+use crate::{
+    accn::Accn,
+    journal::{parser::Rule, Txn},
+};
+
+use super::{
+    util::{choose, find_accn, find_or_create_accn},
+    *,
+};
+
+/// Record a purchase written like `12.5 coffee`: paid from the source accn
+/// on the session date, booked to the expense accn the description names.
+pub(super) fn quick(
+    journal: &mut Journal,
+    mut pairs: Pairs<'_, Rule>,
+    state: &ReplState,
+) -> Result<Txn> {
+    let source = state
+        .quick_source
+        .as_deref()
+        .ok_or_else(|| anyhow!("no source accn, choose one with `set source <accn>`"))?;
+    let source = find_accn(journal, source)?;
+
+    let amount = pairs.next().unwrap();
+    let money = match amount.as_rule() {
+        // a bare number is in the currency the source accn holds
+        Rule::number => {
+            let code = source.currency().ok_or_else(|| {
+                anyhow!(
+                    "{} has no currency for bare amounts, declare one with `open {} currency <code>`",
+                    source,
+                    source
+                )
+            })?;
+            journal.parse_money(&format!("{} {}", amount.as_str(), code))?
+        }
+        _ => journal.parse_money(amount.as_str())?,
+    }
+    .money();
+    let source = source.id();
+
+    let desc = pairs.next().unwrap().as_str().trim().to_string();
+    let expense = expense_accn(journal, &desc)?;
+    journal
+        .new_txn(state.date, desc)
+        .with_posting(expense, Some(money))
+        .with_posting(source, None::<Money>)
+        .build()
+        .map(|txn| txn.id())
+        .map_err(Into::into)
+}
+
+/// Expense accn named by the first word of `desc` that matches one,
+/// created from the first word when none does. The payee of a
+/// `payee | narration` description comes first.
+pub(super) fn expense_accn(journal: &mut Journal, desc: &str) -> Result<Accn> {
+    let expense = journal.accns().expense();
+    let words = desc
+        .split(|c: char| c.is_whitespace() || c == '|')
+        .filter(|word| !word.is_empty())
+        .collect_vec();
+    for &word in &words {
+        let candidates = journal
+            .accns()
+            .by_name_fuzzy(word)
+            .filter(|accn| accn.is_descendent_of(expense))
+            .collect_vec();
+        match candidates.len() {
+            0 => continue,
+            1 => return Ok(candidates[0].id()),
+            _ => {
+                let prompt = format!("{} matches several expenses, choose one", word.blue());
+                return Ok(choose(candidates.into_iter(), &prompt)?.id());
+            }
+        }
+    }
+
+    let word = words.first().copied().unwrap_or(desc);
+    let matcher = format!("expense:{}", word);
+    Ok(find_or_create_accn(journal, &matcher)?.id())
+}
+
+#[cfg(test)]
+mod test {
+    use pest::Parser;
+
+    use super::*;
+
+    fn entry(input: &str) -> Option<(Rule, String, String)> {
+        let mut pairs = IdentParser::parse(Rule::quick_entry, input)
+            .ok()?
+            .next()?
+            .into_inner();
+        let (amount, desc) = (pairs.next()?, pairs.next()?);
+        Some((
+            amount.as_rule(),
+            amount.as_str().into(),
+            desc.as_str().into(),
+        ))
+    }
+
+    #[test]
+    fn test_quick_entry() {
+        let (rule, amount, desc) = entry("12.5 coffee").unwrap();
+        assert_eq!(
+            (rule, amount.as_str(), desc.as_str()),
+            (Rule::number, "12.5", "coffee")
+        );
+        let (rule, amount, desc) = entry("12.5 usd iced tea").unwrap();
+        assert_eq!(rule, Rule::money_var_4);
+        assert_eq!((amount.as_str(), desc.as_str()), ("12.5 usd", "iced tea"));
+        assert!(entry("reg food").is_none());
+    }
+}
@@ -0,0 +1,329 @@
+use chrono::{DateTime, NaiveDate};
+use tracing::warn;
+
+use crate::{
+    accn::Accn,
+    journal::{Imported, Txn},
+    valuable::Money,
+};
+
+use super::{quick::expense_accn, util::find_accn, *};
+
+/// Metadata key keeping what identifies the email a txn was imported from,
+/// so importing the same export twice adds nothing.
+const RECEIPT_META: &str = "receipt";
+
+/// Where one merchant's receipts keep what a txn needs, by the labels
+/// starting their lines.
+struct Template {
+    merchant: &'static str,
+    /// Text in the sender or subject of the merchant's emails.
+    detect: &'static [&'static str],
+    date: &'static [&'static str],
+    total: &'static [&'static str],
+    /// Labels of lines ending in an amount that are not items, like the
+    /// subtotal and tax.
+    not_items: &'static [&'static str],
+    /// Word naming the expense accn, before the merchant's name.
+    category: &'static str,
+}
+
+const TEMPLATES: [Template; 3] = [
+    Template {
+        merchant: "Amazon",
+        detect: &["amazon."],
+        date: &["Order Placed:", "Order Date:"],
+        total: &["Order Total:", "Grand Total:"],
+        not_items: &[
+            "Item Subtotal",
+            "Subtotal",
+            "Shipping",
+            "Estimated tax",
+            "Tax",
+            "Total",
+        ],
+        category: "shopping",
+    },
+    Template {
+        merchant: "Uber",
+        detect: &["uber."],
+        date: &["Trip Date:", "Date:"],
+        total: &["Total", "Amount Charged"],
+        not_items: &["Subtotal", "Tip", "Total", "Amount Charged"],
+        category: "transport",
+    },
+    // any other merchant, named by the sender
+    Template {
+        merchant: "",
+        detect: &[""],
+        date: &["Date:", "Order Date:", "Purchase Date:"],
+        total: &["Total:", "Total", "Amount Paid:", "Amount:"],
+        not_items: &["Subtotal", "Tax", "Tip", "Shipping", "Total", "Amount"],
+        category: "",
+    },
+];
+
+/// A purchase read from one email, not yet a txn.
+#[derive(Debug)]
+pub(super) struct Receipt {
+    id: String,
+    date: NaiveDate,
+    merchant: String,
+    items: Vec<(String, Money)>,
+    total: Money,
+    category: &'static str,
+}
+
+/// Rest of the first line of `lines` starting with one of `labels`.
+fn labelled<'a>(lines: &[&'a str], labels: &[&str]) -> Option<&'a str> {
+    labels.iter().find_map(|label| {
+        lines
+            .iter()
+            .find_map(|line| line.strip_prefix(label))
+            .map(|rest| rest.trim_start_matches(':').trim())
+    })
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    if let Ok(date) = DateTime::parse_from_rfc2822(s) {
+        return Some(date.date_naive());
+    }
+    [
+        "%Y-%m-%d",
+        "%B %d, %Y",
+        "%b %d, %Y",
+        "%d %B %Y",
+        "%d %b %Y",
+        "%m/%d/%Y",
+    ]
+    .iter()
+    .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+}
+
+/// The amount ending `line`, thousands separators dropped.
+fn trailing_money(journal: &Journal, line: &str) -> Option<Money> {
+    let amount = line.split_whitespace().last()?.replace(',', "");
+    Some(journal.parse_money(&amount).ok()?.money())
+}
+
+impl Receipt {
+    /// Read the receipt of one email with the headers it starts with.
+    fn parse(journal: &Journal, email: &str) -> Result<Self> {
+        let lines = email.lines().map(str::trim).collect_vec();
+        let header = |name: &str| labelled(&lines, &[name]).unwrap_or("");
+        let (from, subject) = (header("From:"), header("Subject:"));
+        let sender = format!("{} {}", from, subject).to_lowercase();
+        let template = TEMPLATES
+            .iter()
+            .find(|t| t.detect.iter().any(|d| sender.contains(d)))
+            .unwrap();
+
+        let merchant = match template.merchant {
+            // the display name of `Shop <receipts@shop.com>`
+            "" => from.split('<').next().unwrap().trim().trim_matches('"'),
+            merchant => merchant,
+        };
+        if merchant.is_empty() {
+            bail!("no sender to name the merchant after");
+        }
+        let date = labelled(&lines, template.date)
+            .and_then(parse_date)
+            .ok_or_else(|| anyhow!("no date in the receipt from {}", merchant))?;
+        let total = labelled(&lines, template.total)
+            .and_then(|total| trailing_money(journal, total))
+            .ok_or_else(|| anyhow!("no total in the receipt from {}", merchant))?;
+
+        let body = lines.iter().skip_while(|line| !line.is_empty());
+        let items = body
+            .filter(|line| {
+                !template
+                    .not_items
+                    .iter()
+                    .any(|label| line.starts_with(label))
+            })
+            .filter(|line| !template.total.iter().any(|label| line.starts_with(label)))
+            .filter_map(|line| {
+                let money = trailing_money(journal, line)?;
+                let (name, _) = line.rsplit_once(char::is_whitespace)?;
+                Some((name.trim().to_string(), money))
+            })
+            .collect_vec();
+
+        let id = match labelled(&lines, &["Message-ID:", "Order #", "Order Number:"]) {
+            Some(id) => id.to_string(),
+            None => format!(
+                "{} {} {}",
+                merchant,
+                date,
+                total.into_money(journal.currencies())
+            ),
+        };
+        Ok(Self {
+            id,
+            date,
+            merchant: merchant.to_string(),
+            items,
+            total,
+            category: template.category,
+        })
+    }
+
+    /// Receipts of the emails of a plain-text export, each starting with a
+    /// `From:` header.
+    pub(super) fn parse_all(journal: &Journal, export: &str) -> Vec<Result<Self>> {
+        let mut emails: Vec<String> = Vec::new();
+        for line in export.lines() {
+            match emails.last_mut() {
+                Some(email) if !line.starts_with("From:") => {
+                    email.push_str(line);
+                    email.push('\n');
+                }
+                _ if line.starts_with("From:") => emails.push(format!("{}\n", line)),
+                _ => {}
+            }
+        }
+        emails
+            .iter()
+            .map(|email| Self::parse(journal, email))
+            .collect()
+    }
+
+    fn desc(&self) -> String {
+        match self.items.as_slice() {
+            [] => self.merchant.clone(),
+            [(item, _)] => format!("{} | {}", self.merchant, item),
+            [(item, _), rest @ ..] => {
+                format!("{} | {} and {} more", self.merchant, item, rest.len())
+            }
+        }
+    }
+
+    /// Draft txn of the receipt, paid from `source`, with its items kept
+    /// as metadata.
+    fn draft(&self, journal: &mut Journal, source: Accn) -> Result<Txn> {
+        let expense = expense_accn(journal, &format!("{} {}", self.category, self.merchant))?;
+        let items = self
+            .items
+            .iter()
+            .map(|(item, money)| format!("{} {}", item, money.into_money(journal.currencies())))
+            .collect_vec();
+        let mut txn = journal.new_txn(self.date, self.desc());
+        txn = txn.with_meta(RECEIPT_META, &self.id);
+        for item in &items {
+            txn = txn.with_meta("item", item);
+        }
+        let txn = txn
+            .with_posting(expense, Some(self.total))
+            .with_posting(source, None::<Money>)
+            .build()?;
+        Ok(txn.id())
+    }
+}
+
+/// Import the receipts of an email export, asking to keep each draft txn.
+/// Receipts imported before are skipped, nothing recorded is changed. A
+/// failed prompt ends the import with the receipts kept until then.
+pub(super) fn import(journal: &mut Journal, export: &str, state: &ReplState) -> Result<Imported> {
+    let source = state.quick_source.as_deref().ok_or_else(|| {
+        anyhow!("no accn receipts are paid from, choose one with `set source <accn>`")
+    })?;
+    let source = find_accn(journal, source)?.id();
+
+    let mut import = Imported::default();
+    for receipt in Receipt::parse_all(journal, export) {
+        let receipt = match receipt {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                import.warnings.push(format!("skipped receipt: {:#}", e));
+                continue;
+            }
+        };
+        if journal
+            .txns()
+            .any(|txn| txn.meta(RECEIPT_META) == Some(&receipt.id))
+        {
+            import.skipped += 1;
+            continue;
+        }
+        let txn = receipt.draft(journal, source)?;
+        println!("{}", journal.txn(txn));
+        let keep = Confirm::new("record this receipt?")
+            .with_default(true)
+            .prompt();
+        match keep {
+            Ok(true) => import.added.push(txn),
+            Ok(false) => journal.txn_mut(txn).remove(),
+            // keep the receipts confirmed so far
+            Err(e) => {
+                journal.txn_mut(txn).remove();
+                warn!("stopped importing receipts: {}", e);
+                break;
+            }
+        }
+    }
+    Ok(import)
+}
+
+#[cfg(test)]
+mod test {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const EXPORT: &str = r#"From: "Amazon.com" <auto-confirm@amazon.com>
+Subject: Your Amazon.com order #113-42
+Message-ID: <abc@amazon.com>
+
+Order Placed: October 3, 2024
+USB cable  $12.99
+Desk lamp  $1,024.50
+Item Subtotal: $1,037.49
+Estimated tax: $3.00
+Order Total: $1,040.49
+
+From: Uber Receipts <noreply@uber.com>
+Subject: Your Tuesday trip with Uber
+
+Trip Date: 2024-10-08
+Trip fare  $18.20
+Booking Fee  $2.30
+Tip  $3.00
+Total  $23.50
+
+From: Corner Bakery <hello@bakery.example>
+Subject: Thanks for stopping by
+Date: Wed, 9 Oct 2024 08:12:00 +0000
+
+Sourdough  $6.50
+Total: $6.50"#;
+
+    #[test]
+    fn test_parse_receipts() {
+        let journal = Journal::from_str("").unwrap();
+        let receipts: Vec<Receipt> = Receipt::parse_all(&journal, EXPORT)
+            .into_iter()
+            .try_collect()
+            .unwrap();
+        assert_eq!(receipts.len(), 3);
+
+        let amazon = &receipts[0];
+        assert_eq!(amazon.id, "<abc@amazon.com>");
+        assert_eq!(amazon.date, NaiveDate::from_ymd_opt(2024, 10, 3).unwrap());
+        assert_eq!(amazon.total.amount(), dec!(1040.49));
+        assert_eq!(amazon.items.len(), 2);
+        assert_eq!(amazon.desc(), "Amazon | USB cable and 1 more");
+
+        let uber = &receipts[1];
+        assert_eq!(
+            (uber.merchant.as_str(), uber.category),
+            ("Uber", "transport")
+        );
+        assert_eq!(uber.total.amount(), dec!(23.50));
+        assert_eq!(uber.items.len(), 2);
+
+        let bakery = &receipts[2];
+        assert_eq!(bakery.merchant, "Corner Bakery");
+        assert_eq!(bakery.date, NaiveDate::from_ymd_opt(2024, 10, 9).unwrap());
+        assert_eq!(bakery.desc(), "Corner Bakery | Sourdough");
+    }
+}
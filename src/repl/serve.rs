@@ -0,0 +1,267 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use rust_decimal::Decimal;
+use tracing::{debug, error, info};
+
+use crate::{
+    journal::{parser::Checkpoint, Journal},
+    valuable::ProviderChain,
+};
+
+/// How long a scrape may take to send its request or read the answer,
+/// as connections are served one at a time.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A journal as last read from its file.
+struct Loaded {
+    journal: Journal,
+    /// When the file was last saved.
+    modified: SystemTime,
+    /// How long reading it took.
+    parse: Duration,
+    /// Where the file ended, to read only what is appended to it later.
+    checkpoint: Checkpoint,
+}
+
+fn modified(file: &str) -> Result<SystemTime> {
+    std::fs::metadata(file)
+        .and_then(|meta| meta.modified())
+        .with_context(|| format!("Failed to open journal file: {}", file))
+}
+
+impl Loaded {
+    fn read(file: &str) -> Result<Self> {
+        let modified = modified(file)?;
+        let input = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to open journal file: {}", file))?;
+        let start = Instant::now();
+        let journal = Journal::from_str(&input)?;
+        Ok(Loaded {
+            journal,
+            modified,
+            parse: start.elapsed(),
+            checkpoint: Checkpoint::of(&input),
+        })
+    }
+
+    /// Read the file again, only what was appended to it if nothing else
+    /// changed.
+    fn reload(&mut self, file: &str) -> Result<()> {
+        let modified = modified(file)?;
+        let input = std::fs::read_to_string(file)
+            .with_context(|| format!("Failed to open journal file: {}", file))?;
+        let start = Instant::now();
+        match self.journal.read_appended(&input, &self.checkpoint) {
+            Ok(true) => {
+                debug!(file, "read what was appended");
+                self.modified = modified;
+                self.parse = start.elapsed();
+                self.checkpoint = Checkpoint::of(&input);
+                Ok(())
+            }
+            Ok(false) => {
+                *self = Loaded::read(file)?;
+                Ok(())
+            }
+            // the last journal read whole is served until the file is fixed
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Serve the metrics of the journal `file` at `/metrics` of `addr`, for
+/// Prometheus to scrape. The journal is read again when its file changes.
+pub(super) fn serve(file: &str, addr: &str, base: Option<&str>) -> Result<()> {
+    let listener = TcpListener::bind(addr).with_context(|| format!("cannot listen on {}", addr))?;
+    println!("serving the metrics of {} at http://{}/metrics", file, addr);
+    let mut loaded = Loaded::read(file)?;
+    for stream in listener.incoming() {
+        let result = stream.map_err(anyhow::Error::from).and_then(|stream| {
+            if modified(file)? != loaded.modified {
+                info!(file, "journal changed, reading it again");
+                loaded.reload(file)?;
+            }
+            respond(stream, &loaded, file, base)
+        });
+        if let Err(e) = result {
+            error!("{:#}", e);
+        }
+    }
+    Ok(())
+}
+
+fn respond(stream: TcpStream, loaded: &Loaded, file: &str, base: Option<&str>) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers are not needed, but read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    debug!(request = request.trim_end(), "scraped");
+    let (status, body) = match request.split_whitespace().nth(1) {
+        Some("/metrics") => {
+            let date = Local::now().date_naive();
+            ("200 OK", metrics(loaded, file, base, date))
+        }
+        _ => ("404 Not Found", "not found, try /metrics\n".to_string()),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(stream.flush()?)
+}
+
+/// Metrics of a journal in the Prometheus text format, balances in `base`
+/// or the one currency the journal uses besides commodities.
+fn metrics(loaded: &Loaded, file: &str, base: Option<&str>, date: NaiveDate) -> String {
+    let journal = &loaded.journal;
+    let label = format!(
+        "journal=\"{}\"",
+        file.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let mut out = String::new();
+    let mut gauge = |name: &str, help: &str, samples: Vec<(String, String)>| {
+        writeln!(out, "# HELP coinjar_{} {}", name, help).unwrap();
+        writeln!(out, "# TYPE coinjar_{} gauge", name).unwrap();
+        for (labels, value) in samples {
+            writeln!(out, "coinjar_{}{{{}}} {}", name, labels, value).unwrap();
+        }
+    };
+
+    gauge(
+        "txns",
+        "Transactions in the journal.",
+        vec![(label.clone(), journal.txns().count().to_string())],
+    );
+    let base = base.map(Ok).unwrap_or_else(|| journal.base_code());
+    if let Ok(base) = base {
+        let rates = ProviderChain::default().with(journal.rates());
+        let balances = journal
+            .accns()
+            .root()
+            .children()
+            .filter_map(|accn| {
+                let mut total = Decimal::ZERO;
+                for money in journal.balance(accn) {
+                    let money = money.into_money(journal.currencies());
+                    // an accn not all in the base has no balance to chart
+                    total += money.convert_to(base, date, &rates).ok()?.money().amount();
+                }
+                let labels = format!("{},accn=\"{}\",currency=\"{}\"", label, accn.name(), base);
+                Some((labels, total.normalize().to_string()))
+            })
+            .collect();
+        gauge(
+            "balance",
+            "Balance of a top-level accn in the base currency.",
+            balances,
+        );
+    }
+    let saved = loaded
+        .modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    gauge(
+        "last_save_timestamp_seconds",
+        "When the journal file was last saved.",
+        vec![(label.clone(), saved.to_string())],
+    );
+    gauge(
+        "parse_duration_seconds",
+        "How long reading the journal took.",
+        vec![(label, loaded.parse.as_secs_f64().to_string())],
+    );
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"2024-01-01 salary
+    asset:bank  $4000
+    income:salary
+
+2024-01-02 rent
+    expense:rent  $1500
+    asset:bank"#;
+
+    #[test]
+    fn test_metrics() {
+        let loaded = Loaded {
+            journal: Journal::from_str(INPUT).unwrap(),
+            modified: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+            parse: Duration::from_millis(12),
+            checkpoint: Checkpoint::of(INPUT),
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 2, 1).unwrap();
+        let text = metrics(&loaded, "main.coin", None, date);
+        let lines: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(
+            lines,
+            [
+                r#"coinjar_txns{journal="main.coin"} 2"#,
+                r#"coinjar_balance{journal="main.coin",accn="asset",currency="USD"} 2500"#,
+                r#"coinjar_balance{journal="main.coin",accn="liability",currency="USD"} 0"#,
+                r#"coinjar_balance{journal="main.coin",accn="equity",currency="USD"} 0"#,
+                r#"coinjar_balance{journal="main.coin",accn="income",currency="USD"} -4000"#,
+                r#"coinjar_balance{journal="main.coin",accn="expense",currency="USD"} 1500"#,
+                r#"coinjar_last_save_timestamp_seconds{journal="main.coin"} 1700000000"#,
+                r#"coinjar_parse_duration_seconds{journal="main.coin"} 0.012"#,
+            ]
+        );
+        assert!(text.contains("# TYPE coinjar_balance gauge"));
+    }
+
+    #[test]
+    fn test_reload_error() {
+        let path = std::env::temp_dir().join(format!("coinjar-serve-{}.coin", std::process::id()));
+        let file = path.to_str().unwrap();
+        std::fs::write(file, INPUT).unwrap();
+        let mut loaded = Loaded::read(file).unwrap();
+        let broken = format!(
+            "{}\n\n2024-01-03 lunch\n    expense:food  $12\n    asset:cash  $-1\n",
+            INPUT
+        );
+        std::fs::write(file, broken).unwrap();
+        assert!(loaded.reload(file).is_err());
+        // the journal read before is still served
+        assert_eq!(loaded.journal.txns().count(), 2);
+        std::fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn test_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // a client that connects and never sends its request
+        let _idle = TcpStream::connect(addr).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let loaded = Loaded {
+            journal: Journal::from_str(INPUT).unwrap(),
+            modified: UNIX_EPOCH,
+            parse: Duration::ZERO,
+            checkpoint: Checkpoint::of(INPUT),
+        };
+        let start = Instant::now();
+        assert!(respond(stream, &loaded, "main.coin", None).is_err());
+        assert!(start.elapsed() < TIMEOUT * 2);
+    }
+}
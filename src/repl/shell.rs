@@ -0,0 +1,71 @@
+use std::io::Write;
+
+use anyhow::Result;
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use super::Args;
+use crate::journal::Journal;
+
+/// Write the completion script of `shell` to `out`, followed by a helper
+/// completing accn names of the default journal where the shell allows it.
+pub(super) fn completions(shell: Shell, out: &mut impl Write) -> Result<()> {
+    clap_complete::generate(shell, &mut Args::command(), "coinjar", out);
+    let helper = match shell {
+        Shell::Bash => {
+            r#"
+# complete accn names of the default journal, e.g. `complete -F _coinjar_accns my-script`
+_coinjar_accns() {
+    COMPREPLY=($(compgen -W "$(coinjar accns 2>/dev/null)" -- "${COMP_WORDS[COMP_CWORD]}"))
+}
+"#
+        }
+        Shell::Zsh => {
+            r#"
+# complete accn names of the default journal, e.g. `compdef _coinjar_accns my-script`
+_coinjar_accns() {
+    compadd -- ${(f)"$(coinjar accns 2>/dev/null)"}
+}
+"#
+        }
+        Shell::Fish => {
+            r#"
+# complete accn names of the default journal, e.g. `complete -c my-script -a '(__coinjar_accns)'`
+function __coinjar_accns
+    coinjar accns 2>/dev/null
+end
+"#
+        }
+        _ => "",
+    };
+    out.write_all(helper.as_bytes())?;
+    Ok(())
+}
+
+/// Write the accn names of `journal` to `out`, one per line.
+pub(super) fn accns(journal: &Journal, out: &mut impl Write) -> Result<()> {
+    for accn in journal.accns().accns() {
+        writeln!(out, "{}", accn)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_completions() {
+        let mut out = Vec::new();
+        completions(Shell::Bash, &mut out).unwrap();
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("completions"));
+        assert!(script.contains("_coinjar_accns()"));
+
+        let journal = Journal::from_str("open asset:cash\nopen expense:food").unwrap();
+        let mut out = Vec::new();
+        accns(&journal, &mut out).unwrap();
+        let names = String::from_utf8(out).unwrap();
+        assert!(names.lines().any(|name| name == "expense:food"));
+    }
+}
@@ -1,25 +1,40 @@
 use anyhow::{anyhow, bail};
 
 use pest::{iterators::Pairs, Parser};
+use rust_decimal::Decimal;
 use split::util::find_or_create_accn;
 
 use crate::{
     accn::Accn,
-    journal::{
-        entry::TxnEntry,
-        parser::{IdentParser, Rule},
-    },
-    valuable::Money,
+    journal::parser::{IdentParser, Rule},
+    valuable::{Money, MoneyError, Valuable},
 };
 
 use super::*;
 
+/// Spreading a split over several dated txns, like `over 3 months`.
+#[derive(Debug, Clone, Copy)]
+struct Installments {
+    count: u32,
+    period: Period,
+    /// Whether the first or the last installments absorb the cents that
+    /// do not divide evenly.
+    remainder_last: bool,
+}
+
 #[derive(Debug, Default)]
 struct SplitBuilder {
     money: Option<Money>,
+    /// What was typed where the money goes, if it was not money.
+    loose_amount: Option<String>,
+    /// Total of a receipt in several currencies, instead of `money`.
+    receipt: Option<Valuable>,
     desc: Option<String>,
     recv: Option<Accn>,
-    payees: Vec<Accn>,
+    /// Payees and the amounts given for them, the ones without share what
+    /// is left of the total.
+    payees: Vec<(Accn, Option<Money>)>,
+    installments: Option<Installments>,
 }
 
 impl SplitBuilder {
@@ -28,6 +43,16 @@ impl SplitBuilder {
         self
     }
 
+    fn with_receipt(&mut self, receipt: Valuable) -> &mut Self {
+        self.receipt = Some(receipt);
+        self
+    }
+
+    fn with_loose_amount(&mut self, typed: &str) -> &mut Self {
+        self.loose_amount = Some(typed.to_string());
+        self
+    }
+
     fn with_recv(&mut self, recv: impl Into<Accn>) -> &mut Self {
         self.recv = Some(recv.into());
         self
@@ -38,44 +63,267 @@ impl SplitBuilder {
         self
     }
 
-    fn with_payee(&mut self, payee: impl Into<Accn>) -> &mut Self {
-        self.payees.push(payee.into());
+    fn with_payee(&mut self, payee: impl Into<Accn>, money: Option<Money>) -> &mut Self {
+        self.payees.push((payee.into(), money));
         self
     }
 
-    fn build(mut self, journal: &mut Journal, date: NaiveDate) -> Result<TxnEntry> {
-        let money = self.money.ok_or_else(|| anyhow!("missing money"))?;
+    /// The total, which may be left out when every payee has an amount.
+    fn total(&self) -> Result<Option<Money>> {
+        if self.money.is_some() {
+            return Ok(self.money);
+        }
+        let Some(mut moneys) = self
+            .payees
+            .iter()
+            .map(|(_, money)| *money)
+            .collect::<Option<Vec<_>>>()
+            .filter(|moneys| !moneys.is_empty())
+        else {
+            return Ok(None);
+        };
+        let first = moneys.remove(0);
+        let total = moneys
+            .into_iter()
+            .try_fold(first, Money::checked_add)
+            .map_err(|e: MoneyError| anyhow!("{}", e))?;
+        Ok(Some(total))
+    }
+
+    /// What every payee gets of `total`: the amounts given for them, and
+    /// an even share of the rest for the others.
+    fn allocate(&self, total: Money, journal: &Journal) -> Result<Vec<(Accn, Money)>> {
+        let store = journal.currencies();
+        let mut rest = total;
+        for money in self.payees.iter().filter_map(|(_, money)| *money) {
+            rest = rest.checked_sub(money).map_err(|e| anyhow!("{}", e))?;
+        }
+        let rest_sign = rest.amount().is_sign_positive();
+        if !rest.amount().is_zero() && rest_sign != total.amount().is_sign_positive() {
+            bail!(
+                "amounts given to payees exceed the total {} by {}",
+                total.into_money(store),
+                (-rest).into_money(store)
+            );
+        }
+
+        let sharing = self
+            .payees
+            .iter()
+            .filter(|(_, money)| money.is_none())
+            .count();
+        if sharing == 0 && !rest.amount().is_zero() {
+            bail!(
+                "amounts given to payees leave {} of the total {} to no one",
+                rest.into_money(store),
+                total.into_money(store)
+            );
+        }
+        let dp = store.minor_units(total.currency());
+        // like an even split, the last payees get the odd cents
+        let mut shares = match sharing {
+            0 => Vec::new(),
+            _ => rest.split(sharing, dp).collect_vec(),
+        };
+        Ok(self
+            .payees
+            .iter()
+            .map(|(payee, money)| (*payee, money.unwrap_or_else(|| shares.pop().unwrap())))
+            .collect())
+    }
+
+    /// What to offer when asking for the amount, as the text before and
+    /// after the cursor: the amount typed so far, followed by the currency
+    /// the recv accn holds unless it was more than a bare number.
+    fn amount_suggestion(&self, journal: &Journal) -> (String, String) {
+        let typed = self.loose_amount.clone().unwrap_or_default();
+        let code = self
+            .recv
+            .and_then(|recv| recv.into_accn(journal.accns()).currency());
+        match code {
+            Some(code) if typed.is_empty() || typed.parse::<Decimal>().is_ok() => {
+                (typed, format!(" {}", code))
+            }
+            _ => (typed, String::new()),
+        }
+    }
+
+    /// Ask for the amount until it reads as money.
+    fn ask_amount(&mut self, journal: &Journal) -> Result<()> {
+        let (left, right) = self.amount_suggestion(journal);
+        let mut editor = rustyline::DefaultEditor::new()?;
+        loop {
+            let input = editor.readline_with_initial(tr(Label::EnterAmount), (&left, &right))?;
+            match journal.parse_money(&input) {
+                Ok(money) => {
+                    self.with_money(money);
+                    return Ok(());
+                }
+                Err(e) => eprintln!("{}: {}", tr(Label::Error).red().bold(), e),
+            }
+        }
+    }
+
+    fn with_installments(&mut self, installments: Installments) -> &mut Self {
+        self.installments = Some(installments);
+        self
+    }
+
+    /// Date and amount of every txn the split turns into.
+    fn schedule(&self, money: Money, date: NaiveDate, dp: u32) -> Result<Vec<(NaiveDate, Money)>> {
+        let Some(installments) = self.installments else {
+            return Ok(vec![(date, money)]);
+        };
+        let mut parts = money.split(installments.count as usize, dp).collect_vec();
+        if installments.remainder_last {
+            parts.reverse();
+        }
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(i, part)| Ok((installments.period.advance(date, i as u32)?, part)))
+            .collect()
+    }
+
+    /// Build the txn of a receipt, splitting the amount in every currency
+    /// evenly between the payees.
+    fn build_receipt(
+        &self,
+        receipt: &Valuable,
+        journal: &mut Journal,
+        date: NaiveDate,
+        recv: Accn,
+        desc: String,
+    ) -> Result<Vec<Txn>> {
+        if self.installments.is_some() || self.payees.iter().any(|(_, money)| money.is_some()) {
+            bail!("a receipt in several currencies can only be split evenly");
+        }
+        let store = journal.currencies();
+        let paid = receipt.sorted(store);
+        // like an even split, the last payees get the odd cents
+        let shares = receipt
+            .split(self.payees.len(), store)
+            .into_iter()
+            .rev()
+            .map(|share| share.sorted(store))
+            .collect_vec();
+
+        let mut txn = journal.new_txn(date, desc);
+        for money in paid {
+            txn = txn.with_posting_combined(recv, Some(-money));
+        }
+        for ((payee, _), share) in self.payees.iter().zip(shares) {
+            for money in share {
+                txn = txn.with_posting_combined(*payee, Some(money));
+            }
+        }
+        Ok(vec![txn.build()?.id()])
+    }
+
+    /// Build the txns of the split. Installments are only added once
+    /// `confirm` accepts the summary of them.
+    fn build(
+        self,
+        journal: &mut Journal,
+        date: NaiveDate,
+        confirm: impl FnOnce(&str) -> Result<bool>,
+    ) -> Result<Vec<Txn>> {
+        let total = match &self.receipt {
+            Some(_) => None,
+            None => Some(self.total()?.ok_or_else(|| anyhow!("missing money"))?),
+        };
         let recv = self.recv.ok_or_else(|| anyhow!("missing recv"))?;
         let desc = self
             .desc
+            .clone()
             .ok_or(())
-            .or_else(|_| rustyline::DefaultEditor::new()?.readline("enter desc: "))?;
+            .or_else(|_| rustyline::DefaultEditor::new()?.readline(tr(Label::EnterDesc)))?;
         if self.payees.is_empty() {
             bail!("missing payees");
         }
+        let Some(money) = total else {
+            let receipt = self.receipt.as_ref().unwrap();
+            return self.build_receipt(receipt, journal, date, recv, desc);
+        };
 
-        let moneys = money.split(self.payees.len(), 2);
-        let mut txn = journal.new_txn(date, desc).with_posting(recv, Some(-money));
+        let dp = journal.currencies().minor_units(money.currency());
+        // every share is spread over the installments on its own, so what
+        // is paid in one is whatever its parts add up to
+        let parts = self
+            .allocate(money, journal)?
+            .into_iter()
+            .map(|(payee, share)| Ok((payee, self.schedule(share, date, dp)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let schedule = (0..parts[0].1.len())
+            .map(|i| {
+                let zero = Money::new(Decimal::ZERO, money.currency());
+                let money = parts.iter().fold(zero, |mut total, (_, parts)| {
+                    total += parts[i].1;
+                    total
+                });
+                (parts[0].1[i].0, money)
+            })
+            .collect_vec();
+        let n = schedule.len();
+        if n > 1 {
+            let summary = schedule
+                .iter()
+                .map(|(date, money)| {
+                    let money = money.into_money(journal.currencies());
+                    format!("  {} {:>15}", date, money.to_string())
+                })
+                .join("\n");
+            if !confirm(&summary)? {
+                bail!("installments discarded");
+            }
+        }
 
-        for money in moneys {
-            txn = txn.with_posting_combined(self.payees.pop().unwrap(), Some(money));
+        let mut txns = Vec::new();
+        for (i, (date, money)) in schedule.into_iter().enumerate() {
+            let desc = match n {
+                1 => desc.clone(),
+                _ => format!("{} ({}/{})", desc, i + 1, n),
+            };
+            let mut txn = journal.new_txn(date, desc).with_posting(recv, Some(-money));
+            for (payee, parts) in &parts {
+                txn = txn.with_posting_combined(*payee, Some(parts[i].1));
+            }
+            txns.push(txn.build()?.id());
         }
-        txn.build()
+        Ok(txns)
     }
 
     fn from_str(journal: &mut Journal, input: &str) -> Result<Self> {
-        let pairs = IdentParser::parse(Rule::split, input)?;
-        Self::from_pairs(journal, pairs)
+        let pair = IdentParser::parse(Rule::split, input)?.next().unwrap();
+        Self::from_pairs(journal, pair.into_inner())
     }
 
-    fn from_pairs(journal: &mut Journal, mut pairs: Pairs<Rule>) -> Result<Self> {
+    fn from_pairs(journal: &mut Journal, pairs: Pairs<Rule>) -> Result<Self> {
         let mut builder = Self::default();
 
-        let money = journal.parse_money(pairs.next().unwrap().as_str())?;
-        builder.with_money(money);
-
         for pair in pairs {
             match pair.as_rule() {
+                Rule::money_var_1 | Rule::money_var_2 | Rule::money_var_3 | Rule::money_var_4 => {
+                    // money in a currency not known is asked for again
+                    match journal.parse_money(pair.as_str()) {
+                        Ok(money) => builder.with_money(money),
+                        Err(_) => builder.with_loose_amount(pair.as_str()),
+                    };
+                }
+                Rule::receipt => {
+                    let mut receipt = Valuable::default();
+                    for money in pair.into_inner() {
+                        receipt += journal.parse_money(money.as_str())?.money();
+                    }
+                    // a receipt in one currency is just money
+                    match receipt.clone().into_iter().at_most_one() {
+                        Ok(Some(money)) => builder.with_money(money),
+                        _ => builder.with_receipt(receipt),
+                    };
+                }
+                Rule::loose_amount => {
+                    builder.with_loose_amount(pair.as_str());
+                }
                 Rule::from_accn => {
                     let accn = pair
                         .into_inner()
@@ -84,13 +332,33 @@ impl SplitBuilder {
                     builder.with_recv(find_or_create_accn(journal, accn.as_str())?);
                 }
                 Rule::to_accn => {
-                    for pair in pair.into_inner() {
-                        builder.with_payee(find_or_create_accn(journal, pair.as_str())?);
+                    for share in pair.into_inner() {
+                        let mut pairs = share.into_inner();
+                        let payee = find_or_create_accn(journal, pairs.next().unwrap().as_str())?;
+                        let payee = payee.id();
+                        let money = pairs
+                            .next()
+                            .map(|money| journal.parse_money(money.as_str()))
+                            .transpose()?
+                            .map(|money| money.money());
+                        builder.with_payee(payee, money);
                     }
                 }
                 Rule::desc => {
                     builder.with_desc(pair.as_str());
                 }
+                Rule::installments => {
+                    let mut pairs = pair.into_inner();
+                    let count = pairs.next().unwrap().as_str().parse()?;
+                    if count == 0 {
+                        bail!("installments must be at least one");
+                    }
+                    builder.with_installments(Installments {
+                        count,
+                        period: pairs.next().unwrap().as_str().parse()?,
+                        remainder_last: pairs.next().is_some_and(|p| p.as_str() == "last"),
+                    });
+                }
                 _ => unreachable!("unexpected rule: {:?}", pair.as_rule()),
             }
         }
@@ -99,24 +367,202 @@ impl SplitBuilder {
     }
 }
 
-pub(super) fn split<'a>(
-    journal: &'a mut Journal,
+pub(super) fn split(
+    journal: &mut Journal,
     pairs: Pairs<'_, Rule>,
     state: &ReplState,
-) -> Result<TxnEntry<'a>> {
-    SplitBuilder::from_pairs(journal, pairs)?.build(journal, state.date)
+) -> Result<Vec<Txn>> {
+    let mut builder = SplitBuilder::from_pairs(journal, pairs)?;
+    if builder.receipt.is_none() && builder.total()?.is_none() {
+        builder.ask_amount(journal)?;
+    }
+    builder.build(journal, state.date, |summary| {
+        println!("{}", summary);
+        // a dry run shows what would be added anyway
+        if state.dry_run {
+            return Ok(true);
+        }
+        Ok(Confirm::new("add these installments?")
+            .with_default(true)
+            .prompt()?)
+    })
 }
 
 #[cfg(test)]
 mod test {
     use pest::Parser;
 
-    use crate::journal::parser::{IdentParser, Rule};
+    use super::*;
 
     #[test]
     fn test_parse_split() {
         let cmd = "split 100 usd from food to groceries    , snacks ";
         let pairs = IdentParser::parse(Rule::split, cmd).unwrap_or_else(|e| panic!("{}", e));
-        dbg!(pairs);
+        let split = pairs.into_iter().next().unwrap();
+        assert_eq!(split.as_rule(), Rule::split);
+    }
+
+    #[test]
+    fn test_missing_amount() {
+        let mut journal = Journal::from_str(
+            "currency EUR €\nopen asset:cash currency EUR\nopen expense:food\nopen expense:drinks",
+        )
+        .unwrap();
+        let builder =
+            SplitBuilder::from_str(&mut journal, "split from cash to food, drinks").unwrap();
+        assert!(builder.money.is_none());
+        assert_eq!(builder.payees.len(), 2);
+        assert_eq!(
+            builder.amount_suggestion(&journal),
+            (String::new(), " EUR".to_string())
+        );
+
+        let builder = SplitBuilder::from_str(&mut journal, "split 12 from cash to food").unwrap();
+        assert_eq!(
+            builder.amount_suggestion(&journal),
+            ("12".to_string(), " EUR".to_string())
+        );
+        let builder = SplitBuilder::from_str(&mut journal, "split 12,50 to food").unwrap();
+        assert_eq!(
+            builder.amount_suggestion(&journal),
+            ("12,50".to_string(), String::new())
+        );
+        let builder = SplitBuilder::from_str(&mut journal, "split 12 XYZ to food").unwrap();
+        assert_eq!(
+            (builder.money, builder.loose_amount.as_deref()),
+            (None, Some("12 XYZ"))
+        );
+
+        let builder = SplitBuilder::from_str(&mut journal, "€12 from cash to food").unwrap();
+        assert!(builder.money.is_some());
+    }
+
+    #[test]
+    fn test_explicit_shares() {
+        let mut journal = Journal::from_str(
+            "open asset:cash\nopen expense:groceries\nopen expense:snacks\nopen expense:drinks",
+        )
+        .unwrap();
+        let shares = |journal: &mut Journal, cmd: &str| -> Result<Vec<String>> {
+            let builder = SplitBuilder::from_str(journal, cmd)?;
+            let total = builder.total()?.unwrap();
+            let shares = builder.allocate(total, journal)?;
+            Ok(shares
+                .into_iter()
+                .map(|(payee, money)| {
+                    let name = payee.into_accn(journal.accns()).name().to_string();
+                    format!("{} {}", name, money.into_money(journal.currencies()))
+                })
+                .collect())
+        };
+
+        let explicit = shares(
+            &mut journal,
+            "split from cash to groceries $30, snacks $12.50",
+        );
+        assert_eq!(explicit.unwrap(), ["groceries $30", "snacks $12.50"]);
+        let rest = shares(
+            &mut journal,
+            "split $50 from cash to groceries $30, snacks, drinks",
+        );
+        assert_eq!(rest.unwrap(), ["groceries $30", "snacks $10", "drinks $10"]);
+        let odd = shares(&mut journal, "$1 from cash to snacks, drinks, groceries");
+        assert_eq!(
+            odd.unwrap(),
+            ["snacks $0.33", "drinks $0.33", "groceries $0.34"]
+        );
+
+        assert!(shares(&mut journal, "$20 from cash to groceries $30, snacks").is_err());
+        assert!(shares(&mut journal, "$50 from cash to groceries $30, snacks $10").is_err());
+        let builder =
+            SplitBuilder::from_str(&mut journal, "split from cash to groceries $30, snacks")
+                .unwrap();
+        assert!(builder.total().unwrap().is_none());
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let builder = SplitBuilder::from_str(
+            &mut journal,
+            "$50 from cash to groceries $30, snacks for market",
+        )
+        .unwrap();
+        let txns = builder.build(&mut journal, date, |_| Ok(true)).unwrap();
+        let txn = journal.txn(txns[0]).to_string();
+        assert!(txn.contains("expense:snacks") && txn.contains("$20"));
+    }
+
+    #[test]
+    fn test_receipt() {
+        let mut journal =
+            Journal::from_str("open liability:card\nopen expense:alice\nopen expense:bob").unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let builder = SplitBuilder::from_str(
+            &mut journal,
+            "split $10.01 + 501 JPY from card to alice, bob for trip",
+        )
+        .unwrap();
+        let txns = builder.build(&mut journal, date, |_| Ok(true)).unwrap();
+        let lines = journal
+            .txn(txns[0])
+            .to_string()
+            .lines()
+            .map(|line| line.split_whitespace().join(" "))
+            .collect_vec();
+        assert_eq!(
+            lines[1..],
+            [
+                "liability:card -501 JPY",
+                "liability:card -$10.01",
+                "expense:alice 250 JPY",
+                "expense:alice $5.00",
+                "expense:bob 251 JPY",
+                "expense:bob $5.01",
+            ]
+        );
+
+        let builder = SplitBuilder::from_str(
+            &mut journal,
+            "split $10 + 500 JPY from card to alice $5, bob for trip",
+        )
+        .unwrap();
+        assert!(builder.build(&mut journal, date, |_| Ok(true)).is_err());
+        let builder =
+            SplitBuilder::from_str(&mut journal, "split $10 + $5 from card to alice").unwrap();
+        assert_eq!(
+            builder.total().unwrap().unwrap().amount(),
+            Decimal::from(15)
+        );
+    }
+
+    #[test]
+    fn test_installments() {
+        let journal = Journal::from_str("").unwrap();
+        let mut builder = SplitBuilder::default();
+        builder.with_installments(Installments {
+            count: 3,
+            period: Period::Monthly,
+            remainder_last: true,
+        });
+        let money = journal.parse_money("$100").unwrap().money();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        let schedule = builder
+            .schedule(money, date, 2)
+            .unwrap()
+            .into_iter()
+            .map(|(date, money)| {
+                let money = money.into_money(journal.currencies());
+                (date.to_string(), money.to_string())
+            })
+            .collect_vec();
+        assert_eq!(
+            schedule,
+            [
+                ("2024-01-31".to_string(), "$33.33".to_string()),
+                ("2024-02-29".to_string(), "$33.33".to_string()),
+                ("2024-03-31".to_string(), "$33.34".to_string()),
+            ]
+        );
+
+        let date = NaiveDate::MAX - chrono::Duration::days(40);
+        assert!(builder.schedule(money, date, 2).is_err());
     }
 }
@@ -0,0 +1,112 @@
+use std::{collections::HashSet, fmt::Display};
+
+use colored::Colorize;
+use itertools::Itertools;
+
+use crate::{
+    accn::Accn,
+    journal::{Journal, Txn},
+    valuable::Valuable,
+};
+
+/// What a command adding txns in bulk did, printed after it and kept for
+/// `inspect` to show again.
+#[derive(Debug, Clone)]
+pub(super) struct BulkSummary {
+    /// The command, like `split`.
+    op: String,
+    added: usize,
+    /// Txns left out as duplicates of ones the journal already has.
+    skipped: usize,
+    accns_created: Vec<String>,
+    /// What the added txns move per currency.
+    total: String,
+}
+
+impl BulkSummary {
+    /// Accns of `journal`, to tell which ones a command creates.
+    pub(super) fn accns(journal: &Journal) -> HashSet<Accn> {
+        journal.accns().accns().map(|accn| accn.id()).collect()
+    }
+
+    /// Summary of `txns` just added to `journal`, which had the accns
+    /// `before` until then.
+    pub(super) fn new(
+        op: &str,
+        journal: &Journal,
+        txns: &[Txn],
+        skipped: usize,
+        before: &HashSet<Accn>,
+    ) -> Self {
+        let total: Valuable = txns.iter().map(|txn| journal.txn(*txn).amount()).sum();
+        Self {
+            op: op.to_string(),
+            added: txns.len(),
+            skipped,
+            accns_created: journal
+                .accns()
+                .accns()
+                .filter(|accn| !before.contains(&accn.id()))
+                .map(|accn| accn.to_string())
+                .collect(),
+            total: total.into_valuable(journal.currencies()).to_string(),
+        }
+    }
+}
+
+impl Display for BulkSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} added, {} skipped as duplicates, {} accns created",
+            self.op.bold(),
+            self.added,
+            self.skipped,
+            self.accns_created.len()
+        )?;
+        if !self.accns_created.is_empty() {
+            write!(f, " ({})", self.accns_created.iter().join(", "))?;
+        }
+        if !self.total.is_empty() {
+            write!(f, "\n  total {}", self.total)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use crate::valuable::Money;
+
+    use super::*;
+
+    #[test]
+    fn test_bulk_summary() {
+        let mut journal = Journal::from_str("open asset:cash\nopen expense:food").unwrap();
+        let before = BulkSummary::accns(&journal);
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let cash = journal.accns().by_name_unique("cash").ok().unwrap().id();
+        let txns = ["$12.50", "$7.50", "¥300"].map(|money| {
+            let money = journal.parse_money(money).unwrap().money();
+            let snacks = journal
+                .accns_mut()
+                .root_mut()
+                .or_open_child("expense")
+                .or_open_child("snacks")
+                .into_ref()
+                .id();
+            let txn = journal.new_txn(date, "snacks".to_string());
+            let txn = txn.with_posting(snacks, Some(money));
+            txn.with_posting(cash, None::<Money>).build().unwrap().id()
+        });
+
+        let summary = BulkSummary::new("split", &journal, &txns, 1, &before);
+        colored::control::set_override(false);
+        assert_eq!(
+            summary.to_string(),
+            "split: 3 added, 1 skipped as duplicates, 1 accns created (expense:snacks)\n  total ¥300, $20.00"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::journal::{
+    merge::{Conflict, Resolution},
+    Journal,
+};
+
+/// Where a journal is synced to, parsed from a location like
+/// `webdav+https://host/dav/main.coin`, `s3://bucket/main.coin`,
+/// `ssh://host/path/main.coin` or a plain path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Remote {
+    /// A file on a mounted drive or a synced folder.
+    Path(PathBuf),
+    /// A WebDAV url, fetched and stored by plain GET and PUT.
+    WebDav(String),
+    /// An object copied with the `aws` cli, which brings its credentials.
+    S3(String),
+    /// `host:path` copied with `scp`.
+    Ssh(String),
+}
+
+impl std::str::FromStr for Remote {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(url) = s.strip_prefix("webdav+") {
+            return Ok(Remote::WebDav(url.to_string()));
+        }
+        if s.starts_with("s3://") {
+            return Ok(Remote::S3(s.to_string()));
+        }
+        if let Some(rest) = s.strip_prefix("ssh://") {
+            let (host, path) = rest
+                .split_once('/')
+                .ok_or_else(|| anyhow!("expected ssh://host/path, got {}", s))?;
+            return Ok(Remote::Ssh(format!("{}:/{}", host, path)));
+        }
+        if s.contains("://") {
+            bail!(
+                "unknown sync backend {}, use a path, webdav+https://, s3:// or ssh://",
+                s
+            );
+        }
+        Ok(Remote::Path(PathBuf::from(s)))
+    }
+}
+
+impl Remote {
+    /// The remote journal, none if nothing was pushed yet.
+    fn pull(&self) -> Result<Option<String>> {
+        match self {
+            Remote::Path(path) => match path.exists() {
+                true => Ok(Some(std::fs::read_to_string(path)?)),
+                false => Ok(None),
+            },
+            Remote::WebDav(url) => {
+                let response = reqwest::blocking::get(url)?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(None);
+                }
+                Ok(Some(response.error_for_status()?.text()?))
+            }
+            Remote::S3(url) => {
+                let tmp = tmp_file("pull");
+                let copied = run(Command::new("aws").args(["s3", "cp", url]).arg(&tmp));
+                read_copied(copied, &tmp, "(404)")
+            }
+            Remote::Ssh(target) => {
+                let tmp = tmp_file("pull");
+                let copied = run(Command::new("scp").arg("-q").arg(target).arg(&tmp));
+                read_copied(copied, &tmp, "No such file or directory")
+            }
+        }
+    }
+
+    fn push(&self, content: &str) -> Result<()> {
+        match self {
+            Remote::Path(path) => Ok(std::fs::write(path, content)?),
+            Remote::WebDav(url) => {
+                reqwest::blocking::Client::new()
+                    .put(url)
+                    .body(content.to_string())
+                    .send()?
+                    .error_for_status()?;
+                Ok(())
+            }
+            Remote::S3(url) => {
+                let tmp = tmp_file("push");
+                std::fs::write(&tmp, content)?;
+                let copied = run(Command::new("aws").args(["s3", "cp"]).arg(&tmp).arg(url));
+                std::fs::remove_file(&tmp).ok();
+                copied
+            }
+            Remote::Ssh(target) => {
+                let tmp = tmp_file("push");
+                std::fs::write(&tmp, content)?;
+                let copied = run(Command::new("scp").arg("-q").arg(&tmp).arg(target));
+                std::fs::remove_file(&tmp).ok();
+                copied
+            }
+        }
+    }
+}
+
+fn tmp_file(what: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("coinjar-sync-{}-{}", what, std::process::id()))
+}
+
+/// Run `command`, failing with what it wrote to stderr if it fails.
+fn run(command: &mut Command) -> Result<()> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => bail!(
+            "{} failed with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+/// What a copy to `tmp` brought, none if it failed with `missing` in its
+/// error, as the remote file does not exist yet.
+fn read_copied(copied: Result<()>, tmp: &Path, missing: &str) -> Result<Option<String>> {
+    if let Err(e) = copied {
+        std::fs::remove_file(tmp).ok();
+        return match e.to_string().contains(missing) {
+            true => Ok(None),
+            false => Err(e),
+        };
+    }
+    let content = std::fs::read_to_string(tmp)?;
+    std::fs::remove_file(tmp).ok();
+    Ok(Some(content))
+}
+
+/// 64-bit FNV-1a of `content`, stable across runs and platforms.
+fn hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Sidecar next to a journal file holding the version last synced, the
+/// base both sides are merged from.
+fn base_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(file);
+    path.with_file_name(format!(".{}.coinjar.sync", stem))
+}
+
+/// What syncing did, from the side of the local file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Outcome {
+    UpToDate,
+    Pushed,
+    Pulled,
+    Merged,
+}
+
+/// Bring the journal `file` and `remote` to the same content. Whichever
+/// side changed since the last sync wins, and if both did their txns are
+/// merged with `resolve` deciding on conflicts.
+pub(super) fn sync(
+    file: &str,
+    remote: &Remote,
+    resolve: impl FnMut(&Conflict) -> Result<Resolution>,
+) -> Result<Outcome> {
+    let local = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to open journal file: {}", file))?;
+    let base = std::fs::read_to_string(base_path(file)).ok();
+    let remote_content = remote.pull().context("Failed to pull from the remote")?;
+
+    let changed = |content: &str| {
+        base.as_deref()
+            .is_none_or(|base| hash(base) != hash(content))
+    };
+    let (outcome, content) = match remote_content {
+        None => (Outcome::Pushed, local),
+        Some(theirs) if hash(&theirs) == hash(&local) => (Outcome::UpToDate, local),
+        Some(theirs) if !changed(&theirs) => (Outcome::Pushed, local),
+        Some(theirs) if !changed(&local) => (Outcome::Pulled, theirs),
+        Some(theirs) => {
+            let base = Journal::from_str(base.as_deref().unwrap_or(""))?;
+            let merged = Journal::merge(
+                &base,
+                &Journal::from_str(&local)?,
+                &Journal::from_str(&theirs)?,
+                resolve,
+            )?;
+            (Outcome::Merged, merged.to_string())
+        }
+    };
+
+    if matches!(outcome, Outcome::Pulled | Outcome::Merged) {
+        std::fs::write(file, &content)?;
+    }
+    if matches!(outcome, Outcome::Pushed | Outcome::Merged) {
+        remote
+            .push(&content)
+            .context("Failed to push to the remote")?;
+    }
+    std::fs::write(base_path(file), &content)?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TXN: &str = "2024-01-01 lunch\n    expense:food  $12\n    asset:bank\n";
+
+    #[test]
+    fn test_remote_from_str() {
+        assert_eq!(
+            "ssh://nas/home/me/main.coin".parse::<Remote>().unwrap(),
+            Remote::Ssh("nas:/home/me/main.coin".to_string())
+        );
+        assert_eq!(
+            "webdav+https://dav.example.com/main.coin"
+                .parse::<Remote>()
+                .unwrap(),
+            Remote::WebDav("https://dav.example.com/main.coin".to_string())
+        );
+        assert!("ftp://host/main.coin".parse::<Remote>().is_err());
+    }
+
+    #[test]
+    fn test_read_copied() {
+        let tmp = tmp_file("test-read-copied");
+        let failed = |stderr: &str| Err(anyhow!("scp failed with exit status: 1: {}", stderr));
+        let missing = "No such file or directory";
+        let pulled = read_copied(
+            failed("scp: /main.coin: No such file or directory"),
+            &tmp,
+            missing,
+        );
+        assert!(pulled.unwrap().is_none());
+        let pulled = read_copied(
+            failed("ssh: connect to host nas: Connection refused"),
+            &tmp,
+            missing,
+        );
+        assert!(pulled.is_err());
+
+        std::fs::write(&tmp, "2024-01-01").unwrap();
+        let pulled = read_copied(Ok(()), &tmp, missing).unwrap();
+        assert_eq!(pulled.as_deref(), Some("2024-01-01"));
+        assert!(!tmp.exists());
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = std::env::temp_dir().join(format!("coinjar-sync-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let laptop = dir.join("laptop.coin").display().to_string();
+        let phone = dir.join("phone.coin").display().to_string();
+        let remote = Remote::Path(dir.join("remote.coin"));
+        let no_conflict = |_: &_| bail!("no conflict expected");
+
+        std::fs::write(&laptop, TXN).unwrap();
+        assert_eq!(
+            sync(&laptop, &remote, no_conflict).unwrap(),
+            Outcome::Pushed
+        );
+        std::fs::write(&phone, "").unwrap();
+        std::fs::write(base_path(&phone), "").unwrap();
+        assert_eq!(sync(&phone, &remote, no_conflict).unwrap(), Outcome::Pulled);
+        assert_eq!(
+            sync(&phone, &remote, no_conflict).unwrap(),
+            Outcome::UpToDate
+        );
+
+        // both add a txn while apart
+        let phone_txn = "2024-01-02 coffee\n    expense:food  $4\n    asset:bank\n";
+        let laptop_txn = "2024-01-03 dinner\n    expense:food  $30\n    asset:bank\n";
+        let phone_content = std::fs::read_to_string(&phone).unwrap() + "\n" + phone_txn;
+        std::fs::write(&phone, phone_content).unwrap();
+        std::fs::write(&laptop, format!("{}\n{}", TXN, laptop_txn)).unwrap();
+        assert_eq!(sync(&phone, &remote, no_conflict).unwrap(), Outcome::Pushed);
+        assert_eq!(
+            sync(&laptop, &remote, no_conflict).unwrap(),
+            Outcome::Merged
+        );
+        let merged = Journal::from_file(&laptop).unwrap();
+        assert_eq!(merged.txns().count(), 3);
+        assert_eq!(sync(&phone, &remote, no_conflict).unwrap(), Outcome::Pulled);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
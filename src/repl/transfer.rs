@@ -0,0 +1,33 @@
+use pest::iterators::Pairs;
+
+use crate::journal::{parser::Rule, Txn};
+
+use super::{util::find_or_create_accn, *};
+
+/// Record a transfer from an account of the active journal into an account
+/// of another journal of the workspace.
+pub(super) fn transfer(
+    workspace: &mut Workspace,
+    mut pairs: Pairs<'_, Rule>,
+    state: &ReplState,
+) -> Result<(Txn, Txn)> {
+    let money = workspace
+        .active()
+        .parse_money(pairs.next().unwrap().as_str())?
+        .money();
+    let from = pairs.next().unwrap().as_str();
+    let target = pairs.next().unwrap().as_str();
+    let to = pairs.next().unwrap().as_str();
+    let desc = match pairs.next() {
+        Some(desc) => desc.as_str().to_string(),
+        None => rustyline::DefaultEditor::new()?.readline(tr(Label::EnterDesc))?,
+    };
+
+    workspace.check_transfer(target)?;
+    // accns made for a transfer that fails go with it
+    workspace.or_restore(target, |workspace| {
+        let from = find_or_create_accn(workspace.active_mut(), from)?.id();
+        let to = find_or_create_accn(workspace.journal_mut(target)?, to)?.id();
+        workspace.transfer(target, state.date, &desc, money, from, to)
+    })
+}
@@ -1,10 +1,10 @@
 use std::fmt::Display;
 
 use anyhow::bail;
-use inquire::Select;
+use inquire::{Confirm, Select};
 
 use crate::{
-    accn::{Accn, AccnEntry, AccnEntryMut},
+    accn::{Accn, AccnEntry, AccnEntryMut, AccnPath, AutoCreate},
     util::{Formatted, NotEmpty},
 };
 
@@ -21,13 +21,16 @@ pub(crate) fn find_or_create_accn<'a>(
         .collect_vec();
 
     let ret = match accn.len() {
-        0 => fuzzy_create_accn(journal, matcher)?.into_ref(),
+        0 => {
+            let policy = journal.accns().autocreate();
+            fuzzy_create_accn(journal, matcher, policy)?.into_ref()
+        }
         1 => accn[0].into_accn(journal.accns()),
         _ => choose(
             accn.into_iter().map(|id| id.into_accn(journal.accns())),
             &format!(
                 "{}: {} not unique, choose from candidates",
-                "info".green().bold(),
+                tr(Label::Info).green().bold(),
                 matcher.blue()
             ),
         )?,
@@ -37,7 +40,38 @@ pub(crate) fn find_or_create_accn<'a>(
     Ok(ret)
 }
 
-fn choose<T: Display>(accns: impl Iterator<Item = T>, prompt: &str) -> Result<T> {
+/// Find an existing account, letting the user choose when several match.
+pub(crate) fn find_accn<'a>(journal: &'a Journal, matcher: &'a str) -> Result<AccnEntry<'a>> {
+    let accns = journal.accns().by_name_fuzzy(matcher).collect_vec();
+    match accns.len() {
+        0 => bail!("no accn matching {}", matcher),
+        1 => Ok(accns[0]),
+        _ => choose(
+            accns.into_iter(),
+            &format!(
+                "{}: {} not unique, choose from candidates",
+                tr(Label::Info).green().bold(),
+                matcher.blue()
+            ),
+        ),
+    }
+}
+
+/// Render `item` line by line, diff-style, as added (`+`) or removed (`-`).
+pub(crate) fn diff_lines(sign: char, item: impl Display) -> String {
+    item.to_string()
+        .lines()
+        .map(|line| {
+            let line = format!("{} {}", sign, line);
+            match sign {
+                '+' => line.green().to_string(),
+                _ => line.red().to_string(),
+            }
+        })
+        .join("\n")
+}
+
+pub(crate) fn choose<T: Display>(accns: impl Iterator<Item = T>, prompt: &str) -> Result<T> {
     let items = accns.collect::<Vec<_>>();
     let ret = Select::new(prompt, items).prompt()?;
     Ok(ret)
@@ -48,12 +82,19 @@ fn choose<T: Display>(accns: impl Iterator<Item = T>, prompt: &str) -> Result<T>
 /// 1. If food:groceries exists, return it
 /// 2. If food exists, create food:groceries and return it
 /// 3. If food does not exist, return err
+///
+/// New accns are only created as far as `policy` allows.
 pub(crate) fn fuzzy_create_accn<'a>(
     journal: &'a mut Journal,
     matcher: &'a str,
+    policy: AutoCreate,
 ) -> Result<AccnEntryMut<'a>> {
     let original_matcher = matcher;
-    let mut matcher = matcher.split(':').collect_vec();
+    // fail before offering candidates that could not be created anyway
+    if policy == AutoCreate::Strict {
+        policy.check(matcher, |_| Ok(false))?;
+    }
+    let mut matcher = matcher.accn_path().collect_vec();
     let mut unmatched = Vec::new();
 
     // find a match
@@ -75,7 +116,7 @@ pub(crate) fn fuzzy_create_accn<'a>(
             let candidate = Select::new(
                 &format!(
                     "{}: {} not found, create one from candidates",
-                    "info".yellow().bold(),
+                    tr(Label::Info).yellow().bold(),
                     original_matcher.red()
                 ),
                 candidates,
@@ -83,12 +124,22 @@ pub(crate) fn fuzzy_create_accn<'a>(
             .prompt();
 
             return try {
-                let candidate = candidate?;
+                let candidate = candidate.map_err(anyhow::Error::from)?;
                 let id = candidate.id();
                 let mut accn = id.into_accn_mut(journal.accns_mut());
 
+                // one confirmation covers every part of the new path
+                let mut confirmed = false;
                 for part in unmatched.into_iter().rev() {
-                    accn = accn.or_open_child(part);
+                    accn = accn
+                        .resolve_child(part, policy, |name| {
+                            confirmed = confirmed
+                                || Confirm::new(&format!("{} {}?", tr(Label::CreateAccn), name))
+                                    .with_default(true)
+                                    .prompt()?;
+                            Ok(confirmed)
+                        })
+                        .map_err(anyhow::Error::from)?;
                 }
 
                 accn
@@ -1,4 +1,46 @@
-use std::{fmt::Display, iter::Peekable, ops::Deref};
+use std::{
+    fmt::Display,
+    iter::Peekable,
+    ops::Deref,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_width::UnicodeWidthStr;
+
+/// A fresh id for an accn or txn. Ids are cheap to hash and compare, never
+/// reused and unique across every journal of the process, so a txn id still
+/// names one txn of a workspace. 0 is left to the root accn.
+pub(crate) fn next_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// `s` without case and accents, so `Groß Einkauf` and `gross einkauf`
+/// compare equal.
+pub(crate) fn fold(s: &str) -> String {
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .replace('ß', "ss")
+}
+
+/// Columns `s` takes on a terminal, where a character like `円` takes two,
+/// unlike `{:<10}` which counts chars.
+pub(crate) fn width(s: &str) -> usize {
+    s.width()
+}
+
+/// `s` followed by spaces up to `width` columns.
+pub(crate) fn pad_right(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(self::width(s))))
+}
+
+/// `s` after spaces up to `width` columns.
+pub(crate) fn pad_left(s: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(self::width(s))), s)
+}
 
 pub(crate) trait NotEmpty {
     type Ok;
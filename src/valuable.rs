@@ -1,11 +1,12 @@
 use std::{
+    cmp::Ordering,
     collections::HashMap,
     fmt::Display,
     iter::Sum,
     ops::{Add, AddAssign, Neg},
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use itertools::Itertools;
 use rust_decimal::{
     prelude::{Signed, ToPrimitive, Zero},
@@ -13,8 +14,25 @@ use rust_decimal::{
 };
 use uuid::Uuid;
 
+use iso4217::ISO_4217;
+
+use crate::error::CoinError;
+
+pub(crate) use conversion::ExchangeBook;
+pub(crate) use prefetch::RateCache;
+pub(crate) use provider::{HttpPolicy, ProviderChain, RateProvider, RateSource};
+
+mod bundle;
+mod conversion;
+mod iso4217;
+mod prefetch;
+mod provider;
+
+/// Most digits after the decimal point a `Decimal` can hold.
+const MAX_SCALE: u32 = 28;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Currency {
+pub(crate) struct Currency {
     id: Uuid,
 }
 
@@ -24,18 +42,51 @@ impl Currency {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct CurrencyData {
     code: String,
     symbol: Option<String>,
     symbol_first: bool,
+    /// Digits after the decimal point, if known.
+    minor_units: Option<u32>,
+    name: Option<&'static str>,
+    /// Smaller unit amounts are written in instead, like sats for BTC.
+    subunit: Option<SubUnit>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
+struct SubUnit {
+    name: String,
+    /// Power of ten the sub-unit is smaller than the currency itself.
+    exponent: u32,
+}
+
+impl CurrencyData {
+    fn new(code: String, symbol: Option<String>, symbol_first: bool) -> Self {
+        let iso = ISO_4217.get(code.as_str());
+        Self {
+            code,
+            symbol,
+            symbol_first,
+            minor_units: iso.and_then(|iso| iso.minor_units),
+            name: iso.map(|iso| iso.name),
+            subunit: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
 pub(crate) struct CurrencyStore {
     codes: HashMap<String, Currency>,
-    symbols: HashMap<String, Currency>,
+    /// Every currency using a symbol, in the order they were added.
+    symbols: HashMap<String, Vec<Currency>>,
+    /// Sub-unit names usable in place of a currency code.
+    subunits: HashMap<String, Currency>,
+    /// Currency a symbol resolves to when several currencies share it.
+    preferred: HashMap<String, Currency>,
     currencies: HashMap<Currency, CurrencyData>,
+    /// Currencies declared or overridden by the journal itself.
+    declared: Vec<Currency>,
 }
 
 impl CurrencyStore {
@@ -46,7 +97,18 @@ impl CurrencyStore {
         store.insert("EUR".to_string(), "€".to_string(), true);
         store.insert("RUB".to_string(), "₽".to_string(), false);
         store.insert("CNY".to_string(), "¥".to_string(), true);
-        store.insert("BTC".to_string(), "₿".to_string(), true);
+        // cryptocurrencies are not in ISO 4217 but need their full precision
+        for (code, symbol, minor_units) in [("BTC", "₿", 8), ("ETH", "Ξ", 18)] {
+            store.insert(code.to_string(), symbol.to_string(), true);
+            let currency = store.get_by_code(code).unwrap();
+            store.currencies.get_mut(&currency).unwrap().minor_units = Some(minor_units);
+        }
+        // every other standard currency is known by its code only
+        for iso in ISO_4217.values() {
+            if store.get_by_code(iso.code).is_none() {
+                store.add(CurrencyData::new(iso.code.to_string(), None, true));
+            }
+        }
         store
     }
 
@@ -54,26 +116,190 @@ impl CurrencyStore {
         if self.get_by_code(&code).is_some() {
             return;
         }
+        self.add(CurrencyData::new(code, Some(symbol), symbol_first));
+    }
 
+    fn add(&mut self, data: CurrencyData) -> Currency {
         let currency = Currency::new();
-        let data = CurrencyData {
-            code: code.clone(),
-            symbol: Some(symbol.clone()),
-            symbol_first,
+        self.codes.insert(data.code.clone(), currency);
+        if let Some(symbol) = &data.symbol {
+            self.symbols
+                .entry(symbol.clone())
+                .or_default()
+                .push(currency);
+        }
+        self.currencies.insert(currency, data);
+        currency
+    }
+
+    /// Declare a currency, or override the symbol and formatting of a known
+    /// one. Without an explicit position an existing currency keeps its own.
+    pub(crate) fn declare(&mut self, code: &str, symbol: Option<&str>, symbol_first: Option<bool>) {
+        let code = code.to_uppercase();
+        let currency = match self.get_by_code(&code) {
+            Some(currency) => currency,
+            None => self.add(CurrencyData::new(code, None, true)),
         };
 
-        self.codes.insert(code, currency);
-        self.symbols.insert(symbol.clone(), currency);
-        self.currencies.insert(currency, data);
+        let data = self.currencies.get_mut(&currency).unwrap();
+        if let Some(old) = data.symbol.take() {
+            self.symbols
+                .entry(old)
+                .or_default()
+                .retain(|c| *c != currency);
+        }
+        data.symbol = symbol.map(str::to_string);
+        data.symbol_first = symbol_first.unwrap_or(data.symbol_first);
+        if let Some(symbol) = symbol {
+            self.symbols
+                .entry(symbol.to_string())
+                .or_default()
+                .push(currency);
+        }
+
+        if !self.declared.contains(&currency) {
+            self.declared.push(currency);
+        }
+    }
+
+    /// Write amounts of `code` in its sub-unit `name`, which is `exponent`
+    /// powers of ten smaller, defaulting to the currency's minor units.
+    pub(crate) fn declare_subunit(
+        &mut self,
+        code: &str,
+        name: &str,
+        exponent: Option<u32>,
+    ) -> Result<(), CoinError> {
+        let currency = self
+            .get_by_code(code)
+            .ok_or_else(|| CoinError::UnknownCurrency(code.to_string()))?;
+        if self.get_by_code(name).is_some() {
+            let msg = format!("sub-unit {} clashes with a currency code", name);
+            return Err(msg.into());
+        }
+        if let Some(other) = self.subunits.get(name).filter(|c| **c != currency) {
+            let msg = format!("sub-unit {} already used by {}", name, self.code(*other));
+            return Err(msg.into());
+        }
+        let exponent = exponent.unwrap_or_else(|| self.minor_units(currency));
+        if exponent == 0 || exponent > MAX_SCALE {
+            let msg = format!("sub-unit exponent must be between 1 and {}", MAX_SCALE);
+            return Err(msg.into());
+        }
+
+        let data = self.currencies.get_mut(&currency).unwrap();
+        if let Some(old) = data.subunit.take() {
+            self.subunits.remove(&old.name);
+        }
+        data.subunit = Some(SubUnit {
+            name: name.to_string(),
+            exponent,
+        });
+        self.subunits.insert(name.to_string(), currency);
+        Ok(())
+    }
+
+    /// Make `symbol` resolve to the currency `code` when it is ambiguous.
+    pub(crate) fn prefer(&mut self, symbol: &str, code: &str) -> Result<(), CoinError> {
+        let currency = self
+            .get_by_code(code)
+            .ok_or_else(|| CoinError::UnknownCurrency(code.to_string()))?;
+        if self.currencies[&currency].symbol.as_deref() != Some(symbol) {
+            let msg = format!("currency {} does not use symbol {}", code, symbol);
+            return Err(msg.into());
+        }
+        self.preferred.insert(symbol.to_string(), currency);
+        Ok(())
     }
 
-    fn get_by_code(&self, code: &str) -> Option<Currency> {
+    pub(crate) fn get_by_code(&self, code: &str) -> Option<Currency> {
         // WARNING: Assuming all codes are uppercase.
         self.codes.get(&code.to_uppercase()).copied()
     }
 
-    fn get_by_symbol(&self, symbol: &str) -> Option<Currency> {
-        self.symbols.get(symbol).copied()
+    fn get_by_symbol(&self, symbol: &str) -> Result<Currency, CoinError> {
+        if let Some(currency) = self.preferred.get(symbol) {
+            return Ok(*currency);
+        }
+
+        match self.symbols.get(symbol).map(Vec::as_slice) {
+            None | Some([]) => Err(format!("symbol {} not found", symbol).into()),
+            Some([currency]) => Ok(*currency),
+            Some(candidates) => Err(format!(
+                "symbol {} is ambiguous between {}, declare `symbol {} <code>` to choose one",
+                symbol,
+                candidates.iter().map(|c| self.describe(*c)).join(", "),
+                symbol
+            )
+            .into()),
+        }
+    }
+
+    pub(crate) fn code(&self, currency: Currency) -> &str {
+        &self.currencies[&currency].code
+    }
+
+    /// Every known currency code, sorted.
+    pub(crate) fn codes(&self) -> impl Iterator<Item = &str> {
+        self.codes.keys().map(String::as_str).sorted()
+    }
+
+    /// Code of `currency` followed by its name when it has one.
+    fn describe(&self, currency: Currency) -> String {
+        match self.name(currency) {
+            Some(name) => format!("{} ({})", self.code(currency), name),
+            None => self.code(currency).to_string(),
+        }
+    }
+
+    /// Digits after the decimal point amounts of `currency` are rounded to,
+    /// two when the currency is not in ISO 4217.
+    pub(crate) fn minor_units(&self, currency: Currency) -> u32 {
+        self.currencies[&currency].minor_units.unwrap_or(2)
+    }
+
+    /// ISO 4217 name of `currency`, if it has one.
+    pub(crate) fn name(&self, currency: Currency) -> Option<&str> {
+        self.currencies[&currency].name
+    }
+}
+
+impl Display for CurrencyStore {
+    /// Writes the directives needed to rebuild the journal's own currency
+    /// settings on top of the built-in ones.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let currencies = self.declared.iter().map(|currency| {
+            let data = &self.currencies[currency];
+            match &data.symbol {
+                Some(symbol) => format!(
+                    "currency {} {} {}",
+                    data.code,
+                    symbol,
+                    match data.symbol_first {
+                        true => "prefix",
+                        false => "suffix",
+                    }
+                ),
+                None => format!("currency {}", data.code),
+            }
+        });
+        let subunits = self
+            .currencies
+            .values()
+            .filter_map(|data| data.subunit.as_ref().map(|sub| (&data.code, sub)))
+            .sorted_by_key(|(code, _)| code.as_str())
+            .map(|(code, sub)| format!("subunit {} {} {}", code, sub.name, sub.exponent));
+        let preferred = self
+            .preferred
+            .iter()
+            .sorted_by_key(|(symbol, _)| symbol.as_str())
+            .map(|(symbol, currency)| format!("symbol {} {}", symbol, self.code(*currency)));
+
+        currencies
+            .chain(subunits)
+            .chain(preferred)
+            .join("\n")
+            .fmt(f)
     }
 }
 
@@ -86,17 +312,30 @@ pub(crate) struct Money {
 impl Money {
     pub(crate) fn fmt(&self, store: &CurrencyStore) -> String {
         let data = store.currencies.get(&self.currency).unwrap();
-        let s = data.symbol.as_ref().unwrap();
-        let symbol_first = data.symbol_first;
 
         let sign = match self.amount.is_sign_positive() {
             true => "",
             false => "-",
         };
 
-        match symbol_first {
-            true => format!("{}{}{}", sign, s, self.amount.abs()),
-            false => format!("{}{}{}", sign, self.amount.abs(), s),
+        if let Some(sub) = &data.subunit {
+            return format!(
+                "{}{} {}",
+                sign,
+                to_subunit(self.amount.abs(), sub.exponent),
+                sub.name
+            );
+        }
+
+        // fall back to the code whenever the symbol would read back as another currency
+        match &data.symbol {
+            Some(s) if store.get_by_symbol(s).ok() == Some(self.currency) => {
+                match data.symbol_first {
+                    true => format!("{}{}{}", sign, s, self.amount.abs()),
+                    false => format!("{}{}{}", sign, self.amount.abs(), s),
+                }
+            }
+            _ => format!("{}{} {}", sign, self.amount.abs(), data.code),
         }
     }
 
@@ -107,36 +346,77 @@ impl Money {
         }
     }
 
-    fn new(amount: Decimal, currency: Currency) -> Self {
+    pub(crate) fn new(amount: Decimal, currency: Currency) -> Self {
         Self { amount, currency }
     }
 
+    pub(crate) fn amount(&self) -> Decimal {
+        self.amount
+    }
+
+    pub(crate) fn currency(&self) -> Currency {
+        self.currency
+    }
+
     pub(super) fn eq_currency(&self, other: &Self) -> bool {
         self.currency == other.currency
     }
 
-    pub(crate) fn into_money(self, store: &CurrencyStore) -> MoneyEntry {
+    pub(crate) fn checked_add(self, rhs: Self) -> Result<Self, MoneyError> {
+        if !self.eq_currency(&rhs) {
+            return Err(MoneyError::CurrencyMismatch {
+                left: self.currency,
+                right: rhs.currency,
+            });
+        }
+        let amount = self
+            .amount
+            .checked_add(rhs.amount)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(amount, self.currency))
+    }
+
+    pub(crate) fn checked_sub(self, rhs: Self) -> Result<Self, MoneyError> {
+        self.checked_add(-rhs)
+    }
+
+    pub(crate) fn into_money(self, store: &CurrencyStore) -> MoneyEntry<'_> {
         MoneyEntry { money: self, store }
     }
 
+    /// Re-express money recorded against `from` in terms of the currencies of
+    /// `to`, matching currencies by their code.
+    pub(crate) fn rebase(
+        self,
+        from: &CurrencyStore,
+        to: &CurrencyStore,
+    ) -> Result<Self, CoinError> {
+        let code = from.code(self.currency);
+        let currency = to
+            .get_by_code(code)
+            .ok_or_else(|| CoinError::UnknownCurrency(code.to_string()))?;
+        Ok(Self::new(self.amount, currency))
+    }
+
     /// Split money into n parts, each with dp decimal places, guaranteeing that
     /// the sum of the parts is equal to the original amount, and that the
     /// difference between the largest and smallest part is less than or equal
     /// to 1e-dp.
     pub(crate) fn split(self, n: usize, dp: u32) -> impl Iterator<Item = Self> {
+        // finer steps than a decimal can hold would never add back up
+        let dp = dp.min(MAX_SCALE);
         let amount: Decimal = self.amount / Decimal::from(n);
         let amount = amount.round_dp_with_strategy(dp, RoundingStrategy::MidpointNearestEven);
         let remainder: Decimal = self.amount - amount * Decimal::from(n);
         let signum = remainder.signum();
 
-        let complement = Decimal::from_scientific(&format!("1e-{}", dp)).unwrap() * signum;
+        let complement = Decimal::new(1, dp) * signum;
         let n_complements = match complement.is_zero() {
             true => 0,
             false => (remainder / complement).abs().to_usize().unwrap(),
         };
 
-        std::iter::repeat(amount)
-            .take(n)
+        std::iter::repeat_n(amount, n)
             .enumerate()
             .map(move |(i, amount)| match i < n_complements {
                 true => amount + complement,
@@ -146,6 +426,17 @@ impl Money {
     }
 }
 
+/// Express `amount` in a sub-unit `exponent` powers of ten smaller, without
+/// rounding.
+fn to_subunit(amount: Decimal, exponent: u32) -> Decimal {
+    let mut amount = amount;
+    if amount.scale() < exponent {
+        amount.rescale(exponent);
+    }
+    amount.set_scale(amount.scale() - exponent).unwrap();
+    amount
+}
+
 impl Neg for Money {
     type Output = Self;
     fn neg(self) -> Self::Output {
@@ -157,12 +448,43 @@ impl Neg for Money {
 }
 
 impl AddAssign for Money {
+    /// Panics when the currencies differ or the sum overflows, use
+    /// [`Money::checked_add`] to handle those cases instead.
     fn add_assign(&mut self, rhs: Self) {
-        debug_assert!(self.eq_currency(&rhs));
-        self.amount += rhs.amount;
+        *self = self.checked_add(rhs).unwrap_or_else(|e| panic!("{}", e));
     }
 }
 
+impl PartialOrd for Money {
+    /// Only moneys of the same currency are comparable.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.eq_currency(other)
+            .then(|| self.amount.cmp(&other.amount))
+    }
+}
+
+/// Error of arithmetic between moneys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MoneyError {
+    /// The moneys are of different currencies.
+    CurrencyMismatch { left: Currency, right: Currency },
+    /// The result is too large for an amount.
+    Overflow,
+}
+
+impl Display for MoneyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { .. } => {
+                write!(f, "cannot combine amounts of different currencies")
+            }
+            MoneyError::Overflow => write!(f, "amount overflows"),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
 pub(crate) struct MoneyEntry<'a> {
     money: Money,
     store: &'a CurrencyStore,
@@ -172,6 +494,10 @@ impl MoneyEntry<'_> {
     pub(crate) fn money(&self) -> Money {
         self.money
     }
+
+    pub(crate) fn code(&self) -> &str {
+        self.store.code(self.money.currency)
+    }
 }
 
 impl From<MoneyEntry<'_>> for Money {
@@ -215,34 +541,90 @@ impl<'a> MoneyBuilder<'a> {
         self
     }
 
-    pub(crate) fn into_money(self, store: &CurrencyStore) -> Result<Money> {
-        let amount = self.amount.ok_or_else(|| anyhow!("amount missing"))?;
+    pub(crate) fn into_money(self, store: &CurrencyStore) -> Result<Money, CoinError> {
+        let amount = self
+            .amount
+            .ok_or_else(|| CoinError::Invalid("amount missing".to_string()))?;
         let amount = match self.neg {
             true => -amount,
             false => amount,
         };
         let currency = match self.code {
-            Some(code) => store
-                .get_by_code(code)
-                .ok_or_else(|| anyhow!("code {} not found", code))?,
+            Some(code) => match (store.get_by_code(code), store.subunits.get(code)) {
+                (Some(currency), _) => currency,
+                (None, Some(&currency)) => {
+                    let exponent = store.currencies[&currency]
+                        .subunit
+                        .as_ref()
+                        .unwrap()
+                        .exponent;
+                    let mut amount = amount;
+                    amount
+                        .set_scale(amount.scale() + exponent)
+                        .map_err(|_| format!("{} {} is too precise", amount, code))?;
+                    return Ok(Money { amount, currency });
+                }
+                (None, None) => return Err(CoinError::UnknownCurrency(code.to_string())),
+            },
             None => {
                 let symbol = self
                     .symbol
-                    .ok_or_else(|| anyhow!("currency code or symbol missing"))?;
-                store
-                    .get_by_symbol(symbol)
-                    .ok_or_else(|| anyhow!("symbol {} not found", symbol))?
+                    .ok_or_else(|| CoinError::Invalid("currency code or symbol missing".into()))?;
+                store.get_by_symbol(symbol)?
             }
         };
         Ok(Money { amount, currency })
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub(crate) struct Valuable {
     moneys: HashMap<Currency, Money>,
 }
 
+impl Valuable {
+    /// Moneys ordered by their currency code.
+    pub(crate) fn sorted(&self, store: &CurrencyStore) -> Vec<Money> {
+        self.moneys
+            .values()
+            .copied()
+            .sorted_by_key(|money| store.code(money.currency))
+            .collect()
+    }
+
+    pub(crate) fn into_valuable(self, store: &CurrencyStore) -> ValuableEntry<'_> {
+        self.into_iter().map(|money| money.into_money(store)).sum()
+    }
+
+    /// Split into `n` valuables that add up to this one exactly, every
+    /// currency split on its own down to its minor units.
+    pub(crate) fn split(&self, n: usize, store: &CurrencyStore) -> Vec<Valuable> {
+        let mut parts = vec![Valuable::default(); n];
+        for money in self.sorted(store) {
+            let dp = store.minor_units(money.currency);
+            for (part, money) in parts.iter_mut().zip(money.split(n, dp)) {
+                *part += money;
+            }
+        }
+        parts
+    }
+
+    /// Divide every money by `n`, rounding each to its minor units.
+    pub(crate) fn div_round(self, n: usize, store: &CurrencyStore) -> Self {
+        if n == 0 {
+            return self;
+        }
+        self.into_iter()
+            .map(|money| {
+                let dp = store.minor_units(money.currency);
+                let amount = (money.amount / Decimal::from(n))
+                    .round_dp_with_strategy(dp, RoundingStrategy::MidpointNearestEven);
+                Money::new(amount, money.currency)
+            })
+            .sum()
+    }
+}
+
 impl IntoIterator for Valuable {
     type Item = Money;
     type IntoIter = impl Iterator<Item = Self::Item>;
@@ -284,6 +666,14 @@ impl Add<Money> for Valuable {
     }
 }
 
+impl AddAssign<Valuable> for Valuable {
+    fn add_assign(&mut self, rhs: Valuable) {
+        for money in rhs {
+            *self += money;
+        }
+    }
+}
+
 impl Add<Valuable> for Valuable {
     type Output = Self;
     fn add(mut self, rhs: Valuable) -> Self::Output {
@@ -294,6 +684,16 @@ impl Add<Valuable> for Valuable {
     }
 }
 
+impl Sum<Valuable> for Valuable {
+    fn sum<I: Iterator<Item = Valuable>>(iter: I) -> Self {
+        let mut valuable = Self::default();
+        for v in iter {
+            valuable += v;
+        }
+        valuable
+    }
+}
+
 impl Sum<Money> for Valuable {
     fn sum<I: Iterator<Item = Money>>(iter: I) -> Self {
         let mut valuable = Self::default();
@@ -309,6 +709,20 @@ pub(crate) struct ValuableEntry<'a> {
     valuable: HashMap<Currency, MoneyEntry<'a>>,
 }
 
+impl<'a> ValuableEntry<'a> {
+    /// Moneys ordered by their currency code.
+    pub(crate) fn moneys(&self) -> impl Iterator<Item = &MoneyEntry<'a>> {
+        self.valuable.values().sorted_by_key(|money| money.code())
+    }
+
+    /// Currency codes paired with their amounts, ordered by code.
+    pub(crate) fn amounts(&self) -> Vec<(&str, Decimal)> {
+        self.moneys()
+            .map(|money| (money.code(), money.money.amount))
+            .collect()
+    }
+}
+
 impl<'a> AddAssign<MoneyEntry<'a>> for ValuableEntry<'a> {
     fn add_assign(&mut self, rhs: MoneyEntry<'a>) {
         let currency = rhs.money.currency;
@@ -355,7 +769,7 @@ impl Display for ValuableEntry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.valuable.is_empty() {
             true => write!(f, "{}", 0),
-            false => self.valuable.values().format(", ").fmt(f),
+            false => self.moneys().format(", ").fmt(f),
         }
     }
 }
@@ -365,6 +779,144 @@ mod test {
     use super::*;
     use rust_decimal_macros::dec;
 
+    #[test]
+    fn test_iso_4217() {
+        let mut store = CurrencyStore::new();
+        let yen = store.get_by_code("jpy").unwrap();
+        assert_eq!(store.minor_units(yen), 0);
+        assert_eq!(store.name(yen), Some("Yen"));
+        assert_eq!(Money::new(dec!(500), yen).fmt(&store), "500 JPY");
+
+        store.declare("JPY", Some("円"), Some(false));
+        assert_eq!(store.get_by_symbol("円").unwrap(), yen);
+        assert_eq!(Money::new(dec!(500), yen).fmt(&store), "500円");
+        assert_eq!(store.to_string(), "currency JPY 円 suffix");
+
+        let dinar = store.get_by_code("KWD").unwrap();
+        assert_eq!(store.minor_units(dinar), 3);
+        let btc = store.get_by_code("BTC").unwrap();
+        assert_eq!(store.name(btc), None);
+    }
+
+    #[test]
+    fn test_subunit() {
+        let mut store = CurrencyStore::new();
+        let btc = store.get_by_code("BTC").unwrap();
+        assert_eq!(store.minor_units(btc), 8);
+        assert_eq!(Money::new(dec!(0.00000001), btc).fmt(&store), "₿0.00000001");
+
+        store.declare_subunit("BTC", "sat", None).unwrap();
+        assert_eq!(Money::new(dec!(0.0015), btc).fmt(&store), "150000 sat");
+        assert_eq!(Money::new(dec!(-1), btc).fmt(&store), "-100000000 sat");
+
+        let mut builder = MoneyBuilder::default();
+        builder.with_amount(dec!(2.5)).with_code("sat");
+        assert_eq!(
+            builder.into_money(&store).unwrap(),
+            Money::new(dec!(0.000000025), btc)
+        );
+
+        assert!(store.declare_subunit("USD", "BTC", None).is_err());
+        assert!(store.declare_subunit("USD", "sat", None).is_err());
+        assert!(store.declare_subunit("JPY", "sen", None).is_err());
+    }
+
+    #[test]
+    fn test_split_precise() {
+        let money = Money::new(dec!(1), Currency::new());
+        let parts = money.split(3, 18).map(|m| m.amount).collect_vec();
+        assert_eq!(parts.iter().sum::<Decimal>(), dec!(1));
+        assert_eq!(parts[0], dec!(0.333333333333333334));
+    }
+
+    #[test]
+    fn test_valuable_order() {
+        let store = CurrencyStore::new();
+        let code = |code| store.get_by_code(code).unwrap();
+        let valuable: Valuable = ["USD", "EUR", "CNY", "AUD"]
+            .into_iter()
+            .map(|c| Money::new(dec!(1), code(c)))
+            .sum();
+
+        let codes = valuable
+            .sorted(&store)
+            .iter()
+            .map(|m| store.code(m.currency))
+            .collect_vec();
+        assert_eq!(codes, ["AUD", "CNY", "EUR", "USD"]);
+
+        let entry = valuable.into_valuable(&store);
+        assert_eq!(entry.to_string(), "1 AUD, ¥1, €1, $1");
+        assert_eq!(entry.amounts()[0], ("AUD", dec!(1)));
+    }
+
+    #[test]
+    fn test_valuable_split() {
+        let store = CurrencyStore::new();
+        let code = |code| store.get_by_code(code).unwrap();
+        let valuable: Valuable = [
+            Money::new(dec!(10), code("USD")),
+            Money::new(dec!(1000), code("JPY")),
+            Money::new(dec!(1), code("KWD")),
+        ]
+        .into_iter()
+        .sum();
+
+        let parts = valuable.split(3, &store);
+        let amounts = parts
+            .iter()
+            .map(|part| part.clone().into_valuable(&store).to_string())
+            .collect_vec();
+        assert_eq!(
+            amounts,
+            [
+                "334 JPY, 0.334 KWD, $3.34",
+                "333 JPY, 0.333 KWD, $3.33",
+                "333 JPY, 0.333 KWD, $3.33"
+            ]
+        );
+        let total: Valuable = parts.into_iter().sum();
+        assert_eq!(total.sorted(&store), valuable.sorted(&store));
+        assert!(Valuable::default()
+            .split(2, &store)
+            .iter()
+            .all(Zero::is_zero));
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let store = CurrencyStore::new();
+        let usd = store.get_by_code("USD").unwrap();
+        let eur = store.get_by_code("EUR").unwrap();
+        let (a, b) = (Money::new(dec!(10), usd), Money::new(dec!(2.5), usd));
+
+        assert_eq!(a.checked_add(b).unwrap().amount, dec!(12.5));
+        assert_eq!(a.checked_sub(b).unwrap().amount, dec!(7.5));
+        assert!(a > b);
+
+        let c = Money::new(dec!(1), eur);
+        let err = a.checked_add(c).unwrap_err();
+        assert_eq!(
+            err,
+            MoneyError::CurrencyMismatch {
+                left: usd,
+                right: eur
+            }
+        );
+        assert_eq!(a.partial_cmp(&c), None);
+
+        let max = Money::new(Decimal::MAX, usd);
+        assert_eq!(max.checked_add(a).unwrap_err(), MoneyError::Overflow);
+        assert_eq!((-max).checked_sub(a).unwrap_err(), MoneyError::Overflow);
+    }
+
+    #[test]
+    #[should_panic(expected = "different currencies")]
+    fn test_add_assign_mismatch() {
+        let mut a = Money::new(dec!(1), Currency::new());
+        a += Money::new(dec!(1), Currency::new());
+    }
+
     #[test]
     fn test_split() {
         let de = dec!(100.00);
@@ -375,7 +927,6 @@ mod test {
         let money = Money::new(de, Currency::new());
         let moneys: Vec<_> = money.split(n, dp).map(|money| money.amount).collect();
 
-        dbg!(&moneys);
         let sum = moneys.iter().sum::<Decimal>();
         let max = moneys.iter().max().unwrap();
         let min = moneys.iter().min().unwrap();
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    io::Read,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use itertools::Itertools;
+use rust_decimal::Decimal;
+
+use super::RateProvider;
+
+/// Monthly averages of the ECB reference rates, built by `just rates-bundle`.
+#[cfg(feature = "bundled-rates")]
+const BUNDLE: &[u8] = include_bytes!("../../assets/rates-monthly.csv.gz");
+
+/// Monthly average rates of the major currencies, so reports convert them
+/// without the network. A day takes the average of its month, or of the
+/// latest month before it the bundle has, with a warning the first time a
+/// day is past the newest month.
+pub(crate) struct BundledRates {
+    /// Units of each currency one euro bought, by the first day of the month.
+    per_eur: HashMap<String, Vec<(NaiveDate, Decimal)>>,
+    warned: AtomicBool,
+}
+
+impl BundledRates {
+    /// The bundle built into coinjar.
+    #[cfg(feature = "bundled-rates")]
+    pub(crate) fn builtin() -> Result<Self> {
+        Self::from_gz(BUNDLE)
+    }
+
+    /// Rates of a gzipped csv of `month,code,per_eur` lines, like
+    /// `2024-01,USD,1.0905`, under a header.
+    pub(crate) fn from_gz(bytes: &[u8]) -> Result<Self> {
+        let mut csv = String::new();
+        GzDecoder::new(bytes)
+            .read_to_string(&mut csv)
+            .context("invalid rates bundle")?;
+        let mut per_eur: HashMap<String, Vec<(NaiveDate, Decimal)>> = HashMap::new();
+        for (n, line) in csv.lines().enumerate().skip(1) {
+            let row: Result<()> = try {
+                let (month, code, rate) = line
+                    .split(',')
+                    .map(str::trim)
+                    .collect_tuple()
+                    .ok_or_else(|| anyhow!("expected month,code,per_eur"))?;
+                let month = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+                    .map_err(anyhow::Error::from)?;
+                let rate = rate.parse().map_err(anyhow::Error::from)?;
+                per_eur
+                    .entry(code.to_string())
+                    .or_default()
+                    .push((month, rate));
+            };
+            row.with_context(|| format!("rates bundle:{}: invalid rate {}", n + 1, line))?;
+        }
+        if per_eur.is_empty() {
+            bail!("rates bundle holds no rates, rebuild it with `just rates-bundle`");
+        }
+        per_eur.values_mut().for_each(|months| months.sort());
+        Ok(Self {
+            per_eur,
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    /// The newest month of the bundle, if `date` is past it.
+    fn outdated(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let newest = self
+            .per_eur
+            .values()
+            .filter_map(|months| months.last())
+            .map(|(month, _)| *month)
+            .max()?;
+        (date.with_day(1).unwrap() > newest).then_some(newest)
+    }
+
+    /// Units of `code` one euro bought on average in the month of `date`.
+    fn per_eur(&self, code: &str, date: NaiveDate) -> Option<Decimal> {
+        if code == "EUR" {
+            return Some(Decimal::ONE);
+        }
+        let month = date.with_day(1).unwrap();
+        let months = self.per_eur.get(code)?;
+        let at = months.partition_point(|(m, _)| *m <= month);
+        months[..at].last().map(|(_, rate)| *rate)
+    }
+}
+
+impl RateProvider for BundledRates {
+    fn name(&self) -> &str {
+        "bundled"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if let Some(newest) = self.outdated(date) {
+            if !self.warned.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "{}: bundled rates end in {}, older than {}",
+                    "warning".yellow().bold(),
+                    newest.format("%Y-%m"),
+                    date
+                );
+            }
+        }
+        let rate = |code: &str| {
+            self.per_eur(code, date)
+                .filter(|rate| !rate.is_zero())
+                .ok_or_else(|| anyhow!("no monthly rate of {} by {}", code, date))
+        };
+        Ok(rate(&to)? / rate(&from)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    const CSV: &str = "month,code,per_eur
+2024-01,USD,1.1
+2024-01,JPY,160
+2024-02,USD,1.08";
+
+    #[test]
+    fn test_bundle() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(CSV.as_bytes()).unwrap();
+        let bundle = BundledRates::from_gz(&gz.finish().unwrap()).unwrap();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        assert_eq!(bundle.rate("eur", "usd", date(1, 20)).unwrap(), dec!(1.1));
+        assert_eq!(
+            bundle.rate("USD", "EUR", date(2, 5)).unwrap(),
+            dec!(1) / dec!(1.08)
+        );
+        assert_eq!(bundle.outdated(date(2, 29)), None);
+        assert_eq!(bundle.outdated(date(3, 1)), Some(date(2, 1)));
+        // the latest month known stands in for the months after it
+        let yen = bundle.rate("USD", "JPY", date(6, 1)).unwrap();
+        assert_eq!(yen, dec!(160) / dec!(1.08));
+        assert!(bundle
+            .rate("USD", "EUR", date(1, 1) - chrono::Days::new(1))
+            .is_err());
+        assert!(bundle.rate("USD", "THB", date(1, 20)).is_err());
+
+        assert!(BundledRates::from_gz(b"not gzip").is_err());
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(b"month,code,per_eur\n").unwrap();
+        assert!(BundledRates::from_gz(&gz.finish().unwrap()).is_err());
+    }
+
+    #[cfg(feature = "bundled-rates")]
+    #[test]
+    fn test_builtin() {
+        let bundle = BundledRates::builtin().unwrap_or_else(|e| panic!("{:#}", e));
+        // the ECB's euro averaged about 1.09 dollars in January 2024
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let usd = bundle.rate("EUR", "USD", date).unwrap();
+        assert!(dec!(1.08) < usd && usd < dec!(1.10), "{}", usd);
+    }
+}
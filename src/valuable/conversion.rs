@@ -0,0 +1,167 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use chrono::NaiveDate;
+use itertools::Itertools;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::error::CoinError;
+
+use super::{Money, MoneyEntry, RateProvider};
+
+/// Exchange rates recorded at given dates, where one unit of the first code
+/// buys `rate` units of the second.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ExchangeBook {
+    rates: BTreeMap<(String, String), BTreeMap<NaiveDate, Decimal>>,
+}
+
+impl ExchangeBook {
+    pub(crate) fn insert(&mut self, date: NaiveDate, from: &str, to: &str, rate: Decimal) {
+        self.rates
+            .entry((from.to_uppercase(), to.to_uppercase()))
+            .or_default()
+            .insert(date, rate);
+    }
+
+    /// Rate from `from` to `to` on `date`. Days without a recorded rate, like
+    /// weekends, are interpolated between the surrounding rates, and the last
+    /// known rate carries over to later days.
+    pub(crate) fn get(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal, CoinError> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Ok(Decimal::ONE);
+        }
+
+        let quote = |from: &str, to: &str| {
+            self.rates
+                .get(&(from.to_string(), to.to_string()))
+                .and_then(|rates| quote(rates, date))
+        };
+        match (quote(&from, &to), quote(&to, &from)) {
+            (Some(rate), _) => Ok(rate),
+            (None, Some(rate)) if !rate.is_zero() => Ok(Decimal::ONE / rate),
+            _ => Err(CoinError::RateUnavailable {
+                from,
+                to,
+                date,
+                reason: None,
+            }),
+        }
+    }
+}
+
+fn quote(rates: &BTreeMap<NaiveDate, Decimal>, date: NaiveDate) -> Option<Decimal> {
+    let before = rates.range(..=date).next_back();
+    let after = rates.range(date..).next();
+    match (before, after) {
+        (Some((d0, r0)), Some((d1, r1))) if d0 != d1 => {
+            let elapsed = Decimal::from((date - *d0).num_days());
+            let span = Decimal::from((*d1 - *d0).num_days());
+            Some(r0 + (r1 - r0) * elapsed / span)
+        }
+        (Some((_, rate)), _) => Some(*rate),
+        (None, _) => None,
+    }
+}
+
+impl Display for ExchangeBook {
+    /// Writes one `rate` directive per recorded rate.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.rates
+            .iter()
+            .flat_map(|((from, to), rates)| {
+                rates
+                    .iter()
+                    .map(move |(date, rate)| format!("rate {} {} {} {}", date, from, to, rate))
+            })
+            .join("\n")
+            .fmt(f)
+    }
+}
+
+impl<'a> MoneyEntry<'a> {
+    /// Convert to the currency `to` at the rate `rates` give for `date`,
+    /// rounded to the minor units of `to`.
+    pub(crate) fn convert_to(
+        &self,
+        to: &str,
+        date: NaiveDate,
+        rates: &impl RateProvider,
+    ) -> Result<MoneyEntry<'a>, CoinError> {
+        let currency = self
+            .store
+            .get_by_code(to)
+            .ok_or_else(|| CoinError::UnknownCurrency(to.to_string()))?;
+        let from = self.store.code(self.money.currency);
+        let rate = rates
+            .rate(from, to, date)
+            .map_err(|e| match e.downcast::<CoinError>() {
+                Ok(e) => e,
+                Err(e) => CoinError::RateUnavailable {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    date,
+                    reason: Some(format!("{:#}", e)),
+                },
+            })?;
+        let amount = self
+            .money
+            .amount
+            .checked_mul(rate)
+            .ok_or_else(|| format!("{} is too large to convert to {}", self, to))?
+            .round_dp_with_strategy(
+                self.store.minor_units(currency),
+                RoundingStrategy::MidpointNearestEven,
+            );
+        Ok(Money::new(amount, currency).into_money(self.store))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::valuable::CurrencyStore;
+    use rust_decimal_macros::dec;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_historical_rate() {
+        let mut book = ExchangeBook::default();
+        book.insert(date("2024-01-05"), "USD", "JPY", dec!(140));
+        book.insert(date("2024-01-08"), "USD", "JPY", dec!(146));
+
+        assert_eq!(
+            book.get("USD", "JPY", date("2024-01-05")).unwrap(),
+            dec!(140)
+        );
+        // the weekend lies between friday's and monday's rates
+        assert_eq!(
+            book.get("USD", "JPY", date("2024-01-06")).unwrap(),
+            dec!(142)
+        );
+        assert_eq!(
+            book.get("usd", "jpy", date("2024-02-01")).unwrap(),
+            dec!(146)
+        );
+        assert!(book.get("USD", "JPY", date("2024-01-01")).is_err());
+        let inverse = book.get("JPY", "USD", date("2024-01-08")).unwrap();
+        assert_eq!((inverse * dec!(146)).round_dp(10), dec!(1));
+        assert_eq!(book.get("EUR", "EUR", date("2024-01-01")).unwrap(), dec!(1));
+    }
+
+    #[test]
+    fn test_convert_to() {
+        let store = CurrencyStore::new();
+        let mut book = ExchangeBook::default();
+        book.insert(date("2024-01-05"), "USD", "JPY", dec!(140.55));
+
+        let usd = store.get_by_code("USD").unwrap();
+        let money = Money::new(dec!(10.5), usd).into_money(&store);
+        let yen = money.convert_to("JPY", date("2024-01-07"), &book).unwrap();
+        assert_eq!(yen.to_string(), "1476 JPY");
+        assert!(money.convert_to("EUR", date("2024-01-07"), &book).is_err());
+    }
+}
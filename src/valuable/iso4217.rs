@@ -0,0 +1,203 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+/// A currency as listed in ISO 4217.
+#[derive(Debug)]
+pub(super) struct IsoCurrency {
+    pub(super) code: &'static str,
+    /// Digits after the decimal point, `None` for units like precious metals
+    /// that have no minor unit.
+    pub(super) minor_units: Option<u32>,
+    pub(super) name: &'static str,
+}
+
+/// Every active ISO 4217 currency by code, built on first use.
+pub(super) static ISO_4217: LazyLock<HashMap<&'static str, IsoCurrency>> = LazyLock::new(|| {
+    TABLE
+        .iter()
+        .map(|&(code, minor_units, name)| {
+            let currency = IsoCurrency {
+                code,
+                minor_units,
+                name,
+            };
+            (code, currency)
+        })
+        .collect()
+});
+
+#[rustfmt::skip]
+const TABLE: &[(&str, Option<u32>, &str)] = &[
+    ("AED", Some(2), "UAE Dirham"),
+    ("AFN", Some(2), "Afghani"),
+    ("ALL", Some(2), "Lek"),
+    ("AMD", Some(2), "Armenian Dram"),
+    ("ANG", Some(2), "Netherlands Antillean Guilder"),
+    ("AOA", Some(2), "Kwanza"),
+    ("ARS", Some(2), "Argentine Peso"),
+    ("AUD", Some(2), "Australian Dollar"),
+    ("AWG", Some(2), "Aruban Florin"),
+    ("AZN", Some(2), "Azerbaijan Manat"),
+    ("BAM", Some(2), "Convertible Mark"),
+    ("BBD", Some(2), "Barbados Dollar"),
+    ("BDT", Some(2), "Taka"),
+    ("BGN", Some(2), "Bulgarian Lev"),
+    ("BHD", Some(3), "Bahraini Dinar"),
+    ("BIF", Some(0), "Burundi Franc"),
+    ("BMD", Some(2), "Bermudian Dollar"),
+    ("BND", Some(2), "Brunei Dollar"),
+    ("BOB", Some(2), "Boliviano"),
+    ("BOV", Some(2), "Mvdol"),
+    ("BRL", Some(2), "Brazilian Real"),
+    ("BSD", Some(2), "Bahamian Dollar"),
+    ("BTN", Some(2), "Ngultrum"),
+    ("BWP", Some(2), "Pula"),
+    ("BYN", Some(2), "Belarusian Ruble"),
+    ("BZD", Some(2), "Belize Dollar"),
+    ("CAD", Some(2), "Canadian Dollar"),
+    ("CDF", Some(2), "Congolese Franc"),
+    ("CHE", Some(2), "WIR Euro"),
+    ("CHF", Some(2), "Swiss Franc"),
+    ("CHW", Some(2), "WIR Franc"),
+    ("CLF", Some(4), "Unidad de Fomento"),
+    ("CLP", Some(0), "Chilean Peso"),
+    ("CNY", Some(2), "Yuan Renminbi"),
+    ("COP", Some(2), "Colombian Peso"),
+    ("COU", Some(2), "Unidad de Valor Real"),
+    ("CRC", Some(2), "Costa Rican Colon"),
+    ("CUP", Some(2), "Cuban Peso"),
+    ("CVE", Some(2), "Cabo Verde Escudo"),
+    ("CZK", Some(2), "Czech Koruna"),
+    ("DJF", Some(0), "Djibouti Franc"),
+    ("DKK", Some(2), "Danish Krone"),
+    ("DOP", Some(2), "Dominican Peso"),
+    ("DZD", Some(2), "Algerian Dinar"),
+    ("EGP", Some(2), "Egyptian Pound"),
+    ("ERN", Some(2), "Nakfa"),
+    ("ETB", Some(2), "Ethiopian Birr"),
+    ("EUR", Some(2), "Euro"),
+    ("FJD", Some(2), "Fiji Dollar"),
+    ("FKP", Some(2), "Falkland Islands Pound"),
+    ("GBP", Some(2), "Pound Sterling"),
+    ("GEL", Some(2), "Lari"),
+    ("GHS", Some(2), "Ghana Cedi"),
+    ("GIP", Some(2), "Gibraltar Pound"),
+    ("GMD", Some(2), "Dalasi"),
+    ("GNF", Some(0), "Guinean Franc"),
+    ("GTQ", Some(2), "Quetzal"),
+    ("GYD", Some(2), "Guyana Dollar"),
+    ("HKD", Some(2), "Hong Kong Dollar"),
+    ("HNL", Some(2), "Lempira"),
+    ("HTG", Some(2), "Gourde"),
+    ("HUF", Some(2), "Forint"),
+    ("IDR", Some(2), "Rupiah"),
+    ("ILS", Some(2), "New Israeli Sheqel"),
+    ("INR", Some(2), "Indian Rupee"),
+    ("IQD", Some(3), "Iraqi Dinar"),
+    ("IRR", Some(2), "Iranian Rial"),
+    ("ISK", Some(0), "Iceland Krona"),
+    ("JMD", Some(2), "Jamaican Dollar"),
+    ("JOD", Some(3), "Jordanian Dinar"),
+    ("JPY", Some(0), "Yen"),
+    ("KES", Some(2), "Kenyan Shilling"),
+    ("KGS", Some(2), "Som"),
+    ("KHR", Some(2), "Riel"),
+    ("KMF", Some(0), "Comorian Franc"),
+    ("KPW", Some(2), "North Korean Won"),
+    ("KRW", Some(0), "Won"),
+    ("KWD", Some(3), "Kuwaiti Dinar"),
+    ("KYD", Some(2), "Cayman Islands Dollar"),
+    ("KZT", Some(2), "Tenge"),
+    ("LAK", Some(2), "Lao Kip"),
+    ("LBP", Some(2), "Lebanese Pound"),
+    ("LKR", Some(2), "Sri Lanka Rupee"),
+    ("LRD", Some(2), "Liberian Dollar"),
+    ("LSL", Some(2), "Loti"),
+    ("LYD", Some(3), "Libyan Dinar"),
+    ("MAD", Some(2), "Moroccan Dirham"),
+    ("MDL", Some(2), "Moldovan Leu"),
+    ("MGA", Some(2), "Malagasy Ariary"),
+    ("MKD", Some(2), "Denar"),
+    ("MMK", Some(2), "Kyat"),
+    ("MNT", Some(2), "Tugrik"),
+    ("MOP", Some(2), "Pataca"),
+    ("MRU", Some(2), "Ouguiya"),
+    ("MUR", Some(2), "Mauritius Rupee"),
+    ("MVR", Some(2), "Rufiyaa"),
+    ("MWK", Some(2), "Malawi Kwacha"),
+    ("MXN", Some(2), "Mexican Peso"),
+    ("MXV", Some(2), "Mexican Unidad de Inversion (UDI)"),
+    ("MYR", Some(2), "Malaysian Ringgit"),
+    ("MZN", Some(2), "Mozambique Metical"),
+    ("NAD", Some(2), "Namibia Dollar"),
+    ("NGN", Some(2), "Naira"),
+    ("NIO", Some(2), "Cordoba Oro"),
+    ("NOK", Some(2), "Norwegian Krone"),
+    ("NPR", Some(2), "Nepalese Rupee"),
+    ("NZD", Some(2), "New Zealand Dollar"),
+    ("OMR", Some(3), "Rial Omani"),
+    ("PAB", Some(2), "Balboa"),
+    ("PEN", Some(2), "Sol"),
+    ("PGK", Some(2), "Kina"),
+    ("PHP", Some(2), "Philippine Peso"),
+    ("PKR", Some(2), "Pakistan Rupee"),
+    ("PLN", Some(2), "Zloty"),
+    ("PYG", Some(0), "Guarani"),
+    ("QAR", Some(2), "Qatari Rial"),
+    ("RON", Some(2), "Romanian Leu"),
+    ("RSD", Some(2), "Serbian Dinar"),
+    ("RUB", Some(2), "Russian Ruble"),
+    ("RWF", Some(0), "Rwanda Franc"),
+    ("SAR", Some(2), "Saudi Riyal"),
+    ("SBD", Some(2), "Solomon Islands Dollar"),
+    ("SCR", Some(2), "Seychelles Rupee"),
+    ("SDG", Some(2), "Sudanese Pound"),
+    ("SEK", Some(2), "Swedish Krona"),
+    ("SGD", Some(2), "Singapore Dollar"),
+    ("SHP", Some(2), "Saint Helena Pound"),
+    ("SLE", Some(2), "Leone"),
+    ("SOS", Some(2), "Somali Shilling"),
+    ("SRD", Some(2), "Surinam Dollar"),
+    ("SSP", Some(2), "South Sudanese Pound"),
+    ("STN", Some(2), "Dobra"),
+    ("SVC", Some(2), "El Salvador Colon"),
+    ("SYP", Some(2), "Syrian Pound"),
+    ("SZL", Some(2), "Lilangeni"),
+    ("THB", Some(2), "Baht"),
+    ("TJS", Some(2), "Somoni"),
+    ("TMT", Some(2), "Turkmenistan New Manat"),
+    ("TND", Some(3), "Tunisian Dinar"),
+    ("TOP", Some(2), "Pa'anga"),
+    ("TRY", Some(2), "Turkish Lira"),
+    ("TTD", Some(2), "Trinidad and Tobago Dollar"),
+    ("TWD", Some(2), "New Taiwan Dollar"),
+    ("TZS", Some(2), "Tanzanian Shilling"),
+    ("UAH", Some(2), "Hryvnia"),
+    ("UGX", Some(0), "Uganda Shilling"),
+    ("USD", Some(2), "US Dollar"),
+    ("USN", Some(2), "US Dollar (Next day)"),
+    ("UYI", Some(0), "Uruguay Peso en Unidades Indexadas (UI)"),
+    ("UYU", Some(2), "Peso Uruguayo"),
+    ("UYW", Some(4), "Unidad Previsional"),
+    ("UZS", Some(2), "Uzbekistan Sum"),
+    ("VED", Some(2), "Bolívar Soberano"),
+    ("VES", Some(2), "Bolívar Soberano"),
+    ("VND", Some(0), "Dong"),
+    ("VUV", Some(0), "Vatu"),
+    ("WST", Some(2), "Tala"),
+    ("XAF", Some(0), "CFA Franc BEAC"),
+    ("XAG", None, "Silver"),
+    ("XAU", None, "Gold"),
+    ("XCD", Some(2), "East Caribbean Dollar"),
+    ("XCG", Some(2), "Caribbean Guilder"),
+    ("XDR", None, "SDR (Special Drawing Right)"),
+    ("XOF", Some(0), "CFA Franc BCEAO"),
+    ("XPD", None, "Palladium"),
+    ("XPF", Some(0), "CFP Franc"),
+    ("XPT", None, "Platinum"),
+    ("XSU", None, "Sucre"),
+    ("XUA", None, "ADB Unit of Account"),
+    ("YER", Some(2), "Yemeni Rial"),
+    ("ZAR", Some(2), "Rand"),
+    ("ZMW", Some(2), "Zambian Kwacha"),
+    ("ZWG", Some(2), "Zimbabwe Gold"),
+];
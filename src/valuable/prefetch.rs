@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use tracing::warn;
+
+use super::{ProviderChain, RateProvider};
+
+type Key = (String, String, NaiveDate);
+
+/// How long a failed fetch is remembered before a lookup asks again.
+const RETRY_AFTER: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+struct CacheState {
+    rates: HashMap<Key, Decimal>,
+    /// Why the last fetch of a rate failed, and when.
+    failed: HashMap<Key, (String, Instant)>,
+    /// When each rate was last queued.
+    requested: HashMap<Key, Instant>,
+    /// Rates that failed and were answered with the last one known, with the
+    /// day of that one, until the next `take_stale`.
+    stale: BTreeSet<(Key, NaiveDate)>,
+}
+
+impl CacheState {
+    /// Latest rate from `from` to `to` fetched for a day before `date`.
+    fn last_known(&self, from: &str, to: &str, date: NaiveDate) -> Option<(NaiveDate, Decimal)> {
+        self.rates
+            .iter()
+            .filter(|((_, _, day), _)| *day < date)
+            .filter_map(
+                |((f, t, day), rate)| match (f == from && t == to, f == to && t == from) {
+                    (true, _) => Some((*day, *rate)),
+                    (_, true) if !rate.is_zero() => Some((*day, Decimal::ONE / rate)),
+                    _ => None,
+                },
+            )
+            .max_by_key(|(day, _)| *day)
+    }
+}
+
+/// Rates fetched by a background thread, so lookups never wait on the
+/// network. A rate missing from the cache is queued for fetching and the
+/// lookup fails until it arrives. A rate that cannot be fetched falls back
+/// to the last one known, which `take_stale` tells about, and is fetched
+/// again on the next `prefetch` of it or a lookup after `RETRY_AFTER`.
+#[derive(Clone)]
+pub(crate) struct RateCache {
+    state: Arc<Mutex<CacheState>>,
+    queue: Sender<Key>,
+}
+
+impl RateCache {
+    /// Start the thread fetching queued rates from `providers`.
+    pub(crate) fn spawn(providers: ProviderChain<'static>) -> Self {
+        let state = Arc::new(Mutex::new(CacheState::default()));
+        let (queue, requests) = mpsc::channel::<Key>();
+
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            // ends once every handle to the cache is dropped
+            for key in requests {
+                let (from, to, date) = &key;
+                let rate = providers.rate(from, to, *date);
+                let mut state = shared.lock().unwrap();
+                match rate {
+                    Ok(rate) => {
+                        state.failed.remove(&key);
+                        state.rates.insert(key, rate);
+                    }
+                    Err(e) => {
+                        warn!(from, to, %date, "rate fetch failed: {:#}", e);
+                        state
+                            .failed
+                            .insert(key, (format!("{:#}", e), Instant::now()));
+                    }
+                }
+            }
+        });
+
+        Self { state, queue }
+    }
+
+    /// Warnings about the stale rates answered since the last call.
+    pub(crate) fn take_stale(&self) -> Vec<String> {
+        let stale = std::mem::take(&mut self.state.lock().unwrap().stale);
+        stale
+            .into_iter()
+            .map(|((from, to, date), day)| {
+                format!(
+                    "no rate from {} to {} on {}, used the last known one of {}",
+                    from, to, date, day
+                )
+            })
+            .collect()
+    }
+
+    /// Queue the rate from `from` to `to` on `date` unless it was already
+    /// asked for in either direction, or again if that failed.
+    pub(crate) fn prefetch(&self, from: &str, to: &str, date: NaiveDate) {
+        self.request(from, to, date, Duration::ZERO);
+    }
+
+    /// Queue the rate unless it was asked for in either direction and is
+    /// still being fetched, was fetched, or failed less than `retry_after`
+    /// ago.
+    fn request(&self, from: &str, to: &str, date: NaiveDate, retry_after: Duration) {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let inverse = (to.clone(), from.clone(), date);
+        let key = (from, to, date);
+        let asked = [&key, &inverse]
+            .into_iter()
+            .find(|key| state.requested.contains_key(*key))
+            .cloned();
+        let key = match asked {
+            None => key,
+            Some(key) => {
+                let Some((_, failed)) = state.failed.get(&key) else {
+                    return;
+                };
+                if *failed <= state.requested[&key] || failed.elapsed() < retry_after {
+                    return;
+                }
+                key
+            }
+        };
+        state.requested.insert(key.clone(), Instant::now());
+        // the thread only stops when the cache is gone, so sending cannot fail
+        self.queue.send(key).ok();
+    }
+}
+
+impl RateProvider for RateCache {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        if from == to {
+            return Ok(Decimal::ONE);
+        }
+
+        let key = (from.clone(), to.clone(), date);
+        let inverse = (to.clone(), from.clone(), date);
+        let failed = {
+            let mut state = self.state.lock().unwrap();
+            if let Some(rate) = state.rates.get(&key) {
+                return Ok(*rate);
+            }
+            if let Some(rate) = state.rates.get(&inverse).filter(|r| !r.is_zero()) {
+                return Ok(Decimal::ONE / rate);
+            }
+            let failed = state.failed.get(&key).or(state.failed.get(&inverse));
+            failed
+                .map(|(e, _)| e.clone())
+                .map(|e| match state.last_known(&from, &to, date) {
+                    Some((day, rate)) => {
+                        state.stale.insert((key, day));
+                        Ok(rate)
+                    }
+                    None => Err(anyhow!("{}", e)),
+                })
+        };
+
+        self.request(&from, &to, date, RETRY_AFTER);
+        if let Some(failed) = failed {
+            return failed;
+        }
+        bail!(
+            "rate from {} to {} on {} is being fetched, try again shortly",
+            from,
+            to,
+            date
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+    use crate::valuable::ExchangeBook;
+    use rust_decimal_macros::dec;
+
+    fn wait_for(cache: &RateCache, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let start = Instant::now();
+        loop {
+            let rate = cache.rate(from, to, date);
+            let pending = matches!(&rate, Err(e) if e.to_string().contains("being fetched"));
+            if !pending || start.elapsed() > Duration::from_secs(5) {
+                return rate;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let mut book = ExchangeBook::default();
+        book.insert(date, "USD", "JPY", dec!(144));
+        let cache = RateCache::spawn(ProviderChain::default().with(book));
+
+        cache.prefetch("usd", "jpy", date);
+        assert_eq!(wait_for(&cache, "USD", "JPY", date).unwrap(), dec!(144));
+        let inverse = wait_for(&cache, "JPY", "USD", date).unwrap();
+        assert_eq!((inverse * dec!(144)).round_dp(10), dec!(1));
+
+        // misses are fetched in the background too, failures are remembered
+        let err = cache.rate("USD", "EUR", date).unwrap_err();
+        assert!(err.to_string().contains("being fetched"));
+        let err = wait_for(&cache, "USD", "EUR", date).unwrap_err();
+        assert!(err.to_string().contains("no exchange rate"));
+        assert!(cache.take_stale().is_empty());
+    }
+
+    /// Answers only for the first day it is asked about.
+    struct OneDay(Mutex<Option<NaiveDate>>);
+
+    impl RateProvider for OneDay {
+        fn name(&self) -> &str {
+            "one-day"
+        }
+
+        fn rate(&self, _: &str, _: &str, date: NaiveDate) -> Result<Decimal> {
+            let mut day = self.0.lock().unwrap();
+            match *day.get_or_insert(date) == date {
+                true => Ok(dec!(144)),
+                false => bail!("down"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_stale() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let cache = RateCache::spawn(ProviderChain::default().with(OneDay(Mutex::new(None))));
+        assert_eq!(wait_for(&cache, "USD", "JPY", date(5)).unwrap(), dec!(144));
+        // nothing known before the first day
+        assert!(wait_for(&cache, "USD", "JPY", date(4)).is_err());
+        let inverse = wait_for(&cache, "JPY", "USD", date(8)).unwrap();
+        assert_eq!((inverse * dec!(144)).round_dp(10), dec!(1));
+        assert_eq!(
+            cache.take_stale(),
+            ["no rate from JPY to USD on 2024-01-08, used the last known one of 2024-01-05"]
+        );
+        assert!(cache.take_stale().is_empty());
+    }
+
+    /// Fails the first time it is asked.
+    struct Flaky(Mutex<bool>);
+
+    impl RateProvider for Flaky {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn rate(&self, _: &str, _: &str, _: NaiveDate) -> Result<Decimal> {
+            match std::mem::replace(&mut *self.0.lock().unwrap(), true) {
+                true => Ok(dec!(144)),
+                false => bail!("down"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+        let cache = RateCache::spawn(ProviderChain::default().with(Flaky(Mutex::new(false))));
+        assert!(wait_for(&cache, "USD", "JPY", date).is_err());
+        // a lookup does not ask again right away, a prefetch does
+        assert!(cache
+            .rate("USD", "JPY", date)
+            .unwrap_err()
+            .to_string()
+            .contains("down"));
+        cache.prefetch("JPY", "USD", date);
+        let start = Instant::now();
+        while cache.rate("USD", "JPY", date).is_err() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(cache.rate("USD", "JPY", date).unwrap(), dec!(144));
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use reqwest::{
+    header::{CACHE_CONTROL, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    StatusCode,
+};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use tracing::{debug, debug_span, warn};
+
+use crate::error::CoinError;
+
+use super::ExchangeBook;
+
+/// A source of exchange rates, where one unit of `from` buys the returned
+/// rate of `to` on `date`.
+pub(crate) trait RateProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal>;
+}
+
+impl<P: RateProvider + ?Sized> RateProvider for &P {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        (**self).rate(from, to, date)
+    }
+}
+
+impl RateProvider for ExchangeBook {
+    fn name(&self) -> &str {
+        "journal"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        Ok(self.get(from, to, date)?)
+    }
+}
+
+/// Asks each provider in turn and answers with the first rate found.
+#[derive(Default)]
+pub(crate) struct ProviderChain<'a> {
+    providers: Vec<Box<dyn RateProvider + 'a>>,
+}
+
+impl<'a> ProviderChain<'a> {
+    pub(crate) fn with(mut self, provider: impl RateProvider + 'a) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    pub(crate) fn push(&mut self, provider: Box<dyn RateProvider + 'a>) {
+        self.providers.push(provider);
+    }
+}
+
+impl RateProvider for ProviderChain<'_> {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let mut errors = Vec::new();
+        for provider in &self.providers {
+            match provider.rate(from, to, date) {
+                Ok(rate) => return Ok(rate),
+                Err(e) => {
+                    debug!(provider = provider.name(), from, to, %date, "no rate: {:#}", e);
+                    errors.push(format!("{}: {:#}", provider.name(), e))
+                }
+            }
+        }
+        match errors.is_empty() {
+            true => Err(CoinError::RateUnavailable {
+                from: from.to_string(),
+                to: to.to_string(),
+                date,
+                reason: None,
+            }
+            .into()),
+            false => bail!("{}", errors.join("; ")),
+        }
+    }
+}
+
+/// Waits between calls so a provider is asked at most once per `interval`.
+pub(crate) struct RateLimited<P> {
+    inner: P,
+    interval: Duration,
+    last: Mutex<Option<Instant>>,
+}
+
+impl<P> RateLimited<P> {
+    pub(crate) fn new(inner: P, interval: Duration) -> Self {
+        Self {
+            inner,
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+}
+
+impl<P: RateProvider> RateProvider for RateLimited<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        {
+            let mut last = self.last.lock().unwrap();
+            if let Some(wait) = last.and_then(|last| self.interval.checked_sub(last.elapsed())) {
+                std::thread::sleep(wait);
+            }
+            *last = Some(Instant::now());
+        }
+        self.inner.rate(from, to, date)
+    }
+}
+
+/// Rates read from a local file with one `date,from,to,rate` line each.
+pub(crate) struct CsvRates {
+    name: String,
+    book: ExchangeBook,
+}
+
+impl CsvRates {
+    pub(crate) fn from_file(path: &str) -> Result<Self> {
+        let input = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to open rates file: {}", path))?;
+        let mut book = ExchangeBook::default();
+        for (n, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let row: Result<()> = try {
+                let (date, from, to, rate) = line
+                    .split(',')
+                    .map(str::trim)
+                    .collect_tuple()
+                    .ok_or_else(|| anyhow!("expected date,from,to,rate"))?;
+                book.insert(
+                    date.parse().map_err(anyhow::Error::from)?,
+                    from,
+                    to,
+                    rate.parse().map_err(anyhow::Error::from)?,
+                );
+            };
+            row.with_context(|| format!("{}:{}: invalid rate {}", path, n + 1, line))?;
+        }
+        Ok(Self {
+            name: path.to_string(),
+            book,
+        })
+    }
+}
+
+impl RateProvider for CsvRates {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        Ok(self.book.get(from, to, date)?)
+    }
+}
+
+/// How the network providers ask: how long a request may take and how often
+/// a failed one is tried again, waiting twice as long each time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct HttpPolicy {
+    pub(crate) timeout: Duration,
+    pub(crate) retries: u32,
+    pub(crate) backoff: Duration,
+}
+
+impl Default for HttpPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Why an attempt failed, and whether another one may do better.
+enum Failure {
+    Transient(anyhow::Error),
+    Permanent(anyhow::Error),
+}
+
+/// Run `attempt` until it succeeds, fails for good or has been retried
+/// `policy.retries` times.
+fn with_backoff<T>(
+    policy: HttpPolicy,
+    mut attempt: impl FnMut() -> Result<T, Failure>,
+) -> Result<T> {
+    let mut wait = policy.backoff;
+    for n in 0.. {
+        match attempt() {
+            Ok(value) => return Ok(value),
+            Err(Failure::Permanent(e)) => return Err(e),
+            Err(Failure::Transient(e)) if n >= policy.retries => return Err(e),
+            Err(Failure::Transient(e)) => {
+                warn!(retry = n + 1, ?wait, "{:#}", e);
+                std::thread::sleep(wait);
+                wait *= 2;
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// A response kept with what its caching headers say about it.
+#[derive(Clone)]
+struct Cached {
+    body: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Until when it may be used without asking again.
+    fresh_until: Option<Instant>,
+}
+
+/// `max-age` of a `Cache-Control` header, none if it forbids reusing the
+/// response unchecked.
+fn max_age(cache_control: &str) -> Option<Duration> {
+    let directives = cache_control.split(',').map(str::trim).collect_vec();
+    if directives
+        .iter()
+        .any(|d| *d == "no-store" || *d == "no-cache")
+    {
+        return None;
+    }
+    directives
+        .iter()
+        .find_map(|d| d.strip_prefix("max-age="))
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// A client for rate apis that retries what fails on the way, and keeps
+/// the responses so a url is asked again only once they are stale, then
+/// conditionally.
+pub(crate) struct Http {
+    client: reqwest::blocking::Client,
+    policy: HttpPolicy,
+    cache: Mutex<HashMap<String, Cached>>,
+}
+
+impl Http {
+    pub(crate) fn new(policy: HttpPolicy) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(policy.timeout)
+            .build()?;
+        Ok(Self {
+            client,
+            policy,
+            cache: Mutex::default(),
+        })
+    }
+
+    fn fetch(&self, url: &str) -> Result<String> {
+        let _span = debug_span!("fetch", url).entered();
+        let start = Instant::now();
+        // not locked while asking, so other urls are not held up
+        let cached = self.cache.lock().unwrap().get(url).cloned();
+        let cached = cached.as_ref();
+        if let Some(cached) = cached.filter(|c| c.fresh_until.is_some_and(|t| t > Instant::now())) {
+            debug!("fresh in cache");
+            return Ok(cached.body.clone());
+        }
+        let response = with_backoff(self.policy, || {
+            let mut request = self.client.get(url);
+            if let Some(etag) = cached.and_then(|c| c.etag.as_ref()) {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = cached.and_then(|c| c.last_modified.as_ref()) {
+                request = request.header(IF_MODIFIED_SINCE, modified);
+            }
+            let response = request.send().map_err(|e| {
+                match e.is_timeout() || e.is_connect() || e.is_request() {
+                    true => Failure::Transient(e.into()),
+                    false => Failure::Permanent(e.into()),
+                }
+            })?;
+            let status = response.status();
+            match status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                true => Err(Failure::Transient(anyhow!("{} answered {}", url, status))),
+                false => Ok(response),
+            }
+        })?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let fresh_until = header(CACHE_CONTROL)
+            .as_deref()
+            .and_then(max_age)
+            .map(|age| Instant::now() + age);
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = self.cache.lock().unwrap().get_mut(url) {
+                debug!("not modified");
+                cached.fresh_until = fresh_until;
+                return Ok(cached.body.clone());
+            }
+        }
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        let body = response.error_for_status()?.text()?;
+        debug!(bytes = body.len(), elapsed = ?start.elapsed(), "fetched");
+        self.cache.lock().unwrap().insert(
+            url.to_string(),
+            Cached {
+                body: body.clone(),
+                etag,
+                last_modified,
+                fresh_until,
+            },
+        );
+        Ok(body)
+    }
+}
+
+/// `a / b`, failing where a provider gave a zero rate for `code`.
+fn divide(a: Decimal, b: Decimal, code: &str) -> Result<Decimal> {
+    a.checked_div(b)
+        .ok_or_else(|| anyhow!("cannot convert with a rate of {} for {}", b, code))
+}
+
+fn decimal(value: &Value) -> Result<Decimal> {
+    let s = value.to_string();
+    Decimal::from_str(&s)
+        .or_else(|_| Decimal::from_scientific(&s))
+        .map_err(|_| anyhow!("invalid rate {}", s))
+}
+
+/// Daily rates of the free currency-api served by jsdelivr.
+pub(crate) struct JsDelivr {
+    http: Http,
+}
+
+impl JsDelivr {
+    fn parse(body: &str, from: &str, to: &str) -> Result<Decimal> {
+        let json: Value = serde_json::from_str(body)?;
+        let rate = json
+            .get(from)
+            .and_then(|rates| rates.get(to))
+            .ok_or_else(|| anyhow!("no rate for {}", to.to_uppercase()))?;
+        decimal(rate)
+    }
+}
+
+impl RateProvider for JsDelivr {
+    fn name(&self) -> &str {
+        "jsdelivr"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_lowercase(), to.to_lowercase());
+        let url = format!(
+            "https://cdn.jsdelivr.net/npm/@fawazahmed0/currency-api@{}/v1/currencies/{}.json",
+            date, from
+        );
+        Self::parse(&self.http.fetch(&url)?, &from, &to)
+    }
+}
+
+/// Euro reference rates of the European Central Bank.
+pub(crate) struct Ecb {
+    http: Http,
+}
+
+impl Ecb {
+    /// Latest observation of an ECB csv series.
+    fn parse(body: &str) -> Result<Decimal> {
+        let mut lines = body.lines();
+        let header = lines.next().ok_or_else(|| anyhow!("empty response"))?;
+        let column = header
+            .split(',')
+            .position(|h| h == "OBS_VALUE")
+            .ok_or_else(|| anyhow!("response has no OBS_VALUE"))?;
+        let value = lines
+            .filter_map(|line| line.split(',').nth(column))
+            .next_back()
+            .ok_or_else(|| anyhow!("no observation"))?;
+        Ok(value.parse()?)
+    }
+
+    /// Units of `code` one euro buys, from the last observation up to `date`
+    /// since the ECB publishes no rates on weekends and holidays.
+    fn euro_rate(&self, code: &str, date: NaiveDate) -> Result<Decimal> {
+        if code == "EUR" {
+            return Ok(Decimal::ONE);
+        }
+        let url = format!(
+            "https://data-api.ecb.europa.eu/service/data/EXR/D.{}.EUR.SP00.A?startPeriod={}&endPeriod={}&format=csvdata",
+            code,
+            date - chrono::Duration::days(7),
+            date
+        );
+        Self::parse(&self.http.fetch(&url)?)
+    }
+}
+
+impl RateProvider for Ecb {
+    fn name(&self) -> &str {
+        "ecb"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        let per_eur = self.euro_rate(&from, date)?;
+        divide(self.euro_rate(&to, date)?, per_eur, &from)
+    }
+}
+
+/// Historical cryptocurrency prices from CoinGecko.
+pub(crate) struct CoinGecko {
+    http: Http,
+}
+
+impl CoinGecko {
+    fn coin_id(code: &str) -> Option<&'static str> {
+        match code {
+            "BTC" => Some("bitcoin"),
+            "ETH" => Some("ethereum"),
+            _ => None,
+        }
+    }
+
+    fn parse(body: &str, vs: &str) -> Result<Decimal> {
+        let json: Value = serde_json::from_str(body)?;
+        let price = json
+            .pointer(&format!("/market_data/current_price/{}", vs))
+            .ok_or_else(|| anyhow!("no price in {}", vs.to_uppercase()))?;
+        decimal(price)
+    }
+
+    fn price(&self, coin: &str, vs: &str, date: NaiveDate) -> Result<Decimal> {
+        let url = format!(
+            "https://api.coingecko.com/api/v3/coins/{}/history?date={}&localization=false",
+            coin,
+            date.format("%d-%m-%Y")
+        );
+        Self::parse(&self.http.fetch(&url)?, &vs.to_lowercase())
+    }
+}
+
+impl RateProvider for CoinGecko {
+    fn name(&self) -> &str {
+        "coingecko"
+    }
+
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Result<Decimal> {
+        let (from, to) = (from.to_uppercase(), to.to_uppercase());
+        match (Self::coin_id(&from), Self::coin_id(&to)) {
+            (Some(coin), _) => self.price(coin, &to, date),
+            (None, Some(coin)) => divide(Decimal::ONE, self.price(coin, &from, date)?, &to),
+            (None, None) => bail!("neither {} nor {} is a known coin", from, to),
+        }
+    }
+}
+
+/// A provider as chosen on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RateSource {
+    JsDelivr,
+    Ecb,
+    CoinGecko,
+    Csv(String),
+    /// The monthly averages built in with the `bundled-rates` feature.
+    Bundled,
+}
+
+impl RateSource {
+    pub(crate) fn into_provider(self, policy: HttpPolicy) -> Result<Box<dyn RateProvider>> {
+        // stay well below the request limits of the free apis
+        Ok(match self {
+            RateSource::JsDelivr => Box::new(RateLimited::new(
+                JsDelivr {
+                    http: Http::new(policy)?,
+                },
+                Duration::from_millis(100),
+            )),
+            RateSource::Ecb => {
+                let ecb = Ecb {
+                    http: Http::new(policy)?,
+                };
+                Box::new(RateLimited::new(ecb, Duration::from_millis(500)))
+            }
+            RateSource::CoinGecko => Box::new(RateLimited::new(
+                CoinGecko {
+                    http: Http::new(policy)?,
+                },
+                Duration::from_secs(2),
+            )),
+            RateSource::Csv(path) => Box::new(CsvRates::from_file(&path)?),
+            #[cfg(feature = "bundled-rates")]
+            RateSource::Bundled => Box::new(super::bundle::BundledRates::builtin()?),
+            #[cfg(not(feature = "bundled-rates"))]
+            RateSource::Bundled => bail!("coinjar was built without the bundled-rates feature"),
+        })
+    }
+}
+
+impl FromStr for RateSource {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsdelivr" => Ok(RateSource::JsDelivr),
+            "ecb" => Ok(RateSource::Ecb),
+            "coingecko" => Ok(RateSource::CoinGecko),
+            "bundled" => Ok(RateSource::Bundled),
+            s => match s.strip_prefix("csv:") {
+                Some(path) => Ok(RateSource::Csv(path.to_string())),
+                None => Err(anyhow!(
+                    "invalid rate source {}, expected jsdelivr, ecb, coingecko, bundled or csv:<file>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    struct Fixed(&'static str, Option<Decimal>);
+
+    impl RateProvider for Fixed {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn rate(&self, _: &str, _: &str, _: NaiveDate) -> Result<Decimal> {
+            self.1.ok_or_else(|| anyhow!("unavailable"))
+        }
+    }
+
+    #[test]
+    fn test_chain() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let chain = ProviderChain::default()
+            .with(Fixed("down", None))
+            .with(Fixed("up", Some(dec!(2))));
+        assert_eq!(chain.rate("USD", "EUR", date).unwrap(), dec!(2));
+
+        let chain = ProviderChain::default().with(Fixed("down", None));
+        let err = chain.rate("USD", "EUR", date).unwrap_err();
+        assert_eq!(err.to_string(), "down: unavailable");
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let limited = RateLimited::new(Fixed("up", Some(dec!(1))), Duration::from_millis(50));
+        let start = Instant::now();
+        for _ in 0..3 {
+            limited.rate("USD", "EUR", date).unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = HttpPolicy {
+            timeout: Duration::from_secs(1),
+            retries: 2,
+            backoff: Duration::from_millis(1),
+        };
+        let mut attempts = 0;
+        let flaky = with_backoff(policy, || {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(Failure::Transient(anyhow!("503"))),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(flaky.unwrap(), 3);
+
+        attempts = 0;
+        let down: Result<()> = with_backoff(policy, || {
+            attempts += 1;
+            Err(Failure::Transient(anyhow!("503")))
+        });
+        assert!(down.is_err());
+        assert_eq!(attempts, 3);
+
+        attempts = 0;
+        let missing: Result<()> = with_backoff(policy, || {
+            attempts += 1;
+            Err(Failure::Permanent(anyhow!("404")))
+        });
+        assert!(missing.is_err());
+        assert_eq!(attempts, 1);
+
+        assert_eq!(
+            max_age("public, max-age=3600"),
+            Some(Duration::from_secs(3600))
+        );
+        assert_eq!(max_age("no-cache, max-age=60"), None);
+        assert_eq!(max_age("private"), None);
+    }
+
+    #[test]
+    fn test_parse_responses() {
+        let body = r#"{"date": "2024-01-05", "usd": {"jpy": 144.8, "eur": 0.91}}"#;
+        assert_eq!(JsDelivr::parse(body, "usd", "jpy").unwrap(), dec!(144.8));
+        assert!(JsDelivr::parse(body, "usd", "gbp").is_err());
+
+        let body = "KEY,FREQ,CURRENCY,TIME_PERIOD,OBS_VALUE\n\
+                    EXR.D.USD.EUR.SP00.A,D,USD,2024-01-04,1.0953\n\
+                    EXR.D.USD.EUR.SP00.A,D,USD,2024-01-05,1.0921\n";
+        assert_eq!(Ecb::parse(body).unwrap(), dec!(1.0921));
+
+        let body = r#"{"id": "bitcoin", "market_data": {"current_price": {"usd": 44183.5}}}"#;
+        assert_eq!(CoinGecko::parse(body, "usd").unwrap(), dec!(44183.5));
+
+        assert_eq!(divide(dec!(1), dec!(4), "BTC").unwrap(), dec!(0.25));
+        assert!(divide(dec!(1), dec!(0), "BTC").is_err());
+    }
+
+    #[test]
+    fn test_rate_source() {
+        assert_eq!("ecb".parse::<RateSource>().unwrap(), RateSource::Ecb);
+        assert_eq!(
+            "csv:rates.csv".parse::<RateSource>().unwrap(),
+            RateSource::Csv("rates.csv".to_string())
+        );
+        assert!("yahoo".parse::<RateSource>().is_err());
+    }
+}
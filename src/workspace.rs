@@ -0,0 +1,756 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use itertools::Itertools;
+use uuid::Uuid;
+
+use crate::{
+    accn::Accn,
+    journal::{entry::TxnEntry, Journal, Txn},
+    valuable::{Money, RateProvider, ValuableEntry},
+};
+
+/// Metadata key linking both halves of an inter-journal transfer.
+pub(crate) const TRANSFER_META: &str = "transfer";
+
+#[derive(Debug)]
+struct Member {
+    name: String,
+    file: String,
+    journal: Journal,
+    read_only: bool,
+    /// Copy of `journal` commands go to while trying things out, never
+    /// written to the file unless committed back.
+    sandbox: Option<Journal>,
+}
+
+impl Member {
+    /// The journal commands see, the sandbox if one is open.
+    fn current(&self) -> &Journal {
+        self.sandbox.as_ref().unwrap_or(&self.journal)
+    }
+
+    fn current_mut(&mut self) -> &mut Journal {
+        self.sandbox.as_mut().unwrap_or(&mut self.journal)
+    }
+}
+
+/// A set of journals opened side by side, e.g. a personal and a business one.
+/// Exactly one of them is active and receives the REPL commands.
+#[derive(Debug)]
+pub(crate) struct Workspace {
+    members: Vec<Member>,
+    active: usize,
+}
+
+impl Workspace {
+    pub(crate) fn open<'a>(files: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let mut members: Vec<Member> = Vec::new();
+        for file in files {
+            let name = Path::new(file)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or_else(|| anyhow!("invalid journal file name: {}", file))?
+                .to_string();
+            if members.iter().any(|m| m.name == name) {
+                bail!("journal {} opened twice in the workspace", name);
+            }
+            let journal = Journal::from_file(file)
+                .with_context(|| format!("Failed to open journal file: {}", file))?;
+            let read_only = std::fs::metadata(file)?.permissions().readonly();
+            members.push(Member {
+                name,
+                file: file.to_string(),
+                journal,
+                read_only,
+                sandbox: None,
+            });
+        }
+
+        if members.is_empty() {
+            bail!("workspace needs at least one journal");
+        }
+
+        Ok(Self { members, active: 0 })
+    }
+
+    /// A read-only workspace of one journal that has no file, like a
+    /// generated example.
+    pub(crate) fn demo(name: &str, journal: Journal) -> Self {
+        let member = Member {
+            name: name.to_string(),
+            file: format!("{}.coin", name),
+            journal,
+            read_only: true,
+            sandbox: None,
+        };
+        Self {
+            members: vec![member],
+            active: 0,
+        }
+    }
+
+    fn member(&self) -> &Member {
+        &self.members[self.active]
+    }
+
+    pub(crate) fn active(&self) -> &Journal {
+        self.member().current()
+    }
+
+    pub(crate) fn active_mut(&mut self) -> &mut Journal {
+        self.members[self.active].current_mut()
+    }
+
+    pub(crate) fn active_name(&self) -> &str {
+        &self.member().name
+    }
+
+    pub(crate) fn active_file(&self) -> &str {
+        &self.member().file
+    }
+
+    /// Whether the active journal must not be changed. Its sandbox always
+    /// may be, it is never saved.
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.member().read_only && self.member().sandbox.is_none()
+    }
+
+    /// Forbid changes to every journal of the workspace.
+    pub(crate) fn set_read_only(&mut self) {
+        for member in &mut self.members {
+            member.read_only = true;
+        }
+    }
+
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.members.iter().map(|m| m.name.as_str())
+    }
+
+    /// Journals paired with the file they were opened from, as they would
+    /// be saved, without any sandbox.
+    pub(crate) fn files(&self) -> impl Iterator<Item = (&str, &Journal)> {
+        self.members.iter().map(|m| (m.file.as_str(), &m.journal))
+    }
+
+    pub(crate) fn journals(&self) -> impl Iterator<Item = (&str, &Journal)> {
+        self.members.iter().map(|m| (m.name.as_str(), m.current()))
+    }
+
+    pub(crate) fn journals_mut(&mut self) -> impl Iterator<Item = (&str, &mut Journal)> {
+        self.members.iter_mut().map(|m| {
+            (
+                m.name.as_str(),
+                m.sandbox.as_mut().unwrap_or(&mut m.journal),
+            )
+        })
+    }
+
+    /// Look up a txn in whichever journal of the workspace holds it.
+    pub(crate) fn find_txn(&self, txn: Txn) -> Option<TxnEntry<'_>> {
+        self.journals()
+            .find(|(_, journal)| journal.contains_txn(txn))
+            .map(|(_, journal)| journal.txn(txn))
+    }
+
+    pub(crate) fn remove_txn(&mut self, txn: Txn) {
+        // txns are uniquely keyed, so removing from journals without them is a no-op
+        for member in &mut self.members {
+            member.current_mut().txn_mut(txn).remove();
+        }
+    }
+
+    /// Make the journal called `name` the active one. Only the active
+    /// journal is sandboxed, so there is no switching while a sandbox is
+    /// open.
+    pub(crate) fn switch(&mut self, name: &str) -> Result<()> {
+        let idx = self.position(name)?;
+        if idx != self.active && self.in_sandbox() {
+            bail!("commit or discard the sandbox before switching journals");
+        }
+        self.active = idx;
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.members
+            .iter()
+            .position(|m| m.name == name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "no journal named {} in workspace, choose from: {}",
+                    name,
+                    self.names().join(", ")
+                )
+            })
+    }
+
+    pub(crate) fn journal(&self, name: &str) -> Result<&Journal> {
+        Ok(self.members[self.position(name)?].current())
+    }
+
+    /// File the journal called `name` was opened from.
+    pub(crate) fn file(&self, name: &str) -> Result<&str> {
+        Ok(&self.members[self.position(name)?].file)
+    }
+
+    pub(crate) fn journal_mut(&mut self, name: &str) -> Result<&mut Journal> {
+        let idx = self.position(name)?;
+        Ok(self.members[idx].current_mut())
+    }
+
+    /// Whether a sandbox is open, in which case nothing may be saved.
+    pub(crate) fn in_sandbox(&self) -> bool {
+        self.members.iter().any(|m| m.sandbox.is_some())
+    }
+
+    /// Send the commands of the active journal to a copy of it from now on,
+    /// so txns can be tried out and reported on without touching it.
+    pub(crate) fn open_sandbox(&mut self) -> Result<()> {
+        if let Some(member) = self.members.iter().find(|m| m.sandbox.is_some()) {
+            bail!("a sandbox of {} is already open", member.name);
+        }
+        // a clone keeps the ids of txns and postings, which undo and
+        // reclass still hold once the sandbox is committed
+        let member = &mut self.members[self.active];
+        member.sandbox = Some(member.journal.clone());
+        Ok(())
+    }
+
+    fn sandboxed(&mut self) -> Result<&mut Member> {
+        self.members
+            .iter_mut()
+            .find(|m| m.sandbox.is_some())
+            .ok_or_else(|| anyhow!("no sandbox is open"))
+    }
+
+    /// Make the sandbox the journal it was copied from, with everything
+    /// changed in it, still unsaved.
+    pub(crate) fn commit_sandbox(&mut self) -> Result<&str> {
+        let member = self.sandboxed()?;
+        if member.read_only {
+            bail!("journal {} is read-only", member.name);
+        }
+        member.journal = member.sandbox.take().unwrap();
+        Ok(&member.name)
+    }
+
+    /// Drop the sandbox and whatever was changed in it.
+    pub(crate) fn discard_sandbox(&mut self) -> Result<&str> {
+        let member = self.sandboxed()?;
+        member.sandbox = None;
+        Ok(&member.name)
+    }
+
+    /// Fail if the active journal cannot transfer to the journal called
+    /// `target`. The target would take its half outside the sandbox, so
+    /// there are no transfers while one is open.
+    pub(crate) fn check_transfer(&self, target: &str) -> Result<usize> {
+        let target_idx = self.position(target)?;
+        if target_idx == self.active {
+            bail!("cannot transfer within the same journal {}", target);
+        }
+        if self.in_sandbox() {
+            bail!("commit or discard the sandbox before transferring to another journal");
+        }
+        if self.members[target_idx].read_only {
+            bail!("journal {} is read-only", target);
+        }
+        Ok(target_idx)
+    }
+
+    /// Run `f` on the workspace, putting the active journal and the one
+    /// called `target` back as they were if it fails.
+    pub(crate) fn or_restore<T>(
+        &mut self,
+        target: &str,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let (active, target) = (self.active, self.position(target)?);
+        let before = (
+            self.members[active].current().clone(),
+            self.members[target].current().clone(),
+        );
+        let done = f(self);
+        if done.is_err() {
+            *self.members[active].current_mut() = before.0;
+            *self.members[target].current_mut() = before.1;
+        }
+        done
+    }
+
+    /// Record a transfer of `money` from `from` in the active journal to `to`
+    /// in the journal called `target`. Each half is balanced against an
+    /// `equity:transfer:<other journal>` account and both carry the same
+    /// transfer id, so a missing half can be detected later. Either both
+    /// halves and their clearing accns are recorded or none.
+    pub(crate) fn transfer(
+        &mut self,
+        target: &str,
+        date: NaiveDate,
+        desc: &str,
+        money: Money,
+        from: Accn,
+        to: Accn,
+    ) -> Result<(Txn, Txn)> {
+        let target_idx = self.check_transfer(target)?;
+        self.or_restore(target, |workspace| {
+            workspace.record_transfer(target_idx, date, desc, money, from, to)
+        })
+    }
+
+    fn record_transfer(
+        &mut self,
+        target_idx: usize,
+        date: NaiveDate,
+        desc: &str,
+        money: Money,
+        from: Accn,
+        to: Accn,
+    ) -> Result<(Txn, Txn)> {
+        let target = self.members[target_idx].name.clone();
+        let link = Uuid::new_v4().simple().to_string();
+        let source_name = self.active_name().to_string();
+
+        let target_money = money.rebase(
+            self.active().currencies(),
+            self.members[target_idx].current().currencies(),
+        )?;
+
+        let source = self.active_mut();
+        let clearing = clearing_accn(source, &target);
+        let out = source
+            .new_txn(date, desc.to_string())
+            .with_meta(TRANSFER_META, &link)
+            .with_posting(from, Some(-money))
+            .with_posting(clearing, Some(money))
+            .build()?
+            .id();
+
+        let dest = self.members[target_idx].current_mut();
+        let clearing = clearing_accn(dest, &source_name);
+        let into = dest
+            .new_txn(date, desc.to_string())
+            .with_meta(TRANSFER_META, &link)
+            .with_posting(to, Some(target_money))
+            .with_posting(clearing, Some(-target_money))
+            .build()?
+            .id();
+        Ok((out, into))
+    }
+
+    /// Transfer halves whose counterpart cannot be found in any journal of
+    /// the workspace.
+    pub(crate) fn orphaned_transfers(&self) -> Vec<(&str, TxnEntry<'_>)> {
+        let transfers = self
+            .journals()
+            .flat_map(|(name, journal)| journal.txns().map(move |txn| (name, txn)))
+            .filter(|(_, txn)| txn.meta(TRANSFER_META).is_some())
+            .collect_vec();
+        let counts = transfers
+            .iter()
+            .map(|(_, txn)| txn.meta(TRANSFER_META).unwrap())
+            .counts();
+
+        transfers
+            .into_iter()
+            .filter(|(_, txn)| counts[txn.meta(TRANSFER_META).unwrap()] < 2)
+            .collect()
+    }
+
+    /// Write every writable journal of the workspace back to its file.
+    pub(crate) fn save(&self) -> Result<()> {
+        for member in self.members.iter().filter(|m| !m.read_only) {
+            member.journal.save_to_file(&member.file)?;
+        }
+        Ok(())
+    }
+
+    /// Write the unsaved state of every writable journal to its recovery
+    /// file, so a crash does not lose it.
+    pub(crate) fn autosave(&self) -> Result<()> {
+        for member in self.members.iter().filter(|m| !m.read_only) {
+            let path = recovery_path(&member.file);
+            std::fs::write(&path, member.journal.to_string())
+                .with_context(|| format!("Failed to write recovery file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the recovery files, once the journals are saved.
+    pub(crate) fn clear_recovery(&self) -> Result<()> {
+        for member in &self.members {
+            let path = recovery_path(&member.file);
+            if path.exists() {
+                std::fs::remove_file(&path).with_context(|| {
+                    format!("Failed to remove recovery file: {}", path.display())
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Journals left with a recovery file by a session that did not save.
+    pub(crate) fn recoverable(&self) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|m| !m.read_only && recovery_path(&m.file).exists())
+            .map(|m| m.name.clone())
+            .collect()
+    }
+
+    /// Replace journal `name` with its recovered state, which stays unsaved
+    /// until the next `save`.
+    pub(crate) fn restore(&mut self, name: &str) -> Result<()> {
+        let index = self.position(name)?;
+        let path = recovery_path(&self.members[index].file);
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow!("invalid recovery file name"))?;
+        self.members[index].journal = Journal::from_file(path)
+            .with_context(|| format!("Failed to open recovery file: {}", path))?;
+        Ok(())
+    }
+
+    /// Drop the recovery file of journal `name`.
+    pub(crate) fn discard_recovery(&self, name: &str) -> Result<()> {
+        let path = recovery_path(&self.members[self.position(name)?].file);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove recovery file: {}", path.display()))
+    }
+
+    /// Combined net worth of all journals, expressed in the currencies of the
+    /// active journal.
+    pub(crate) fn net_worth(&self) -> Result<ValuableEntry<'_>> {
+        let store = self.active().currencies();
+        self.journals()
+            .flat_map(|(_, journal)| {
+                journal
+                    .net_worth()
+                    .into_iter()
+                    .map(|money| money.rebase(journal.currencies(), store))
+            })
+            .map_ok(|money| money.into_money(store))
+            .process_results(|moneys| moneys.sum())
+            .map_err(Into::into)
+    }
+
+    /// Combined net worth of all journals converted to `code` at the rates
+    /// of `date`, see [`Journal::net_worth_in`].
+    pub(crate) fn net_worth_in(
+        &self,
+        code: &str,
+        date: NaiveDate,
+        fallback: &dyn RateProvider,
+    ) -> Result<ValuableEntry<'_>> {
+        let store = self.active().currencies();
+        self.journals()
+            .map(|(_, journal)| {
+                journal
+                    .net_worth_in(code, date, fallback)?
+                    .into_iter()
+                    .map(|money| money.rebase(journal.currencies(), store))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(anyhow::Error::from)
+            })
+            .flatten_ok()
+            .map_ok(|money| money.into_money(store))
+            .process_results(|moneys| moneys.sum())
+    }
+}
+
+/// Sidecar next to a journal file keeping its unsaved changes, e.g.
+/// `.main.coinjar.recovery` for `main.coin`.
+fn recovery_path(file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or(file);
+    path.with_file_name(format!(".{}.coinjar.recovery", stem))
+}
+
+/// Account in `journal` balancing transfers with the journal named `other`.
+fn clearing_accn(journal: &mut Journal, other: &str) -> Accn {
+    // journal names come from file names, keep only what the accn grammar accepts
+    let mut name: String = other
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '_' {
+            true => c,
+            false => '-',
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.insert_str(0, "journal-");
+    }
+
+    // declared so the journal still loads under the strict autocreate policy
+    journal
+        .accns_mut()
+        .root_mut()
+        .or_open_child("equity")
+        .or_open_child("transfer")
+        .or_open_child(&name)
+        .declare_open(None, None)
+        .into_ref()
+        .id()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::valuable::ProviderChain;
+
+    #[test]
+    fn test_switch() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        assert_eq!(workspace.active_name(), "simple");
+        workspace.switch("two_txns").unwrap();
+        assert_eq!(workspace.active_file(), "./example/two_txns.coin");
+        assert!(workspace.switch("missing").is_err());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let mut workspace = Workspace::open(["./example/simple.coin"]).unwrap();
+        let count = workspace.active().txns().count();
+        let txn = workspace.active().txns().next().unwrap().id();
+
+        workspace.open_sandbox().unwrap();
+        assert!(workspace.open_sandbox().is_err());
+        let copied = workspace.active().txns().next().unwrap().id();
+        workspace.remove_txn(copied);
+        assert_eq!(workspace.active().txns().count(), count - 1);
+        assert_eq!(workspace.files().next().unwrap().1.txns().count(), count);
+        workspace.discard_sandbox().unwrap();
+        assert!(!workspace.in_sandbox());
+        assert!(workspace.find_txn(txn).is_some());
+
+        workspace.open_sandbox().unwrap();
+        let copied = workspace.active().txns().next().unwrap().id();
+        workspace.remove_txn(copied);
+        assert_eq!(workspace.commit_sandbox().unwrap(), "simple");
+        assert_eq!(
+            workspace.files().next().unwrap().1.txns().count(),
+            count - 1
+        );
+        assert!(workspace.commit_sandbox().is_err());
+    }
+
+    #[test]
+    fn test_undo_after_sandbox() {
+        let dir = std::env::temp_dir().join(format!("coinjar-sandbox-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("simple.coin").display().to_string();
+        std::fs::copy("./example/simple.coin", &file).unwrap();
+        let mut workspace = Workspace::open([file.as_str()]).unwrap();
+        let count = workspace.active().txns().count();
+
+        let journal = workspace.active_mut();
+        let money = journal.parse_money("$5").unwrap().money();
+        let (food, cash) = (
+            open_accn(journal, "expense:food"),
+            open_accn(journal, "asset:cash"),
+        );
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let saved = journal
+            .new_txn(date, "lunch".to_string())
+            .with_posting(food, Some(money))
+            .with_posting(cash, None::<Money>)
+            .build()
+            .unwrap()
+            .id();
+        workspace.save().unwrap();
+
+        workspace.open_sandbox().unwrap();
+        workspace.commit_sandbox().unwrap();
+        // what undo does with the txns of the last save
+        assert!(workspace.find_txn(saved).is_some());
+        workspace.remove_txn(saved);
+        workspace.save().unwrap();
+        assert_eq!(Journal::from_file(&file).unwrap().txns().count(), count);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sandbox_other_journals() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let money = workspace.active().parse_money("$50").unwrap().money();
+        let from = open_accn(workspace.active_mut(), "asset:checking");
+        let to = open_accn(workspace.journal_mut("two_txns").unwrap(), "expense:lunch");
+        let count = workspace.journal("two_txns").unwrap().txns().count();
+
+        workspace.open_sandbox().unwrap();
+        assert!(workspace.switch("two_txns").is_err());
+        assert!(workspace
+            .transfer("two_txns", date, "lunch", money, from, to)
+            .is_err());
+        assert_eq!(workspace.journal("two_txns").unwrap().txns().count(), count);
+        workspace.discard_sandbox().unwrap();
+        workspace.switch("two_txns").unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_name() {
+        let workspace = Workspace::open(["./example/simple.coin", "./example/simple.coin"]);
+        assert!(workspace.is_err());
+    }
+
+    #[test]
+    fn test_txn_ids_unique_across_journals() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        let simple = workspace.active().txns().map(|txn| txn.id()).collect_vec();
+        let other = workspace.journal("two_txns").unwrap();
+        assert!(other.txns().all(|txn| !simple.contains(&txn.id())));
+
+        let txn = other.txns().next().unwrap().id();
+        workspace.remove_txn(txn);
+        assert_eq!(workspace.active().txns().count(), simple.len());
+        assert!(workspace.find_txn(txn).is_none());
+    }
+
+    fn open_accn(journal: &mut Journal, path: &str) -> Accn {
+        path.split(':')
+            .fold(journal.accns_mut().root_mut(), |accn, name| {
+                accn.or_open_child(name)
+            })
+            .into_ref()
+            .id()
+    }
+
+    #[test]
+    fn test_recovery() {
+        let dir = std::env::temp_dir().join(format!("coinjar-recovery-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("simple.coin");
+        std::fs::copy("./example/simple.coin", &file).unwrap();
+        let file = file.to_str().unwrap();
+
+        let mut workspace = Workspace::open([file]).unwrap();
+        assert!(workspace.recoverable().is_empty());
+        let txn = workspace.active().txns().next().unwrap().id();
+        workspace.remove_txn(txn);
+        workspace.autosave().unwrap();
+        let count = workspace.active().txns().count();
+
+        let mut workspace = Workspace::open([file]).unwrap();
+        assert_eq!(workspace.recoverable(), ["simple"]);
+        workspace.restore("simple").unwrap();
+        assert_eq!(workspace.active().txns().count(), count);
+
+        workspace.save().unwrap();
+        workspace.clear_recovery().unwrap();
+        assert!(workspace.recoverable().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transfer() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let money = workspace.active().parse_money("$50").unwrap().money();
+        let from = open_accn(workspace.active_mut(), "asset:checking");
+        let to = open_accn(workspace.journal_mut("two_txns").unwrap(), "expense:lunch");
+
+        let (out, into) = workspace
+            .transfer("two_txns", date, "lunch", money, from, to)
+            .unwrap();
+        assert!(workspace.orphaned_transfers().is_empty());
+
+        let out = workspace.active().txn(out).to_string();
+        assert!(out.contains("equity:transfer:two_txns"));
+        let into = workspace.journal("two_txns").unwrap().txn(into).to_string();
+        assert!(into.contains("equity:transfer:simple"));
+
+        workspace.switch("two_txns").unwrap();
+        for (_, journal) in workspace.journals_mut().take(1) {
+            let txn = journal
+                .txns()
+                .find(|txn| txn.meta(TRANSFER_META).is_some())
+                .unwrap()
+                .id();
+            journal.txn_mut(txn).remove();
+        }
+        let orphans = workspace.orphaned_transfers();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].0, "two_txns");
+    }
+
+    #[test]
+    fn test_transfer_read_only() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        workspace.set_read_only();
+        assert!(workspace.is_read_only());
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let money = workspace.active().parse_money("$50").unwrap().money();
+        let from = open_accn(workspace.active_mut(), "asset:checking");
+        let to = open_accn(workspace.journal_mut("two_txns").unwrap(), "expense:lunch");
+        assert!(workspace
+            .transfer("two_txns", date, "lunch", money, from, to)
+            .is_err());
+    }
+
+    #[test]
+    fn test_failed_transfer_keeps_accns() {
+        let mut workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        *workspace.active_mut() = Journal::from_str(
+            "currency XYZ\n\n2024-01-01 mint\n    asset:vault  1 XYZ\n    equity:mint",
+        )
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let money = workspace.active().parse_money("5 XYZ").unwrap().money();
+        let accns = |workspace: &Workspace| {
+            workspace
+                .journals()
+                .map(|(_, journal)| journal.accns().accns().count())
+                .collect_vec()
+        };
+        let before = accns(&workspace);
+
+        let transfer = workspace.or_restore("two_txns", |workspace| {
+            let from = open_accn(workspace.active_mut(), "asset:vault:spare");
+            let to = open_accn(workspace.journal_mut("two_txns")?, "asset:xyz");
+            workspace.transfer("two_txns", date, "move", money, from, to)
+        });
+        assert!(transfer.is_err());
+        assert_eq!(accns(&workspace), before);
+    }
+
+    #[test]
+    fn test_transfer_same_journal() {
+        let mut workspace = Workspace::open(["./example/simple.coin"]).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let money = workspace.active().parse_money("$50").unwrap().money();
+        let accn = open_accn(workspace.active_mut(), "asset:checking");
+        assert!(workspace
+            .transfer("simple", date, "x", money, accn, accn)
+            .is_err());
+    }
+
+    #[test]
+    fn test_net_worth_in() {
+        let mut workspace = Workspace::open(["./example/simple.coin"]).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let offline = ProviderChain::default();
+        assert!(workspace.net_worth_in("JPY", date, &offline).is_err());
+
+        let rate = "rate 2023-12-29 USD JPY 141\n\n".to_string();
+        *workspace.active_mut() =
+            Journal::from_str(&(rate + &workspace.active().to_string())).unwrap();
+        let worth = workspace.net_worth_in("JPY", date, &offline).unwrap();
+        assert_eq!(worth.to_string(), "-1410 JPY");
+    }
+
+    #[test]
+    fn test_net_worth() {
+        let workspace =
+            Workspace::open(["./example/simple.coin", "./example/two_txns.coin"]).unwrap();
+        assert_eq!(workspace.net_worth().unwrap().to_string(), "-$1020");
+    }
+}